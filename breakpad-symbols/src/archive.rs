@@ -0,0 +1,411 @@
+//! Contains archive symbol retrieval specific functionality
+
+use crate::*;
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tempfile::{NamedTempFile, TempDir};
+use tracing::{debug, trace};
+use zip::ZipArchive;
+
+/// An implementation of `SymbolSupplier` that loads Breakpad text-format
+/// symbols (and other files) from zip and tar archives, without extracting
+/// them.
+///
+/// The archives must be laid out like a local symbol path, see
+/// [`crate::breakpad_sym_lookup`] (this is the case for Mozilla's
+/// `crashreporter-symbols.zip`, for instance). Zip, tar and gzipped tar
+/// (`.tar.gz` or `.tgz`) archives are supported, see [`is_symbol_archive`].
+///
+/// Files in a gzipped tar can only be reached by decompressing everything
+/// before them, so these archives are much slower than the others when they
+/// contain many files.
+pub struct ArchiveSymbolSupplier {
+    /// The archives to search for symbols, in order.
+    archives: Vec<SymbolArchive>,
+    /// A directory where files returned by `locate_file` are extracted.
+    extracted: TempDir,
+}
+
+impl ArchiveSymbolSupplier {
+    /// Instantiate a new `ArchiveSymbolSupplier` that will search in
+    /// `archives`.
+    ///
+    /// Every archive is opened and its contents are indexed here, so this
+    /// fails if any of them can't be read.
+    pub fn new(archives: Vec<PathBuf>) -> io::Result<ArchiveSymbolSupplier> {
+        let archives = archives
+            .into_iter()
+            .map(|path| SymbolArchive::open(&path))
+            .collect::<io::Result<_>>()?;
+        Ok(ArchiveSymbolSupplier {
+            archives,
+            extracted: TempDir::new()?,
+        })
+    }
+}
+
+/// Whether `path` looks like a symbol archive that [`ArchiveSymbolSupplier`]
+/// can read, based on its extension.
+pub fn is_symbol_archive(path: &Path) -> bool {
+    ArchiveKind::from_path(path).is_some()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    fn from_path(path: &Path) -> Option<ArchiveKind> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// An open archive, which can be shared by several readers.
+///
+/// Each clone has its own position, and seeks to it before every read. The
+/// archive is read through the file rather than mapped, so if it's truncated
+/// or rewritten while we use it, we get short reads or garbage (which the
+/// parsers reject) rather than a crash.
+#[derive(Clone)]
+struct SharedFile {
+    file: Arc<Mutex<File>>,
+    pos: u64,
+}
+
+impl SharedFile {
+    fn new(file: File) -> SharedFile {
+        SharedFile {
+            file: Arc::new(Mutex::new(file)),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for SharedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(self.pos))?;
+        let read = file.read(buf)?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for SharedFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => {
+                let len = self.file.lock().unwrap().metadata()?.len();
+                len.checked_add_signed(offset)
+            }
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        self.pos = pos.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek to a negative offset")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+enum ArchiveContents {
+    /// The index of each file in the zip.
+    Zip {
+        archive: ZipArchive<SharedFile>,
+        entries: HashMap<String, usize>,
+    },
+    /// The offset (in the uncompressed tar) and size of each file in the tar.
+    Tar {
+        gzip: bool,
+        entries: HashMap<String, (u64, u64)>,
+    },
+}
+
+struct SymbolArchive {
+    path: PathBuf,
+    file: SharedFile,
+    contents: ArchiveContents,
+}
+
+/// Normalize the path of a file in an archive so it can be compared with
+/// [`FileLookup::cache_rel`].
+fn normalize_entry_name(name: &str) -> String {
+    let name = name.replace('\\', "/");
+    let mut name = &name[..];
+    loop {
+        if let Some(rest) = name.strip_prefix("./") {
+            name = rest;
+        } else if let Some(rest) = name.strip_prefix('/') {
+            name = rest;
+        } else {
+            return name.to_string();
+        }
+    }
+}
+
+impl SymbolArchive {
+    fn open(path: &Path) -> io::Result<SymbolArchive> {
+        let kind = ArchiveKind::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} isn't a zip or tar archive", path.display()),
+            )
+        })?;
+        let file = SharedFile::new(File::open(path)?);
+        let contents = match kind {
+            ArchiveKind::Zip => {
+                let archive = ZipArchive::new(file.clone())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                let entries = (0..archive.len())
+                    .filter_map(|idx| {
+                        Some((normalize_entry_name(archive.name_for_index(idx)?), idx))
+                    })
+                    .collect();
+                ArchiveContents::Zip { archive, entries }
+            }
+            ArchiveKind::Tar | ArchiveKind::TarGz => {
+                let gzip = kind == ArchiveKind::TarGz;
+                let reader = BufReader::new(file.clone());
+                let reader: Box<dyn Read> = if gzip {
+                    Box::new(MultiGzDecoder::new(reader))
+                } else {
+                    Box::new(reader)
+                };
+                let mut archive = tar::Archive::new(reader);
+                let mut entries = HashMap::new();
+                for entry in archive.entries()? {
+                    let entry = entry?;
+                    if !entry.header().entry_type().is_file() {
+                        continue;
+                    }
+                    let name = normalize_entry_name(&entry.path()?.to_string_lossy());
+                    entries.insert(name, (entry.raw_file_position(), entry.size()));
+                }
+                ArchiveContents::Tar { gzip, entries }
+            }
+        };
+        debug!("indexed symbol archive {}", path.display());
+        Ok(SymbolArchive {
+            path: path.to_path_buf(),
+            file,
+            contents,
+        })
+    }
+
+    /// Read the file `name` in the archive with `read`, if there's one.
+    fn with_file<T>(
+        &self,
+        name: &str,
+        read: impl FnOnce(&mut dyn Read) -> T,
+    ) -> Option<io::Result<T>> {
+        match &self.contents {
+            ArchiveContents::Zip { archive, entries } => {
+                let idx = *entries.get(name)?;
+                let mut archive = archive.clone();
+                Some(
+                    archive
+                        .by_index(idx)
+                        .map(|mut file| read(&mut file))
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+                )
+            }
+            ArchiveContents::Tar { gzip, entries } => {
+                let (offset, size) = *entries.get(name)?;
+                if *gzip {
+                    let mut reader = MultiGzDecoder::new(BufReader::new(self.file.clone()));
+                    let skipped = io::copy(&mut (&mut reader).take(offset), &mut io::sink());
+                    Some(skipped.map(|_| read(&mut reader.take(size))))
+                } else {
+                    let mut file = self.file.clone();
+                    Some(
+                        file.seek(SeekFrom::Start(offset))
+                            .map(|_| read(&mut BufReader::new(file).take(size))),
+                    )
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SymbolSupplier for ArchiveSymbolSupplier {
+    #[tracing::instrument(name = "symbols", level = "trace", skip_all, fields(module = crate::basename(&module.code_file())))]
+    async fn locate_symbols(
+        &self,
+        module: &(dyn Module + Sync),
+    ) -> Result<LocateSymbolsResult, SymbolError> {
+        for lookup in lookups(module, FileKind::BreakpadSym) {
+            for archive in &self.archives {
+                if let Some(symbols) =
+                    archive.with_file(&lookup.cache_rel, |file| SymbolFile::from_reader(file))
+                {
+                    trace!(
+                        "ArchiveSymbolSupplier found {} in {}",
+                        lookup.cache_rel,
+                        archive.path.display()
+                    );
+                    let symbols = symbols?.map_err(|e| {
+                        trace!("ArchiveSymbolSupplier failed: {}", e);
+                        e
+                    })?;
+                    return Ok(LocateSymbolsResult {
+                        symbols,
+                        extra_debug_info: None,
+                    });
+                }
+            }
+        }
+        Err(SymbolError::NotFound)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, module), fields(module = crate::basename(&module.code_file())))]
+    async fn locate_file(
+        &self,
+        module: &(dyn Module + Sync),
+        file_kind: FileKind,
+    ) -> Result<PathBuf, FileError> {
+        for lookup in lookups(module, file_kind) {
+            let path = self.extracted.path().join(&lookup.cache_rel);
+            if path.is_file() {
+                return Ok(path);
+            }
+            for archive in &self.archives {
+                let extracted = archive.with_file(&lookup.cache_rel, |file| {
+                    let dir = path.parent().unwrap();
+                    fs::create_dir_all(dir)?;
+                    let mut temp = NamedTempFile::new_in(dir)?;
+                    io::copy(file, &mut temp)?;
+                    temp.flush()?;
+                    // Another lookup may have extracted it already.
+                    let _ = temp.persist_noclobber(&path);
+                    io::Result::Ok(())
+                });
+                match extracted {
+                    Some(Ok(Ok(()))) => {
+                        trace!(
+                            "ArchiveSymbolSupplier extracted {} from {}",
+                            lookup.cache_rel,
+                            archive.path.display()
+                        );
+                        return Ok(path);
+                    }
+                    Some(Ok(Err(e))) | Some(Err(e)) => {
+                        trace!("ArchiveSymbolSupplier failed to extract file: {}", e);
+                    }
+                    None => {}
+                }
+            }
+        }
+        Err(FileError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use debugid::DebugId;
+    use std::str::FromStr;
+
+    const SYM: &[u8] = b"MODULE Linux x86 ABCD1234ABCD1234ABCDABCD12345678a foo
+FUNC 1000 30 10 some func
+";
+    const SYM_PATH: &str = "foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.sym";
+    const PDB_PATH: &str = "foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.pdb";
+
+    fn write_zip(path: &Path) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.add_directory("foo.pdb", options).unwrap();
+        zip.start_file(SYM_PATH, options).unwrap();
+        zip.write_all(SYM).unwrap();
+        zip.start_file(PDB_PATH, options).unwrap();
+        zip.write_all(b"not really a pdb").unwrap();
+        zip.finish().unwrap();
+    }
+
+    fn write_tar(writer: impl Write) {
+        let mut tar = tar::Builder::new(writer);
+        for (path, contents) in [(SYM_PATH, SYM), (PDB_PATH, &b"not really a pdb"[..])] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_cksum();
+            tar.append_data(&mut header, format!("./{path}"), contents)
+                .unwrap();
+        }
+        tar.into_inner().unwrap().flush().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_archive_symbol_supplier() {
+        let t = tempfile::tempdir().unwrap();
+        let zip_path = t.path().join("symbols.zip");
+        write_zip(&zip_path);
+        let tar_path = t.path().join("symbols.tar");
+        write_tar(File::create(&tar_path).unwrap());
+        let tgz_path = t.path().join("symbols.tar.gz");
+        write_tar(flate2::write::GzEncoder::new(
+            File::create(&tgz_path).unwrap(),
+            Default::default(),
+        ));
+        assert!(!is_symbol_archive(&t.path().join("foo.sym")));
+
+        let debug_id = DebugId::from_str("abcd1234-abcd-1234-abcd-abcd12345678-a").unwrap();
+        let module = SimpleModule::new("foo.pdb", debug_id);
+        let missing = SimpleModule::new("bar.pdb", debug_id);
+        let expected = SymbolFile::from_bytes(SYM).unwrap();
+        for path in [zip_path, tar_path, tgz_path] {
+            assert!(is_symbol_archive(&path));
+            let supplier = ArchiveSymbolSupplier::new(vec![path]).unwrap();
+            assert_eq!(
+                supplier.locate_symbols(&module).await.unwrap().symbols,
+                expected
+            );
+            assert_eq!(
+                supplier.locate_symbols(&missing).await,
+                Err(SymbolError::NotFound)
+            );
+
+            let pdb = supplier
+                .locate_file(&module, FileKind::ExtraDebugInfo)
+                .await
+                .unwrap();
+            assert_eq!(fs::read(pdb).unwrap(), b"not really a pdb");
+            assert!(supplier
+                .locate_file(&missing, FileKind::ExtraDebugInfo)
+                .await
+                .is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_archive_truncated() {
+        let t = tempfile::tempdir().unwrap();
+        let tar_path = t.path().join("symbols.tar");
+        write_tar(File::create(&tar_path).unwrap());
+        let debug_id = DebugId::from_str("abcd1234-abcd-1234-abcd-abcd12345678-a").unwrap();
+        let module = SimpleModule::new("foo.pdb", debug_id);
+        let supplier = ArchiveSymbolSupplier::new(vec![tar_path.clone()]).unwrap();
+
+        // An archive which shrinks under us is just an error.
+        File::create(&tar_path).unwrap();
+        assert!(matches!(
+            supplier.locate_symbols(&module).await,
+            Err(SymbolError::ParseError(..))
+        ));
+    }
+}
@@ -0,0 +1,609 @@
+//! Contains the credentials and headers that are sent to symbol servers
+
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{RequestBuilder, Url};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Query parameters whose values are redacted by [`redact_url`].
+const SENSITIVE_QUERY_PARAMS: &[&str] = &[
+    "access_token",
+    "api_key",
+    "apikey",
+    "auth",
+    "key",
+    "password",
+    "sig",
+    "signature",
+    "token",
+    "x-amz-credential",
+    "x-amz-security-token",
+    "x-amz-signature",
+];
+
+/// What replaces credentials in redacted URLs.
+const REDACTED: &str = "redacted";
+
+/// Credentials and extra headers to send to symbol servers.
+///
+/// Each request is sent with the [`ServerCredentials`] whose
+/// [`ServerMatcher`] is the most specific match for its URL: the longest
+/// matching URL prefix, then a matching host, then [`ServerMatcher::Any`].
+/// Redirects are followed with the credentials of the URL they lead to, but
+/// host and `Any` credentials are only sent to the hosts of the symbol servers
+/// the supplier was created with, never to other hosts requests are
+/// redirected to.
+///
+/// Credentials can be read from a config file (see [`Self::parse_config`])
+/// and from a netrc file (see [`Self::parse_netrc`]):
+///
+/// ```
+/// # use breakpad_symbols::SymbolServerCredentials;
+/// let mut credentials = SymbolServerCredentials::parse_config(
+///     "[https://symbols.example.com/private/]\nbearer = secret\n",
+/// )
+/// .unwrap();
+/// credentials.extend(
+///     SymbolServerCredentials::parse_netrc("machine partner.example.org login me password pw")
+///         .unwrap(),
+/// );
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct SymbolServerCredentials {
+    servers: Vec<ServerCredentials>,
+}
+
+/// The credentials and headers to send to some symbol servers.
+#[derive(Clone)]
+pub struct ServerCredentials {
+    /// The servers these credentials are for.
+    pub server: ServerMatcher,
+    /// The `Authorization` to send, if any.
+    pub authorization: Option<Authorization>,
+    /// Extra headers to send, as names and values.
+    pub headers: Vec<(String, String)>,
+}
+
+/// Which URLs some [`ServerCredentials`] apply to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerMatcher {
+    /// URLs under this URL prefix: with the same scheme, host and port, and a
+    /// path which starts with the prefix's path, at a `/` boundary
+    /// (`https://example.com/private` matches `https://example.com/private/foo`
+    /// but not `https://example.com/private-foo`).
+    UrlPrefix(String),
+    /// URLs with this host (a netrc `machine`), if it's the host of one of
+    /// the configured symbol servers.
+    Host(String),
+    /// Every URL on the hosts of the configured symbol servers (a netrc
+    /// `default`).
+    Any,
+}
+
+/// An HTTP `Authorization` for a symbol server.
+#[derive(Clone)]
+pub enum Authorization {
+    /// A bearer token.
+    Bearer(String),
+    /// Basic authentication.
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+}
+
+impl fmt::Debug for ServerCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Header values may be secrets too, so only show their names.
+        let header_names: Vec<_> = self.headers.iter().map(|(name, _)| name).collect();
+        f.debug_struct("ServerCredentials")
+            .field("server", &self.server)
+            .field("authorization", &self.authorization)
+            .field("headers", &header_names)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Authorization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never leak credentials into logs.
+        match self {
+            Authorization::Bearer(_) => f.write_str("Bearer(..)"),
+            Authorization::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// An error produced while reading symbol server credentials.
+#[derive(Debug, thiserror::Error)]
+pub enum CredentialsError {
+    #[error("couldn't read credentials file")]
+    Io(#[from] io::Error),
+    #[error("invalid credentials at line {line}: {message}")]
+    Invalid { line: usize, message: String },
+    #[error("environment variable {0} is not set")]
+    MissingEnvVar(String),
+}
+
+impl SymbolServerCredentials {
+    /// Create an empty set of credentials.
+    pub fn new() -> SymbolServerCredentials {
+        SymbolServerCredentials::default()
+    }
+
+    /// Add credentials for some servers.
+    ///
+    /// When several credentials match a URL equally well, the one added first
+    /// is used.
+    pub fn add(&mut self, server: ServerCredentials) {
+        self.servers.push(server);
+    }
+
+    /// Add all the credentials of `other`, after the existing ones.
+    pub fn extend(&mut self, other: SymbolServerCredentials) {
+        self.servers.extend(other.servers);
+    }
+
+    /// Whether there are no credentials at all.
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    /// Parse a credentials config file.
+    ///
+    /// The file has a section for each URL prefix, containing `bearer`,
+    /// `basic` and `header` settings. `${NAME}` in values is replaced by the
+    /// environment variable `NAME`, so secrets don't have to be written in
+    /// the file. Lines starting with `#` are comments.
+    ///
+    /// ```text
+    /// [https://symbols.example.com/private/]
+    /// bearer = ${PRIVATE_SYMBOLS_TOKEN}
+    /// header = X-Client: minidump-stackwalk
+    ///
+    /// [https://partner.example.org/]
+    /// basic = username:${PARTNER_PASSWORD}
+    /// ```
+    pub fn parse_config(config: &str) -> Result<SymbolServerCredentials, CredentialsError> {
+        let mut credentials = SymbolServerCredentials::new();
+        for (index, line) in config.lines().enumerate() {
+            let line_number = index + 1;
+            let invalid = |message: &str| CredentialsError::Invalid {
+                line: line_number,
+                message: message.to_string(),
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[') {
+                let prefix = section
+                    .strip_suffix(']')
+                    .ok_or_else(|| invalid("unterminated section"))?;
+                credentials.add(ServerCredentials {
+                    server: ServerMatcher::UrlPrefix(prefix.trim().to_string()),
+                    authorization: None,
+                    headers: Vec::new(),
+                });
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `key = value`"))?;
+            let value = expand_env_vars(value.trim())?;
+            let server = credentials
+                .servers
+                .last_mut()
+                .ok_or_else(|| invalid("setting outside of a [url] section"))?;
+            match key.trim() {
+                "bearer" => server.authorization = Some(Authorization::Bearer(value)),
+                "basic" => {
+                    let (username, password) = match value.split_once(':') {
+                        Some((username, password)) => {
+                            (username.to_string(), Some(password.to_string()))
+                        }
+                        None => (value, None),
+                    };
+                    server.authorization = Some(Authorization::Basic { username, password });
+                }
+                "header" => {
+                    let (name, value) = value
+                        .split_once(':')
+                        .ok_or_else(|| invalid("expected `header = Name: value`"))?;
+                    let (name, value) = (name.trim(), value.trim());
+                    if HeaderName::from_bytes(name.as_bytes()).is_err()
+                        || HeaderValue::from_str(value).is_err()
+                    {
+                        return Err(invalid("invalid header"));
+                    }
+                    server.headers.push((name.to_string(), value.to_string()));
+                }
+                _ => return Err(invalid("unknown setting")),
+            }
+        }
+        Ok(credentials)
+    }
+
+    /// Read a credentials config file, see [`Self::parse_config`].
+    pub fn from_config_file(path: &Path) -> Result<SymbolServerCredentials, CredentialsError> {
+        SymbolServerCredentials::parse_config(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse a netrc file, as used by curl and ftp.
+    ///
+    /// Every `machine` with a `login` or `password` becomes basic
+    /// authentication for the symbol servers with that host, and `default`
+    /// for all the other symbol servers (see [`ServerMatcher`]). `account` and
+    /// `macdef` are ignored.
+    pub fn parse_netrc(netrc: &str) -> Result<SymbolServerCredentials, CredentialsError> {
+        let mut credentials = SymbolServerCredentials::new();
+        let mut entry: Option<(ServerMatcher, Option<String>, Option<String>)> = None;
+        let mut finish = |entry: Option<(ServerMatcher, Option<String>, Option<String>)>| {
+            if let Some((server, login, password)) = entry {
+                if login.is_some() || password.is_some() {
+                    credentials.add(ServerCredentials {
+                        server,
+                        authorization: Some(Authorization::Basic {
+                            username: login.unwrap_or_default(),
+                            password,
+                        }),
+                        headers: Vec::new(),
+                    });
+                }
+            }
+        };
+
+        let mut in_macdef = false;
+        for (index, line) in netrc.lines().enumerate() {
+            // A macro definition lasts until the next empty line.
+            if in_macdef {
+                in_macdef = !line.trim().is_empty();
+                continue;
+            }
+            let invalid = |message: &str| CredentialsError::Invalid {
+                line: index + 1,
+                message: message.to_string(),
+            };
+            let mut tokens = line.split_whitespace();
+            while let Some(token) = tokens.next() {
+                if token.starts_with('#') {
+                    break;
+                }
+                let mut value = || tokens.next().ok_or_else(|| invalid("missing value"));
+                match token {
+                    "machine" => {
+                        let host = value()?.to_string();
+                        finish(entry.take());
+                        entry = Some((ServerMatcher::Host(host), None, None));
+                    }
+                    "default" => {
+                        finish(entry.take());
+                        entry = Some((ServerMatcher::Any, None, None));
+                    }
+                    "login" | "password" => {
+                        let value = value()?.to_string();
+                        let (_, login, password) = entry
+                            .as_mut()
+                            .ok_or_else(|| invalid("setting outside of a machine"))?;
+                        if token == "login" {
+                            *login = Some(value);
+                        } else {
+                            *password = Some(value);
+                        }
+                    }
+                    "account" => {
+                        value()?;
+                    }
+                    "macdef" => {
+                        value()?;
+                        in_macdef = true;
+                        break;
+                    }
+                    _ => return Err(invalid("unknown token")),
+                }
+            }
+        }
+        finish(entry);
+        Ok(credentials)
+    }
+
+    /// Read a netrc file, see [`Self::parse_netrc`].
+    pub fn from_netrc_file(path: &Path) -> Result<SymbolServerCredentials, CredentialsError> {
+        SymbolServerCredentials::parse_netrc(&std::fs::read_to_string(path)?)
+    }
+
+    /// The credentials to use for `url`, if any, assuming it's on the host
+    /// of a configured symbol server.
+    pub fn for_url(&self, url: &Url) -> Option<&ServerCredentials> {
+        self.find(url, true)
+    }
+
+    /// The credentials to use for `url`, if any. [`ServerMatcher::Host`] and
+    /// [`ServerMatcher::Any`] are only considered if `on_server` is set.
+    fn find(&self, url: &Url, on_server: bool) -> Option<&ServerCredentials> {
+        let mut best = None;
+        let mut best_rank = None;
+        for server in &self.servers {
+            let rank = match &server.server {
+                ServerMatcher::UrlPrefix(prefix) => match prefix_path_len(url, prefix) {
+                    Some(len) => (2, len),
+                    None => continue,
+                },
+                ServerMatcher::Host(host) if on_server && url.host_str() == Some(host.as_str()) => {
+                    (1, 0)
+                }
+                ServerMatcher::Any if on_server => (0, 0),
+                _ => continue,
+            };
+            if best_rank < Some(rank) {
+                best = Some(server);
+                best_rank = Some(rank);
+            }
+        }
+        best
+    }
+
+    /// Add the credentials for `url` to `request`.
+    ///
+    /// `on_server` is whether `url` is on the host of a configured symbol
+    /// server, rather than another host a request was redirected to.
+    pub(crate) fn apply(
+        &self,
+        mut request: RequestBuilder,
+        url: &Url,
+        on_server: bool,
+    ) -> RequestBuilder {
+        if let Some(server) = self.find(url, on_server) {
+            match &server.authorization {
+                Some(Authorization::Bearer(token)) => request = request.bearer_auth(token),
+                Some(Authorization::Basic { username, password }) => {
+                    request = request.basic_auth(username, password.as_ref())
+                }
+                None => {}
+            }
+            for (name, value) in &server.headers {
+                request = request.header(name, value);
+            }
+        }
+        request
+    }
+}
+
+/// If `url` is under the URL `prefix` (see [`ServerMatcher::UrlPrefix`]), the
+/// length of the prefix's path.
+fn prefix_path_len(url: &Url, prefix: &str) -> Option<usize> {
+    let prefix = Url::parse(prefix).ok()?;
+    if url.scheme() != prefix.scheme()
+        || url.host() != prefix.host()
+        || url.port_or_known_default() != prefix.port_or_known_default()
+    {
+        return None;
+    }
+    let rest = url.path().strip_prefix(prefix.path())?;
+    if prefix.path().ends_with('/') || rest.is_empty() || rest.starts_with('/') {
+        Some(prefix.path().len())
+    } else {
+        None
+    }
+}
+
+/// Replace every `${NAME}` in `value` with the environment variable `NAME`.
+fn expand_env_vars(value: &str) -> Result<String, CredentialsError> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = &rest[start + 2..end];
+        let var =
+            std::env::var(name).map_err(|_| CredentialsError::MissingEnvVar(name.to_string()))?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&var);
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Remove credentials from `url`, so it can be logged or reported.
+///
+/// This redacts the username and password, and the values of query
+/// parameters that commonly hold tokens or signatures.
+pub fn redact_url(url: &Url) -> String {
+    let mut url = url.clone();
+    if !url.username().is_empty() || url.password().is_some() {
+        let _ = url.set_username(REDACTED);
+        let _ = url.set_password(None);
+    }
+    if url.query().is_some() {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(name, value)| {
+                let value = if SENSITIVE_QUERY_PARAMS.contains(&name.to_lowercase().as_str()) {
+                    REDACTED.into()
+                } else {
+                    value
+                };
+                (name.into_owned(), value.into_owned())
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        std::env::set_var("BREAKPAD_SYMBOLS_TEST_TOKEN", "secret");
+        let credentials = SymbolServerCredentials::parse_config(
+            "# comment
+[https://symbols.example.com/]
+header = X-Client: test
+
+[https://symbols.example.com/private/]
+bearer = ${BREAKPAD_SYMBOLS_TEST_TOKEN}
+
+[https://partner.example.org/]
+basic = user:pass:word
+",
+        )
+        .unwrap();
+
+        let url = |url: &str| Url::parse(url).unwrap();
+        let public = credentials
+            .for_url(&url("https://symbols.example.com/foo.pdb"))
+            .unwrap();
+        assert!(public.authorization.is_none());
+        assert_eq!(
+            public.headers,
+            vec![("X-Client".to_string(), "test".to_string())]
+        );
+        let private = credentials
+            .for_url(&url("https://symbols.example.com/private/foo.pdb"))
+            .unwrap();
+        assert!(matches!(
+            &private.authorization,
+            Some(Authorization::Bearer(token)) if token == "secret"
+        ));
+        let partner = credentials
+            .for_url(&url("https://partner.example.org/foo.pdb"))
+            .unwrap();
+        assert!(matches!(
+            &partner.authorization,
+            Some(Authorization::Basic { username, password: Some(password) })
+                if username == "user" && password == "pass:word"
+        ));
+        assert!(credentials
+            .for_url(&url("https://other.example.com/"))
+            .is_none());
+
+        // Prefixes only match the same server, and whole path components.
+        let credentials = SymbolServerCredentials::parse_config(
+            "[https://symbols.example.com]
+bearer = secret
+
+[https://partner.example.org/private]
+bearer = secret
+",
+        )
+        .unwrap();
+        for lookalike in [
+            "https://symbols.example.com.evil.org/foo.pdb",
+            "https://symbols.example.com@evil.org/foo.pdb",
+            "https://symbols.example.com:8443/foo.pdb",
+            "http://symbols.example.com/foo.pdb",
+            "https://partner.example.org/private-other/foo.pdb",
+            "https://partner.example.org/foo.pdb",
+        ] {
+            assert!(
+                credentials.for_url(&url(lookalike)).is_none(),
+                "{}",
+                lookalike
+            );
+        }
+        for matching in [
+            "https://symbols.example.com/foo.pdb",
+            "https://symbols.example.com:443/foo.pdb",
+            "https://partner.example.org/private",
+            "https://partner.example.org/private/foo.pdb",
+        ] {
+            assert!(
+                credentials.for_url(&url(matching)).is_some(),
+                "{}",
+                matching
+            );
+        }
+
+        assert!(matches!(
+            SymbolServerCredentials::parse_config("bearer = token"),
+            Err(CredentialsError::Invalid { line: 1, .. })
+        ));
+        assert!(matches!(
+            SymbolServerCredentials::parse_config("[https://a/]\nbearer = ${NOT_A_REAL_VAR_X}"),
+            Err(CredentialsError::MissingEnvVar(name)) if name == "NOT_A_REAL_VAR_X"
+        ));
+    }
+
+    #[test]
+    fn test_parse_netrc() {
+        let mut credentials = SymbolServerCredentials::parse_config(
+            "[https://symbols.example.com/private/]\nbearer = token\n",
+        )
+        .unwrap();
+        credentials.extend(
+            SymbolServerCredentials::parse_netrc(
+                "machine symbols.example.com login user password pw
+macdef init
+machine ignored.example.com login nobody
+
+default
+  login anonymous
+",
+            )
+            .unwrap(),
+        );
+
+        let url = |url: &str| Url::parse(url).unwrap();
+        let auth = |url_str: &str| {
+            credentials
+                .for_url(&url(url_str))
+                .unwrap()
+                .authorization
+                .clone()
+                .unwrap()
+        };
+        assert!(matches!(
+            auth("https://symbols.example.com/private/a"),
+            Authorization::Bearer(_)
+        ));
+        assert!(matches!(
+            auth("https://symbols.example.com/public/a"),
+            Authorization::Basic { username, password: Some(password) }
+                if username == "user" && password == "pw"
+        ));
+        assert!(matches!(
+            auth("https://ignored.example.com/a"),
+            Authorization::Basic { username, password: None } if username == "anonymous"
+        ));
+
+        // Hosts which aren't symbol servers only get URL prefix credentials.
+        assert!(credentials
+            .find(&url("https://symbols.example.com/public/a"), false)
+            .is_none());
+        assert!(credentials
+            .find(&url("https://ignored.example.com/a"), false)
+            .is_none());
+        assert!(matches!(
+            credentials
+                .find(&url("https://symbols.example.com/private/a"), false)
+                .unwrap()
+                .authorization,
+            Some(Authorization::Bearer(_))
+        ));
+    }
+
+    #[test]
+    fn test_redact_url() {
+        let url = Url::parse(
+            "https://user:pw@symbols.example.com/foo.pdb/1/foo.sym?code_file=foo.dll&token=abc",
+        )
+        .unwrap();
+        assert_eq!(
+            redact_url(&url),
+            "https://redacted@symbols.example.com/foo.pdb/1/foo.sym?code_file=foo.dll&token=redacted"
+        );
+        let url = Url::parse("https://symbols.example.com/foo.sym").unwrap();
+        assert_eq!(redact_url(&url), url.as_str());
+    }
+}
@@ -1,16 +1,18 @@
 //! Contains HTTP symbol retrieval specific functionality
 
 use crate::*;
-use reqwest::{redirect, Client, Url};
+use cachemap2::CacheMap;
+use reqwest::{header, redirect, Client, RequestBuilder, Response, StatusCode, Url};
 use std::io::{self, Write};
 use std::path::Path;
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use tempfile::NamedTempFile;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tracing::{debug, trace, warn};
 
 /// A key that uniquely identifies a File associated with a module
-type FileKey = (ModuleKey, FileKind);
+pub(crate) type FileKey = (ModuleKey, FileKind);
 
 /// An implementation of `SymbolSupplier` that loads Breakpad text-format
 /// symbols from HTTP URLs.
@@ -21,11 +23,13 @@ pub struct HttpSymbolSupplier {
     #[allow(clippy::type_complexity)]
     cached_file_paths: CacheMap<FileKey, CachedAsyncResult<(PathBuf, Option<Url>), FileError>>,
     /// HTTP Client to use for fetching symbols.
-    client: Client,
+    client: SymbolClient,
     /// URLs to search for symbols.
     urls: Vec<Url>,
     /// A `SimpleSymbolSupplier` to use for local symbol paths.
     local: SimpleSymbolSupplier,
+    /// A `SimpleSymbolSupplier` to use for the cache.
+    cached: SimpleSymbolSupplier,
     /// A path at which to cache downloaded symbols.
     ///
     /// We recommend using a subdirectory of `std::env::temp_dir()`, as this
@@ -39,6 +43,13 @@ pub struct HttpSymbolSupplier {
     /// We recommend using `std::env::temp_dir()`, as this will be your OS's
     /// intended location for temporary files.
     tmp: PathBuf,
+    /// How long to remember on disk that a server doesn't have a file, if at
+    /// all.
+    negative_cache_ttl: Option<Duration>,
+    /// Whether to look for compressed symbol files on symbol servers.
+    compressed_symbols: bool,
+    /// Whether to save the preprocessed version of symbol files in the cache.
+    write_preprocessed: bool,
 }
 
 impl HttpSymbolSupplier {
@@ -51,32 +62,95 @@ impl HttpSymbolSupplier {
         urls: Vec<String>,
         cache: PathBuf,
         tmp: PathBuf,
-        mut local_paths: Vec<PathBuf>,
+        local_paths: Vec<PathBuf>,
         timeout: Duration,
     ) -> HttpSymbolSupplier {
-        let client = Client::builder().timeout(timeout).build().unwrap();
-        let urls = urls
-            .into_iter()
-            .filter_map(|mut u| {
-                if !u.ends_with('/') {
-                    u.push('/');
-                }
-                Url::parse(&u).ok()
-            })
-            .collect();
-        local_paths.push(cache.clone());
+        let urls = base_urls(urls);
+        let client = SymbolClient::new(timeout, &urls);
         let local = SimpleSymbolSupplier::new(local_paths);
+        let cached = SimpleSymbolSupplier::new(vec![cache.clone()]);
         let cached_file_paths = Default::default();
         HttpSymbolSupplier {
             client,
             cached_file_paths,
             urls,
             local,
+            cached,
             cache,
             tmp,
+            negative_cache_ttl: None,
+            compressed_symbols: false,
+            write_preprocessed: false,
         }
     }
 
+    /// Save a preprocessed version (see [`preprocessed_path`]) next to every
+    /// symbol file in the cache, so later runs load it faster.
+    ///
+    /// Preprocessed files are loaded lazily, like
+    /// [`SymbolFile::from_preprocessed`], so the symbols loaded from them
+    /// don't fill in [`SymbolFile::functions`] and the other record maps.
+    /// This is disabled by default.
+    pub fn write_preprocessed(mut self, enabled: bool) -> HttpSymbolSupplier {
+        self.cached =
+            SimpleSymbolSupplier::new(vec![self.cache.clone()]).write_preprocessed(enabled);
+        self.write_preprocessed = enabled;
+        self
+    }
+
+    /// Remember in the cache which files symbol servers don't have, for
+    /// `ttl`, so that later runs using the same cache don't ask for them
+    /// again.
+    ///
+    /// Only definitive answers (404 or 410 responses) are remembered, server
+    /// errors are not. This is disabled by default.
+    pub fn negative_cache(mut self, ttl: Option<Duration>) -> HttpSymbolSupplier {
+        self.negative_cache_ttl = ttl;
+        self
+    }
+
+    /// Also look for compressed symbol files (`foo.sym.gz` and `foo.sym.zst`,
+    /// see [`crate::lookups`]) on symbol servers which don't have `foo.sym`.
+    ///
+    /// This costs an extra request per server and compression format for every
+    /// missing symbol file, so it's disabled by default. Servers can always
+    /// send plain symbol files with a gzip or zstd `Content-Encoding`.
+    /// Downloaded files are decompressed before they're cached.
+    ///
+    /// This needs the `compressed_symbols` feature.
+    pub fn compressed_symbols(mut self, enabled: bool) -> HttpSymbolSupplier {
+        self.compressed_symbols = enabled;
+        self
+    }
+
+    /// Retry requests which fail with a server error (5xx), a timeout or a
+    /// connection error up to `max_retries` times, waiting `initial_backoff`
+    /// before the first retry and twice as long before each of the next ones.
+    ///
+    /// Requests are not retried by default.
+    pub fn retries(mut self, max_retries: u32, initial_backoff: Duration) -> HttpSymbolSupplier {
+        self.client.set_retries(max_retries, initial_backoff);
+        self
+    }
+
+    /// Download at most `limit` files at once, other downloads wait for their
+    /// turn. Downloads waiting to be retried (see [`Self::retries`]) don't
+    /// count against the limit. This is unlimited by default.
+    pub fn max_concurrent_downloads(mut self, limit: Option<usize>) -> HttpSymbolSupplier {
+        self.client.set_max_concurrent_downloads(limit);
+        self
+    }
+
+    /// Send `credentials` (authorization and extra headers) to the symbol
+    /// servers they match.
+    ///
+    /// Credentials are never included in logs or in [`SymbolFile::url`], see
+    /// [`redact_url`].
+    pub fn credentials(mut self, credentials: SymbolServerCredentials) -> HttpSymbolSupplier {
+        self.client.set_credentials(credentials);
+        self
+    }
+
     #[tracing::instrument(level = "trace", skip(self, module), fields(module = crate::basename(&module.code_file())))]
     pub async fn locate_file_internal(
         &self,
@@ -86,9 +160,11 @@ impl HttpSymbolSupplier {
         self.cached_file_paths
             .cache_default(file_key(module, file_kind))
             .get(|| async {
-                // First look for the file in the cache
-                if let Ok(path) = self.local.locate_file(module, file_kind).await {
-                    return Ok((path, None));
+                // First look for the file locally and in the cache
+                for local in [&self.local, &self.cached] {
+                    if let Ok(path) = local.locate_file(module, file_kind).await {
+                        return Ok((path, None));
+                    }
                 }
 
                 // Then try to download the file
@@ -96,27 +172,30 @@ impl HttpSymbolSupplier {
                 // we'll end up downloading all of them at once and having them race to write into
                 // the cache... is that ok? Maybe? Since only one will ever win the swap, and it's
                 // unlikely to get multiple hits... this might actually be ok!
-                if let Some(lookup) = lookup(module, file_kind) {
+                let lookups = self.server_lookups(module, file_kind);
+                for lookup in &lookups {
                     for url in &self.urls {
+                        if self.is_known_missing(lookup, url) {
+                            continue;
+                        }
                         let fetch =
-                            fetch_lookup(&self.client, url, &lookup, &self.cache, &self.tmp).await;
+                            fetch_lookup(&self.client, url, lookup, &self.cache, &self.tmp).await;
 
-                        if let Ok((path, url)) = fetch {
-                            return Ok((path, url));
+                        match fetch {
+                            Ok((path, url)) => return Ok((path, url)),
+                            Err(SymbolError::NotFound) => self.remember_missing(lookup, url),
+                            Err(_) => {}
                         }
                     }
+                }
 
+                if let Some(lookup) = lookups.first() {
                     // If we're allowed to look for mozilla's special CAB paths, do that
                     if cfg!(feature = "mozilla_cab_symbols") {
                         for url in &self.urls {
-                            let fetch = fetch_cab_lookup(
-                                &self.client,
-                                url,
-                                &lookup,
-                                &self.cache,
-                                &self.tmp,
-                            )
-                            .await;
+                            let fetch =
+                                fetch_cab_lookup(&self.client, url, lookup, &self.cache, &self.tmp)
+                                    .await;
 
                             if let Ok((path, url)) = fetch {
                                 return Ok((path, url));
@@ -132,7 +211,297 @@ impl HttpSymbolSupplier {
     }
 }
 
-fn file_key(module: &(dyn Module + Sync), file_kind: FileKind) -> FileKey {
+/// Parse `urls` as base URLs that paths can be joined to.
+fn base_urls(urls: Vec<String>) -> Vec<Url> {
+    urls.into_iter()
+        .filter_map(|mut u| {
+            if !u.ends_with('/') {
+                u.push('/');
+            }
+            Url::parse(&u).ok()
+        })
+        .collect()
+}
+
+impl HttpSymbolSupplier {
+    /// The lookups to try on symbol servers for a module's file.
+    ///
+    /// This is [`lookups`], without the compressed variants unless
+    /// [`HttpSymbolSupplier::compressed_symbols`] is enabled.
+    fn server_lookups(&self, module: &(dyn Module + Sync), file_kind: FileKind) -> Vec<FileLookup> {
+        let mut lookups = lookups(module, file_kind);
+        if !self.compressed_symbols {
+            lookups.truncate(1);
+        }
+        lookups
+    }
+
+    /// Whether the negative cache says that the server at `base_url`
+    /// doesn't have the file of `lookup`.
+    fn is_known_missing(&self, lookup: &FileLookup, base_url: &Url) -> bool {
+        let ttl = match self.negative_cache_ttl {
+            Some(ttl) => ttl,
+            None => return false,
+        };
+        let server = redact_url(base_url);
+        let missing = read_negative_cache(&negative_cache_path(&self.cache, lookup), ttl)
+            .iter()
+            .any(|(_, url)| *url == server);
+        if missing {
+            trace!(
+                "HttpSymbolSupplier skipping {} (recently not found on {})",
+                lookup.server_rel,
+                server
+            );
+        }
+        missing
+    }
+
+    /// Record in the negative cache that the server at `base_url` doesn't
+    /// have the file of `lookup`.
+    fn remember_missing(&self, lookup: &FileLookup, base_url: &Url) {
+        let ttl = match self.negative_cache_ttl {
+            Some(ttl) => ttl,
+            None => return,
+        };
+        let path = negative_cache_path(&self.cache, lookup);
+        let server = redact_url(base_url);
+        let mut entries = read_negative_cache(&path, ttl);
+        entries.retain(|(_, url)| *url != server);
+        entries.push((unix_time(SystemTime::now()), server));
+
+        let result = create_cache_file(&self.tmp, &path).and_then(|mut temp| {
+            for (time, url) in &entries {
+                writeln!(temp, "{} {}", time, url)?;
+            }
+            temp.persist(&path)?;
+            Ok(())
+        });
+        if let Err(e) = result {
+            warn!("Failed to save negative cache entry: {}", e);
+        }
+    }
+}
+
+/// The file listing the servers which don't have the file of `lookup`.
+///
+/// Each line has the time the server answered (in seconds since the Unix
+/// epoch) and the server's URL.
+fn negative_cache_path(cache: &Path, lookup: &FileLookup) -> PathBuf {
+    cache.join(format!("{}.notfound", lookup.cache_rel))
+}
+
+/// Read the entries of a negative cache file which are younger than `ttl`.
+fn read_negative_cache(path: &Path, ttl: Duration) -> Vec<(u64, String)> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+    let now = unix_time(SystemTime::now());
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (time, url) = line.split_once(' ')?;
+            let time = time.parse::<u64>().ok()?;
+            if now.saturating_sub(time) < ttl.as_secs() {
+                Some((time, url.to_string()))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+/// Why a download failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DownloadError {
+    /// The server doesn't have the file (404 or 410).
+    NotFound,
+    /// The server failed to answer, or rejected the request.
+    ServerError,
+}
+
+impl From<DownloadError> for SymbolError {
+    fn from(e: DownloadError) -> SymbolError {
+        match e {
+            DownloadError::NotFound => SymbolError::NotFound,
+            DownloadError::ServerError => SymbolError::ServerError,
+        }
+    }
+}
+
+/// The most redirects [`SymbolClient`] follows for a request, the same as
+/// reqwest's default policy.
+const MAX_REDIRECTS: usize = 10;
+
+/// An HTTP client which sends the configured credentials to symbol servers,
+/// retries failed requests and limits concurrent downloads.
+pub(crate) struct SymbolClient {
+    /// A client that doesn't follow redirects, so that each URL along the way
+    /// gets its own credentials (see [`SymbolClient::send`]).
+    client: Client,
+    credentials: SymbolServerCredentials,
+    /// The hosts of the symbol servers, the only ones which get host and
+    /// `Any` credentials.
+    server_hosts: Vec<String>,
+    max_retries: u32,
+    initial_backoff: Duration,
+    /// Limits concurrent downloads, if set.
+    downloads: Option<Arc<Semaphore>>,
+}
+
+impl SymbolClient {
+    /// Create a client for the symbol servers at `servers`.
+    pub(crate) fn new(timeout: Duration, servers: &[Url]) -> SymbolClient {
+        SymbolClient {
+            client: Client::builder()
+                .timeout(timeout)
+                .redirect(redirect::Policy::none())
+                .build()
+                .unwrap(),
+            credentials: SymbolServerCredentials::new(),
+            server_hosts: servers
+                .iter()
+                .filter_map(|url| Some(url.host_str()?.to_string()))
+                .collect(),
+            max_retries: 0,
+            initial_backoff: Duration::from_secs(1),
+            downloads: None,
+        }
+    }
+
+    pub(crate) fn set_credentials(&mut self, credentials: SymbolServerCredentials) {
+        self.credentials = credentials;
+    }
+
+    pub(crate) fn set_retries(&mut self, max_retries: u32, initial_backoff: Duration) {
+        self.max_retries = max_retries;
+        self.initial_backoff = initial_backoff;
+    }
+
+    pub(crate) fn set_max_concurrent_downloads(&mut self, limit: Option<usize>) {
+        self.downloads = limit.map(|limit| Arc::new(Semaphore::new(limit)));
+    }
+
+    /// Start a GET request to `url` that doesn't follow redirects, with its
+    /// credentials.
+    pub(crate) fn get_without_redirects(&self, url: Url) -> RequestBuilder {
+        let on_server = url
+            .host_str()
+            .is_some_and(|host| self.server_hosts.iter().any(|server| server == host));
+        self.credentials
+            .apply(self.client.get(url.clone()), &url, on_server)
+    }
+
+    /// GET `url`, following redirects.
+    ///
+    /// reqwest would send the headers of the first request to every URL it's
+    /// redirected to, including credentials meant for another server, so
+    /// redirects are followed here with the credentials of each URL.
+    async fn send(&self, url: &Url) -> reqwest::Result<Response> {
+        let mut url = url.clone();
+        let mut redirects = 0;
+        loop {
+            let res = self.get_without_redirects(url.clone()).send().await?;
+            let redirected = matches!(
+                res.status(),
+                StatusCode::MOVED_PERMANENTLY
+                    | StatusCode::FOUND
+                    | StatusCode::SEE_OTHER
+                    | StatusCode::TEMPORARY_REDIRECT
+                    | StatusCode::PERMANENT_REDIRECT
+            );
+            if !redirected {
+                return Ok(res);
+            }
+            let location = res
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok());
+            match location {
+                Some(location) if redirects < MAX_REDIRECTS => {
+                    debug!(
+                        "{} redirected to {}",
+                        redact_url(&url),
+                        redact_url(&location)
+                    );
+                    url = location;
+                    redirects += 1;
+                }
+                _ => {
+                    debug!("{} has a bad or too many redirects", redact_url(&url));
+                    return Ok(res);
+                }
+            }
+        }
+    }
+
+    /// Wait until another download is allowed, if they're limited.
+    async fn download_permit(&self) -> Result<Option<OwnedSemaphorePermit>, DownloadError> {
+        match &self.downloads {
+            Some(downloads) => Ok(Some(
+                downloads
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .map_err(|_| DownloadError::ServerError)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// GET `url`, retrying server errors, timeouts and connection errors.
+    ///
+    /// Each attempt counts against the concurrency limit, but waiting to retry
+    /// doesn't. The body of the response should be read before dropping the
+    /// returned permit, so that the download counts against the limit too.
+    pub(crate) async fn fetch(
+        &self,
+        url: &Url,
+    ) -> Result<(Response, Option<OwnedSemaphorePermit>), DownloadError> {
+        let mut backoff = self.initial_backoff;
+        let mut retries = 0;
+        loop {
+            let permit = self.download_permit().await?;
+            let result = self.send(url).await;
+            let retry = match &result {
+                Ok(res) => res.status().is_server_error(),
+                Err(e) => e.is_timeout() || e.is_connect(),
+            };
+            if retry && retries < self.max_retries {
+                drop(permit);
+                retries += 1;
+                debug!("Retrying {} in {:?}", redact_url(url), backoff);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                continue;
+            }
+            return match result {
+                Ok(res) if res.status().is_success() => Ok((res, permit)),
+                Ok(res) if matches!(res.status(), StatusCode::NOT_FOUND | StatusCode::GONE) => {
+                    Err(DownloadError::NotFound)
+                }
+                Ok(res) => {
+                    debug!("{} failed with {}", redact_url(url), res.status());
+                    Err(DownloadError::ServerError)
+                }
+                Err(e) => {
+                    debug!("{} failed: {}", redact_url(url), e);
+                    Err(DownloadError::ServerError)
+                }
+            };
+        }
+    }
+}
+
+pub(crate) fn file_key(module: &(dyn Module + Sync), file_kind: FileKind) -> FileKey {
     (module_key(module), file_kind)
 }
 
@@ -165,7 +534,7 @@ fn commit_cache_file(mut temp: NamedTempFile, final_path: &Path, url: &Url) -> i
 
     // INFO URL allows us to properly report the url we retrieved a symbol file
     // from, even when the file is loaded from our on-disk cache.
-    let cache_metadata = format!("INFO URL {url}\n");
+    let cache_metadata = format!("INFO URL {}\n", redact_url(url));
     temp.write_all(cache_metadata.as_bytes())?;
 
     // TODO: don't do this
@@ -182,20 +551,19 @@ fn commit_cache_file(mut temp: NamedTempFile, final_path: &Path, url: &Url) -> i
 
 /// Perform a code_file/code_identifier lookup for a specific symbol server.
 async fn individual_lookup_debug_info_by_code_info(
+    client: &SymbolClient,
     base_url: &Url,
     lookup_path: &str,
 ) -> Option<DebugInfoResult> {
     let url = base_url.join(lookup_path).ok()?;
 
-    debug!("Trying code file / code identifier lookup: {}", url);
+    debug!(
+        "Trying code file / code identifier lookup: {}",
+        redact_url(&url)
+    );
 
     // This should not follow redirects--we want the next url if there is one
-    let no_redirects_client = Client::builder()
-        .redirect(redirect::Policy::none())
-        .build()
-        .ok()?;
-
-    let response = no_redirects_client.get(url.clone()).send().await;
+    let response = client.get_without_redirects(url).send().await;
     if let Ok(res) = response {
         let res_status = res.status();
         if res_status == reqwest::StatusCode::FOUND
@@ -239,6 +607,7 @@ async fn individual_lookup_debug_info_by_code_info(
 /// This returns a DebugInfoResult with the new debug file and debug identifier
 /// or None.
 async fn lookup_debug_info_by_code_info(
+    client: &SymbolClient,
     symbol_urls: &Vec<Url>,
     module: &(dyn Module + Sync),
 ) -> Option<DebugInfoResult> {
@@ -246,7 +615,7 @@ async fn lookup_debug_info_by_code_info(
 
     for base_url in symbol_urls {
         if let Some(result) =
-            individual_lookup_debug_info_by_code_info(base_url, &lookup_path).await
+            individual_lookup_debug_info_by_code_info(client, base_url, &lookup_path).await
         {
             return Some(result);
         }
@@ -260,16 +629,28 @@ async fn lookup_debug_info_by_code_info(
     None
 }
 
-/// Fetch a symbol file from the URL made by combining `base_url` and `rel_path` using `client`,
-/// save the file contents under `cache` + `rel_path` and also return them.
+/// Fetch a symbol file from the URL made by combining `base_url` and `server_rel` using `client`,
+/// save the file contents under `cache` + `cache_rel` and also return them.
+///
+/// The file may be compressed, either as a whole (`foo.sym.gz`) or with a
+/// `Content-Encoding`. The HTTP client transparently decodes the latter, and
+/// the parser decompresses the former, so the cache always gets the plain
+/// symbol file, with its preprocessed version next to it if `write_preprocessed` is set.
+#[allow(clippy::too_many_arguments)]
 async fn fetch_symbol_file(
-    client: &Client,
+    client: &SymbolClient,
     base_url: &Url,
     module: &(dyn Module + Sync),
+    server_rel: &str,
+    cache_rel: &str,
     cache: &Path,
     tmp: &Path,
+    write_preprocessed: bool,
 ) -> Result<SymbolFile, SymbolError> {
-    trace!("HttpSymbolSupplier trying symbol server {}", base_url);
+    trace!(
+        "HttpSymbolSupplier trying symbol server {}",
+        redact_url(base_url)
+    );
     // This function is a bit of a complicated mess because we want to write
     // the input to our symbol cache, but we're a streaming parser. So we
     // use the bare SymbolFile::parse to get access to the contents of
@@ -280,24 +661,19 @@ async fn fetch_symbol_file(
     // give up on caching but let the parse+download continue.
 
     // First try to GET the file from a server
-    let sym_lookup = breakpad_sym_lookup(module).ok_or(SymbolError::MissingDebugFileOrId)?;
     let mut url = base_url
-        .join(&sym_lookup.server_rel)
+        .join(server_rel)
         .map_err(|_| SymbolError::NotFound)?;
     let code_id = module.code_identifier().unwrap_or_default();
     url.query_pairs_mut()
         .append_pair("code_file", crate::basename(&module.code_file()))
         .append_pair("code_id", code_id.as_str());
-    debug!("Trying {}", url);
-    let res = client
-        .get(url.clone())
-        .send()
-        .await
-        .and_then(|res| res.error_for_status())
-        .map_err(|_| SymbolError::NotFound)?;
+    debug!("Trying {}", redact_url(&url));
+    // Keep the download permit until the whole file is downloaded.
+    let (res, _permit) = client.fetch(&url).await?;
 
     // Now try to create the temp cache file (not yet in the cache)
-    let final_cache_path = cache.join(sym_lookup.cache_rel);
+    let final_cache_path = cache.join(cache_rel);
     let mut temp = create_cache_file(tmp, &final_cache_path)
         .map_err(|e| {
             warn!("Failed to save symbol file in local disk cache: {}", e);
@@ -317,13 +693,23 @@ async fn fetch_symbol_file(
     })
     .await?;
     // Make note of what URL this symbol file was downloaded from.
-    symbol_file.url = Some(url.to_string());
+    symbol_file.url = Some(redact_url(&url));
 
-    // Try to finish the cache file and atomically swap it into the cache.
+    // Try to finish the cache file and atomically swap it into the cache,
+    // and save the preprocessed symbols next to it if asked to.
     if let Some(temp) = temp {
-        let _ = commit_cache_file(temp, &final_cache_path, &url).map_err(|e| {
-            warn!("Failed to save symbol file in local disk cache: {}", e);
-        });
+        match commit_cache_file(temp, &final_cache_path, &url) {
+            Ok(()) if !write_preprocessed => {}
+            Ok(()) => {
+                if let Err(e) = symbol_file.save_preprocessed(&final_cache_path) {
+                    warn!(
+                        "Failed to save preprocessed symbol file in local disk cache: {}",
+                        e
+                    );
+                }
+            }
+            Err(e) => warn!("Failed to save symbol file in local disk cache: {}", e),
+        }
     }
 
     Ok(symbol_file)
@@ -333,8 +719,8 @@ async fn fetch_symbol_file(
 /// on the given Lookup.
 ///
 /// The returned value is the path to the downloaded file and the url it was downloaded from.
-async fn fetch_lookup(
-    client: &Client,
+pub(crate) async fn fetch_lookup(
+    client: &SymbolClient,
     base_url: &Url,
     lookup: &FileLookup,
     cache: &Path,
@@ -344,13 +730,8 @@ async fn fetch_lookup(
     let url = base_url
         .join(&lookup.server_rel)
         .map_err(|_| SymbolError::NotFound)?;
-    debug!("Trying {}", url);
-    let mut res = client
-        .get(url.clone())
-        .send()
-        .await
-        .and_then(|res| res.error_for_status())
-        .map_err(|_| SymbolError::NotFound)?;
+    debug!("Trying {}", redact_url(&url));
+    let (mut res, _permit) = client.fetch(&url).await?;
 
     // Now try to create the temp cache file (not yet in the cache)
     let final_cache_path = cache.join(&lookup.cache_rel);
@@ -376,7 +757,7 @@ async fn fetch_lookup(
 
 #[cfg(feature = "mozilla_cab_symbols")]
 async fn fetch_cab_lookup(
-    client: &Client,
+    client: &SymbolClient,
     base_url: &Url,
     lookup: &FileLookup,
     cache: &Path,
@@ -387,13 +768,8 @@ async fn fetch_cab_lookup(
     let url = base_url
         .join(&cab_lookup.server_rel)
         .map_err(|_| FileError::NotFound)?;
-    debug!("Trying {}", url);
-    let res = client
-        .get(url.clone())
-        .send()
-        .await
-        .and_then(|res| res.error_for_status())
-        .map_err(|_| FileError::NotFound)?;
+    debug!("Trying {}", redact_url(&url));
+    let (res, _permit) = client.fetch(&url).await.map_err(|_| FileError::NotFound)?;
 
     let cab_bytes = res.bytes().await.map_err(|_| FileError::NotFound)?;
     let final_cache_path =
@@ -406,7 +782,7 @@ async fn fetch_cab_lookup(
 
 #[cfg(not(feature = "mozilla_cab_symbols"))]
 async fn fetch_cab_lookup(
-    _client: &Client,
+    _client: &SymbolClient,
     _base_url: &Url,
     _lookup: &FileLookup,
     _cache: &Path,
@@ -415,7 +791,7 @@ async fn fetch_cab_lookup(
     Err(FileError::NotFound)
 }
 
-#[cfg(feature = "mozilla_cab_symbols")]
+#[cfg(any(feature = "mozilla_cab_symbols", feature = "symstore"))]
 pub fn unpack_cabinet_file(
     buf: &[u8],
     lookup: &FileLookup,
@@ -478,7 +854,8 @@ impl SymbolSupplier for HttpSymbolSupplier {
 
         if missing_debug_info {
             debug!("Missing debug file or debug identifier--trying lookup with code info");
-            extra_debug_info = lookup_debug_info_by_code_info(&self.urls, module).await;
+            extra_debug_info =
+                lookup_debug_info_by_code_info(&self.client, &self.urls, module).await;
             if let Some(debug_info_result) = &extra_debug_info {
                 debug_file = Some(debug_info_result.debug_file.clone());
                 debug_id = Some(debug_info_result.debug_identifier);
@@ -496,38 +873,68 @@ impl SymbolSupplier for HttpSymbolSupplier {
             module.code_identifier(),
         );
 
-        // First: try local paths for sym files
-        let local_result = self.local.locate_symbols(&lookup_module).await;
-        if !matches!(local_result, Err(SymbolError::NotFound)) {
-            // Everything but NotFound prevents cascading
-            return local_result.map(|r| LocateSymbolsResult {
-                symbols: r.symbols,
-                extra_debug_info: r.extra_debug_info.or(extra_debug_info),
-            });
+        // First: try local paths and the cache for sym files
+        for local in [&self.local, &self.cached] {
+            let local_result = local.locate_symbols(&lookup_module).await;
+            if !matches!(local_result, Err(SymbolError::NotFound)) {
+                // Everything but NotFound prevents cascading
+                return local_result.map(|r| LocateSymbolsResult {
+                    symbols: r.symbols,
+                    extra_debug_info: r.extra_debug_info.or(extra_debug_info),
+                });
+            }
         }
         trace!("HttpSymbolSupplier search (SimpleSymbolSupplier found nothing)");
 
-        // Second: try to directly download sym files
-        for url in &self.urls {
-            // First, try to get a breakpad .sym file from the symbol server
-            let sym =
-                fetch_symbol_file(&self.client, url, &lookup_module, &self.cache, &self.tmp).await;
-            match sym {
-                Ok(symbols) => {
-                    trace!("HttpSymbolSupplier parsed file!");
-                    return Ok(LocateSymbolsResult {
-                        symbols,
-                        extra_debug_info,
-                    });
+        // Second: try to directly download sym files, and then compressed ones
+        let mut server_error = false;
+        let sym_lookups = self.server_lookups(&lookup_module, FileKind::BreakpadSym);
+        for sym_lookup in &sym_lookups {
+            // Compressed symbol files are decompressed as they're parsed, and
+            // cached as the plain symbol file.
+            let cache_rel = &sym_lookups[0].cache_rel;
+            for url in &self.urls {
+                if self.is_known_missing(sym_lookup, url) {
+                    continue;
                 }
-                Err(e) => {
-                    trace!("HttpSymbolSupplier failed: {}", e);
+                // First, try to get a breakpad .sym file from the symbol server
+                let sym = fetch_symbol_file(
+                    &self.client,
+                    url,
+                    &lookup_module,
+                    &sym_lookup.server_rel,
+                    cache_rel,
+                    &self.cache,
+                    &self.tmp,
+                    self.write_preprocessed,
+                )
+                .await;
+                match sym {
+                    Ok(symbols) => {
+                        trace!("HttpSymbolSupplier parsed file!");
+                        return Ok(LocateSymbolsResult {
+                            symbols,
+                            extra_debug_info,
+                        });
+                    }
+                    Err(SymbolError::NotFound) => {
+                        trace!("HttpSymbolSupplier failed: {}", SymbolError::NotFound);
+                        self.remember_missing(sym_lookup, url);
+                    }
+                    Err(e) => {
+                        trace!("HttpSymbolSupplier failed: {}", e);
+                        server_error |= matches!(e, SymbolError::ServerError);
+                    }
                 }
             }
         }
 
         // If we get this far, we have failed to find anything
-        Err(SymbolError::NotFound)
+        if server_error {
+            Err(SymbolError::ServerError)
+        } else {
+            Err(SymbolError::NotFound)
+        }
     }
 
     async fn locate_file(
@@ -540,3 +947,714 @@ impl SymbolSupplier for HttpSymbolSupplier {
             .map(|(path, _url)| path)
     }
 }
+
+/// The environment variable listing the debuginfod servers to use, separated
+/// by spaces.
+pub const DEBUGINFOD_URLS_VAR: &str = "DEBUGINFOD_URLS";
+
+/// The debuginfod servers listed in the `DEBUGINFOD_URLS` environment
+/// variable.
+pub fn debuginfod_urls_from_env() -> Vec<String> {
+    std::env::var(DEBUGINFOD_URLS_VAR)
+        .map(|urls| urls.split_whitespace().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Get the lookup of a module's file on a debuginfod server.
+///
+/// The build-id is the module's code identifier. The file is cached where
+/// [`crate::lookup`] would put it, except that separate debug info is cached
+/// with a `.debug` extension, since ELF modules usually have the same debug
+/// and code file names.
+pub fn debuginfod_lookup(module: &(dyn Module + Sync), file_kind: FileKind) -> Option<FileLookup> {
+    let kind = match file_kind {
+        FileKind::BreakpadSym => return None,
+        FileKind::Binary => "executable",
+        FileKind::ExtraDebugInfo => "debuginfo",
+    };
+    let build_id = module.code_identifier()?;
+    let mut lookup = lookup(module, file_kind)?;
+    if file_kind == FileKind::ExtraDebugInfo && !lookup.cache_rel.ends_with(".debug") {
+        lookup.cache_rel.push_str(".debug");
+    }
+    lookup.server_rel = format!("buildid/{}/{}", build_id.as_str().to_lowercase(), kind);
+    Some(lookup)
+}
+
+/// An implementation of `SymbolSupplier` that fetches ELF binaries and their
+/// separate debug info from [debuginfod] servers, by GNU build-id.
+///
+/// debuginfod servers don't have Breakpad symbols, so this only locates files
+/// of kind [`FileKind::Binary`] (`/buildid/<id>/executable`) and
+/// [`FileKind::ExtraDebugInfo`] (`/buildid/<id>/debuginfo`), see
+/// [`debuginfod_lookup`]. These are meant for symbol providers which read
+/// debug info directly, such as minidump-unwind's `DebugInfoSymbolProvider`.
+///
+/// [debuginfod]: https://sourceware.org/elfutils/Debuginfod.html
+pub struct DebuginfodSymbolSupplier {
+    /// File paths that are known to be in the cache
+    #[allow(clippy::type_complexity)]
+    cached_file_paths: CacheMap<FileKey, CachedAsyncResult<PathBuf, FileError>>,
+    /// HTTP Client to use for fetching files.
+    client: SymbolClient,
+    /// debuginfod servers to query.
+    urls: Vec<Url>,
+    /// A path at which to cache downloaded files, see
+    /// [`HttpSymbolSupplier::new`].
+    cache: PathBuf,
+    /// A path to a temporary location where downloaded files can be written
+    /// before being atomically swapped into the cache.
+    tmp: PathBuf,
+}
+
+impl DebuginfodSymbolSupplier {
+    /// Create a new `DebuginfodSymbolSupplier`.
+    ///
+    /// Files will be searched for in `cache` first, then on each of the
+    /// debuginfod servers at `urls`. Downloaded files are saved under `cache`.
+    pub fn new(
+        urls: Vec<String>,
+        cache: PathBuf,
+        tmp: PathBuf,
+        timeout: Duration,
+    ) -> DebuginfodSymbolSupplier {
+        let urls = base_urls(urls);
+        DebuginfodSymbolSupplier {
+            cached_file_paths: Default::default(),
+            client: SymbolClient::new(timeout, &urls),
+            urls,
+            cache,
+            tmp,
+        }
+    }
+
+    /// Create a new `DebuginfodSymbolSupplier` for the servers listed in the
+    /// `DEBUGINFOD_URLS` environment variable, if there are any.
+    pub fn from_env(
+        cache: PathBuf,
+        tmp: PathBuf,
+        timeout: Duration,
+    ) -> Option<DebuginfodSymbolSupplier> {
+        let urls = debuginfod_urls_from_env();
+        (!urls.is_empty()).then(|| Self::new(urls, cache, tmp, timeout))
+    }
+
+    /// Send `credentials` to the debuginfod servers they match, as with
+    /// [`HttpSymbolSupplier::credentials`].
+    pub fn credentials(mut self, credentials: SymbolServerCredentials) -> DebuginfodSymbolSupplier {
+        self.client.set_credentials(credentials);
+        self
+    }
+}
+
+#[async_trait]
+impl SymbolSupplier for DebuginfodSymbolSupplier {
+    async fn locate_symbols(
+        &self,
+        _module: &(dyn Module + Sync),
+    ) -> Result<LocateSymbolsResult, SymbolError> {
+        Err(SymbolError::NotFound)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, module), fields(module = crate::basename(&module.code_file())))]
+    async fn locate_file(
+        &self,
+        module: &(dyn Module + Sync),
+        file_kind: FileKind,
+    ) -> Result<PathBuf, FileError> {
+        self.cached_file_paths
+            .cache_default(file_key(module, file_kind))
+            .get(|| async {
+                let lookup = debuginfod_lookup(module, file_kind).ok_or(FileError::NotFound)?;
+                let cache_path = self.cache.join(&lookup.cache_rel);
+                if cache_path.is_file() {
+                    trace!("DebuginfodSymbolSupplier found cached file");
+                    return Ok(cache_path);
+                }
+                for url in &self.urls {
+                    let fetch =
+                        fetch_lookup(&self.client, url, &lookup, &self.cache, &self.tmp).await;
+                    match fetch {
+                        Ok((path, _url)) => return Ok(path),
+                        Err(e) => trace!("DebuginfodSymbolSupplier failed: {}", e),
+                    }
+                }
+                Err(FileError::NotFound)
+            })
+            .await
+            .as_ref()
+            .clone()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve `files` by path from a local HTTP server, and return its URL.
+    pub(crate) async fn serve(files: HashMap<String, &'static [u8]>) -> String {
+        serve_requiring(files, None).await
+    }
+
+    /// Like `serve`, but only answers requests with the `required` header
+    /// line.
+    async fn serve_requiring(
+        files: HashMap<String, &'static [u8]>,
+        required: Option<&'static str>,
+    ) -> String {
+        serve_fn(move |request, path| {
+            let authorized = match required {
+                Some(required) => request.to_lowercase().contains(&required.to_lowercase()),
+                None => true,
+            };
+            match files.get(path) {
+                _ if !authorized => ("401 Unauthorized", &b""[..]),
+                Some(body) => ("200 OK", *body),
+                None => ("404 Not Found", &b""[..]),
+            }
+        })
+        .await
+    }
+
+    /// Answer requests with the status and body `respond` returns for the
+    /// request and its path (without the query string), and return the
+    /// server's URL.
+    async fn serve_fn<F>(respond: F) -> String
+    where
+        F: Fn(&str, &str) -> (&'static str, &'static [u8]) + Send + 'static,
+    {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(len) => request.extend_from_slice(&buf[..len]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split(' ').nth(1).unwrap_or_default();
+                let path = path.split('?').next().unwrap_or_default();
+                let (status, body) = respond(&request, path);
+                let mut response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                )
+                .into_bytes();
+                response.extend_from_slice(body);
+                let _ = stream.write_all(&response).await;
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_debuginfod_symbol_supplier() {
+        let build_id = "0123456789abcdef0123456789abcdef01234567";
+        let files = HashMap::from([
+            (
+                format!("/debuginfod/buildid/{build_id}/executable"),
+                &b"executable"[..],
+            ),
+            (
+                format!("/debuginfod/buildid/{build_id}/debuginfo"),
+                &b"debuginfo"[..],
+            ),
+        ]);
+        let url = format!("{}/debuginfod", serve(files).await);
+
+        let t = tempfile::tempdir().unwrap();
+        let cache = t.path().join("cache");
+        let module = SimpleModule::from_basic_info(
+            Some("libfoo.so".to_string()),
+            Some(DebugId::from_str("67452301-ab89-efcd-0123-456789abcdef").unwrap()),
+            Some("/usr/lib/libfoo.so".to_string()),
+            Some(CodeId::from_str(&build_id.to_uppercase()).unwrap()),
+        );
+        let missing = SimpleModule::from_basic_info(
+            Some("libbar.so".to_string()),
+            Some(DebugId::from_str("67452301-ab89-efcd-0123-456789abcdef").unwrap()),
+            Some("/usr/lib/libbar.so".to_string()),
+            Some(CodeId::from_str("ffffffff").unwrap()),
+        );
+
+        let supplier = DebuginfodSymbolSupplier::new(
+            vec![url],
+            cache.clone(),
+            t.path().to_path_buf(),
+            Duration::from_secs(10),
+        );
+        let executable = supplier
+            .locate_file(&module, FileKind::Binary)
+            .await
+            .unwrap();
+        let debuginfo = supplier
+            .locate_file(&module, FileKind::ExtraDebugInfo)
+            .await
+            .unwrap();
+        assert!(executable.starts_with(&cache));
+        assert_eq!(fs::read(&executable).unwrap(), b"executable");
+        assert_eq!(fs::read(&debuginfo).unwrap(), b"debuginfo");
+        assert!(supplier
+            .locate_file(&missing, FileKind::Binary)
+            .await
+            .is_err());
+        assert!(supplier
+            .locate_file(&module, FileKind::BreakpadSym)
+            .await
+            .is_err());
+        assert!(matches!(
+            supplier.locate_symbols(&module).await,
+            Err(SymbolError::NotFound)
+        ));
+
+        // Files are found in the cache without any server.
+        let supplier = DebuginfodSymbolSupplier::new(
+            vec![],
+            cache,
+            t.path().to_path_buf(),
+            Duration::from_secs(10),
+        );
+        assert_eq!(
+            supplier
+                .locate_file(&module, FileKind::Binary)
+                .await
+                .unwrap(),
+            executable
+        );
+        assert_eq!(
+            supplier
+                .locate_file(&module, FileKind::ExtraDebugInfo)
+                .await
+                .unwrap(),
+            debuginfo
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_symbol_supplier_credentials() {
+        let sym: &'static [u8] = b"MODULE Linux x86 ABCD1234ABCD1234ABCDABCD12345678a foo
+FUNC 1000 30 10 some func
+";
+        let files = HashMap::from([(
+            "/foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.sym".to_string(),
+            sym,
+        )]);
+        let url = serve_requiring(files, Some("authorization: Bearer secret")).await;
+        let module = SimpleModule::new(
+            "foo.pdb",
+            DebugId::from_str("ABCD1234-ABCD-1234-ABCD-ABCD12345678-a").unwrap(),
+        );
+        let supplier = |url: &str, credentials| {
+            let t = tempfile::tempdir().unwrap();
+            HttpSymbolSupplier::new(
+                vec![url.to_string()],
+                t.path().join("cache"),
+                t.path().to_path_buf(),
+                vec![],
+                Duration::from_secs(10),
+            )
+            .credentials(credentials)
+        };
+
+        // Without credentials, the server refuses the download.
+        assert!(matches!(
+            supplier(&url, SymbolServerCredentials::new())
+                .locate_symbols(&module)
+                .await,
+            Err(SymbolError::ServerError)
+        ));
+
+        let config = format!("[{}/]\nbearer = secret\n", url);
+        let credentials = SymbolServerCredentials::parse_config(&config).unwrap();
+        let symbols = supplier(&url, credentials)
+            .locate_symbols(&module)
+            .await
+            .unwrap()
+            .symbols;
+        assert_eq!(symbols.functions.num_ranges(), 1);
+
+        // Credentials in the URL are used, but not reported.
+        let url = serve_requiring(
+            HashMap::from([(
+                "/foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.sym".to_string(),
+                sym,
+            )]),
+            Some("authorization: Basic dXNlcjpzZWNyZXQ="),
+        )
+        .await;
+        let url = url.replace("http://", "http://user:secret@");
+        let symbols = supplier(&url, SymbolServerCredentials::new())
+            .locate_symbols(&module)
+            .await
+            .unwrap()
+            .symbols;
+        let symbol_url = symbols.url.unwrap();
+        assert!(!symbol_url.contains("secret"), "{}", symbol_url);
+        assert!(symbol_url.starts_with("http://redacted@"), "{}", symbol_url);
+    }
+
+    #[tokio::test]
+    async fn test_http_symbol_supplier_credentials_redirect() {
+        let sym: &'static [u8] = b"MODULE Linux x86 ABCD1234ABCD1234ABCDABCD12345678a foo
+FUNC 1000 30 10 some func
+";
+        let path = "/foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.sym";
+        // The server that files are redirected to only accepts its own
+        // credentials.
+        let files = serve_fn(move |request, _| {
+            let request = request.to_lowercase();
+            if request.contains("x-secret") || !request.contains("x-files: files") {
+                ("401 Unauthorized", &b""[..])
+            } else {
+                ("200 OK", sym)
+            }
+        })
+        .await;
+        // serve_fn can't add headers, so slip the Location into the status.
+        let status = format!("302 Found\r\nLocation: {}{}", files, path);
+        let status: &'static str = Box::leak(status.into_boxed_str());
+        let url = serve_fn(move |_, _| (status, &b""[..])).await;
+
+        let config = format!(
+            "[{}/]\nheader = X-Secret: secret\n\n[{}/]\nheader = X-Files: files\n",
+            url, files
+        );
+        let t = tempfile::tempdir().unwrap();
+        let supplier = HttpSymbolSupplier::new(
+            vec![url],
+            t.path().join("cache"),
+            t.path().to_path_buf(),
+            vec![],
+            Duration::from_secs(10),
+        )
+        .credentials(SymbolServerCredentials::parse_config(&config).unwrap());
+        let module = SimpleModule::new(
+            "foo.pdb",
+            DebugId::from_str("ABCD1234-ABCD-1234-ABCD-ABCD12345678-a").unwrap(),
+        );
+        let symbols = supplier.locate_symbols(&module).await.unwrap().symbols;
+        assert_eq!(symbols.functions.num_ranges(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_http_symbol_supplier_netrc_redirect() {
+        let sym: &'static [u8] = b"MODULE Linux x86 ABCD1234ABCD1234ABCDABCD12345678a foo
+FUNC 1000 30 10 some func
+";
+        let path = "/foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.sym";
+        // The files are on another host, which must not get the symbol
+        // server's credentials.
+        let files = serve_fn(move |request, _| {
+            if request.to_lowercase().contains("authorization:") {
+                ("401 Unauthorized", &b""[..])
+            } else {
+                ("200 OK", sym)
+            }
+        })
+        .await;
+        let files = files.replace("127.0.0.1", "localhost");
+        let status = format!("302 Found\r\nLocation: {}{}", files, path);
+        let status: &'static str = Box::leak(status.into_boxed_str());
+        let url = serve_fn(move |request, _| {
+            if request
+                .to_lowercase()
+                .contains("authorization: basic dxnlcjpzzwnyzxq=")
+            {
+                (status, &b""[..])
+            } else {
+                ("401 Unauthorized", &b""[..])
+            }
+        })
+        .await;
+
+        let t = tempfile::tempdir().unwrap();
+        let supplier = HttpSymbolSupplier::new(
+            vec![url],
+            t.path().join("cache"),
+            t.path().to_path_buf(),
+            vec![],
+            Duration::from_secs(10),
+        )
+        .credentials(
+            SymbolServerCredentials::parse_netrc("default login user password secret").unwrap(),
+        );
+        let module = SimpleModule::new(
+            "foo.pdb",
+            DebugId::from_str("ABCD1234-ABCD-1234-ABCD-ABCD12345678-a").unwrap(),
+        );
+        let symbols = supplier.locate_symbols(&module).await.unwrap().symbols;
+        assert_eq!(symbols.functions.num_ranges(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_retry_releases_permit() {
+        let failed = Arc::new(AtomicUsize::new(0));
+        let counter = failed.clone();
+        let url = serve_fn(move |_, path| {
+            if path == "/fail" {
+                counter.fetch_add(1, Ordering::SeqCst);
+                ("503 Service Unavailable", &b""[..])
+            } else {
+                ("200 OK", &b"ok"[..])
+            }
+        })
+        .await;
+        let mut client = SymbolClient::new(Duration::from_secs(10), &[]);
+        client.set_max_concurrent_downloads(Some(1));
+        client.set_retries(1, Duration::from_secs(60));
+        let client = Arc::new(client);
+
+        let failing = {
+            let client = client.clone();
+            let url = Url::parse(&format!("{url}/fail")).unwrap();
+            tokio::spawn(async move { client.fetch(&url).await.map(|_| ()) })
+        };
+        while failed.load(Ordering::SeqCst) == 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        // The other download doesn't wait for the failed one's backoff.
+        let url = Url::parse(&format!("{url}/ok")).unwrap();
+        let (res, _permit) = tokio::time::timeout(Duration::from_secs(10), client.fetch(&url))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.bytes().await.unwrap(), &b"ok"[..]);
+        failing.abort();
+    }
+
+    #[tokio::test]
+    async fn test_http_symbol_supplier_retries() {
+        let sym: &'static [u8] = b"MODULE Linux x86 ABCD1234ABCD1234ABCDABCD12345678a foo
+FUNC 1000 30 10 some func
+";
+        let module = SimpleModule::from_basic_info(
+            Some("foo.pdb".to_string()),
+            Some(DebugId::from_str("ABCD1234-ABCD-1234-ABCD-ABCD12345678-a").unwrap()),
+            Some("foo.dll".to_string()),
+            None,
+        );
+        let missing = SimpleModule::from_basic_info(
+            Some("bar.pdb".to_string()),
+            Some(DebugId::from_str("ABCD1234-ABCD-1234-ABCD-ABCD12345678-a").unwrap()),
+            Some("bar.dll".to_string()),
+            None,
+        );
+        // Fail the first two requests for the symbol file.
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let url = serve_fn(move |_, path| {
+            if path != "/foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.sym" {
+                ("404 Not Found", &b""[..])
+            } else if counter.fetch_add(1, Ordering::SeqCst) % 3 < 2 {
+                ("503 Service Unavailable", &b""[..])
+            } else {
+                ("200 OK", sym)
+            }
+        })
+        .await;
+        let t = tempfile::tempdir().unwrap();
+        let supplier = |cache: &str| {
+            HttpSymbolSupplier::new(
+                vec![url.clone()],
+                t.path().join(cache),
+                t.path().to_path_buf(),
+                vec![],
+                Duration::from_secs(10),
+            )
+            .negative_cache(Some(Duration::from_secs(60)))
+        };
+
+        let symbols = supplier("retries")
+            .retries(2, Duration::from_millis(1))
+            .locate_symbols(&module)
+            .await
+            .unwrap()
+            .symbols;
+        assert_eq!(symbols.functions.num_ranges(), 1);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        // Without retries, the server error is reported as such, and isn't
+        // remembered as the file being missing.
+        let symbolizer = Symbolizer::new(supplier("no-retries"));
+        let mut frame = SimpleFrame::with_instruction(0x1010);
+        assert!(symbolizer.fill_symbol(&module, &mut frame).await.is_err());
+        assert!(symbolizer.fill_symbol(&missing, &mut frame).await.is_err());
+        let stats = symbolizer.stats();
+        assert!(stats["foo.dll"].server_error);
+        assert!(!stats["bar.dll"].server_error);
+        assert!(supplier("no-retries")
+            .retries(2, Duration::from_millis(1))
+            .locate_symbols(&module)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_http_symbol_supplier_negative_cache() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let url = serve_fn(move |_, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            ("404 Not Found", &b""[..])
+        })
+        .await;
+        let module = SimpleModule::new(
+            "foo.pdb",
+            DebugId::from_str("ABCD1234-ABCD-1234-ABCD-ABCD12345678-a").unwrap(),
+        );
+        let t = tempfile::tempdir().unwrap();
+        let supplier = |ttl| {
+            HttpSymbolSupplier::new(
+                vec![url.clone()],
+                t.path().join("cache"),
+                t.path().to_path_buf(),
+                vec![],
+                Duration::from_secs(10),
+            )
+            .negative_cache(ttl)
+        };
+
+        let ttl = Some(Duration::from_secs(60));
+        assert_eq!(
+            supplier(ttl).locate_symbols(&module).await,
+            Err(SymbolError::NotFound)
+        );
+        assert!(supplier(ttl)
+            .locate_file(&module, FileKind::Binary)
+            .await
+            .is_err());
+        let first = requests.load(Ordering::SeqCst);
+        assert!(first > 0);
+
+        // Another supplier sharing the cache doesn't ask again...
+        assert_eq!(
+            supplier(ttl).locate_symbols(&module).await,
+            Err(SymbolError::NotFound)
+        );
+        assert!(supplier(ttl)
+            .locate_file(&module, FileKind::Binary)
+            .await
+            .is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), first);
+
+        // ...unless the entries are too old for it.
+        assert_eq!(
+            supplier(Some(Duration::ZERO)).locate_symbols(&module).await,
+            Err(SymbolError::NotFound)
+        );
+        assert!(requests.load(Ordering::SeqCst) > first);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "compressed_symbols")]
+    async fn test_http_symbol_supplier_compressed() {
+        let contents = b"MODULE Linux x86 ABCD1234ABCD1234ABCDABCD12345678a foo
+FUNC 1000 30 10 some func
+";
+        let zstd = zstd::encode_all(&contents[..], 0).unwrap();
+        let url = serve(HashMap::from([(
+            "/foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.sym.zst".to_string(),
+            &*Box::leak(zstd.into_boxed_slice()),
+        )]))
+        .await;
+        let module = SimpleModule::new(
+            "foo.pdb",
+            DebugId::from_str("ABCD1234-ABCD-1234-ABCD-ABCD12345678-a").unwrap(),
+        );
+        let t = tempfile::tempdir().unwrap();
+        let cache = t.path().join("cache");
+        let supplier = |urls| {
+            HttpSymbolSupplier::new(
+                urls,
+                cache.clone(),
+                t.path().to_path_buf(),
+                vec![],
+                Duration::from_secs(10),
+            )
+        };
+
+        // Compressed symbol files are only looked for when enabled.
+        assert_eq!(
+            supplier(vec![url.clone()]).locate_symbols(&module).await,
+            Err(SymbolError::NotFound)
+        );
+        let symbols = supplier(vec![url.clone()])
+            .compressed_symbols(true)
+            .locate_symbols(&module)
+            .await
+            .unwrap()
+            .symbols;
+        assert_eq!(symbols.function_at(0x1000).unwrap().name, "some func");
+        assert!(symbols.url.as_ref().unwrap().starts_with(&url));
+
+        // The decompressed file is cached, so it can be loaded again (with the
+        // URL it came from).
+        let sym_path = cache.join("foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.sym");
+        assert_eq!(fs::read(&sym_path).unwrap()[..contents.len()], contents[..]);
+        assert!(!preprocessed_path(&sym_path).exists());
+        let cached = supplier(vec![])
+            .locate_symbols(&module)
+            .await
+            .unwrap()
+            .symbols;
+        assert_eq!(cached.function_at(0x1000).unwrap().name, "some func");
+        assert_eq!(cached.url, symbols.url);
+    }
+
+    #[tokio::test]
+    async fn test_http_symbol_supplier_write_preprocessed() {
+        let contents = b"MODULE Linux x86 ABCD1234ABCD1234ABCDABCD12345678a foo
+FUNC 1000 30 10 some func
+";
+        let url = serve(HashMap::from([(
+            "/foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.sym".to_string(),
+            &contents[..],
+        )]))
+        .await;
+        let module = SimpleModule::new(
+            "foo.pdb",
+            DebugId::from_str("ABCD1234-ABCD-1234-ABCD-ABCD12345678-a").unwrap(),
+        );
+        let t = tempfile::tempdir().unwrap();
+        let cache = t.path().join("cache");
+        let supplier = |urls| {
+            HttpSymbolSupplier::new(
+                urls,
+                cache.clone(),
+                t.path().to_path_buf(),
+                vec![],
+                Duration::from_secs(10),
+            )
+            .write_preprocessed(true)
+        };
+
+        let symbols = supplier(vec![url.clone()])
+            .locate_symbols(&module)
+            .await
+            .unwrap()
+            .symbols;
+        assert_eq!(symbols.function_at(0x1000).unwrap().name, "some func");
+
+        // The preprocessed version is saved next to the cached file, and
+        // loaded instead of it.
+        let sym_path = cache.join("foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.sym");
+        assert!(preprocessed_path(&sym_path).exists());
+        let cached = supplier(vec![])
+            .locate_symbols(&module)
+            .await
+            .unwrap()
+            .symbols;
+        assert!(cached.functions.is_empty());
+        assert_eq!(cached.function_at(0x1000).unwrap().name, "some func");
+        assert_eq!(cached.url, symbols.url);
+    }
+}
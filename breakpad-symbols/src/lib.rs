@@ -38,28 +38,46 @@
 //! ```
 
 use async_trait::async_trait;
-use cachemap2::CacheMap;
 use debugid::{CodeId, DebugId};
 use futures_util::lock::Mutex as FutMutex;
 use tracing::trace;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use std::{borrow::Cow, sync::Arc};
 
 pub use minidump_common::{traits::Module, utils::basename};
 pub use sym_file::walker;
 
-pub use crate::sym_file::{CfiRules, SymbolFile};
+pub use crate::sym_file::{
+    preprocessed_path, CfiRules, SymbolCompression, SymbolFile, PREPROCESSED_EXTENSION,
+    PREPROCESSED_FORMAT_VERSION,
+};
 
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(feature = "http")]
+pub mod credentials;
 #[cfg(feature = "http")]
 pub mod http;
 mod sym_file;
+#[cfg(feature = "symstore")]
+pub mod symstore;
 
+#[cfg(feature = "archive")]
+pub use archive::*;
+#[cfg(feature = "http")]
+pub use credentials::{
+    redact_url, Authorization, CredentialsError, ServerCredentials, ServerMatcher,
+    SymbolServerCredentials,
+};
 #[cfg(feature = "http")]
 pub use http::*;
+#[cfg(feature = "symstore")]
+pub use symstore::*;
 
 // Re-exports for the purposes of the cfi_eval fuzzer. Not public API.
 #[doc(hidden)]
@@ -78,8 +96,23 @@ pub struct SymbolStats {
     pub loaded_symbols: bool,
     /// If we tried to parse the symbols, but failed.
     pub corrupt_symbols: bool,
+    /// If the symbols couldn't be found because a symbol server failed,
+    /// rather than because no server had them.
+    pub server_error: bool,
     /// If the module's debug info had to be looked up, this is the debug info used.
     pub extra_debug_info: Option<DebugInfoResult>,
+    /// How many times the module's symbols were needed (to symbolize or unwind
+    /// a frame).
+    pub lookups: u64,
+    /// How many of those lookups didn't have to load the symbols, because they
+    /// were already cached (or being loaded for someone else).
+    pub cache_hits: u64,
+    /// The time spent locating (including downloading) and parsing the symbols.
+    ///
+    /// This can add up over multiple loads if the symbols were evicted from the
+    /// cache. For a [`SymbolizerSession`], this includes the time it waited for
+    /// symbols which another session was loading.
+    pub load_time: Duration,
 }
 
 /// Statistics on pending symbols.
@@ -212,6 +245,10 @@ pub struct FileLookup {
 /// You can use [this script][packagesymbols] to run dump_syms and put the
 /// resulting symbol files in the proper directory structure.
 ///
+/// Symbol files may also be stored compressed, with the extension of the
+/// compression format appended (`<debug filename>.sym.gz`, see
+/// [`compressed_lookup`]). Suppliers try the plain file first.
+///
 /// [module_line]: https://chromium.googlesource.com/breakpad/breakpad/+/master/docs/symbol_files.md#MODULE-records
 /// [packagesymbols]: https://gist.github.com/luser/2ad32d290f224782fcfc#file-packagesymbols-py
 pub fn breakpad_sym_lookup(module: &(dyn Module + Sync)) -> Option<FileLookup> {
@@ -312,6 +349,36 @@ pub fn lookup(module: &(dyn Module + Sync), file_kind: FileKind) -> Option<FileL
     }
 }
 
+/// Mangles a lookup to the path of the file compressed with `compression`, by
+/// appending its extension (`foo.sym` becomes `foo.sym.gz`).
+pub fn compressed_lookup(mut lookup: FileLookup, compression: SymbolCompression) -> FileLookup {
+    for path in [&mut lookup.cache_rel, &mut lookup.server_rel] {
+        path.push('.');
+        path.push_str(compression.extension());
+    }
+    lookup
+}
+
+/// Every lookup to try for this module's file, in order.
+///
+/// This is [`lookup`], followed by its [compressed variants][compressed_lookup]
+/// for Breakpad symbol files if the `compressed_symbols` feature is enabled.
+pub fn lookups(module: &(dyn Module + Sync), file_kind: FileKind) -> Vec<FileLookup> {
+    let lookup = match lookup(module, file_kind) {
+        Some(lookup) => lookup,
+        None => return Vec::new(),
+    };
+    let mut lookups = vec![lookup.clone()];
+    if cfg!(feature = "compressed_symbols") && file_kind == FileKind::BreakpadSym {
+        lookups.extend(
+            SymbolCompression::ALL
+                .iter()
+                .map(|&compression| compressed_lookup(lookup.clone(), compression)),
+        );
+    }
+    lookups
+}
+
 /// Possible results of locating symbols for a module.
 ///
 /// Because symbols may be found from different sources, symbol providers
@@ -343,6 +410,13 @@ pub enum SymbolError {
     /// path of the symbol could not be generated.
     #[error("the debug file or id were missing")]
     MissingDebugFileOrId,
+    /// The symbol servers failed to answer whether they have the symbol file
+    /// (e.g. they kept returning server errors or timing out).
+    ///
+    /// Unlike [`SymbolError::NotFound`], the symbol file may well exist, so
+    /// this outcome shouldn't be remembered for long.
+    #[error("symbol server error")]
+    ServerError,
     /// Symbol file could not be loaded into memory.
     #[error("couldn't read input stream")]
     LoadError(#[from] std::io::Error),
@@ -385,6 +459,7 @@ impl PartialEq for SymbolError {
         matches!(
             (self, other),
             (SymbolError::NotFound, SymbolError::NotFound)
+                | (SymbolError::ServerError, SymbolError::ServerError)
                 | (SymbolError::LoadError(_), SymbolError::LoadError(_))
                 | (SymbolError::ParseError(..), SymbolError::ParseError(..))
         )
@@ -433,15 +508,49 @@ pub trait SymbolSupplier {
 /// paths.
 ///
 /// See [`breakpad_sym_lookup`] for details on how paths are searched.
+///
+/// If a .sym file has a valid preprocessed version next to it (see
+/// [`preprocessed_path`]), that is loaded instead of parsing the text. Its
+/// functions and CFI are decoded when they're looked up (see
+/// [`SymbolFile::from_preprocessed`]).
 pub struct SimpleSymbolSupplier {
     /// Local disk paths in which to search for symbols.
     paths: Vec<PathBuf>,
+    /// Whether to save the preprocessed version of .sym files we parse.
+    write_preprocessed: bool,
+    /// Whether to load .sym files lazily.
+    lazy: bool,
 }
 
 impl SimpleSymbolSupplier {
     /// Instantiate a new `SimpleSymbolSupplier` that will search in `paths`.
     pub fn new(paths: Vec<PathBuf>) -> SimpleSymbolSupplier {
-        SimpleSymbolSupplier { paths }
+        SimpleSymbolSupplier {
+            paths,
+            write_preprocessed: false,
+            lazy: false,
+        }
+    }
+
+    /// Save a preprocessed version next to every .sym file that has to be
+    /// parsed, so later loads are faster.
+    ///
+    /// This requires write access to the symbol paths, and is disabled by
+    /// default.
+    pub fn write_preprocessed(mut self, enabled: bool) -> SimpleSymbolSupplier {
+        self.write_preprocessed = enabled;
+        self
+    }
+
+    /// Load .sym files lazily (see [`SymbolFile::from_file_lazy`]), which is
+    /// much faster when only a few functions of each module are needed.
+    ///
+    /// Valid preprocessed versions are still preferred, as they're loaded
+    /// lazily too, but no new ones are saved for lazily loaded files. This is
+    /// disabled by default.
+    pub fn lazy(mut self, enabled: bool) -> SimpleSymbolSupplier {
+        self.lazy = enabled;
+        self
     }
 }
 
@@ -456,7 +565,13 @@ impl SymbolSupplier for SimpleSymbolSupplier {
             .locate_file(module, FileKind::BreakpadSym)
             .await
             .map_err(|_| SymbolError::NotFound)?;
-        let symbols = SymbolFile::from_file(&file_path).map_err(|e| {
+        let symbols = if self.lazy {
+            SymbolFile::from_preprocessed(&file_path)
+                .or_else(|_| SymbolFile::from_file_lazy(&file_path))
+        } else {
+            SymbolFile::from_file_preprocessed(&file_path, self.write_preprocessed)
+        };
+        let symbols = symbols.map_err(|e| {
             trace!("SimpleSymbolSupplier failed: {}", e);
             e
         })?;
@@ -474,7 +589,8 @@ impl SymbolSupplier for SimpleSymbolSupplier {
         file_kind: FileKind,
     ) -> Result<PathBuf, FileError> {
         trace!("SimpleSymbolSupplier search");
-        if let Some(lookup) = lookup(module, file_kind) {
+        let lookups = lookups(module, file_kind);
+        if let Some(lookup) = lookups.first() {
             for path in self.paths.iter() {
                 if path.is_file() && file_kind == FileKind::BreakpadSym {
                     if let Ok(sf) = SymbolFile::from_file(path) {
@@ -484,10 +600,12 @@ impl SymbolSupplier for SimpleSymbolSupplier {
                         }
                     }
                 } else if path.is_dir() {
-                    let test_path = path.join(lookup.cache_rel.clone());
-                    if fs::metadata(&test_path).ok().map_or(false, |m| m.is_file()) {
-                        trace!("SimpleSymbolSupplier found file {}", test_path.display());
-                        return Ok(test_path);
+                    for lookup in &lookups {
+                        let test_path = path.join(lookup.cache_rel.clone());
+                        if fs::metadata(&test_path).ok().map_or(false, |m| m.is_file()) {
+                            trace!("SimpleSymbolSupplier found file {}", test_path.display());
+                            return Ok(test_path);
+                        }
                     }
                 }
             }
@@ -695,6 +813,131 @@ impl<T, E> CachedAsyncResult<T, E> {
         }
         guard.as_ref().unwrap().clone()
     }
+
+    /// Whether the result is available without waiting.
+    pub fn is_ready(&self) -> bool {
+        self.inner.try_lock().is_some_and(|guard| guard.is_some())
+    }
+}
+
+/// Limits on how many symbol files a [`Symbolizer`] keeps in memory.
+///
+/// Once a limit is exceeded the least recently used symbol files are evicted,
+/// except for those of modules pinned with [`Symbolizer::pin_module`]. Evicted
+/// symbols are located and parsed again the next time they're needed, but
+/// their [`SymbolStats`] are kept.
+///
+/// The default has no limits, so symbols are only ever loaded once.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolCacheLimits {
+    /// The maximum number of modules to cache symbols (or lookup failures) for.
+    pub max_entries: Option<usize>,
+    /// The maximum total size of the cached symbol files in bytes, as estimated
+    /// by [`SymbolFile::approximate_size`].
+    pub max_bytes: Option<usize>,
+}
+
+type CachedSymbols = Arc<CachedAsyncResult<SymbolFile, SymbolError>>;
+
+struct SymbolCacheEntry {
+    symbols: CachedSymbols,
+    /// The value of [`SymbolCache::clock`] when this entry was last used.
+    last_used: u64,
+    /// The approximate size of the symbol file, 0 until it's loaded.
+    size: usize,
+    /// The part of `size` taken by records which were parsed on demand, for
+    /// lazily loaded symbol files.
+    parsed_size: usize,
+}
+
+/// The symbol files loaded by a [`Symbolizer`], evicted in LRU order.
+#[derive(Default)]
+struct SymbolCache {
+    limits: SymbolCacheLimits,
+    entries: HashMap<ModuleKey, SymbolCacheEntry>,
+    pinned: HashSet<ModuleKey>,
+    /// Incremented on every lookup, to order entries by their last use.
+    clock: u64,
+    /// The sum of the sizes of all entries.
+    total_size: usize,
+}
+
+impl SymbolCache {
+    /// Get the entry for `key`, creating an empty one if needed.
+    fn get(&mut self, key: &ModuleKey) -> CachedSymbols {
+        self.clock += 1;
+        let clock = self.clock;
+        let entry = self
+            .entries
+            .entry(key.clone())
+            .or_insert_with(|| SymbolCacheEntry {
+                symbols: CachedSymbols::default(),
+                last_used: clock,
+                size: 0,
+                parsed_size: 0,
+            });
+        entry.last_used = clock;
+        let symbols = entry.symbols.clone();
+        self.evict();
+        symbols
+    }
+
+    /// Record the size of a freshly loaded symbol file.
+    fn set_size(&mut self, key: &ModuleKey, symbols: &CachedSymbols, size: usize) {
+        // The entry may have been evicted (and maybe even replaced) while loading.
+        if let Some(entry) = self.entries.get_mut(key) {
+            if Arc::ptr_eq(&entry.symbols, symbols) {
+                self.total_size = self.total_size - entry.size + size;
+                entry.size = size;
+                entry.parsed_size = 0;
+            }
+        }
+        self.evict();
+    }
+
+    /// Record how much the records parsed on demand in a lazily loaded symbol
+    /// file take, which grows as it's used.
+    fn set_parsed_size(&mut self, key: &ModuleKey, symbols: &CachedSymbols, parsed_size: usize) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            if Arc::ptr_eq(&entry.symbols, symbols) && parsed_size > entry.parsed_size {
+                let growth = parsed_size - entry.parsed_size;
+                self.total_size += growth;
+                entry.size += growth;
+                entry.parsed_size = parsed_size;
+            }
+        }
+        self.evict();
+    }
+
+    fn over_limits(&self) -> bool {
+        self.limits
+            .max_entries
+            .is_some_and(|max| self.entries.len() > max)
+            || self
+                .limits
+                .max_bytes
+                .is_some_and(|max| self.total_size > max)
+    }
+
+    /// Evict the least recently used unpinned entries until we're within limits.
+    fn evict(&mut self) {
+        while self.over_limits() {
+            let victim = self
+                .entries
+                .iter()
+                .filter(|(key, _)| !self.pinned.contains(*key))
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            let Some(victim) = victim else {
+                // Everything left is pinned.
+                break;
+            };
+            trace!("evicting symbols for module {}", victim.0);
+            if let Some(entry) = self.entries.remove(&victim) {
+                self.total_size -= entry.size;
+            }
+        }
+    }
 }
 
 /// Symbolicate stack frames.
@@ -710,20 +953,23 @@ impl<T, E> CachedAsyncResult<T, E> {
 /// Use [`get_symbol_at_address`][get_symbol] or [`fill_symbol`][fill_symbol] to
 /// do symbol lookup.
 ///
+/// By default every symbol file is kept in memory for the lifetime of the
+/// `Symbolizer`. Long-lived Symbolizers can bound this with
+/// [`set_cache_limits`][limits].
+///
 /// [new]: struct.Symbolizer.html#method.new
 /// [supplier]: trait.SymbolSupplier.html
 /// [simple]: struct.SimpleSymbolSupplier.html
 /// [get_symbol]: struct.Symbolizer.html#method.get_symbol_at_address
 /// [fill_symbol]: struct.Symbolizer.html#method.fill_symbol
+/// [limits]: struct.Symbolizer.html#method.set_cache_limits
 pub struct Symbolizer {
     /// Symbol supplier for locating symbols.
     supplier: Box<dyn SymbolSupplier + Send + Sync + 'static>,
     /// Cache of symbol locating results.
-    // TODO?: use lru-cache: https://crates.io/crates/lru-cache/
-    // note that using an lru-cache would mess up the fact that we currently
-    // use this for statistics collection. Splitting out statistics would be
-    // way messier but not impossible.
-    symbols: CacheMap<ModuleKey, CachedAsyncResult<SymbolFile, SymbolError>>,
+    ///
+    /// Statistics are recorded separately, so they outlive evicted entries.
+    symbols: Mutex<SymbolCache>,
     pending_stats: Mutex<PendingSymbolStats>,
     stats: Mutex<HashMap<String, SymbolStats>>,
 }
@@ -733,12 +979,39 @@ impl Symbolizer {
     pub fn new<T: SymbolSupplier + Send + Sync + 'static>(supplier: T) -> Symbolizer {
         Symbolizer {
             supplier: Box::new(supplier),
-            symbols: CacheMap::default(),
+            symbols: Mutex::default(),
             pending_stats: Mutex::default(),
             stats: Mutex::default(),
         }
     }
 
+    /// Set the limits on how many symbol files are kept in memory.
+    ///
+    /// If the cache is already over the new limits, symbols are evicted
+    /// right away.
+    pub fn set_cache_limits(&self, limits: SymbolCacheLimits) {
+        let mut cache = self.symbols.lock().unwrap();
+        cache.limits = limits;
+        cache.evict();
+    }
+
+    /// Never evict the symbols of `module` from the cache, regardless of the
+    /// [cache limits](Self::set_cache_limits).
+    ///
+    /// Pinned symbols still count towards the limits.
+    pub fn pin_module(&self, module: &(dyn Module + Sync)) {
+        let mut cache = self.symbols.lock().unwrap();
+        cache.pinned.insert(module_key(module));
+    }
+
+    /// Undo [`pin_module`](Self::pin_module), making `module`'s symbols
+    /// evictable again.
+    pub fn unpin_module(&self, module: &(dyn Module + Sync)) {
+        let mut cache = self.symbols.lock().unwrap();
+        cache.pinned.remove(&module_key(module));
+        cache.evict();
+    }
+
     /// Helper method for non-minidump-using callers.
     ///
     /// Pass `debug_file` and `debug_id` describing a specific module,
@@ -800,22 +1073,45 @@ impl Symbolizer {
         module: &(dyn Module + Sync),
         frame: &mut (dyn FrameSymbolizer + Send),
     ) -> Result<(), FillSymbolError> {
-        let cached_sym = self.get_symbols(module).await;
+        self.fill_symbol_in_session(module, frame, None).await
+    }
+
+    async fn fill_symbol_in_session(
+        &self,
+        module: &(dyn Module + Sync),
+        frame: &mut (dyn FrameSymbolizer + Send),
+        session: Option<&SymbolizerSession>,
+    ) -> Result<(), FillSymbolError> {
+        let (cached, cached_sym) = self.get_symbols(module, session).await;
         let sym = cached_sym
             .as_ref()
             .as_ref()
             .map_err(|_| FillSymbolError {})?;
         sym.fill_symbol(module, frame);
+        self.update_parsed_size(module, &cached, sym);
         Ok(())
     }
 
     /// Collect various statistics on the symbols.
     ///
     /// Keys are the file name of the module (code_file's file name).
+    ///
+    /// These cover every lookup made through this `Symbolizer`. Use a
+    /// [`session`](Self::session) to get the statistics of a single minidump.
     pub fn stats(&self) -> HashMap<String, SymbolStats> {
         self.stats.lock().unwrap().clone()
     }
 
+    /// Create a [`SymbolizerSession`] which shares this `Symbolizer`'s symbols,
+    /// but keeps statistics of its own.
+    pub fn session(self: &Arc<Self>) -> SymbolizerSession {
+        SymbolizerSession {
+            symbolizer: self.clone(),
+            pending_stats: Mutex::default(),
+            stats: Mutex::default(),
+        }
+    }
+
     /// Get live symbol stats for interactive updates.
     pub fn pending_stats(&self) -> PendingSymbolStats {
         self.pending_stats.lock().unwrap().clone()
@@ -829,31 +1125,78 @@ impl Symbolizer {
         module: &(dyn Module + Sync),
         walker: &mut (dyn FrameWalker + Send),
     ) -> Option<()> {
-        let cached_sym = self.get_symbols(module).await;
+        self.walk_frame_in_session(module, walker, None).await
+    }
+
+    async fn walk_frame_in_session(
+        &self,
+        module: &(dyn Module + Sync),
+        walker: &mut (dyn FrameWalker + Send),
+        session: Option<&SymbolizerSession>,
+    ) -> Option<()> {
+        let (cached, cached_sym) = self.get_symbols(module, session).await;
         let sym = cached_sym.as_ref();
         if let Ok(sym) = sym {
             trace!("found symbols for address, searching for cfi entries");
-            sym.walk_frame(module, walker)
+            let result = sym.walk_frame(module, walker);
+            self.update_parsed_size(module, &cached, sym);
+            result
         } else {
             trace!("couldn't find symbols for address, cannot use cfi");
             None
         }
     }
 
+    /// Lazily loaded symbol files grow as their records are parsed, so update
+    /// their size in the cache after looking something up in them.
+    fn update_parsed_size(
+        &self,
+        module: &(dyn Module + Sync),
+        cached: &CachedSymbols,
+        symbols: &SymbolFile,
+    ) {
+        if symbols.is_lazy() {
+            self.symbols.lock().unwrap().set_parsed_size(
+                &module_key(module),
+                cached,
+                symbols.parsed_size(),
+            );
+        }
+    }
+
     /// Gets the fully parsed SymbolFile for a given module (or an Error).
     ///
-    /// This returns a CachedOperation which is guaranteed to already be resolved (lifetime stuff).
+    /// This returns the cache entry along with its result, which is guaranteed
+    /// to already be resolved (lifetime stuff).
+    ///
+    /// The lookup is recorded in the statistics of the `Symbolizer` and of
+    /// `session`, if any.
     async fn get_symbols(
         &self,
         module: &(dyn Module + Sync),
-    ) -> Arc<Result<SymbolFile, SymbolError>> {
-        self.symbols
-            .cache_default(module_key(module))
+        session: Option<&SymbolizerSession>,
+    ) -> (CachedSymbols, Arc<Result<SymbolFile, SymbolError>>) {
+        let key = module_key(module);
+        let name = leafname(module.code_file().as_ref()).to_string();
+        let cached = self.symbols.lock().unwrap().get(&key);
+        // Symbols which aren't ready are either loaded by us, or we wait for
+        // someone else to load them.
+        let wait_start = (!cached.is_ready()).then(Instant::now);
+        let mut loaded = None;
+        let result = cached
             .get(|| async {
                 trace!("locating symbols for module {}", module.code_file());
-                self.pending_stats.lock().unwrap().symbols_requested += 1;
+                let pending = std::iter::once(&self.pending_stats)
+                    .chain(session.map(|session| &session.pending_stats));
+                for pending_stats in pending.clone() {
+                    pending_stats.lock().unwrap().symbols_requested += 1;
+                }
+                let start = Instant::now();
                 let result = self.supplier.locate_symbols(module).await;
-                self.pending_stats.lock().unwrap().symbols_processed += 1;
+                let load_time = start.elapsed();
+                for pending_stats in pending {
+                    pending_stats.lock().unwrap().symbols_processed += 1;
+                }
 
                 let mut stats = SymbolStats::default();
                 match &result {
@@ -869,6 +1212,10 @@ impl Symbolizer {
                     Err(SymbolError::MissingDebugFileOrId) => {
                         stats.loaded_symbols = false;
                     }
+                    Err(SymbolError::ServerError) => {
+                        stats.loaded_symbols = false;
+                        stats.server_error = true;
+                    }
                     Err(SymbolError::LoadError(_)) => {
                         stats.loaded_symbols = false;
                     }
@@ -877,12 +1224,41 @@ impl Symbolizer {
                         stats.corrupt_symbols = true;
                     }
                 }
-                let key = leafname(module.code_file().as_ref()).to_string();
-                self.stats.lock().unwrap().insert(key, stats);
+                // Record the outcome right away, as anyone waiting for these
+                // symbols will look it up as soon as we're done.
+                stats.load_time = load_time;
+                record_load(&self.stats, &name, &stats);
+                if let Some(session) = session {
+                    record_load(&session.stats, &name, &stats);
+                }
 
-                result.map(|r| r.symbols)
+                let symbols = result.map(|r| r.symbols);
+                let size = symbols.as_ref().map_or(0, SymbolFile::approximate_size);
+                loaded = Some(size);
+                symbols
             })
-            .await
+            .await;
+        // Only whoever loaded the symbols knows their size has to be recorded.
+        if let Some(size) = loaded {
+            self.symbols.lock().unwrap().set_size(&key, &cached, size);
+        }
+
+        let cache_hit = loaded.is_none();
+        record_lookup(&self.stats, &name, cache_hit, None, Duration::ZERO);
+        if let Some(session) = session {
+            // The load time is only recorded by whoever loaded the symbols.
+            let wait_time = wait_start
+                .filter(|_| cache_hit)
+                .map_or(Duration::ZERO, |start| start.elapsed());
+            record_lookup(
+                &session.stats,
+                &name,
+                cache_hit,
+                Some(&self.stats),
+                wait_time,
+            );
+        }
+        (cached, result)
     }
 
     /// Gets the path to a file for a given module (or an Error).
@@ -897,6 +1273,116 @@ impl Symbolizer {
     }
 }
 
+/// Record the outcome of loading the symbols of the module `name`.
+fn record_load(stats: &Mutex<HashMap<String, SymbolStats>>, name: &str, load: &SymbolStats) {
+    let mut stats = stats.lock().unwrap();
+    let stats = stats.entry(name.to_string()).or_default();
+    *stats = SymbolStats {
+        lookups: stats.lookups,
+        cache_hits: stats.cache_hits,
+        load_time: stats.load_time + load.load_time,
+        ..load.clone()
+    };
+}
+
+/// Record a lookup of the symbols of the module `name`.
+///
+/// If the symbols were loaded by someone else, the outcome of that load is
+/// copied from `shared_stats`, and `wait_time` is how long we waited for it.
+fn record_lookup(
+    stats: &Mutex<HashMap<String, SymbolStats>>,
+    name: &str,
+    cache_hit: bool,
+    shared_stats: Option<&Mutex<HashMap<String, SymbolStats>>>,
+    wait_time: Duration,
+) {
+    let mut stats = stats.lock().unwrap();
+    if !stats.contains_key(name) {
+        let load = shared_stats
+            .and_then(|shared| shared.lock().unwrap().get(name).cloned())
+            .unwrap_or_default();
+        let stats_from_load = SymbolStats {
+            lookups: 0,
+            cache_hits: 0,
+            load_time: Duration::ZERO,
+            ..load
+        };
+        stats.insert(name.to_string(), stats_from_load);
+    }
+    let stats = stats.get_mut(name).unwrap();
+    stats.lookups += 1;
+    stats.load_time += wait_time;
+    if cache_hit {
+        stats.cache_hits += 1;
+    }
+}
+
+/// A handle on a shared [`Symbolizer`] which keeps its own statistics.
+///
+/// When one `Symbolizer` is used to process many minidumps concurrently, its
+/// [`stats`](Symbolizer::stats) mix up all of them. Create a session with
+/// [`Symbolizer::session`] for each minidump and do the lookups through it
+/// instead: the symbols are still shared, but [`SymbolizerSession::stats`]
+/// only covers the lookups of this session (which symbols it needed, how many
+/// were already cached, and how long it waited for the rest to load).
+pub struct SymbolizerSession {
+    symbolizer: Arc<Symbolizer>,
+    pending_stats: Mutex<PendingSymbolStats>,
+    stats: Mutex<HashMap<String, SymbolStats>>,
+}
+
+impl SymbolizerSession {
+    /// The shared `Symbolizer`.
+    pub fn symbolizer(&self) -> &Arc<Symbolizer> {
+        &self.symbolizer
+    }
+
+    /// Like [`Symbolizer::fill_symbol`], recording the lookup in this session.
+    pub async fn fill_symbol(
+        &self,
+        module: &(dyn Module + Sync),
+        frame: &mut (dyn FrameSymbolizer + Send),
+    ) -> Result<(), FillSymbolError> {
+        self.symbolizer
+            .fill_symbol_in_session(module, frame, Some(self))
+            .await
+    }
+
+    /// Like [`Symbolizer::walk_frame`], recording the lookup in this session.
+    pub async fn walk_frame(
+        &self,
+        module: &(dyn Module + Sync),
+        walker: &mut (dyn FrameWalker + Send),
+    ) -> Option<()> {
+        self.symbolizer
+            .walk_frame_in_session(module, walker, Some(self))
+            .await
+    }
+
+    /// Same as [`Symbolizer::get_file_path`].
+    pub async fn get_file_path(
+        &self,
+        module: &(dyn Module + Sync),
+        file_kind: FileKind,
+    ) -> Result<PathBuf, FileError> {
+        self.symbolizer.get_file_path(module, file_kind).await
+    }
+
+    /// Collect statistics on the symbols used by this session.
+    ///
+    /// Keys are the file name of the module (code_file's file name).
+    pub fn stats(&self) -> HashMap<String, SymbolStats> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Get live symbol stats on the symbols this session is loading.
+    ///
+    /// Symbols being loaded for another session aren't counted.
+    pub fn pending_stats(&self) -> PendingSymbolStats {
+        self.pending_stats.lock().unwrap().clone()
+    }
+}
+
 #[test]
 fn test_leafname() {
     assert_eq!(leafname("c:\\foo\\bar\\test.pdb"), "test.pdb");
@@ -1123,6 +1609,105 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_simple_symbol_supplier_preprocessed() {
+        let t = tempfile::tempdir().unwrap();
+        let debug_id = DebugId::from_str("abcd1234-abcd-1234-abcd-abcd12345678-a").unwrap();
+        let module = SimpleModule::new("foo.pdb", debug_id);
+        let sym_path = t
+            .path()
+            .join("foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.sym");
+        write_symbol_file(
+            &sym_path,
+            b"MODULE Linux x86 ABCD1234ABCD1234ABCDABCD12345678a foo
+FUNC 1000 30 10 some func
+",
+        );
+
+        let supplier = SimpleSymbolSupplier::new(vec![t.path().to_path_buf()]);
+        let symbols = supplier.locate_symbols(&module).await.unwrap().symbols;
+        assert!(!preprocessed_path(&sym_path).exists());
+
+        let supplier = supplier.write_preprocessed(true);
+        assert_eq!(
+            supplier.locate_symbols(&module).await.unwrap().symbols,
+            symbols
+        );
+        let preprocessed = SymbolFile::from_preprocessed(&sym_path).unwrap();
+        assert!(preprocessed.is_lazy());
+        assert_eq!(
+            supplier.locate_symbols(&module).await.unwrap().symbols,
+            preprocessed
+        );
+        assert_eq!(
+            preprocessed.function_at(0x1000).as_deref(),
+            symbols.functions.get(0x1000)
+        );
+
+        // Lazy suppliers use the preprocessed file, which is also lazily decoded.
+        let lazy = supplier.lazy(true).locate_symbols(&module).await.unwrap();
+        assert_eq!(lazy.symbols, preprocessed);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "compressed_symbols")]
+    async fn test_simple_symbol_supplier_compressed() {
+        let t = tempfile::tempdir().unwrap();
+        let debug_id = DebugId::from_str("abcd1234-abcd-1234-abcd-abcd12345678-a").unwrap();
+        let module = SimpleModule::new("foo.pdb", debug_id);
+        let sym_path = t
+            .path()
+            .join("foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.sym");
+        let contents = b"MODULE Linux x86 ABCD1234ABCD1234ABCDABCD12345678a foo
+FUNC 1000 30 10 some func
+";
+        let expected = SymbolFile::from_bytes(contents).unwrap();
+        let supplier = SimpleSymbolSupplier::new(vec![t.path().to_path_buf()]);
+
+        let zst_path = sym_path.with_extension("sym.zst");
+        write_symbol_file(&zst_path, &zstd::encode_all(&contents[..], 0).unwrap());
+        assert_eq!(
+            supplier
+                .locate_file(&module, FileKind::BreakpadSym)
+                .await
+                .unwrap(),
+            zst_path
+        );
+        assert_eq!(
+            supplier.locate_symbols(&module).await.unwrap().symbols,
+            expected
+        );
+
+        let gz_path = sym_path.with_extension("sym.gz");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(contents).unwrap();
+        write_symbol_file(&gz_path, &encoder.finish().unwrap());
+        assert_eq!(
+            supplier
+                .locate_file(&module, FileKind::BreakpadSym)
+                .await
+                .unwrap(),
+            gz_path
+        );
+        assert_eq!(
+            supplier.locate_symbols(&module).await.unwrap().symbols,
+            expected
+        );
+        let lazy = supplier.lazy(true).locate_symbols(&module).await.unwrap();
+        assert_eq!(lazy.symbols.function_at(0x1000).unwrap().name, "some func");
+
+        // Uncompressed symbol files are preferred.
+        write_symbol_file(&sym_path, contents);
+        let supplier = SimpleSymbolSupplier::new(vec![t.path().to_path_buf()]);
+        assert_eq!(
+            supplier
+                .locate_file(&module, FileKind::BreakpadSym)
+                .await
+                .unwrap(),
+            sym_path
+        );
+    }
+
     #[tokio::test]
     async fn test_symbolizer() {
         let t = tempfile::tempdir().unwrap();
@@ -1187,6 +1772,260 @@ FUNC 1000 30 10 another func
             .is_none());
     }
 
+    fn cache_test_symbolizer() -> Symbolizer {
+        let mut modules = HashMap::new();
+        for name in ["a.pdb", "b.pdb", "c.pdb"] {
+            modules.insert(
+                name.to_string(),
+                format!(
+                    "MODULE Linux x86 ABCD1234ABCD1234ABCDABCD12345678a {name}
+FILE 1 {name}.c
+FUNC 1000 30 10 {name} func
+1000 30 100 1
+"
+                ),
+            );
+        }
+        Symbolizer::new(StringSymbolSupplier::new(modules))
+    }
+
+    fn cache_test_module(name: &str) -> SimpleModule {
+        SimpleModule::from_basic_info(
+            Some(name.to_string()),
+            Some(DebugId::from_str("abcd1234-abcd-1234-abcd-abcd12345678-a").unwrap()),
+            Some(name.to_string()),
+            None,
+        )
+    }
+
+    async fn lookup(symbolizer: &Symbolizer, module: &str) -> Option<String> {
+        let mut frame = SimpleFrame::with_instruction(0x1010);
+        symbolizer
+            .fill_symbol(&cache_test_module(module), &mut frame)
+            .await
+            .ok()?;
+        frame.function
+    }
+
+    #[tokio::test]
+    async fn test_symbol_cache_max_entries() {
+        let symbolizer = cache_test_symbolizer();
+        symbolizer.set_cache_limits(SymbolCacheLimits {
+            max_entries: Some(2),
+            max_bytes: None,
+        });
+        let requested = || symbolizer.pending_stats().symbols_requested;
+
+        assert_eq!(lookup(&symbolizer, "a.pdb").await.unwrap(), "a.pdb func");
+        assert_eq!(lookup(&symbolizer, "b.pdb").await.unwrap(), "b.pdb func");
+        // Make b.pdb the least recently used entry.
+        lookup(&symbolizer, "a.pdb").await;
+        assert_eq!(requested(), 2);
+        assert_eq!(lookup(&symbolizer, "c.pdb").await.unwrap(), "c.pdb func");
+        assert_eq!(requested(), 3);
+        lookup(&symbolizer, "a.pdb").await;
+        assert_eq!(requested(), 3);
+
+        // b.pdb was evicted, so it's loaded again.
+        assert_eq!(lookup(&symbolizer, "b.pdb").await.unwrap(), "b.pdb func");
+        assert_eq!(requested(), 4);
+
+        // Failed lookups take up an entry too.
+        assert!(lookup(&symbolizer, "missing.pdb").await.is_none());
+        assert_eq!(symbolizer.symbols.lock().unwrap().entries.len(), 2);
+
+        // Stats outlive the evicted symbols.
+        let stats = symbolizer.stats();
+        assert_eq!(stats.len(), 4);
+        assert!(stats["c.pdb"].loaded_symbols);
+        assert!(!stats["missing.pdb"].loaded_symbols);
+    }
+
+    #[tokio::test]
+    async fn test_symbol_cache_max_bytes() {
+        let symbolizer = cache_test_symbolizer();
+        lookup(&symbolizer, "a.pdb").await;
+        let size = symbolizer.symbols.lock().unwrap().total_size;
+        assert!(size > 0);
+
+        // Room for a single symbol file.
+        symbolizer.set_cache_limits(SymbolCacheLimits {
+            max_entries: None,
+            max_bytes: Some(size + size / 2),
+        });
+        lookup(&symbolizer, "b.pdb").await;
+        lookup(&symbolizer, "a.pdb").await;
+        assert_eq!(symbolizer.pending_stats().symbols_requested, 3);
+        let cache = symbolizer.symbols.lock().unwrap();
+        assert_eq!(cache.entries.len(), 1);
+        assert_eq!(cache.total_size, size);
+    }
+
+    #[tokio::test]
+    async fn test_symbol_cache_lazy_size() {
+        let t = tempfile::tempdir().unwrap();
+        let module = cache_test_module("a.pdb");
+        write_symbol_file(
+            &t.path()
+                .join("a.pdb/ABCD1234ABCD1234ABCDABCD12345678a/a.sym"),
+            b"MODULE Linux x86 ABCD1234ABCD1234ABCDABCD12345678a a.pdb
+FUNC 1000 30 10 a.pdb func
+1000 30 100 1
+FUNC 2000 30 10 another func
+2000 30 100 1
+",
+        );
+        let supplier = SimpleSymbolSupplier::new(vec![t.path().to_path_buf()]).lazy(true);
+        let symbolizer = Symbolizer::new(supplier);
+        let size = || symbolizer.symbols.lock().unwrap().total_size;
+
+        let mut frame = SimpleFrame::with_instruction(0x1010);
+        symbolizer.fill_symbol(&module, &mut frame).await.unwrap();
+        let loaded = size();
+        assert!(loaded > 0);
+        symbolizer.fill_symbol(&module, &mut frame).await.unwrap();
+        assert_eq!(size(), loaded);
+
+        // Parsing another function makes the symbol file bigger.
+        let mut frame = SimpleFrame::with_instruction(0x2010);
+        symbolizer.fill_symbol(&module, &mut frame).await.unwrap();
+        assert_eq!(frame.function.unwrap(), "another func");
+        assert!(size() > loaded);
+    }
+
+    #[tokio::test]
+    async fn test_symbol_cache_pinning() {
+        let symbolizer = cache_test_symbolizer();
+        let a = cache_test_module("a.pdb");
+        symbolizer.pin_module(&a);
+        symbolizer.set_cache_limits(SymbolCacheLimits {
+            max_entries: Some(1),
+            max_bytes: None,
+        });
+        let requested = || symbolizer.pending_stats().symbols_requested;
+
+        lookup(&symbolizer, "a.pdb").await;
+        lookup(&symbolizer, "b.pdb").await;
+        lookup(&symbolizer, "c.pdb").await;
+        assert_eq!(requested(), 3);
+        // a.pdb stayed in the cache even though it's the least recently used.
+        assert_eq!(lookup(&symbolizer, "a.pdb").await.unwrap(), "a.pdb func");
+        assert_eq!(requested(), 3);
+
+        // Unpinning makes it evictable again.
+        symbolizer.unpin_module(&a);
+        assert_eq!(symbolizer.symbols.lock().unwrap().entries.len(), 1);
+        lookup(&symbolizer, "b.pdb").await;
+        lookup(&symbolizer, "a.pdb").await;
+        assert_eq!(requested(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_symbolizer_sessions() {
+        let symbolizer = Arc::new(cache_test_symbolizer());
+        let session1 = symbolizer.session();
+        let session2 = symbolizer.session();
+        let mut frame = SimpleFrame::with_instruction(0x1010);
+
+        for module in ["a.pdb", "a.pdb", "missing.pdb"] {
+            let _ = session1
+                .fill_symbol(&cache_test_module(module), &mut frame)
+                .await;
+        }
+        for module in ["a.pdb", "b.pdb"] {
+            let _ = session2
+                .fill_symbol(&cache_test_module(module), &mut frame)
+                .await;
+        }
+
+        let stats1 = session1.stats();
+        assert_eq!(stats1.len(), 2);
+        assert!(stats1["a.pdb"].loaded_symbols);
+        assert_eq!(stats1["a.pdb"].lookups, 2);
+        assert_eq!(stats1["a.pdb"].cache_hits, 1);
+        assert!(!stats1["missing.pdb"].loaded_symbols);
+        assert_eq!(stats1["missing.pdb"].cache_hits, 0);
+        assert_eq!(session1.pending_stats().symbols_requested, 2);
+
+        // a.pdb was loaded by the first session.
+        let stats2 = session2.stats();
+        assert_eq!(stats2.len(), 2);
+        assert!(stats2["a.pdb"].loaded_symbols);
+        assert_eq!(stats2["a.pdb"].lookups, 1);
+        assert_eq!(stats2["a.pdb"].cache_hits, 1);
+        assert_eq!(stats2["a.pdb"].load_time, Duration::ZERO);
+        assert_eq!(stats2["b.pdb"].cache_hits, 0);
+        assert_eq!(session2.pending_stats().symbols_requested, 1);
+
+        // The Symbolizer's stats cover both sessions.
+        let stats = symbolizer.stats();
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats["a.pdb"].lookups, 3);
+        assert_eq!(stats["a.pdb"].cache_hits, 2);
+        assert_eq!(symbolizer.pending_stats().symbols_requested, 3);
+    }
+
+    /// A supplier which takes a while to load the symbols of `inner`.
+    struct SlowSymbolSupplier {
+        inner: StringSymbolSupplier,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl SymbolSupplier for SlowSymbolSupplier {
+        async fn locate_symbols(
+            &self,
+            module: &(dyn Module + Sync),
+        ) -> Result<LocateSymbolsResult, SymbolError> {
+            tokio::time::sleep(self.delay).await;
+            self.inner.locate_symbols(module).await
+        }
+
+        async fn locate_file(
+            &self,
+            module: &(dyn Module + Sync),
+            file_kind: FileKind,
+        ) -> Result<PathBuf, FileError> {
+            self.inner.locate_file(module, file_kind).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_symbolizer_session_wait_time() {
+        let delay = Duration::from_millis(50);
+        let modules = HashMap::from([(
+            "a.pdb".to_string(),
+            "MODULE Linux x86 ABCD1234ABCD1234ABCDABCD12345678a a.pdb\n".to_string(),
+        )]);
+        let symbolizer = Arc::new(Symbolizer::new(SlowSymbolSupplier {
+            inner: StringSymbolSupplier::new(modules),
+            delay,
+        }));
+        let session1 = symbolizer.session();
+        let session2 = symbolizer.session();
+        let module = cache_test_module("a.pdb");
+        let (mut frame1, mut frame2) = (
+            SimpleFrame::with_instruction(0x1010),
+            SimpleFrame::with_instruction(0x1010),
+        );
+
+        // The first session loads the symbols, while the second one waits for them.
+        let _ = tokio::join!(
+            session1.fill_symbol(&module, &mut frame1),
+            session2.fill_symbol(&module, &mut frame2),
+        );
+        let (stats1, stats2) = (session1.stats(), session2.stats());
+        assert_eq!(stats1["a.pdb"].cache_hits, 0);
+        assert!(stats1["a.pdb"].load_time >= delay);
+        assert_eq!(stats2["a.pdb"].cache_hits, 1);
+        assert!(stats2["a.pdb"].load_time >= delay / 2);
+
+        // Once loaded, there's nothing to wait for.
+        let session3 = symbolizer.session();
+        let _ = session3.fill_symbol(&module, &mut frame1).await;
+        assert_eq!(session3.stats()["a.pdb"].load_time, Duration::ZERO);
+    }
+
     #[tokio::test]
     async fn test_extra_debug_info() {
         let debug_info = DebugInfoResult {
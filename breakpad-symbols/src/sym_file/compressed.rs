@@ -0,0 +1,190 @@
+// # Compressed symbol files
+//
+// Symbol stores often keep .sym files compressed (`foo.sym.gz` or
+// `foo.sym.zst`) to save space. We recognize compressed input by its magic
+// bytes rather than by its file extension, so misnamed files and files which
+// were already decoded by the HTTP client (because they were served with a
+// `Content-Encoding`) are handled too. A text .sym file always starts with
+// `MODULE`, so it can't be mistaken for compressed data.
+//
+// Decompression is streamed into the parser. Local compressed files stay
+// compressed, but the HTTP supplier caches the decompressed contents of the
+// files it downloads, as it does for plain ones.
+//
+// Decompression needs the `compressed_symbols` feature. Without it,
+// compressed data is still recognized, but fails to load with an error.
+
+#[cfg(all(feature = "http", feature = "compressed_symbols"))]
+use std::io::Write;
+use std::io::{self, BufRead, Read};
+
+/// A compression format symbol files can be stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolCompression {
+    /// gzip, stored with a `.gz` extension.
+    Gzip,
+    /// Zstandard, stored with a `.zst` extension.
+    Zstd,
+}
+
+impl SymbolCompression {
+    /// Every supported compression format, in the order they're looked up.
+    pub const ALL: [SymbolCompression; 2] = [SymbolCompression::Gzip, SymbolCompression::Zstd];
+
+    /// The extension (without the dot) that is appended to the name of a
+    /// file compressed with this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            SymbolCompression::Gzip => "gz",
+            SymbolCompression::Zstd => "zst",
+        }
+    }
+
+    /// Recognize compressed data from its first bytes.
+    pub fn detect(data: &[u8]) -> Option<SymbolCompression> {
+        if data.starts_with(&[0x1f, 0x8b]) {
+            Some(SymbolCompression::Gzip)
+        } else if data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(SymbolCompression::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// Wrap `reader` to decompress its contents.
+    #[cfg(feature = "compressed_symbols")]
+    pub fn decoder<'a>(self, reader: impl BufRead + 'a) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            SymbolCompression::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+            SymbolCompression::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+        })
+    }
+
+    /// Wrap `reader` to decompress its contents.
+    #[cfg(not(feature = "compressed_symbols"))]
+    pub fn decoder<'a>(self, _reader: impl BufRead + 'a) -> io::Result<Box<dyn Read + 'a>> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(not(feature = "compressed_symbols"))]
+fn unsupported() -> io::Error {
+    io::Error::other("compressed symbol files need the compressed_symbols feature")
+}
+
+/// Decompresses data which arrives in chunks (such as an HTTP response body)
+/// if it's compressed, and passes it through otherwise.
+#[cfg(feature = "http")]
+pub(crate) enum ChunkDecoder {
+    /// Not enough data has arrived yet to tell whether it's compressed.
+    Detecting(Vec<u8>),
+    /// The data isn't compressed.
+    Plain,
+    #[cfg(feature = "compressed_symbols")]
+    Gzip(flate2::write::MultiGzDecoder<Vec<u8>>),
+    #[cfg(feature = "compressed_symbols")]
+    Zstd(zstd::stream::write::Decoder<'static, Vec<u8>>),
+}
+
+// The longest magic SymbolCompression::detect looks for.
+#[cfg(feature = "http")]
+const MAX_MAGIC_LEN: usize = 4;
+
+#[cfg(feature = "http")]
+impl ChunkDecoder {
+    pub fn new() -> ChunkDecoder {
+        ChunkDecoder::Detecting(Vec::new())
+    }
+
+    /// Decode the next chunk, appending whatever data is ready to `out`.
+    pub fn decode(&mut self, chunk: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        if let ChunkDecoder::Detecting(pending) = self {
+            pending.extend_from_slice(chunk);
+            if pending.len() < MAX_MAGIC_LEN {
+                return Ok(());
+            }
+            let pending = std::mem::take(pending);
+            self.start(&pending)?;
+            return self.decode(&pending, out);
+        }
+        match self {
+            ChunkDecoder::Detecting(_) => unreachable!(),
+            ChunkDecoder::Plain => out.extend_from_slice(chunk),
+            #[cfg(feature = "compressed_symbols")]
+            ChunkDecoder::Gzip(decoder) => {
+                decoder.write_all(chunk)?;
+                out.append(decoder.get_mut());
+            }
+            #[cfg(feature = "compressed_symbols")]
+            ChunkDecoder::Zstd(decoder) => {
+                decoder.write_all(chunk)?;
+                out.append(decoder.get_mut());
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish decoding after the last chunk, appending the rest of the data
+    /// to `out`. This can be called more than once.
+    pub fn finish(&mut self, out: &mut Vec<u8>) -> io::Result<()> {
+        if let ChunkDecoder::Detecting(pending) = self {
+            let pending = std::mem::take(pending);
+            self.start(&pending)?;
+            self.decode(&pending, out)?;
+        }
+        match self {
+            ChunkDecoder::Detecting(_) | ChunkDecoder::Plain => {}
+            #[cfg(feature = "compressed_symbols")]
+            ChunkDecoder::Gzip(decoder) => {
+                decoder.try_finish()?;
+                out.append(decoder.get_mut());
+            }
+            #[cfg(feature = "compressed_symbols")]
+            ChunkDecoder::Zstd(decoder) => {
+                decoder.flush()?;
+                out.append(decoder.get_mut());
+            }
+        }
+        Ok(())
+    }
+
+    /// Pick the decoder for data starting with `magic`.
+    fn start(&mut self, magic: &[u8]) -> io::Result<()> {
+        *self = match SymbolCompression::detect(magic) {
+            None => ChunkDecoder::Plain,
+            #[cfg(feature = "compressed_symbols")]
+            Some(SymbolCompression::Gzip) => {
+                ChunkDecoder::Gzip(flate2::write::MultiGzDecoder::new(Vec::new()))
+            }
+            #[cfg(feature = "compressed_symbols")]
+            Some(SymbolCompression::Zstd) => {
+                ChunkDecoder::Zstd(zstd::stream::write::Decoder::new(Vec::new())?)
+            }
+            #[cfg(not(feature = "compressed_symbols"))]
+            Some(_) => return Err(unsupported()),
+        };
+        Ok(())
+    }
+}
+
+/// Wrap `reader` to decompress its contents if they're compressed.
+pub(crate) fn maybe_decompress<'a>(
+    mut reader: impl BufRead + 'a,
+) -> io::Result<Box<dyn Read + 'a>> {
+    match SymbolCompression::detect(reader.fill_buf()?) {
+        Some(compression) => compression.decoder(reader),
+        None => Ok(Box::new(reader)),
+    }
+}
+
+/// Decompress `data` into memory if it's compressed.
+pub(crate) fn decompress_to_vec(data: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    match SymbolCompression::detect(data) {
+        Some(compression) => {
+            let mut decompressed = Vec::new();
+            compression.decoder(data)?.read_to_end(&mut decompressed)?;
+            Ok(Some(decompressed))
+        }
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,374 @@
+// # Lazy loading
+//
+// Most minidumps only need a handful of the functions in a module, so fully
+// parsing a .sym file with hundreds of thousands of FUNC and STACK CFI records
+// wastes a lot of time and memory. Instead, a lazily loaded SymbolFile makes a
+// single pass over the file which only records where each FUNC
+// block (the FUNC line and its line/INLINE records) and each STACK CFI block
+// (STACK CFI INIT and its STACK CFI lines) starts and ends, along with the
+// address range it covers. A block is parsed by the regular SymbolParser the
+// first time an address in its range is looked up, and the result is kept.
+//
+// Everything else (MODULE, INFO, FILE, INLINE_ORIGIN, PUBLIC and STACK WIN) is
+// parsed eagerly during the indexing pass. STACK WIN records are small, and
+// the parser fixes up overlapping ones based on their neighbours, so they
+// can't be parsed in isolation.
+//
+// One difference with eager parsing is that a corrupt line in a FUNC or STACK
+// CFI block isn't noticed until that block is parsed, at which point we just
+// pretend the block doesn't exist instead of rejecting the whole file.
+
+use super::parser::into_rangemap_safe;
+use super::*;
+use range_map::{Range, RangeMap};
+use std::collections::hash_map::{Entry, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// The byte range of a FUNC or STACK CFI block in the symbol file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Block {
+    pub(super) start: usize,
+    pub(super) end: usize,
+}
+
+/// A record of a [`SymbolFile`], either stored in it or parsed on demand.
+pub enum RecordRef<'a, T> {
+    Stored(&'a T),
+    Parsed(Arc<T>),
+}
+
+impl<T> Deref for RecordRef<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        match self {
+            RecordRef::Stored(record) => record,
+            RecordRef::Parsed(record) => record,
+        }
+    }
+}
+
+/// Where to find the records covering each address.
+pub(crate) enum Index {
+    /// The blocks of a .sym file, found by scanning it.
+    Text(RangeMap<u64, Block>),
+    /// A table of a preprocessed file, which is searched in place.
+    Preprocessed(preprocessed::Table),
+}
+
+impl Index {
+    fn get(&self, data: &[u8], addr: u64) -> Option<Block> {
+        match self {
+            Index::Text(blocks) => blocks.get(addr).copied(),
+            Index::Preprocessed(table) => table.get(data, addr),
+        }
+    }
+
+    /// The start address of the nearest record which starts before `addr`.
+    fn prev_start(&self, data: &[u8], addr: u64) -> Option<u64> {
+        match self {
+            Index::Text(blocks) => {
+                let blocks = blocks.ranges_values().as_slice();
+                let idx = blocks.partition_point(|(range, _)| range.start < addr);
+                Some(blocks.get(idx.checked_sub(1)?)?.0.start)
+            }
+            Index::Preprocessed(table) => table.prev_start(data, addr),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Index::Text(blocks) => blocks.num_ranges(),
+            Index::Preprocessed(table) => table.len(),
+        }
+    }
+}
+
+/// The FUNC and STACK CFI records of a lazily loaded symbol file.
+pub(crate) struct LazyRecords {
+    /// The contents of the file.
+    ///
+    /// This is read into memory rather than mapped: symbol files can be
+    /// replaced or truncated under us (e.g. by a symbol cache), and touching a
+    /// page of a mapping past the new end of the file kills the process with
+    /// SIGBUS, which no bounds check can prevent.
+    data: Vec<u8>,
+    functions: Index,
+    cfi_stack_info: Index,
+    /// Blocks which were already parsed, by their start offset (`None` if
+    /// they turned out to be corrupt).
+    parsed_functions: Mutex<HashMap<usize, Option<Arc<Function>>>>,
+    parsed_cfi_stack_info: Mutex<HashMap<usize, Option<Arc<StackInfoCfi>>>>,
+    /// The approximate size of the parsed blocks, which grows as they're
+    /// looked up.
+    parsed_size: AtomicUsize,
+}
+
+impl fmt::Debug for LazyRecords {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyRecords")
+            .field("len", &self.data.len())
+            .field("functions", &self.functions.len())
+            .field("cfi_stack_info", &self.cfi_stack_info.len())
+            .finish()
+    }
+}
+
+impl PartialEq for LazyRecords {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl Eq for LazyRecords {}
+
+impl LazyRecords {
+    pub(crate) fn new(data: Vec<u8>, functions: Index, cfi_stack_info: Index) -> LazyRecords {
+        LazyRecords {
+            data,
+            functions,
+            cfi_stack_info,
+            parsed_functions: Mutex::default(),
+            parsed_cfi_stack_info: Mutex::default(),
+            parsed_size: AtomicUsize::new(0),
+        }
+    }
+
+    /// The function covering `addr`, parsing it if needed.
+    pub(crate) fn function(&self, addr: u64) -> Option<Arc<Function>> {
+        let block = self.functions.get(&self.data, addr)?;
+        let parsed = parse_cached(&self.parsed_functions, block, || match self.functions {
+            Index::Text(_) => {
+                let symbols = self.parse_block(block)?;
+                let (_, function) = symbols.functions.ranges_values().next()?;
+                Some(function.clone())
+            }
+            Index::Preprocessed(_) => self.decode_block(block, preprocessed::decode_function),
+        });
+        self.record_parsed(parsed, function_heap_size)
+    }
+
+    /// The CFI covering `addr`, parsing it if needed.
+    pub(crate) fn cfi_stack_info(&self, addr: u64) -> Option<Arc<StackInfoCfi>> {
+        let block = self.cfi_stack_info.get(&self.data, addr)?;
+        let parsed = parse_cached(&self.parsed_cfi_stack_info, block, || {
+            match self.cfi_stack_info {
+                Index::Text(_) => {
+                    let symbols = self.parse_block(block)?;
+                    let (_, info) = symbols.cfi_stack_info.ranges_values().next()?;
+                    Some(info.clone())
+                }
+                Index::Preprocessed(_) => {
+                    self.decode_block(block, preprocessed::decode_cfi_stack_info)
+                }
+            }
+        });
+        self.record_parsed(parsed, cfi_heap_size)
+    }
+
+    /// Count the size of a block if it was just parsed, and return it.
+    fn record_parsed<T>(
+        &self,
+        (parsed, is_new): (Option<Arc<T>>, bool),
+        heap_size: fn(&T) -> usize,
+    ) -> Option<Arc<T>> {
+        if is_new {
+            let size = std::mem::size_of::<(usize, Option<Arc<T>>)>()
+                + parsed
+                    .as_deref()
+                    .map_or(0, |parsed| std::mem::size_of::<T>() + heap_size(parsed));
+            self.parsed_size.fetch_add(size, Ordering::Relaxed);
+        }
+        parsed
+    }
+
+    /// The approximate size of the blocks which were parsed so far.
+    pub(crate) fn parsed_size(&self) -> usize {
+        self.parsed_size.load(Ordering::Relaxed)
+    }
+
+    /// The start address of the nearest function which starts before `addr`.
+    pub(crate) fn prev_function_address(&self, addr: u64) -> Option<u64> {
+        self.functions.prev_start(&self.data, addr)
+    }
+
+    /// The number of blocks which were parsed so far.
+    #[cfg(test)]
+    pub(crate) fn num_parsed(&self) -> usize {
+        self.parsed_functions.lock().unwrap().len()
+            + self.parsed_cfi_stack_info.lock().unwrap().len()
+    }
+
+    /// The approximate heap size of the index, see [`SymbolFile::approximate_size`].
+    ///
+    /// This includes the blocks which were parsed so far, so it grows as
+    /// records are looked up.
+    pub(crate) fn approximate_size(&self) -> usize {
+        let index = |index: &Index| match index {
+            Index::Text(blocks) => blocks.num_ranges() * std::mem::size_of::<(Range<u64>, Block)>(),
+            // Preprocessed tables are part of the data.
+            Index::Preprocessed(_) => 0,
+        };
+        self.data.len() + index(&self.functions) + index(&self.cfi_stack_info) + self.parsed_size()
+    }
+
+    fn parse_block(&self, block: Block) -> Option<SymbolFile> {
+        let input = &self.data[block.start..block.end];
+        let mut parser = SymbolParser::new();
+        match parser.parse_more(input) {
+            Ok(consumed) if consumed == input.len() => Some(parser.finish()),
+            _ => {
+                warn!("discarding corrupt symbol record at offset {}", block.start);
+                None
+            }
+        }
+    }
+
+    fn decode_block<T>(&self, block: Block, decode: fn(&[u8]) -> Option<T>) -> Option<T> {
+        let decoded = decode(&self.data[block.start..block.end]);
+        if decoded.is_none() {
+            warn!("discarding corrupt symbol record at offset {}", block.start);
+        }
+        decoded
+    }
+}
+
+/// Get the parsed version of `block` from `cache`, parsing it if needed.
+///
+/// Also returns whether the block was just added to the cache.
+fn parse_cached<T>(
+    cache: &Mutex<HashMap<usize, Option<Arc<T>>>>,
+    block: Block,
+    parse: impl FnOnce() -> Option<T>,
+) -> (Option<Arc<T>>, bool) {
+    if let Some(parsed) = cache.lock().unwrap().get(&block.start) {
+        return (parsed.clone(), false);
+    }
+    // Parse without holding the lock, at worst two threads parse the same block.
+    let parsed = parse().map(Arc::new);
+    match cache.lock().unwrap().entry(block.start) {
+        Entry::Occupied(entry) => (entry.get().clone(), false),
+        Entry::Vacant(entry) => (entry.insert(parsed).clone(), true),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BlockKind {
+    Function,
+    Cfi,
+}
+
+/// Parse the address range of a FUNC or STACK CFI INIT line, given the part
+/// after the keyword (`[m] address size ...`).
+///
+/// Returns `Some(None)` for records which cover no memory, which are ignored.
+fn block_range(fields: &[u8], kind: BlockKind) -> Option<Option<Range<u64>>> {
+    let mut fields = fields
+        .split(|&byte| byte == b' ')
+        .filter(|field| !field.is_empty());
+    let mut address = fields.next()?;
+    if kind == BlockKind::Function && address == b"m" {
+        address = fields.next()?;
+    }
+    let hex = |field: &[u8]| u64::from_str_radix(std::str::from_utf8(field).ok()?, 16).ok();
+    let address = hex(address)?;
+    let size = u32::try_from(hex(fields.next()?)?).ok()?;
+    if size == 0 {
+        return Some(None);
+    }
+    Some(
+        address
+            .checked_add(size as u64)
+            .map(|end| Range::new(address, end - 1)),
+    )
+}
+
+/// Whether `line` can be part of the current block.
+fn is_subline(line: &[u8], kind: BlockKind) -> bool {
+    match kind {
+        BlockKind::Function => {
+            line.starts_with(b"INLINE ")
+                || line.starts_with(b"INLINE_ORIGIN ")
+                // Line records start with a hex address, but so do FILE and
+                // FUNC if you only look at the first character.
+                || line
+                    .iter()
+                    .position(|&byte| byte == b' ')
+                    .is_some_and(|idx| idx > 0 && line[..idx].iter().all(u8::is_ascii_hexdigit))
+        }
+        BlockKind::Cfi => line.starts_with(b"STACK CFI ") && !line.starts_with(b"STACK CFI INIT "),
+    }
+}
+
+type Blocks = Vec<(Range<u64>, Block)>;
+
+/// Record the block `cur` (if any), which ends at `end`.
+fn finish_block(
+    cur: Option<(BlockKind, Option<Range<u64>>, usize)>,
+    end: usize,
+    functions: &mut Blocks,
+    cfi_stack_info: &mut Blocks,
+) {
+    if let Some((kind, Some(range), start)) = cur {
+        let blocks = match kind {
+            BlockKind::Function => functions,
+            BlockKind::Cfi => cfi_stack_info,
+        };
+        blocks.push((range, Block { start, end }));
+    }
+}
+
+/// Index the FUNC and STACK CFI records of `data`, and parse everything else.
+pub(crate) fn index(mut data: Vec<u8>) -> Result<SymbolFile, SymbolError> {
+    // The parser only consumes complete lines.
+    if data.last().is_some_and(|&byte| byte != b'\n') {
+        data.push(b'\n');
+    }
+
+    let mut parser = SymbolParser::new();
+    let mut functions = Vec::new();
+    let mut cfi_stack_info = Vec::new();
+    // The block we're in: its kind, address range and start offset.
+    let mut cur: Option<(BlockKind, Option<Range<u64>>, usize)> = None;
+    let mut offset = 0;
+    let mut line_number = 0;
+    while offset < data.len() {
+        let end = data[offset..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(data.len(), |idx| offset + idx + 1);
+        let line = &data[offset..end];
+
+        let is_subline = cur.is_some_and(|(kind, ..)| is_subline(line, kind));
+        if !is_subline {
+            finish_block(cur.take(), offset, &mut functions, &mut cfi_stack_info);
+        }
+
+        let corrupt = SymbolError::ParseError("failed to parse file", line_number);
+        if let Some(fields) = line.strip_prefix(b"FUNC ") {
+            let range = block_range(fields, BlockKind::Function).ok_or(corrupt)?;
+            cur = Some((BlockKind::Function, range, offset));
+        } else if let Some(fields) = line.strip_prefix(b"STACK CFI INIT ") {
+            let range = block_range(fields, BlockKind::Cfi).ok_or(corrupt)?;
+            cur = Some((BlockKind::Cfi, range, offset));
+        } else if !is_subline || line.starts_with(b"INLINE_ORIGIN ") {
+            // INLINE_ORIGIN records can appear in FUNC blocks, but they're
+            // needed to symbolize any function.
+            parser.parse_more(line)?;
+        }
+        offset = end;
+        line_number += 1;
+    }
+    finish_block(cur, offset, &mut functions, &mut cfi_stack_info);
+
+    let mut symbols = parser.finish();
+    symbols.lazy = Some(LazyRecords::new(
+        data,
+        Index::Text(into_rangemap_safe(functions)),
+        Index::Text(into_rangemap_safe(cfi_stack_info)),
+    ));
+    Ok(symbols)
+}
@@ -3,16 +3,23 @@
 use crate::{FrameSymbolizer, FrameWalker, Module, SymbolError};
 
 pub use crate::sym_file::types::*;
+pub use compressed::SymbolCompression;
+pub use lazy::RecordRef;
 pub use parser::SymbolParser;
+pub use preprocessed::{preprocessed_path, PREPROCESSED_EXTENSION, PREPROCESSED_FORMAT_VERSION};
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufReader, Read};
 use std::ops::Deref;
 use std::path::Path;
 use tracing::trace;
 
+mod compressed;
+mod lazy;
 mod parser;
+mod preprocessed;
 mod types;
 pub mod walker;
+mod writer;
 
 // # Sync VS Async
 //
@@ -191,12 +198,16 @@ impl SymbolFile {
     }
 
     /// `parse` but async
+    ///
+    /// The response may be a compressed symbol file, which is decompressed
+    /// while it's parsed. `callback` is given the decompressed data.
     #[cfg(feature = "http")]
     pub async fn parse_async(
         mut response: reqwest::Response,
         mut callback: impl FnMut(&[u8]),
     ) -> Result<SymbolFile, SymbolError> {
-        let mut chunk;
+        let mut decoder = compressed::ChunkDecoder::new();
+        let mut chunk = Vec::new();
         let mut slice = &[][..];
         let mut input_reader = &mut slice;
         let mut buf = circular::Buffer::with_capacity(INITIAL_BUFFER_CAPACITY);
@@ -238,16 +249,22 @@ impl SymbolFile {
 
             // Little rube-goldberg machine to stream the contents:
             // * get a chunk (Bytes) from the Response
-            // * get its underlying slice
+            // * decompress it if the response is compressed
+            // * get the slice of the (decompressed) chunk
             // * then get a mutable reference to that slice
             // * then Read that mutable reference in our circular buffer
             // * when the slice runs out, get the next chunk and repeat
             if input_reader.is_empty() {
-                chunk = response
-                    .chunk()
-                    .await
-                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
-                    .unwrap_or_default();
+                chunk.clear();
+                while chunk.is_empty() {
+                    match response.chunk().await.map_err(std::io::Error::other)? {
+                        Some(compressed) => decoder.decode(&compressed, &mut chunk)?,
+                        None => {
+                            decoder.finish(&mut chunk)?;
+                            break;
+                        }
+                    }
+                }
                 slice = &chunk[..];
                 input_reader = &mut slice;
             }
@@ -322,15 +339,91 @@ impl SymbolFile {
         }
     }
 
-    // Parse a SymbolFile from bytes.
+    // Parse a SymbolFile from bytes, which may be compressed.
     pub fn from_bytes(bytes: &[u8]) -> Result<SymbolFile, SymbolError> {
-        Self::parse(bytes, |_| ())
+        Self::parse(compressed::maybe_decompress(bytes)?, |_| ())
     }
 
-    // Parse a SymbolFile from a file.
+    // Parse a SymbolFile from a file, which may be compressed.
     pub fn from_file(path: &Path) -> Result<SymbolFile, SymbolError> {
-        let file = File::open(path)?;
-        Self::parse(file, |_| ())
+        Self::from_reader(File::open(path)?)
+    }
+
+    // Parse a SymbolFile from a reader, which may be compressed.
+    pub(crate) fn from_reader(reader: impl Read) -> Result<SymbolFile, SymbolError> {
+        Self::parse(
+            compressed::maybe_decompress(BufReader::new(reader))?,
+            |_| (),
+        )
+    }
+
+    /// Lazily load a SymbolFile from a file.
+    ///
+    /// Only the positions of the FUNC and STACK CFI records are indexed, and
+    /// they are parsed the first time they're looked up. This is much faster
+    /// and uses much less memory when only a few functions are needed, but
+    /// corrupt records are only noticed (and ignored) when they're parsed.
+    /// The `functions` and `cfi_stack_info` maps of the result are empty, use
+    /// [`SymbolFile::function_at`] and [`SymbolFile::cfi_stack_info_at`]
+    /// instead.
+    ///
+    /// The whole file is read into memory (and decompressed if needed), and
+    /// kept for the lifetime of the SymbolFile.
+    pub fn from_file_lazy(path: &Path) -> Result<SymbolFile, SymbolError> {
+        let bytes = std::fs::read(path)?;
+        let bytes = compressed::decompress_to_vec(&bytes)?.unwrap_or(bytes);
+        lazy::index(bytes)
+    }
+
+    /// Lazily load a SymbolFile from bytes, see [`SymbolFile::from_file_lazy`].
+    pub fn from_bytes_lazy(bytes: Vec<u8>) -> Result<SymbolFile, SymbolError> {
+        let bytes = compressed::decompress_to_vec(&bytes)?.unwrap_or(bytes);
+        lazy::index(bytes)
+    }
+
+    /// Whether this SymbolFile was loaded lazily.
+    pub fn is_lazy(&self) -> bool {
+        self.lazy.is_some()
+    }
+
+    /// The part of [`SymbolFile::approximate_size`] taken by the records of a
+    /// lazily loaded SymbolFile which were parsed so far.
+    pub(crate) fn parsed_size(&self) -> usize {
+        self.lazy.as_ref().map_or(0, |lazy| lazy.parsed_size())
+    }
+
+    /// The function covering `addr`, which is parsed if it wasn't yet.
+    pub fn function_at(&self, addr: u64) -> Option<RecordRef<'_, Function>> {
+        match &self.lazy {
+            Some(lazy) => lazy.function(addr).map(RecordRef::Parsed),
+            None => self.functions.get(addr).map(RecordRef::Stored),
+        }
+    }
+
+    /// The DWARF CFI covering `addr`, which is parsed if it wasn't yet.
+    pub fn cfi_stack_info_at(&self, addr: u64) -> Option<RecordRef<'_, StackInfoCfi>> {
+        match &self.lazy {
+            Some(lazy) => lazy.cfi_stack_info(addr).map(RecordRef::Parsed),
+            None => self.cfi_stack_info.get(addr).map(RecordRef::Stored),
+        }
+    }
+
+    /// The start address of the nearest function which starts before `addr`.
+    fn prev_function_address(&self, addr: u64) -> Option<u64> {
+        fn prev<V>(
+            ranges: &[(range_map::Range<u64>, V)],
+            addr: u64,
+        ) -> Option<&(range_map::Range<u64>, V)> {
+            ranges
+                .binary_search_by_key(&addr, |(range, _)| range.start)
+                .err()
+                .and_then(|idx| idx.checked_sub(1))
+                .and_then(|idx| ranges.get(idx))
+        }
+        match &self.lazy {
+            Some(lazy) => lazy.prev_function_address(addr),
+            None => prev(self.functions.ranges_values().as_slice(), addr).map(|(_, f)| f.address),
+        }
     }
 
     /// Fill in as much source information for `frame` as possible.
@@ -340,7 +433,7 @@ impl SymbolFile {
             return;
         }
         let addr = frame.get_instruction() - module.base_address();
-        if let Some(func) = self.functions.get(addr) {
+        if let Some(func) = self.function_at(addr) {
             // TODO: although FUNC records have a parameter size, it appears that
             // they aren't to be trusted? The STACK WIN records are more reliable
             // when available. This is important precisely because these values
@@ -464,15 +557,8 @@ impl SymbolFile {
             // * if the address we were looking for came *after* every FUNC, binary search
             //   would yield "slice.len()", and the nearest FUNC is indeed at `len-1`, so
             //   again correct.
-            let funcs_slice = self.functions.ranges_values().as_slice();
-            let prev_func = funcs_slice
-                .binary_search_by_key(&addr, |(range, _)| range.start)
-                .err()
-                .and_then(|idx| idx.checked_sub(1))
-                .and_then(|idx| funcs_slice.get(idx));
-
-            if let Some(prev_func) = prev_func {
-                if public.address <= prev_func.1.address {
+            if let Some(prev_func_address) = self.prev_function_address(addr) {
+                if public.address <= prev_func_address {
                     // This PUBLIC is truncated by a FUNC before it gets to `addr`,
                     // so we shouldn't use it.
                     return;
@@ -506,7 +592,7 @@ impl SymbolFile {
 
         // If STACK WIN failed, try STACK CFI
         win_stack_result.or_else(|| {
-            if let Some(info) = self.cfi_stack_info.get(addr) {
+            if let Some(info) = self.cfi_stack_info_at(addr) {
                 // Don't use add_rules that come after this address
                 let mut count = 0;
                 let len = info.add_rules.len();
@@ -525,6 +611,80 @@ impl SymbolFile {
     pub fn find_nearest_public(&self, addr: u64) -> Option<&PublicSymbol> {
         self.publics.iter().rev().find(|&p| p.address <= addr)
     }
+
+    /// A rough estimate of the memory used by this symbol file, in bytes.
+    ///
+    /// This counts the entries and the strings they own, but not allocator overhead
+    /// or unused capacity. The size of lazily loaded symbol files grows as their
+    /// records are parsed.
+    pub fn approximate_size(&self) -> usize {
+        use std::mem::size_of;
+
+        let strings = |map: &std::collections::HashMap<u32, String>| {
+            map.values()
+                .map(|s| size_of::<(u32, String)>() + s.len())
+                .sum::<usize>()
+        };
+        let functions = self
+            .functions
+            .ranges_values()
+            .map(|(_, f)| size_of::<(range_map::Range<u64>, Function)>() + function_heap_size(f))
+            .sum::<usize>();
+        let publics = self
+            .publics
+            .iter()
+            .map(|p| size_of::<PublicSymbol>() + p.name.len())
+            .sum::<usize>();
+        let cfi = self
+            .cfi_stack_info
+            .ranges_values()
+            .map(|(_, info)| {
+                size_of::<(range_map::Range<u64>, StackInfoCfi)>() + cfi_heap_size(info)
+            })
+            .sum::<usize>();
+        let win = |map: &range_map::RangeMap<u64, StackInfoWin>| {
+            map.ranges_values()
+                .map(|(_, info)| {
+                    size_of::<(range_map::Range<u64>, StackInfoWin)>()
+                        + match &info.program_string_or_base_pointer {
+                            WinStackThing::ProgramString(s) => s.len(),
+                            WinStackThing::AllocatesBasePointer(_) => 0,
+                        }
+                })
+                .sum::<usize>()
+        };
+
+        size_of::<SymbolFile>()
+            + self.os.len()
+            + self.arch.len()
+            + self.module_id.len()
+            + self.debug_file.len()
+            + strings(&self.files)
+            + strings(&self.inline_origins)
+            + functions
+            + publics
+            + cfi
+            + win(&self.win_stack_framedata_info)
+            + win(&self.win_stack_fpo_info)
+            + self.lazy.as_ref().map_or(0, |lazy| lazy.approximate_size())
+    }
+}
+
+/// The approximate size of the name, lines and inlinees of a function.
+fn function_heap_size(function: &Function) -> usize {
+    function.name.len()
+        + function.lines.num_ranges() * std::mem::size_of::<(range_map::Range<u64>, SourceLine)>()
+        + function.inlinees.len() * std::mem::size_of::<Inlinee>()
+}
+
+/// The approximate size of the rules of a DWARF CFI record.
+fn cfi_heap_size(info: &StackInfoCfi) -> usize {
+    info.init.rules.len()
+        + info
+            .add_rules
+            .iter()
+            .map(|rules| std::mem::size_of::<CfiRules>() + rules.rules.len())
+            .sum::<usize>()
 }
 
 #[cfg(test)]
@@ -582,6 +742,151 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_symbolfile_from_file_lazy() {
+        let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("testdata/symbols/test_app.pdb/5A9832E5287241C1838ED98914E9B7FF1/test_app.sym");
+        let eager = SymbolFile::from_file(&path).unwrap();
+        let sym = SymbolFile::from_file_lazy(&path).unwrap();
+        assert!(sym.is_lazy());
+        assert_eq!(sym.functions.ranges_values().count(), 0);
+        assert_eq!(sym.files, eager.files);
+        assert_eq!(sym.publics, eager.publics);
+        assert_eq!(sym.win_stack_framedata_info, eager.win_stack_framedata_info);
+        assert_eq!(sym.win_stack_fpo_info, eager.win_stack_fpo_info);
+
+        let lazy = sym.lazy.as_ref().unwrap();
+        assert_eq!(lazy.num_parsed(), 0);
+        let size = sym.approximate_size();
+        assert_eq!(sym.function_at(0x1012).unwrap().name, "vswprintf");
+        assert!(sym.function_at(0x1013).is_none());
+        assert_eq!(lazy.num_parsed(), 1);
+        // Parsed records count towards the size, but only once.
+        let parsed_size = sym.approximate_size();
+        assert!(parsed_size > size);
+        assert_eq!(sym.parsed_size(), parsed_size - size);
+        sym.function_at(0x1000).unwrap();
+        assert_eq!(sym.approximate_size(), parsed_size);
+        for (range, func) in eager.functions.ranges_values() {
+            assert_eq!(*sym.function_at(range.start).unwrap(), *func);
+        }
+        for (range, info) in eager.cfi_stack_info.ranges_values() {
+            assert_eq!(*sym.cfi_stack_info_at(range.start).unwrap(), *info);
+        }
+    }
+
+    #[test]
+    fn test_symbolfile_from_bytes_lazy() {
+        let sym = SymbolFile::from_bytes_lazy(
+            b"MODULE Linux x86 ffff0000 bar
+FILE 53 bar.c
+FUNC 1000 30 10 another func
+1000 30 7 53
+INLINE_ORIGIN 0 inlined func
+FUNC 2000 30 10 corrupt func
+2000 30 x 53
+PUBLIC 1234 10 some public
+STACK CFI INIT 1000 30 .cfa: $esp 4 + .ra: .cfa 4 - ^
+STACK CFI 1010 .cfa: $esp 8 +"
+                .to_vec(),
+        )
+        .unwrap();
+
+        assert_eq!(sym.files.len(), 1);
+        assert_eq!(sym.publics.len(), 1);
+        assert_eq!(sym.inline_origins.len(), 1);
+        let func = sym.function_at(0x1001).unwrap();
+        assert_eq!(func.name, "another func");
+        assert_eq!(func.lines.ranges_values().count(), 1);
+        // Corrupt blocks are only discarded when they're needed.
+        assert!(sym.function_at(0x2000).is_none());
+        let cfi = sym.cfi_stack_info_at(0x1020).unwrap();
+        assert_eq!(cfi.add_rules.len(), 1);
+        assert!(sym.cfi_stack_info_at(0x1030).is_none());
+    }
+
+    #[test]
+    fn test_symbolfile_from_file_lazy_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bar.sym");
+        std::fs::write(
+            &path,
+            "MODULE Linux x86 ffff0000 bar\nFUNC 1000 30 10 another func\n",
+        )
+        .unwrap();
+        let sym = SymbolFile::from_file_lazy(&path).unwrap();
+
+        // The file isn't used after it's loaded, so it can change under us.
+        File::create(&path).unwrap();
+        assert_eq!(sym.function_at(0x1000).unwrap().name, "another func");
+    }
+
+    #[test]
+    #[cfg(feature = "compressed_symbols")]
+    fn test_symbolfile_from_compressed_bytes() {
+        use std::io::Write;
+
+        let contents = b"MODULE Linux x86 ffff0000 bar
+FILE 53 bar.c
+PUBLIC 1234 10 some public
+FUNC 1000 30 10 another func
+1000 30 7 53
+";
+        let expected = SymbolFile::from_bytes(contents).unwrap();
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(contents).unwrap();
+        let gzip = encoder.finish().unwrap();
+        assert_eq!(
+            SymbolCompression::detect(&gzip),
+            Some(SymbolCompression::Gzip)
+        );
+        assert_eq!(SymbolFile::from_bytes(&gzip).unwrap(), expected);
+
+        // Zstandard files can be made of several frames.
+        let mut zstd = zstd::encode_all(&contents[..40], 0).unwrap();
+        zstd.extend(zstd::encode_all(&contents[40..], 0).unwrap());
+        assert_eq!(
+            SymbolCompression::detect(&zstd),
+            Some(SymbolCompression::Zstd)
+        );
+        assert_eq!(SymbolFile::from_bytes(&zstd).unwrap(), expected);
+
+        let lazy = SymbolFile::from_bytes_lazy(zstd).unwrap();
+        assert_eq!(
+            *lazy.function_at(0x1000).unwrap(),
+            expected.functions.get(0x1000).unwrap().clone()
+        );
+
+        assert_eq!(SymbolCompression::detect(contents), None);
+        assert!(SymbolFile::from_bytes(&gzip[..gzip.len() / 2]).is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "http", feature = "compressed_symbols"))]
+    fn test_chunk_decoder() {
+        use std::io::Write;
+        let contents = b"MODULE Linux x86 ffff0000 bar
+FUNC 1000 30 10 another func
+";
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(contents).unwrap();
+        let gzip = encoder.finish().unwrap();
+        let zstd = zstd::encode_all(&contents[..], 0).unwrap();
+
+        // Feed the data a byte at a time, so the magic is split across chunks.
+        for data in &[&contents[..], &gzip[..], &zstd[..]] {
+            let mut decoder = compressed::ChunkDecoder::new();
+            let mut decoded = Vec::new();
+            for chunk in data.chunks(1) {
+                decoder.decode(chunk, &mut decoded).unwrap();
+            }
+            decoder.finish(&mut decoded).unwrap();
+            decoder.finish(&mut decoded).unwrap();
+            assert_eq!(decoded, contents);
+        }
+    }
+
     fn test_symbolfile_from_bytes(symbolfile_bytes: &[u8]) {
         let sym = SymbolFile::from_bytes(symbolfile_bytes).unwrap();
 
@@ -414,6 +414,8 @@ fn line(input: &[u8]) -> IResult<&[u8], Line> {
 /// whole input is consumed. Then call [`finish`][].
 #[derive(Debug, Default)]
 pub struct SymbolParser {
+    os: String,
+    arch: String,
     module_id: String,
     debug_file: String,
     files: HashMap<u32, String>,
@@ -534,14 +536,16 @@ impl SymbolParser {
             // Now store the item in our partial SymbolFile (or make it the cur_item
             // if it has potential sublines we need to parse first).
             match line {
-                Line::Module(_platform, _arch, module_id, debug_file) => {
-                    // We don't use this but it MUST be the first line
+                Line::Module(os, arch, module_id, debug_file) => {
+                    // This MUST be the first line
                     if self.lines != 0 {
                         return Err(SymbolError::ParseError(
                             "MODULE line found after the start of the file",
                             self.lines,
                         ));
                     }
+                    self.os = os;
+                    self.arch = arch;
                     self.module_id = module_id;
                     self.debug_file = debug_file;
                 }
@@ -704,6 +708,8 @@ impl SymbolParser {
         self.publics.sort();
 
         SymbolFile {
+            os: self.os,
+            arch: self.arch,
             module_id: self.module_id,
             debug_file: self.debug_file,
             files: self.files,
@@ -719,12 +725,15 @@ impl SymbolParser {
             ambiguities_discarded: 0,
             corruptions_discarded: 0,
             cfi_eval_corruptions: 0,
+            lazy: None,
         }
     }
 }
 
 // Copied from minidump-common, because we've preconstructed the array to sort.
-fn into_rangemap_safe<V: Clone + Eq + Debug>(mut input: Vec<(Range<u64>, V)>) -> RangeMap<u64, V> {
+pub(super) fn into_rangemap_safe<V: Clone + Eq + Debug>(
+    mut input: Vec<(Range<u64>, V)>,
+) -> RangeMap<u64, V> {
     input.sort_by_key(|x| x.0);
     let mut vec: Vec<(Range<u64>, V)> = Vec::with_capacity(input.len());
     for (range, val) in input {
@@ -748,6 +757,20 @@ fn parse_symbol_bytes(data: &[u8]) -> Result<SymbolFile, SymbolError> {
     SymbolFile::parse(data, |_| ())
 }
 
+/// Check that writing `symbols` out and parsing the result gives the same
+/// symbol file.
+#[cfg(test)]
+fn assert_round_trip(symbols: &SymbolFile) {
+    let mut written = Vec::new();
+    symbols.write_breakpad(&mut written).unwrap();
+    assert_eq!(
+        SymbolFile::parse(&written[..], |_| ()).unwrap(),
+        *symbols,
+        "round trip through:\n{}",
+        String::from_utf8_lossy(&written)
+    );
+}
+
 #[test]
 fn test_module_line() {
     let line = b"MODULE Linux x86 D3096ED481217FD4C16B29CD9BC208BA0 firefox-bin\n";
@@ -965,6 +988,7 @@ INLINE 0 23 9 16 1020 10
 1020 10 62 15
 ";
     let file = SymbolFile::from_bytes(data).expect("failed to parse!");
+    assert_round_trip(&file);
     let (_, f) = file.functions.ranges_values().next().unwrap();
     assert_eq!(f.address, 0x1000);
     assert_eq!(f.size, 0x30);
@@ -1041,6 +1065,7 @@ INLINE 1 17 4 1 1010 10
 1020 10 62 15
 ";
     let file = SymbolFile::from_bytes(data).expect("failed to parse!");
+    assert_round_trip(&file);
     let (_, f) = file.functions.ranges_values().next().unwrap();
     assert_eq!(f.address, 0x1000);
     assert_eq!(f.size, 0x30);
@@ -1137,6 +1162,7 @@ fn test_func_with_m() {
 1020 10 62 15
 ";
     let file = SymbolFile::from_bytes(data).expect("failed to parse!");
+    assert_round_trip(&file);
     let (_, _f) = file.functions.ranges_values().next().unwrap();
 }
 
@@ -1235,6 +1261,7 @@ STACK CFI deadbeef more rules
 
 ";
     let file = SymbolFile::from_bytes(data).expect("failed to parse!");
+    assert_round_trip(&file);
     let (_, cfi) = file.cfi_stack_info.ranges_values().next().unwrap();
     assert_eq!(
         cfi,
@@ -1281,6 +1308,9 @@ STACK CFI INIT f00f f0 more init rules
 
 "[..];
     let sym = parse_symbol_bytes(bytes).unwrap();
+    assert_round_trip(&sym);
+    assert_eq!(sym.os, "Linux");
+    assert_eq!(sym.arch, "x86");
     assert_eq!(sym.files.len(), 2);
     assert_eq!(sym.files.get(&0).unwrap(), "foo.c");
     assert_eq!(sym.files.get(&100).unwrap(), "bar.c");
@@ -1459,6 +1489,7 @@ FUNC 1001 30 10 some func overlap end
 FUNC 1001 10 10 some func overlap contained
 ";
     let sym = parse_symbol_bytes(&bytes[..]).unwrap();
+    assert_round_trip(&sym);
     assert_eq!(sym.publics.len(), 2);
     {
         let p = &sym.publics[0];
@@ -1598,6 +1629,7 @@ STACK WIN 4 7d93e 4 4 0 0 10 0 0 0 prog string
 STACK WIN 4 8d93e 4 4 0 0 10 0 0 1 prog string
 ";
     let sym = parse_symbol_bytes(&bytes[..]).unwrap();
+    assert_round_trip(&sym);
 
     assert_eq!(sym.win_stack_framedata_info.ranges_values().count(), 1);
     let ws = sym
@@ -1647,6 +1679,7 @@ STACK WIN 4 8d93e 4 4 0 0 10 0 0 1 prog string
 fn address_size_overflow() {
     let bytes = b"FUNC 1 2 3 x\nffffffffffffffff 2 0 0\n";
     let sym = parse_symbol_bytes(bytes.as_slice()).unwrap();
+    assert_round_trip(&sym);
     let fun = sym.functions.get(1).unwrap();
     assert!(fun.lines.is_empty());
     assert!(fun.name == "x");
@@ -0,0 +1,876 @@
+// A compact binary representation of a parsed SymbolFile.
+//
+// Parsing a large .sym file is expensive (mostly number parsing and line
+// splitting), so after the first parse we can save the SymbolFile in this
+// format next to the .sym file. Loading it later doesn't involve any text
+// processing, and like lazy loading (see lazy.rs) it only decodes the small
+// records up front: FUNC and STACK CFI records are found through offset
+// tables which are binary searched in place, and each record is decoded the
+// first time an address in its range is looked up.
+//
+// The layout doesn't need any fixups after loading, so the file could be
+// mapped into memory as is. We read it instead, for the same reason as lazily
+// loaded .sym files (see LazyRecords).
+//
+// # Format
+//
+// Everything is little-endian. The file starts with a header:
+//
+// * 8 bytes of magic (`BPSYMBIN`)
+// * u32 format version (PREPROCESSED_FORMAT_VERSION)
+// * u32 reserved (0)
+// * u64 size of the .sym file this was made from
+// * u64 modification time of that .sym file, in nanoseconds since the epoch
+//
+// Then come the eagerly loaded parts of the SymbolFile: os, arch, module_id,
+// debug_file, files, publics, inline_origins, win_stack_framedata_info,
+// win_stack_fpo_info, url and the counters. Strings are a u32 length followed
+// by UTF-8 bytes, collections are a u32 count followed by their elements, and
+// RangeMaps store each range's inclusive start and end before the value.
+//
+// Then come the tables of functions and of cfi_stack_info. Each is a u32 count
+// followed by fixed-size entries sorted by address: the inclusive start and
+// end of the record's address range, and the u64 offset (from the start of
+// the file) and u64 length of the encoded record. The encoded records follow,
+// and the file ends with the magic again, so truncated files are detected.
+//
+// A preprocessed file is only used if its header matches the current version
+// and the .sym file it was made from, and its tables are sorted and point
+// inside the file; anything else is ignored and the .sym file is parsed (and
+// preprocessed) again. A corrupt record is only noticed when it's decoded, at
+// which point it's treated as missing, like in lazily loaded .sym files. This
+// is purely a cache: bump the version whenever the format or the contents of
+// SymbolFile change.
+
+use super::lazy::{Block, Index, LazyRecords};
+use super::*;
+use range_map::{Range, RangeMap};
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::fmt::Debug;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+const MAGIC: &[u8; 8] = b"BPSYMBIN";
+
+/// The version of the preprocessed symbol file format.
+///
+/// Preprocessed files with another version are ignored.
+pub const PREPROCESSED_FORMAT_VERSION: u32 = 3;
+
+/// The extension appended to the path of a .sym file to get the path of its
+/// preprocessed version (`foo.sym` -> `foo.sym.bin`).
+pub const PREPROCESSED_EXTENSION: &str = "bin";
+
+/// The size of an entry in the tables of functions and cfi_stack_info: the
+/// start and end of its address range, and the offset and length of its record.
+const TABLE_ENTRY_SIZE: usize = 8 + 8 + 8 + 8;
+
+const INVALID: SymbolError = SymbolError::ParseError("invalid preprocessed symbol file", 0);
+
+/// The path of the preprocessed version of the .sym file at `sym_path`.
+pub fn preprocessed_path(sym_path: &Path) -> PathBuf {
+    let mut path = sym_path.as_os_str().to_owned();
+    path.push(".");
+    path.push(PREPROCESSED_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Identifies the version of a .sym file a preprocessed file was made from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SourceStamp {
+    len: u64,
+    mtime: u64,
+}
+
+impl SourceStamp {
+    pub(crate) fn new(sym_path: &Path) -> io::Result<SourceStamp> {
+        let metadata = fs::metadata(sym_path)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Ok(SourceStamp {
+            len: metadata.len(),
+            mtime,
+        })
+    }
+}
+
+impl SymbolFile {
+    /// Load the preprocessed version of the .sym file at `sym_path`.
+    ///
+    /// This fails if there's no preprocessed file, if it's invalid or has
+    /// another format version, or if the .sym file changed since it was made.
+    ///
+    /// Like with [`SymbolFile::from_file_lazy`], the functions and DWARF CFI
+    /// are only decoded when they're looked up, so the `functions` and
+    /// `cfi_stack_info` maps of the result are empty. Use
+    /// [`SymbolFile::function_at`] and [`SymbolFile::cfi_stack_info_at`]
+    /// instead.
+    pub fn from_preprocessed(sym_path: &Path) -> Result<SymbolFile, SymbolError> {
+        let stamp = SourceStamp::new(sym_path)?;
+        let bytes = fs::read(preprocessed_path(sym_path))?;
+        Self::from_preprocessed_bytes(bytes, stamp)
+    }
+
+    /// Save the preprocessed version of the .sym file at `sym_path`, which
+    /// `self` was parsed from.
+    ///
+    /// The file is written to a temporary path and then renamed, so concurrent
+    /// readers never see a partial file.
+    ///
+    /// Lazily loaded symbol files (including preprocessed ones) can't be
+    /// preprocessed, as most of their records were never parsed.
+    pub fn save_preprocessed(&self, sym_path: &Path) -> io::Result<()> {
+        if self.lazy.is_some() {
+            return Err(io::Error::other(
+                "can't preprocess a lazily loaded symbol file",
+            ));
+        }
+        let stamp = SourceStamp::new(sym_path)?;
+        let final_path = preprocessed_path(sym_path);
+        let mut tmp_path = final_path.as_os_str().to_owned();
+        tmp_path.push(format!(".{}.tmp", std::process::id()));
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let result = File::create(&tmp_path)
+            .and_then(|mut file| file.write_all(&self.to_preprocessed_bytes(stamp)))
+            .and_then(|_| fs::rename(&tmp_path, &final_path));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        result
+    }
+
+    /// Load the .sym file at `path`, using its preprocessed version if it's
+    /// valid.
+    ///
+    /// Otherwise the .sym file is parsed, and if `write_preprocessed` is set,
+    /// its preprocessed version is saved for next time.
+    pub fn from_file_preprocessed(
+        path: &Path,
+        write_preprocessed: bool,
+    ) -> Result<SymbolFile, SymbolError> {
+        match Self::from_preprocessed(path) {
+            Ok(symbols) => {
+                trace!("loaded preprocessed symbols for {}", path.display());
+                return Ok(symbols);
+            }
+            Err(e) => trace!(
+                "no usable preprocessed symbols for {}: {}",
+                path.display(),
+                e
+            ),
+        }
+        let symbols = Self::from_file(path)?;
+        if write_preprocessed {
+            if let Err(e) = symbols.save_preprocessed(path) {
+                trace!(
+                    "failed to save preprocessed symbols for {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        Ok(symbols)
+    }
+
+    pub(crate) fn to_preprocessed_bytes(&self, stamp: SourceStamp) -> Vec<u8> {
+        let mut w = Vec::new();
+        w.extend_from_slice(MAGIC);
+        put_u32(&mut w, PREPROCESSED_FORMAT_VERSION);
+        put_u32(&mut w, 0);
+        put_u64(&mut w, stamp.len);
+        put_u64(&mut w, stamp.mtime);
+
+        put_str(&mut w, &self.os);
+        put_str(&mut w, &self.arch);
+        put_str(&mut w, &self.module_id);
+        put_str(&mut w, &self.debug_file);
+        put_strings(&mut w, &self.files);
+        put_u32(&mut w, self.publics.len() as u32);
+        for public in &self.publics {
+            put_u64(&mut w, public.address);
+            put_str(&mut w, &public.name);
+            put_u32(&mut w, public.parameter_size);
+        }
+        put_strings(&mut w, &self.inline_origins);
+        put_range_map(&mut w, &self.win_stack_framedata_info, put_stack_info_win);
+        put_range_map(&mut w, &self.win_stack_fpo_info, put_stack_info_win);
+        match &self.url {
+            Some(url) => {
+                w.push(1);
+                put_str(&mut w, url);
+            }
+            None => w.push(0),
+        }
+        put_u64(&mut w, self.ambiguities_repaired);
+        put_u64(&mut w, self.ambiguities_discarded);
+        put_u64(&mut w, self.corruptions_discarded);
+        put_u64(&mut w, self.cfi_eval_corruptions);
+
+        // Encode the records first, so we know where they are when writing
+        // the tables.
+        let mut records = Vec::new();
+        let functions = put_records(&mut records, &self.functions, put_function);
+        let cfi_stack_info = put_records(&mut records, &self.cfi_stack_info, put_stack_info_cfi);
+        let records_offset =
+            w.len() + 4 + (functions.len() + cfi_stack_info.len()) * TABLE_ENTRY_SIZE + 4;
+        for table in [functions, cfi_stack_info] {
+            put_u32(&mut w, table.len() as u32);
+            for (range, block) in table {
+                put_u64(&mut w, range.start);
+                put_u64(&mut w, range.end);
+                put_u64(&mut w, (records_offset + block.start) as u64);
+                put_u64(&mut w, (block.end - block.start) as u64);
+            }
+        }
+        debug_assert_eq!(w.len(), records_offset);
+        w.extend_from_slice(&records);
+        w.extend_from_slice(MAGIC);
+        w
+    }
+
+    pub(crate) fn from_preprocessed_bytes(
+        bytes: Vec<u8>,
+        stamp: SourceStamp,
+    ) -> Result<SymbolFile, SymbolError> {
+        let mut r = Reader { data: &bytes };
+        if r.bytes(MAGIC.len()) != Some(&MAGIC[..]) {
+            return Err(INVALID);
+        }
+        let version = r.u32().ok_or(INVALID)?;
+        if version != PREPROCESSED_FORMAT_VERSION {
+            return Err(SymbolError::ParseError(
+                "unsupported preprocessed symbol file version",
+                0,
+            ));
+        }
+        let _reserved = r.u32().ok_or(INVALID)?;
+        let source = SourceStamp {
+            len: r.u64().ok_or(INVALID)?,
+            mtime: r.u64().ok_or(INVALID)?,
+        };
+        if source != stamp {
+            return Err(SymbolError::ParseError(
+                "preprocessed symbol file is out of date",
+                0,
+            ));
+        }
+        let mut symbols = r.symbol_file().ok_or(INVALID)?;
+        let functions = r.table(&bytes).ok_or(INVALID)?;
+        let cfi_stack_info = r.table(&bytes).ok_or(INVALID)?;
+        // The records fill the rest of the file, up to the final magic.
+        let records_start = bytes.len() - r.data.len();
+        let records_end = bytes
+            .len()
+            .checked_sub(MAGIC.len())
+            .filter(|&end| end >= records_start && bytes[end..] == MAGIC[..])
+            .ok_or(INVALID)?;
+        for table in [&functions, &cfi_stack_info] {
+            if !table.is_valid(&bytes, records_start..records_end) {
+                return Err(INVALID);
+            }
+        }
+        symbols.lazy = Some(LazyRecords::new(
+            bytes,
+            Index::Preprocessed(functions),
+            Index::Preprocessed(cfi_stack_info),
+        ));
+        Ok(symbols)
+    }
+}
+
+/// A table of records in a preprocessed file, see the format description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Table {
+    /// The offset of the first entry in the file.
+    offset: usize,
+    len: usize,
+}
+
+impl Table {
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    fn entry(&self, data: &[u8], idx: usize) -> Option<(Range<u64>, Block)> {
+        let offset = self.offset + idx * TABLE_ENTRY_SIZE;
+        let mut r = Reader {
+            data: data.get(offset..offset + TABLE_ENTRY_SIZE)?,
+        };
+        let range = Range::new(r.u64()?, r.u64()?);
+        let start = usize::try_from(r.u64()?).ok()?;
+        let end = start.checked_add(usize::try_from(r.u64()?).ok()?)?;
+        Some((range, Block { start, end }))
+    }
+
+    /// The number of entries which start before `addr`.
+    fn count_before(&self, data: &[u8], addr: u64) -> usize {
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.entry(data, mid) {
+                Some((range, _)) if range.start < addr => low = mid + 1,
+                _ => high = mid,
+            }
+        }
+        low
+    }
+
+    /// The block of the entry covering `addr`.
+    pub(crate) fn get(&self, data: &[u8], addr: u64) -> Option<Block> {
+        let idx = self
+            .count_before(data, addr.checked_add(1)?)
+            .checked_sub(1)?;
+        let (range, block) = self.entry(data, idx)?;
+        (range.end >= addr).then_some(block)
+    }
+
+    /// The start address of the nearest entry which starts before `addr`.
+    pub(crate) fn prev_start(&self, data: &[u8], addr: u64) -> Option<u64> {
+        let idx = self.count_before(data, addr).checked_sub(1)?;
+        Some(self.entry(data, idx)?.0.start)
+    }
+
+    /// Whether the entries are sorted and don't overlap, and all their
+    /// records are within `records`.
+    fn is_valid(&self, data: &[u8], records: std::ops::Range<usize>) -> bool {
+        let mut prev_end = None;
+        (0..self.len).all(|idx| {
+            let Some((range, block)) = self.entry(data, idx) else {
+                return false;
+            };
+            let sorted = range.start <= range.end && prev_end.is_none_or(|end| end < range.start);
+            prev_end = Some(range.end);
+            sorted && records.start <= block.start && block.end <= records.end
+        })
+    }
+}
+
+/// Decode a function record of a preprocessed file.
+pub(crate) fn decode_function(bytes: &[u8]) -> Option<Function> {
+    let mut r = Reader { data: bytes };
+    let function = r.function()?;
+    r.data.is_empty().then_some(function)
+}
+
+/// Decode a cfi_stack_info record of a preprocessed file.
+pub(crate) fn decode_cfi_stack_info(bytes: &[u8]) -> Option<StackInfoCfi> {
+    let mut r = Reader { data: bytes };
+    let info = r.stack_info_cfi()?;
+    r.data.is_empty().then_some(info)
+}
+
+fn put_u32(w: &mut Vec<u8>, val: u32) {
+    w.extend_from_slice(&val.to_le_bytes());
+}
+
+fn put_u64(w: &mut Vec<u8>, val: u64) {
+    w.extend_from_slice(&val.to_le_bytes());
+}
+
+fn put_str(w: &mut Vec<u8>, val: &str) {
+    put_u32(w, val.len() as u32);
+    w.extend_from_slice(val.as_bytes());
+}
+
+fn put_strings(w: &mut Vec<u8>, map: &HashMap<u32, String>) {
+    // Sorted so the output doesn't depend on the HashMap's ordering.
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort_unstable_by_key(|(id, _)| **id);
+    put_u32(w, entries.len() as u32);
+    for (id, val) in entries {
+        put_u32(w, *id);
+        put_str(w, val);
+    }
+}
+
+fn put_range_map<V: Clone + Debug + Eq>(
+    w: &mut Vec<u8>,
+    map: &RangeMap<u64, V>,
+    mut put_val: impl FnMut(&mut Vec<u8>, &V),
+) {
+    put_u32(w, map.num_ranges() as u32);
+    for (range, val) in map.ranges_values() {
+        put_u64(w, range.start);
+        put_u64(w, range.end);
+        put_val(w, val);
+    }
+}
+
+/// Encode the values of `map` one after another, and return the range of each
+/// one along with where it was written.
+fn put_records<V: Clone + Debug + Eq>(
+    w: &mut Vec<u8>,
+    map: &RangeMap<u64, V>,
+    put_val: impl Fn(&mut Vec<u8>, &V),
+) -> Vec<(Range<u64>, Block)> {
+    map.ranges_values()
+        .map(|(range, val)| {
+            let start = w.len();
+            put_val(w, val);
+            (
+                *range,
+                Block {
+                    start,
+                    end: w.len(),
+                },
+            )
+        })
+        .collect()
+}
+
+fn put_function(w: &mut Vec<u8>, function: &Function) {
+    put_u64(w, function.address);
+    put_u32(w, function.size);
+    put_u32(w, function.parameter_size);
+    put_str(w, &function.name);
+    put_range_map(w, &function.lines, |w, line| {
+        put_u64(w, line.address);
+        put_u32(w, line.size);
+        put_u32(w, line.file);
+        put_u32(w, line.line);
+    });
+    put_u32(w, function.inlinees.len() as u32);
+    for inlinee in &function.inlinees {
+        put_u32(w, inlinee.depth);
+        put_u64(w, inlinee.address);
+        put_u32(w, inlinee.size);
+        put_u32(w, inlinee.call_file);
+        put_u32(w, inlinee.call_line);
+        put_u32(w, inlinee.origin_id);
+    }
+}
+
+fn put_stack_info_cfi(w: &mut Vec<u8>, info: &StackInfoCfi) {
+    put_cfi_rules(w, &info.init);
+    put_u32(w, info.size);
+    put_u32(w, info.add_rules.len() as u32);
+    for rules in &info.add_rules {
+        put_cfi_rules(w, rules);
+    }
+}
+
+fn put_cfi_rules(w: &mut Vec<u8>, rules: &CfiRules) {
+    put_u64(w, rules.address);
+    put_str(w, &rules.rules);
+}
+
+fn put_stack_info_win(w: &mut Vec<u8>, info: &StackInfoWin) {
+    put_u64(w, info.address);
+    put_u32(w, info.size);
+    put_u32(w, info.prologue_size);
+    put_u32(w, info.epilogue_size);
+    put_u32(w, info.parameter_size);
+    put_u32(w, info.saved_register_size);
+    put_u32(w, info.local_size);
+    put_u32(w, info.max_stack_size);
+    match &info.program_string_or_base_pointer {
+        WinStackThing::ProgramString(program) => {
+            w.push(0);
+            put_str(w, program);
+        }
+        WinStackThing::AllocatesBasePointer(allocates) => {
+            w.push(1);
+            w.push(*allocates as u8);
+        }
+    }
+}
+
+/// The minimum encoded size of a StackInfoWin: the address, seven u32s, and
+/// the tag and value of `program_string_or_base_pointer`.
+const STACK_INFO_WIN_MIN_SIZE: usize = 8 + 7 * 4 + 1 + 1;
+
+/// A bounds-checked cursor over a preprocessed symbol file.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.data.len() {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.bytes(8)?.try_into().ok()?))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u32()? as usize;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+
+    /// Read a count of elements which are encoded in at least `min_size` bytes each.
+    ///
+    /// Fails if the remaining input can't possibly hold that many elements, so a
+    /// corrupt count can't make us allocate an absurd amount of memory.
+    fn count(&mut self, min_size: usize) -> Option<usize> {
+        let count = self.u32()? as usize;
+        (count <= self.data.len() / min_size).then_some(count)
+    }
+
+    /// Read a count, and make a Vec which can hold that many elements.
+    fn vec<T>(&mut self, min_size: usize) -> Option<(usize, Vec<T>)> {
+        let count = self.count(min_size)?;
+        Some((count, Vec::with_capacity(count)))
+    }
+
+    fn strings(&mut self) -> Option<HashMap<u32, String>> {
+        // An id and the length of an empty string.
+        let count = self.count(4 + 4)?;
+        let mut map = HashMap::with_capacity(count);
+        for _ in 0..count {
+            let id = self.u32()?;
+            map.insert(id, self.string()?);
+        }
+        Some(map)
+    }
+
+    /// Read a RangeMap whose values are encoded in at least `min_size` bytes each.
+    fn range_map<V: Clone + Debug + Eq>(
+        &mut self,
+        min_size: usize,
+        mut val: impl FnMut(&mut Self) -> Option<V>,
+    ) -> Option<RangeMap<u64, V>> {
+        let (count, mut entries) = self.vec(8 + 8 + min_size)?;
+        for _ in 0..count {
+            let (start, end) = (self.u64()?, self.u64()?);
+            if start > end {
+                return None;
+            }
+            entries.push((Range::new(start, end), val(self)?));
+        }
+        // This fails on overlapping ranges.
+        RangeMap::try_from_iter(entries).ok()
+    }
+
+    fn cfi_rules(&mut self) -> Option<CfiRules> {
+        Some(CfiRules {
+            address: self.u64()?,
+            rules: self.string()?,
+        })
+    }
+
+    fn stack_info_win(&mut self) -> Option<StackInfoWin> {
+        Some(StackInfoWin {
+            address: self.u64()?,
+            size: self.u32()?,
+            prologue_size: self.u32()?,
+            epilogue_size: self.u32()?,
+            parameter_size: self.u32()?,
+            saved_register_size: self.u32()?,
+            local_size: self.u32()?,
+            max_stack_size: self.u32()?,
+            program_string_or_base_pointer: match self.u8()? {
+                0 => WinStackThing::ProgramString(self.string()?),
+                1 => WinStackThing::AllocatesBasePointer(self.u8()? != 0),
+                _ => return None,
+            },
+        })
+    }
+
+    /// Read a table, given the whole file it's in.
+    fn table(&mut self, file: &[u8]) -> Option<Table> {
+        let len = self.count(TABLE_ENTRY_SIZE)?;
+        let offset = file.len() - self.data.len();
+        self.bytes(len * TABLE_ENTRY_SIZE)?;
+        Some(Table { offset, len })
+    }
+
+    fn function(&mut self) -> Option<Function> {
+        let address = self.u64()?;
+        let size = self.u32()?;
+        let parameter_size = self.u32()?;
+        let name = self.string()?;
+        let lines = self.range_map(8 + 4 + 4 + 4, |r| {
+            Some(SourceLine {
+                address: r.u64()?,
+                size: r.u32()?,
+                file: r.u32()?,
+                line: r.u32()?,
+            })
+        })?;
+        let (count, mut inlinees) = self.vec(4 + 8 + 4 + 4 + 4 + 4)?;
+        for _ in 0..count {
+            inlinees.push(Inlinee {
+                depth: self.u32()?,
+                address: self.u64()?,
+                size: self.u32()?,
+                call_file: self.u32()?,
+                call_line: self.u32()?,
+                origin_id: self.u32()?,
+            });
+        }
+        Some(Function {
+            address,
+            size,
+            parameter_size,
+            name,
+            lines,
+            inlinees,
+        })
+    }
+
+    fn stack_info_cfi(&mut self) -> Option<StackInfoCfi> {
+        let init = self.cfi_rules()?;
+        let size = self.u32()?;
+        let (count, mut add_rules) = self.vec(8 + 4)?;
+        for _ in 0..count {
+            add_rules.push(self.cfi_rules()?);
+        }
+        Some(StackInfoCfi {
+            init,
+            size,
+            add_rules,
+        })
+    }
+
+    /// Read the eagerly loaded parts of a SymbolFile.
+    fn symbol_file(&mut self) -> Option<SymbolFile> {
+        let os = self.string()?;
+        let arch = self.string()?;
+        let module_id = self.string()?;
+        let debug_file = self.string()?;
+        let files = self.strings()?;
+        let (count, mut publics) = self.vec(8 + 4 + 4)?;
+        for _ in 0..count {
+            publics.push(PublicSymbol {
+                address: self.u64()?,
+                name: self.string()?,
+                parameter_size: self.u32()?,
+            });
+        }
+        let inline_origins = self.strings()?;
+        let win_stack_framedata_info =
+            self.range_map(STACK_INFO_WIN_MIN_SIZE, Self::stack_info_win)?;
+        let win_stack_fpo_info = self.range_map(STACK_INFO_WIN_MIN_SIZE, Self::stack_info_win)?;
+        let url = match self.u8()? {
+            0 => None,
+            1 => Some(self.string()?),
+            _ => return None,
+        };
+        Some(SymbolFile {
+            os,
+            arch,
+            module_id,
+            debug_file,
+            files,
+            publics,
+            functions: RangeMap::new(),
+            inline_origins,
+            cfi_stack_info: RangeMap::new(),
+            win_stack_framedata_info,
+            win_stack_fpo_info,
+            url,
+            ambiguities_repaired: self.u64()?,
+            ambiguities_discarded: self.u64()?,
+            corruptions_discarded: self.u64()?,
+            cfi_eval_corruptions: self.u64()?,
+            lazy: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const STAMP: SourceStamp = SourceStamp { len: 1, mtime: 2 };
+
+    fn sample() -> SymbolFile {
+        SymbolFile::from_bytes(
+            b"MODULE Linux x86 ffff0000 bar
+INFO URL https://example.com/bar.sym
+FILE 53 bar.c
+FILE 54 baz.h
+INLINE_ORIGIN 0 inlined func
+FUNC 1000 30 10 another func
+INLINE 0 7 53 0 1010 8
+1000 10 7 53
+1010 8 3 54
+1018 18 8 53
+PUBLIC 1234 10 some public
+STACK CFI INIT 1000 30 .cfa: $esp 4 + .ra: .cfa -4 + ^
+STACK CFI 1001 .cfa: $esp 8 +
+STACK WIN 4 1000 30 1 0 10 0 4 0 0 1 $eip 4 + ^ =
+STACK WIN 0 2000 10 1 0 0 0 0 0 0 0
+",
+        )
+        .unwrap()
+    }
+
+    /// Check that `preprocessed` has the same contents as `expected`.
+    fn assert_same(preprocessed: &SymbolFile, expected: &SymbolFile) {
+        assert!(preprocessed.is_lazy());
+        assert_eq!(preprocessed.functions.num_ranges(), 0);
+        assert_eq!(preprocessed.cfi_stack_info.num_ranges(), 0);
+        for (range, function) in expected.functions.ranges_values() {
+            assert_eq!(*preprocessed.function_at(range.start).unwrap(), *function);
+            assert_eq!(*preprocessed.function_at(range.end).unwrap(), *function);
+        }
+        for (range, info) in expected.cfi_stack_info.ranges_values() {
+            assert_eq!(*preprocessed.cfi_stack_info_at(range.start).unwrap(), *info);
+            assert_eq!(*preprocessed.cfi_stack_info_at(range.end).unwrap(), *info);
+        }
+        assert_eq!(preprocessed.os, expected.os);
+        assert_eq!(preprocessed.arch, expected.arch);
+        assert_eq!(preprocessed.module_id, expected.module_id);
+        assert_eq!(preprocessed.debug_file, expected.debug_file);
+        assert_eq!(preprocessed.files, expected.files);
+        assert_eq!(preprocessed.publics, expected.publics);
+        assert_eq!(preprocessed.inline_origins, expected.inline_origins);
+        assert_eq!(
+            preprocessed.win_stack_framedata_info,
+            expected.win_stack_framedata_info
+        );
+        assert_eq!(preprocessed.win_stack_fpo_info, expected.win_stack_fpo_info);
+        assert_eq!(preprocessed.url, expected.url);
+        assert_eq!(
+            preprocessed.corruptions_discarded,
+            expected.corruptions_discarded
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let symbols = sample();
+        assert_eq!(symbols.cfi_stack_info.num_ranges(), 1);
+        assert_eq!(symbols.win_stack_fpo_info.num_ranges(), 1);
+        let bytes = symbols.to_preprocessed_bytes(STAMP);
+        let preprocessed = SymbolFile::from_preprocessed_bytes(bytes, STAMP).unwrap();
+        assert_same(&preprocessed, &symbols);
+        assert!(preprocessed.function_at(0xfff).is_none());
+        assert!(preprocessed.function_at(0x1030).is_none());
+        assert!(preprocessed.cfi_stack_info_at(0x1030).is_none());
+    }
+
+    #[test]
+    fn test_round_trip_file() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/symbols/test_app.pdb/5A9832E5287241C1838ED98914E9B7FF1/test_app.sym");
+        let symbols = SymbolFile::from_file(&path).unwrap();
+        let bytes = symbols.to_preprocessed_bytes(STAMP);
+        let preprocessed = SymbolFile::from_preprocessed_bytes(bytes, STAMP).unwrap();
+        assert_same(&preprocessed, &symbols);
+    }
+
+    #[test]
+    fn test_decoded_on_lookup() {
+        let symbols = sample();
+        let mut bytes = symbols.to_preprocessed_bytes(STAMP);
+        let preprocessed = SymbolFile::from_preprocessed_bytes(bytes.clone(), STAMP).unwrap();
+        let lazy = preprocessed.lazy.as_ref().unwrap();
+        assert_eq!(lazy.num_parsed(), 0);
+        assert_eq!(
+            preprocessed.function_at(0x1010).unwrap().name,
+            "another func"
+        );
+        assert_eq!(lazy.num_parsed(), 1);
+
+        // A corrupt record is only noticed when it's needed.
+        let name = bytes
+            .windows(b"another func".len())
+            .position(|window| window == b"another func")
+            .unwrap();
+        bytes[name] = 0xff;
+        let corrupt = SymbolFile::from_preprocessed_bytes(bytes, STAMP).unwrap();
+        assert!(corrupt.function_at(0x1010).is_none());
+        assert!(corrupt.cfi_stack_info_at(0x1010).is_some());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let bytes = sample().to_preprocessed_bytes(STAMP);
+        let stale = SourceStamp { len: 3, ..STAMP };
+        assert!(SymbolFile::from_preprocessed_bytes(bytes.clone(), stale).is_err());
+        for len in [0, 8, 32, bytes.len() / 2, bytes.len() - 1] {
+            assert!(SymbolFile::from_preprocessed_bytes(bytes[..len].to_vec(), STAMP).is_err());
+        }
+
+        let mut new_version = bytes.clone();
+        new_version[8] += 1;
+        assert!(SymbolFile::from_preprocessed_bytes(new_version, STAMP).is_err());
+
+        let mut corrupt = bytes.clone();
+        let len = corrupt.len();
+        corrupt[len - 1] = 0;
+        assert!(SymbolFile::from_preprocessed_bytes(corrupt, STAMP).is_err());
+
+        // Table entries pointing outside of the file are rejected up front.
+        // The last entry for 0x1000-0x102f is the CFI one, after the STACK
+        // WIN record and the function entry.
+        let mut entry = 0x1000u64.to_le_bytes().to_vec();
+        entry.extend_from_slice(&0x102fu64.to_le_bytes());
+        let mut out_of_bounds = bytes;
+        let cfi_entry = out_of_bounds
+            .windows(entry.len())
+            .rposition(|window| window == entry)
+            .unwrap();
+        out_of_bounds[cfi_entry + 24..cfi_entry + 32].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(SymbolFile::from_preprocessed_bytes(out_of_bounds, STAMP).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_count() {
+        // Two elements of at least 8 bytes each fit, three don't.
+        let mut data = 2u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0; 16]);
+        assert_eq!(Reader { data: &data }.count(8), Some(2));
+        data[0] = 3;
+        assert_eq!(Reader { data: &data }.count(8), None);
+        assert!(Reader { data: &data }.vec::<u64>(8).is_none());
+        assert!(Reader { data: &data }.strings().is_none());
+    }
+
+    #[test]
+    fn test_from_file_preprocessed() {
+        let dir = tempfile::tempdir().unwrap();
+        let sym_path = dir.path().join("bar.sym");
+        fs::write(
+            &sym_path,
+            "MODULE Linux x86 ffff0000 bar\nFUNC 1000 30 10 another func\n",
+        )
+        .unwrap();
+        let bin_path = preprocessed_path(&sym_path);
+        assert_eq!(bin_path, dir.path().join("bar.sym.bin"));
+
+        let symbols = SymbolFile::from_file_preprocessed(&sym_path, false).unwrap();
+        assert!(!bin_path.exists());
+        assert_eq!(
+            SymbolFile::from_file_preprocessed(&sym_path, true).unwrap(),
+            symbols
+        );
+        assert_same(&SymbolFile::from_preprocessed(&sym_path).unwrap(), &symbols);
+        assert_same(
+            &SymbolFile::from_file_preprocessed(&sym_path, true).unwrap(),
+            &symbols,
+        );
+
+        // Garbage is ignored, and replaced.
+        fs::write(&bin_path, b"garbage").unwrap();
+        assert!(SymbolFile::from_preprocessed(&sym_path).is_err());
+        assert_eq!(
+            SymbolFile::from_file_preprocessed(&sym_path, true).unwrap(),
+            symbols
+        );
+        assert_same(&SymbolFile::from_preprocessed(&sym_path).unwrap(), &symbols);
+    }
+}
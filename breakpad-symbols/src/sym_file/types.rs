@@ -246,6 +246,10 @@ impl StackInfoWin {
 /// A parsed .sym file containing debug symbols.
 #[derive(Debug, PartialEq, Eq)]
 pub struct SymbolFile {
+    /// The operating system from the MODULE record (e.g. `Linux` or `windows`).
+    pub os: String,
+    /// The CPU architecture from the MODULE record (e.g. `x86_64`).
+    pub arch: String,
     pub module_id: String,
     pub debug_file: String,
     /// The set of source files involved in compilation.
@@ -253,10 +257,19 @@ pub struct SymbolFile {
     /// Publicly visible symbols.
     pub publics: Vec<PublicSymbol>,
     /// Functions.
+    ///
+    /// This is empty for lazily loaded symbol files (see
+    /// [`SymbolFile::from_file_lazy`] and [`SymbolFile::from_preprocessed`]),
+    /// use [`SymbolFile::function_at`] to
+    /// look up functions in any symbol file.
     pub functions: RangeMap<u64, Function>,
     /// Function names for inlined functions.
     pub inline_origins: HashMap<u32, String>,
     /// DWARF CFI unwind information.
+    ///
+    /// This is empty for lazily loaded symbol files (see
+    /// [`SymbolFile::from_file_lazy`] and [`SymbolFile::from_preprocessed`]),
+    /// use [`SymbolFile::cfi_stack_info_at`] to look up CFI in any symbol file.
     pub cfi_stack_info: RangeMap<u64, StackInfoCfi>,
     /// Windows unwind information (frame data).
     pub win_stack_framedata_info: RangeMap<u64, StackInfoWin>,
@@ -312,4 +325,13 @@ pub struct SymbolFile {
     /// was not mapped memory). In these situations the cfi entry *may*
     /// still be correct.
     pub cfi_eval_corruptions: u64,
+
+    /// The FUNC and STACK CFI records of a lazily loaded symbol file (see
+    /// [`SymbolFile::from_file_lazy`] and [`SymbolFile::from_preprocessed`]),
+    /// which are parsed when they're needed.
+    ///
+    /// These records are *not* in `functions` and `cfi_stack_info`, use
+    /// [`SymbolFile::function_at`] and [`SymbolFile::cfi_stack_info_at`] to
+    /// look up records in any symbol file.
+    pub(crate) lazy: Option<super::lazy::LazyRecords>,
 }
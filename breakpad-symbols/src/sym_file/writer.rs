@@ -0,0 +1,236 @@
+// Writes a SymbolFile back out in the Breakpad text format.
+//
+// The output is canonical: records are written in a fixed order (MODULE,
+// INFO URL, FILE, INLINE_ORIGIN, FUNC, PUBLIC, STACK CFI, STACK WIN), ids and
+// addresses are sorted, numbers use lowercase hex without a prefix (or decimal
+// where the format requires it), and lines end with a single `\n`. Parsing
+// the output gives back an identical SymbolFile, so two files with the same
+// contents are written identically no matter how their inputs were laid out.
+//
+// Anything the parser doesn't keep (unknown INFO records, the `m` flag of
+// FUNC and PUBLIC records, records it discarded as corrupt or ambiguous) is
+// not written.
+
+use super::*;
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Write};
+
+impl SymbolFile {
+    /// Write this symbol file in the Breakpad text format.
+    ///
+    /// Lazily loaded symbol files can't be written, as most of their records
+    /// were never parsed.
+    pub fn write_breakpad<W: Write>(&self, writer: W) -> io::Result<()> {
+        if self.lazy.is_some() {
+            return Err(io::Error::other("can't write a lazily loaded symbol file"));
+        }
+        let mut w = BufWriter::new(writer);
+
+        // The parser requires a debug id, so a file without a MODULE record
+        // is written without one too.
+        if !self.module_id.is_empty() {
+            writeln!(
+                w,
+                "MODULE {} {} {} {}",
+                self.os, self.arch, self.module_id, self.debug_file
+            )?;
+        }
+        if let Some(url) = &self.url {
+            writeln!(w, "INFO URL {}", url)?;
+        }
+        for (id, name) in sorted(&self.files) {
+            writeln!(w, "FILE {} {}", id, name)?;
+        }
+        for (id, name) in sorted(&self.inline_origins) {
+            writeln!(w, "INLINE_ORIGIN {} {}", id, name)?;
+        }
+        for (_, function) in self.functions.ranges_values() {
+            write_function(&mut w, function)?;
+        }
+        for public in &self.publics {
+            writeln!(
+                w,
+                "PUBLIC {:x} {:x} {}",
+                public.address, public.parameter_size, public.name
+            )?;
+        }
+        for (_, info) in self.cfi_stack_info.ranges_values() {
+            writeln!(
+                w,
+                "STACK CFI INIT {:x} {:x} {}",
+                info.init.address, info.size, info.init.rules
+            )?;
+            for rules in &info.add_rules {
+                writeln!(w, "STACK CFI {:x} {}", rules.address, rules.rules)?;
+            }
+        }
+        for (_, info) in self.win_stack_framedata_info.ranges_values() {
+            write_stack_win(&mut w, 4, info)?;
+        }
+        for (_, info) in self.win_stack_fpo_info.ranges_values() {
+            write_stack_win(&mut w, 0, info)?;
+        }
+        w.flush()
+    }
+}
+
+/// The entries of an id -> name map, sorted by id.
+fn sorted(map: &HashMap<u32, String>) -> Vec<(&u32, &String)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort();
+    entries
+}
+
+fn write_function(w: &mut impl Write, function: &Function) -> io::Result<()> {
+    writeln!(
+        w,
+        "FUNC {:x} {:x} {:x} {}",
+        function.address, function.size, function.parameter_size, function.name
+    )?;
+    // Consecutive ranges of the same inlined call share an INLINE record.
+    let mut inlinees = function.inlinees.iter().peekable();
+    while let Some(inlinee) = inlinees.next() {
+        write!(
+            w,
+            "INLINE {} {} {} {} {:x} {:x}",
+            inlinee.depth,
+            inlinee.call_line,
+            inlinee.call_file,
+            inlinee.origin_id,
+            inlinee.address,
+            inlinee.size
+        )?;
+        while let Some(next) = inlinees.next_if(|next| {
+            (next.depth, next.call_line, next.call_file, next.origin_id)
+                == (
+                    inlinee.depth,
+                    inlinee.call_line,
+                    inlinee.call_file,
+                    inlinee.origin_id,
+                )
+        }) {
+            write!(w, " {:x} {:x}", next.address, next.size)?;
+        }
+        writeln!(w)?;
+    }
+    for (_, line) in function.lines.ranges_values() {
+        writeln!(
+            w,
+            "{:x} {:x} {} {}",
+            line.address, line.size, line.line, line.file
+        )?;
+    }
+    Ok(())
+}
+
+fn write_stack_win(w: &mut impl Write, ty: u8, info: &StackInfoWin) -> io::Result<()> {
+    write!(
+        w,
+        "STACK WIN {:x} {:x} {:x} {:x} {:x} {:x} {:x} {:x} {:x} ",
+        ty,
+        info.address,
+        info.size,
+        info.prologue_size,
+        info.epilogue_size,
+        info.parameter_size,
+        info.saved_register_size,
+        info.local_size,
+        info.max_stack_size
+    )?;
+    match &info.program_string_or_base_pointer {
+        WinStackThing::ProgramString(program) => writeln!(w, "1 {}", program),
+        WinStackThing::AllocatesBasePointer(allocates) => writeln!(w, "0 {}", *allocates as u8),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write(symbols: &SymbolFile) -> String {
+        let mut out = Vec::new();
+        symbols.write_breakpad(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write_canonical() {
+        let canonical = "MODULE Linux x86 ffff0000 bar
+INFO URL https://example.com/bar.sym
+FILE 53 bar.c
+FILE 54 baz.h
+INLINE_ORIGIN 0 inlined func
+INLINE_ORIGIN 1 other inlined func
+FUNC 1000 30 10 another func
+INLINE 0 7 53 0 1010 4 1014 4
+INLINE 0 9 53 1 1018 4
+INLINE 1 3 54 1 1010 2
+1000 10 7 53
+1010 8 3 54
+1018 18 8 53
+FUNC 2000 10 0 fn
+PUBLIC 1234 10 some public
+PUBLIC 2345 0 another public
+STACK CFI INIT 1000 30 .cfa: $esp 4 + .ra: .cfa -4 + ^
+STACK CFI 1001 .cfa: $esp 8 +
+STACK WIN 4 1000 30 1 0 10 0 4 0 1 $eip 4 + ^ =
+STACK WIN 0 2000 10 1 0 0 0 0 0 0 1
+";
+        let symbols = SymbolFile::from_bytes(canonical.as_bytes()).unwrap();
+        assert_eq!(write(&symbols), canonical);
+    }
+
+    #[test]
+    fn test_write_normalizes() {
+        // Records out of order, with the `m` flag, unknown INFO records and
+        // zero-sized lines, all of which the parser drops or sorts.
+        let symbols = SymbolFile::from_bytes(
+            b"MODULE windows x86_64 ABCD1234 foo.pdb
+INFO CODE_ID 1234 foo.dll
+PUBLIC m 2000 0 b
+PUBLIC 1000 0 a
+FILE 2 b.c
+FILE 1 a.c
+STACK WIN 0 2000 10 1 0 0 0 0 0 0 1
+FUNC m 2000 8 0 second
+2000 0 1 2
+2000 8 2 2
+FUNC 1000 8 0 first
+1004 4 2 1
+1000 4 1 1
+",
+        )
+        .unwrap();
+        assert_eq!(
+            write(&symbols),
+            "MODULE windows x86_64 ABCD1234 foo.pdb
+FILE 1 a.c
+FILE 2 b.c
+FUNC 1000 8 0 first
+1000 4 1 1
+1004 4 2 1
+FUNC 2000 8 0 second
+2000 8 2 2
+PUBLIC 1000 0 a
+PUBLIC 2000 0 b
+STACK WIN 0 2000 10 1 0 0 0 0 0 0 1
+"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_file() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/symbols/test_app.pdb/5A9832E5287241C1838ED98914E9B7FF1/test_app.sym");
+        let symbols = SymbolFile::from_file(&path).unwrap();
+        let written = write(&symbols);
+        assert_eq!(SymbolFile::from_bytes(written.as_bytes()).unwrap(), symbols);
+    }
+
+    #[test]
+    fn test_write_lazy() {
+        let symbols =
+            SymbolFile::from_bytes_lazy(b"MODULE Linux x86 ffff0000 bar\n".to_vec()).unwrap();
+        assert!(symbols.write_breakpad(Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,523 @@
+//! Contains Microsoft symbol store specific functionality
+
+use crate::http::SymbolClient;
+use crate::http::{fetch_lookup, file_key, FileKey};
+use crate::*;
+use cachemap2::CacheMap;
+use reqwest::Url;
+use std::convert::Infallible;
+use std::path::Path;
+use tracing::{debug, trace};
+
+/// The file in the root of a symbol store which marks the two-tier layout.
+const INDEX2_FILE: &str = "index2.txt";
+
+/// The file that redirects to the real location of a stored file.
+const FILE_PTR: &str = "file.ptr";
+
+/// An implementation of `SymbolSupplier` that loads files from symbol stores
+/// created by Microsoft's `symstore.exe`, on local (or network share) paths
+/// and on HTTP servers.
+///
+/// Files are stored in the layout described in [`crate::lookup`], but
+/// symstore adds a few conventions, which are all handled here:
+///
+/// * Stores with an `index2.txt` file at their root use the two-tier layout,
+///   where every path is prefixed by the first two characters of the file
+///   name (`fo/foo.pdb/<debug identifier>/foo.pdb`).
+/// * Files may be stored as CAB archives, with the last character of their
+///   name replaced by an underscore (`foo.pd_`).
+/// * Files may be stored elsewhere, with a `file.ptr` in their place that
+///   contains `PATH:<location>` (or `MSG:<reason>` if the file isn't
+///   available). Locations must be HTTP URLs, or paths inside the store for
+///   local stores.
+///
+/// This is mostly meant for [`FileKind::Binary`] and
+/// [`FileKind::ExtraDebugInfo`] files, but Breakpad symbol files which were
+/// added to a store are found too.
+pub struct SymstoreSymbolSupplier {
+    /// File paths that are known to be in the cache
+    cached_file_paths: CacheMap<FileKey, CachedAsyncResult<PathBuf, FileError>>,
+    /// HTTP Client to use for fetching files.
+    client: SymbolClient,
+    /// The stores to search, in order.
+    stores: Vec<Symstore>,
+    /// A path at which to cache downloaded and decompressed files, as in
+    /// [`HttpSymbolSupplier::new`].
+    cache: PathBuf,
+    /// A path to a temporary location where downloaded files can be written
+    /// before being atomically swapped into the cache.
+    tmp: PathBuf,
+}
+
+impl SymstoreSymbolSupplier {
+    /// Create a new `SymstoreSymbolSupplier`.
+    ///
+    /// Every entry of `stores` is either the URL of a symbol store on an HTTP
+    /// server or the path of a local one. Files in local stores are used in
+    /// place, unless they have to be decompressed into `cache`. Files found
+    /// on servers are saved under `cache`, which is searched first.
+    pub fn new(
+        stores: Vec<String>,
+        cache: PathBuf,
+        tmp: PathBuf,
+        timeout: Duration,
+    ) -> SymstoreSymbolSupplier {
+        let stores = stores
+            .into_iter()
+            .filter_map(|store| {
+                let root = if store.starts_with("http://") || store.starts_with("https://") {
+                    let mut store = store;
+                    if !store.ends_with('/') {
+                        store.push('/');
+                    }
+                    StoreRoot::Http(Url::parse(&store).ok()?)
+                } else {
+                    StoreRoot::Local(PathBuf::from(store))
+                };
+                Some(Symstore {
+                    root,
+                    two_tier: Default::default(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let servers = stores
+            .iter()
+            .filter_map(|store| match &store.root {
+                StoreRoot::Http(url) => Some(url.clone()),
+                StoreRoot::Local(_) => None,
+            })
+            .collect::<Vec<_>>();
+        SymstoreSymbolSupplier {
+            cached_file_paths: Default::default(),
+            client: SymbolClient::new(timeout, &servers),
+            stores,
+            cache,
+            tmp,
+        }
+    }
+
+    /// Send `credentials` to the HTTP symbol stores they match, as with
+    /// [`HttpSymbolSupplier::credentials`].
+    pub fn credentials(mut self, credentials: SymbolServerCredentials) -> SymstoreSymbolSupplier {
+        self.client.set_credentials(credentials);
+        self
+    }
+
+    /// Look for the file of `lookup` in `store`.
+    async fn locate_in_store(&self, store: &Symstore, lookup: &FileLookup) -> Option<PathBuf> {
+        let (dir, leaf) = lookup.server_rel.rsplit_once('/')?;
+        let dir = if store.is_two_tier(&self.client).await {
+            let prefix: String = dir.chars().take(2).collect();
+            format!("{}/{}", prefix, dir)
+        } else {
+            dir.to_string()
+        };
+        let plain = format!("{}/{}", dir, leaf);
+        let cab = format!("{}/{}", dir, cab_name(leaf));
+        let ptr = format!("{}/{}", dir, FILE_PTR);
+
+        match &store.root {
+            StoreRoot::Local(root) => {
+                let path = root.join(&plain);
+                if path.is_file() {
+                    trace!("SymstoreSymbolSupplier found file {}", path.display());
+                    return Some(path);
+                }
+                if let Ok(data) = fs::read(root.join(&cab)) {
+                    return self.unpack(&data, lookup);
+                }
+                let pointer = fs::read_to_string(root.join(&ptr)).ok()?;
+                self.follow_pointer(&store.root, &pointer, lookup).await
+            }
+            StoreRoot::Http(url) => {
+                let plain_lookup = FileLookup {
+                    server_rel: plain,
+                    ..lookup.clone()
+                };
+                if let Ok((path, _)) =
+                    fetch_lookup(&self.client, url, &plain_lookup, &self.cache, &self.tmp).await
+                {
+                    return Some(path);
+                }
+                if let Some(data) = get(&self.client, url, &cab).await {
+                    return self.unpack(&data, lookup);
+                }
+                let pointer = get(&self.client, url, &ptr).await?;
+                self.follow_pointer(&store.root, &String::from_utf8_lossy(&pointer), lookup)
+                    .await
+            }
+        }
+    }
+
+    /// Find the file that the `file.ptr` with the contents `pointer` points to.
+    ///
+    /// A `file.ptr` can point to an HTTP server, or to a local path inside the
+    /// store it's in. Pointers on HTTP stores can only point to HTTP servers.
+    async fn follow_pointer(
+        &self,
+        root: &StoreRoot,
+        pointer: &str,
+        lookup: &FileLookup,
+    ) -> Option<PathBuf> {
+        let pointer = pointer.trim();
+        let target = match pointer.strip_prefix("PATH:") {
+            Some(target) => target,
+            None => {
+                debug!("SymstoreSymbolSupplier file isn't available: {}", pointer);
+                return None;
+            }
+        };
+        trace!("SymstoreSymbolSupplier following file.ptr to {}", target);
+
+        // Windows paths like `C:\foo` parse as URLs too, so check the scheme.
+        let url = Url::parse(target)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"));
+        match (root, url) {
+            (_, Some(url)) => {
+                if target.ends_with('_') {
+                    let data = get(&self.client, &url, "").await?;
+                    self.unpack(&data, lookup)
+                } else {
+                    let target_lookup = FileLookup {
+                        server_rel: target.to_string(),
+                        ..lookup.clone()
+                    };
+                    fetch_lookup(&self.client, &url, &target_lookup, &self.cache, &self.tmp)
+                        .await
+                        .ok()
+                        .map(|(path, _)| path)
+                }
+            }
+            (StoreRoot::Http(_), None) => {
+                debug!(
+                    "SymstoreSymbolSupplier ignoring file.ptr to a local path on an HTTP store: {}",
+                    target
+                );
+                None
+            }
+            (StoreRoot::Local(root), None) => {
+                let path = root.join(target);
+                if !is_inside(root, &path) {
+                    debug!(
+                        "SymstoreSymbolSupplier ignoring file.ptr outside of the store: {}",
+                        target
+                    );
+                    return None;
+                }
+                if target.ends_with('_') {
+                    self.unpack(&fs::read(&path).ok()?, lookup)
+                } else {
+                    path.is_file().then_some(path)
+                }
+            }
+        }
+    }
+
+    /// Extract the file of `lookup` from the CAB archive `data` into the cache.
+    fn unpack(&self, data: &[u8], lookup: &FileLookup) -> Option<PathBuf> {
+        unpack_cabinet_file(data, lookup, &self.cache, &self.tmp).ok()
+    }
+}
+
+/// Where a symbol store is.
+enum StoreRoot {
+    Local(PathBuf),
+    Http(Url),
+}
+
+struct Symstore {
+    root: StoreRoot,
+    /// Whether the store uses the two-tier layout, checked on first use.
+    two_tier: CachedAsyncResult<bool, Infallible>,
+}
+
+impl Symstore {
+    async fn is_two_tier(&self, client: &SymbolClient) -> bool {
+        let two_tier = self
+            .two_tier
+            .get(|| async {
+                Ok(match &self.root {
+                    StoreRoot::Local(root) => root.join(INDEX2_FILE).is_file(),
+                    StoreRoot::Http(url) => get(client, url, INDEX2_FILE).await.is_some(),
+                })
+            })
+            .await;
+        matches!(*two_tier, Ok(true))
+    }
+}
+
+/// Adjusts a lookup to the case symstore uses for indices.
+///
+/// Binaries are indexed by their timestamp and size (`"{:08X}{:x}"`), but code
+/// identifiers are lowercase. This matters for stores on case-sensitive file
+/// systems and servers.
+fn symstore_lookup(mut lookup: FileLookup, file_kind: FileKind) -> FileLookup {
+    if file_kind == FileKind::Binary {
+        let mut parts: Vec<String> = lookup.server_rel.split('/').map(String::from).collect();
+        if let [_, index, _] = &mut parts[..] {
+            if index.len() > 8 && index.len() <= 16 && index.is_char_boundary(8) {
+                let (timestamp, size) = index.split_at(8);
+                *index = format!("{}{}", timestamp.to_uppercase(), size);
+            }
+        }
+        lookup.server_rel = parts.join("/");
+    }
+    lookup
+}
+
+/// The name of a file stored as a CAB archive, which has the last character
+/// of the file's name replaced by an underscore.
+fn cab_name(leaf: &str) -> String {
+    let mut name = leaf.to_string();
+    name.pop();
+    name.push('_');
+    name
+}
+
+/// Whether `path` exists and is inside the directory `root`, once symbolic
+/// links and `..` components are resolved.
+fn is_inside(root: &Path, path: &Path) -> bool {
+    match (root.canonicalize(), path.canonicalize()) {
+        (Ok(root), Ok(path)) => path.starts_with(root),
+        _ => false,
+    }
+}
+
+/// Download the file at `rel_path` under `base_url`.
+async fn get(client: &SymbolClient, base_url: &Url, rel_path: &str) -> Option<Vec<u8>> {
+    let url = base_url.join(rel_path).ok()?;
+    debug!("Trying {}", redact_url(&url));
+    // Keep the download permit until the whole file is downloaded.
+    let (res, _permit) = client.fetch(&url).await.ok()?;
+    res.bytes().await.ok().map(|data| data.to_vec())
+}
+
+#[async_trait]
+impl SymbolSupplier for SymstoreSymbolSupplier {
+    #[tracing::instrument(name = "symbols", level = "trace", skip_all, fields(file = crate::basename(&module.code_file())))]
+    async fn locate_symbols(
+        &self,
+        module: &(dyn Module + Sync),
+    ) -> Result<LocateSymbolsResult, SymbolError> {
+        let path = self
+            .locate_file(module, FileKind::BreakpadSym)
+            .await
+            .map_err(|_| SymbolError::NotFound)?;
+        Ok(LocateSymbolsResult {
+            symbols: SymbolFile::from_file(&path)?,
+            extra_debug_info: None,
+        })
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, module), fields(module = crate::basename(&module.code_file())))]
+    async fn locate_file(
+        &self,
+        module: &(dyn Module + Sync),
+        file_kind: FileKind,
+    ) -> Result<PathBuf, FileError> {
+        self.cached_file_paths
+            .cache_default(file_key(module, file_kind))
+            .get(|| async {
+                let lookups: Vec<_> = lookups(module, file_kind)
+                    .into_iter()
+                    .map(|lookup| symstore_lookup(lookup, file_kind))
+                    .collect();
+                for lookup in &lookups {
+                    let cache_path = self.cache.join(&lookup.cache_rel);
+                    if cache_path.is_file() {
+                        trace!("SymstoreSymbolSupplier found cached file");
+                        return Ok(cache_path);
+                    }
+                }
+                for lookup in &lookups {
+                    for store in &self.stores {
+                        if let Some(path) = self.locate_in_store(store, lookup).await {
+                            return Ok(path);
+                        }
+                    }
+                }
+                Err(FileError::NotFound)
+            })
+            .await
+            .as_ref()
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::test::serve;
+    use cab::{CabinetBuilder, CompressionType};
+    use std::io::{Cursor, Write};
+    use std::str::FromStr;
+
+    const PDB_PATH: &str = "foo.pdb/ABCD1234ABCD1234ABCDABCD12345678a/foo.pdb";
+    const DLL_PATH: &str = "foo.dll/5F3A1B2C1000/foo.dll";
+
+    fn module() -> SimpleModule {
+        SimpleModule::from_basic_info(
+            Some("foo.pdb".to_string()),
+            Some(DebugId::from_str("ABCD1234-ABCD-1234-ABCD-ABCD12345678-a").unwrap()),
+            Some("c:\\foo\\foo.dll".to_string()),
+            Some(CodeId::new("5F3A1B2C1000".to_string())),
+        )
+    }
+
+    fn cab(name: &str, contents: &[u8]) -> Vec<u8> {
+        let mut builder = CabinetBuilder::new();
+        builder.add_folder(CompressionType::MsZip).add_file(name);
+        let mut writer = builder.build(Cursor::new(Vec::new())).unwrap();
+        while let Some(mut file) = writer.next_file().unwrap() {
+            file.write_all(contents).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn write(path: &Path, contents: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_symstore_symbol_supplier_local() {
+        let t = tempfile::tempdir().unwrap();
+        let cache = t.path().join("cache");
+
+        // A two-tier store with a compressed pdb and a binary elsewhere in the
+        // store.
+        let store = t.path().join("store");
+        let elsewhere = store.join("elsewhere").join("foo.dll");
+        write(&store.join(INDEX2_FILE), b"");
+        write(
+            &store.join("fo").join(PDB_PATH).with_file_name("foo.pd_"),
+            &cab("foo.pdb", b"a pdb"),
+        );
+        write(&elsewhere, b"a dll");
+        write(
+            &store.join("fo").join(DLL_PATH).with_file_name(FILE_PTR),
+            format!("PATH:{}", elsewhere.display()).as_bytes(),
+        );
+
+        let supplier = SymstoreSymbolSupplier::new(
+            vec![store.display().to_string()],
+            cache.clone(),
+            t.path().to_path_buf(),
+            Duration::from_secs(10),
+        );
+        let pdb = supplier
+            .locate_file(&module(), FileKind::ExtraDebugInfo)
+            .await
+            .unwrap();
+        assert_eq!(pdb, cache.join(PDB_PATH));
+        assert_eq!(fs::read(&pdb).unwrap(), b"a pdb");
+        let dll = supplier
+            .locate_file(&module(), FileKind::Binary)
+            .await
+            .unwrap();
+        assert_eq!(dll, elsewhere);
+
+        // Pointers can't leave the store.
+        let store = t.path().join("store2");
+        let outside = t.path().join("outside").join("foo.dll");
+        write(&outside, b"a dll");
+        write(
+            &store.join(DLL_PATH).with_file_name(FILE_PTR),
+            format!("PATH:{}", outside.display()).as_bytes(),
+        );
+        write(
+            &store.join(PDB_PATH).with_file_name(FILE_PTR),
+            b"PATH:../outside/foo.dll",
+        );
+        let supplier = SymstoreSymbolSupplier::new(
+            vec![store.display().to_string()],
+            t.path().join("cache2"),
+            t.path().to_path_buf(),
+            Duration::from_secs(10),
+        );
+        assert!(supplier
+            .locate_file(&module(), FileKind::Binary)
+            .await
+            .is_err());
+        assert!(supplier
+            .locate_file(&module(), FileKind::ExtraDebugInfo)
+            .await
+            .is_err());
+
+        // A one-tier store where the binary isn't available.
+        let store = t.path().join("store1");
+        write(
+            &store.join(DLL_PATH).with_file_name(FILE_PTR),
+            b"MSG:not archived",
+        );
+        let supplier = SymstoreSymbolSupplier::new(
+            vec![store.display().to_string()],
+            t.path().join("cache1"),
+            t.path().to_path_buf(),
+            Duration::from_secs(10),
+        );
+        assert!(supplier
+            .locate_file(&module(), FileKind::Binary)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_symstore_symbol_supplier_http() {
+        let pdb_cab: &'static [u8] = Box::leak(cab("foo.pdb", b"a pdb").into_boxed_slice());
+        let files = HashMap::from([
+            (format!("/symbols/{}", INDEX2_FILE), &b""[..]),
+            (
+                format!("/symbols/fo/{}", PDB_PATH.replace("/foo.pdb", "/foo.pd_")),
+                pdb_cab,
+            ),
+            (format!("/symbols/fo/{}", DLL_PATH), &b"a dll"[..]),
+        ]);
+        let url = format!("{}/symbols", serve(files).await);
+
+        let t = tempfile::tempdir().unwrap();
+        let cache = t.path().join("cache");
+        let supplier = SymstoreSymbolSupplier::new(
+            vec![url],
+            cache.clone(),
+            t.path().to_path_buf(),
+            Duration::from_secs(10),
+        );
+        let pdb = supplier
+            .locate_file(&module(), FileKind::ExtraDebugInfo)
+            .await
+            .unwrap();
+        assert_eq!(pdb, cache.join(PDB_PATH));
+        assert_eq!(fs::read(&pdb).unwrap(), b"a pdb");
+        let dll = supplier
+            .locate_file(&module(), FileKind::Binary)
+            .await
+            .unwrap();
+        assert_eq!(fs::read(&dll).unwrap(), b"a dll");
+        assert!(supplier
+            .locate_file(&module(), FileKind::BreakpadSym)
+            .await
+            .is_err());
+
+        // Pointers on HTTP stores can't point to local files.
+        let local = t.path().join("foo.dll");
+        write(&local, b"a dll");
+        let pointer = format!("PATH:{}", local.display()).into_bytes();
+        let files = HashMap::from([(
+            format!("/{}", DLL_PATH.replace("/foo.dll", "/file.ptr")),
+            &*Box::leak(pointer.into_boxed_slice()),
+        )]);
+        let supplier = SymstoreSymbolSupplier::new(
+            vec![serve(files).await],
+            t.path().join("cache2"),
+            t.path().to_path_buf(),
+            Duration::from_secs(10),
+        );
+        assert!(supplier
+            .locate_file(&module(), FileKind::Binary)
+            .await
+            .is_err());
+    }
+}
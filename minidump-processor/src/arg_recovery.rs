@@ -1,5 +1,6 @@
-use minidump::{CpuContext, MinidumpRawContext, UnifiedMemory};
-use minidump_unwind::{CallStack, CallingConvention, FunctionArg, FunctionArgs};
+use minidump::system_info::Os;
+use minidump::{MinidumpContext, MinidumpRawContext, UnifiedMemory};
+use minidump_unwind::{ArgumentTrust, CallStack, CallingConvention, FunctionArg, FunctionArgs};
 
 // # Recovering x86 function arguments
 //
@@ -30,7 +31,8 @@ use minidump_unwind::{CallStack, CallingConvention, FunctionArg, FunctionArgs};
 // the function:
 //
 // * If the function name contains a ::, it's probably a C++ member function,
-//   in which case it's thiscall
+//   in which case it's thiscall (the Windows or the other flavour, depending on
+//   the OS of the minidump)
 // * Otherwise, assume it's cdecl
 //
 // It's a blunt heuristic that will misclassify static functions and always
@@ -53,9 +55,10 @@ use minidump_unwind::{CallStack, CallingConvention, FunctionArg, FunctionArgs};
 // function pointer types.
 //
 // thiscall functions have an implicit first argument "this". Windows (Visual C++)
-// toolchains will pass "this" via $eax instead of on the stack. Other (gcc)
-// toolchains will pass "this" just like any other argument (so it will be at
-// the top of the stack frame).
+// toolchains will pass "this" via $ecx instead of on the stack. $ecx is volatile,
+// so we only know it for the crashing frame. Other (gcc) toolchains will pass
+// "this" just like any other argument (so it will be at the top of the stack
+// frame).
 //
 //
 //
@@ -65,99 +68,51 @@ use minidump_unwind::{CallStack, CallingConvention, FunctionArg, FunctionArgs};
 // we just unconditionally assume all arguments are pointer-sized. This is intuitively true
 // most of the time. The major exceptions are `bool` and `uint64_t`. Maybe those
 // are worth carving out special cases for, but until then: it's all pointers!
+//
+//
+//
+// # Recovering amd64 function arguments
+//
+// amd64 has one calling convention per OS family: Windows uses Win64, everyone
+// else uses System V. We reuse the argument list parsing from above, with the
+// implicit "this" of C++ member functions passed as the first argument.
+//
+// Both conventions pass the first few integer arguments in registers
+// (rdi, rsi, rdx, rcx, r8, r9 for System V, rcx, rdx, r8, r9 for Win64) and the
+// rest on the stack, starting at the caller's stack pointer. Win64 also reserves
+// 32 bytes of "home space" there, where the callee may spill its register
+// arguments (unoptimized code usually does). `float` and `double` arguments are
+// passed in xmm registers, which we don't try to recover; in Win64 they still
+// take up one of the four register slots.
+//
+// Argument registers are volatile, so they are only really reliable for the
+// crashing frame (and even then the function may have reused them already).
+// In the callers, a volatile register that CFI happens to recover holds its value
+// at the time of the call, which is an argument of the *callee*, so we don't use
+// registers there at all: Win64 callers fall back to the home space, and System V
+// register arguments of callers are unknown. If none of a frame's arguments can be
+// recovered (like for most System V callers), we don't report any. Every argument
+// records where its value came from (its `ArgumentTrust`).
 
 /// Try to recover function arguments
-pub fn fill_arguments(call_stack: &mut CallStack, stack_memory: Option<UnifiedMemory>) {
+pub fn fill_arguments(call_stack: &mut CallStack, stack_memory: Option<UnifiedMemory>, os: Os) {
     // Collect up all the results at once to avoid borrowing issues.
     let args = call_stack
         .frames
         .iter()
         .enumerate()
         .map(|(frame_idx, frame)| {
-            // Only x86 is implemented because it has friendly calling conventions.
-            // and we need the function name to make any guesses at what the arguments are.
-            if let (Some(mem), Some(func_name), MinidumpRawContext::X86(ctx)) =
-                (stack_memory, &frame.function_name, &frame.context.raw)
-            {
-                const POINTER_WIDTH: u64 = 4;
-
-                if let Some((calling_convention, argument_list)) = parse_x86_arg_list(func_name) {
-                    // We're assuming this is either cdecl or thiscall. In either case,
-                    // all the arguments are saved at the top of the caller's stackframe
-                    // in reverse order (which in fact means we can start at the top
-                    // of the frame and read them off *in order*).
-
-                    // The stack grows down, so the maximum address in the stack
-                    // is actually the base of the stack. Since we're walking down
-                    // the stack, the base of the stack is a good upper-bound
-                    // (and default value) for any stack/frame pointer.
-                    let stack_base = mem.base_address().saturating_add(mem.size());
-
-                    let caller_stack_pointer = call_stack
-                        .frames
-                        .get(frame_idx + 1)
-                        .map(|f| f.context.get_stack_pointer())
-                        .unwrap_or(stack_base);
-                    let caller_frame_pointer = call_stack
-                        .frames
-                        .get(frame_idx + 2)
-                        .map(|f| f.context.get_stack_pointer())
-                        .unwrap_or(stack_base);
-
-                    let mut read_head = caller_stack_pointer;
-                    let mut pop_value = || {
-                        if read_head < caller_frame_pointer {
-                            let val = mem.get_memory_at_address::<u32>(read_head);
-                            read_head += POINTER_WIDTH;
-                            val.map(|val| val as u64)
-                        } else {
-                            None
-                        }
-                    };
-
-                    let mut args = Vec::new();
-
-                    // Handle the first argument of thiscall
-                    match calling_convention {
-                        CallingConvention::WindowsThisCall => {
-                            // On windows, "this" is passed in eax
-                            let value = ctx
-                                .get_register("eax", &frame.context.valid)
-                                .map(|x| x as u64);
-                            args.push(FunctionArg {
-                                name: String::from("this"),
-                                value,
-                            });
-                        }
-                        CallingConvention::OtherThisCall => {
-                            // Everywhere else, "this" is passed like a normal value
-                            let value = pop_value();
-                            args.push(FunctionArg {
-                                name: String::from("this"),
-                                value,
-                            });
-                        }
-                        CallingConvention::Cdecl => {
-                            // Nothing to do
-                        }
-                    }
-
-                    // Now handle the rest
-                    args.extend(argument_list.iter().map(|&arg_name| {
-                        let value = pop_value();
-                        FunctionArg {
-                            name: String::from(arg_name),
-                            value,
-                        }
-                    }));
-
-                    return Some(FunctionArgs {
-                        calling_convention,
-                        args,
-                    });
+            // We need the function name to make any guesses at what the arguments are.
+            let func_name = frame.function_name.as_ref()?;
+            match &frame.context.raw {
+                MinidumpRawContext::X86(_) => {
+                    x86_arguments(call_stack, frame_idx, stack_memory?, func_name, os)
                 }
+                MinidumpRawContext::Amd64(_) => {
+                    amd64_arguments(call_stack, frame_idx, stack_memory, func_name, os)
+                }
+                _ => None,
             }
-            None
         })
         .collect::<Vec<_>>();
 
@@ -167,21 +122,202 @@ pub fn fill_arguments(call_stack: &mut CallStack, stack_memory: Option<UnifiedMe
     }
 }
 
-fn parse_x86_arg_list(func_name: &str) -> Option<(CallingConvention, Vec<&str>)> {
+fn x86_arguments(
+    call_stack: &CallStack,
+    frame_idx: usize,
+    mem: UnifiedMemory,
+    func_name: &str,
+    os: Os,
+) -> Option<FunctionArgs> {
+    const POINTER_WIDTH: u64 = 4;
+
+    let frame = &call_stack.frames[frame_idx];
+    let (is_method, argument_list) = parse_arg_list(func_name)?;
+    let calling_convention = match (is_method, os) {
+        (true, Os::Windows) => CallingConvention::WindowsThisCall,
+        (true, _) => CallingConvention::OtherThisCall,
+        (false, _) => CallingConvention::Cdecl,
+    };
+
+    // We're assuming this is either cdecl or thiscall. In either case,
+    // all the arguments are saved at the top of the caller's stackframe
+    // in reverse order (which in fact means we can start at the top
+    // of the frame and read them off *in order*).
+
+    // The stack grows down, so the maximum address in the stack
+    // is actually the base of the stack. Since we're walking down
+    // the stack, the base of the stack is a good upper-bound
+    // (and default value) for any stack/frame pointer.
+    let stack_base = mem.base_address().saturating_add(mem.size());
+
+    let caller_stack_pointer = call_stack
+        .frames
+        .get(frame_idx + 1)
+        .map(|f| f.context.get_stack_pointer())
+        .unwrap_or(stack_base);
+    let caller_frame_pointer = call_stack
+        .frames
+        .get(frame_idx + 2)
+        .map(|f| f.context.get_stack_pointer())
+        .unwrap_or(stack_base);
+
+    let mut read_head = caller_stack_pointer;
+    let mut pop_value = || {
+        if read_head < caller_frame_pointer {
+            let val = mem.get_memory_at_address::<u32>(read_head);
+            read_head += POINTER_WIDTH;
+            val.map(|val| val as u64)
+        } else {
+            None
+        }
+    };
+
+    let mut args = Vec::new();
+
+    // Handle the first argument of thiscall
+    match calling_convention {
+        CallingConvention::WindowsThisCall => {
+            // On windows, "this" is passed in ecx
+            let value = read_register(frame_idx, &frame.context, "ecx");
+            args.push(make_arg("this", value, ArgumentTrust::Context));
+        }
+        CallingConvention::OtherThisCall => {
+            // Everywhere else, "this" is passed like a normal value
+            let value = pop_value();
+            args.push(make_arg("this", value, ArgumentTrust::Stack));
+        }
+        _ => {
+            // Nothing to do
+        }
+    }
+
+    // Now handle the rest
+    args.extend(
+        argument_list
+            .iter()
+            .map(|&arg_name| make_arg(arg_name, pop_value(), ArgumentTrust::Stack)),
+    );
+
+    Some(FunctionArgs {
+        calling_convention,
+        args,
+    })
+}
+
+fn amd64_arguments(
+    call_stack: &CallStack,
+    frame_idx: usize,
+    mem: Option<UnifiedMemory>,
+    func_name: &str,
+    os: Os,
+) -> Option<FunctionArgs> {
+    const POINTER_WIDTH: u64 = 8;
+    const SYSV_REGISTERS: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+    const WIN64_REGISTERS: [&str; 4] = ["rcx", "rdx", "r8", "r9"];
+    const WIN64_HOME_SPACE: u64 = 4 * POINTER_WIDTH;
+
+    let frame = &call_stack.frames[frame_idx];
+    let (is_method, argument_list) = parse_arg_list(func_name)?;
+    let calling_convention = if os == Os::Windows {
+        CallingConvention::Win64
+    } else {
+        CallingConvention::SysV
+    };
+
+    // The stack arguments (and the home space) start at the caller's stack pointer,
+    // right above our return address.
+    let caller_stack_pointer = call_stack
+        .frames
+        .get(frame_idx + 1)
+        .map(|f| f.context.get_stack_pointer());
+    let read_stack = |slot: u64| {
+        let address = caller_stack_pointer?.checked_add(slot * POINTER_WIDTH)?;
+        mem?.get_memory_at_address::<u64>(address)
+    };
+    let read_register = |reg: &str| {
+        read_register(frame_idx, &frame.context, reg).map(|value| (value, ArgumentTrust::Context))
+    };
+
+    let names = is_method.then_some("this").into_iter().chain(argument_list);
+    let mut args = Vec::new();
+    let mut next_register = 0;
+    let mut next_stack_slot = match calling_convention {
+        CallingConvention::Win64 => WIN64_HOME_SPACE / POINTER_WIDTH,
+        _ => 0,
+    };
+    for (position, name) in names.enumerate() {
+        let is_float = matches!(name, "float" | "double");
+        let recovered = match calling_convention {
+            CallingConvention::Win64 if position < WIN64_REGISTERS.len() => {
+                let from_register = if is_float {
+                    None
+                } else {
+                    read_register(WIN64_REGISTERS[position])
+                };
+                from_register.or_else(|| {
+                    read_stack(position as u64).map(|value| (value, ArgumentTrust::HomeSpace))
+                })
+            }
+            CallingConvention::SysV if is_float => None,
+            CallingConvention::SysV if next_register < SYSV_REGISTERS.len() => {
+                next_register += 1;
+                read_register(SYSV_REGISTERS[next_register - 1])
+            }
+            _ => {
+                next_stack_slot += 1;
+                read_stack(next_stack_slot - 1).map(|value| (value, ArgumentTrust::Stack))
+            }
+        };
+        args.push(match recovered {
+            Some((value, trust)) => make_arg(name, Some(value), trust),
+            None => make_arg(name, None, ArgumentTrust::None),
+        });
+    }
+    if !args.is_empty() && args.iter().all(|arg| arg.value.is_none()) {
+        return None;
+    }
+
+    Some(FunctionArgs {
+        calling_convention,
+        args,
+    })
+}
+
+/// Read an argument register of the given frame.
+///
+/// Only the crashing frame's registers are used, see above.
+fn read_register(frame_idx: usize, context: &MinidumpContext, reg: &str) -> Option<u64> {
+    if frame_idx == 0 {
+        context.get_register(reg)
+    } else {
+        None
+    }
+}
+
+fn make_arg(name: &str, value: Option<u64>, trust: ArgumentTrust) -> FunctionArg {
+    FunctionArg {
+        name: String::from(name),
+        value,
+        trust: if value.is_some() {
+            trust
+        } else {
+            ArgumentTrust::None
+        },
+    }
+}
+
+/// Parse the argument list out of a function name, and whether the function looks
+/// like a C++ member function.
+fn parse_arg_list(func_name: &str) -> Option<(bool, Vec<&str>)> {
     if let Some((func_name, arg_list)) = func_name.split_once('(') {
         if let Some((arg_list, _junk)) = arg_list.rsplit_once(')') {
-            let calling_convention = if func_name.contains("::") {
-                // Assume this is a C++ method (thiscall)
-                let windows = true; // TODO
-                if windows {
-                    CallingConvention::WindowsThisCall
-                } else {
-                    CallingConvention::OtherThisCall
-                }
-            } else {
-                CallingConvention::Cdecl
-                // Assume this is a static function (cdecl)
-            };
+            // Assume this is a C++ method if it has a `::`, and a static function otherwise
+            let is_method = func_name.contains("::");
+
+            // No arguments at all
+            if matches!(arg_list.trim(), "" | "void") {
+                return Some((is_method, Vec::new()));
+            }
 
             let mut args = Vec::new();
 
@@ -227,7 +363,7 @@ fn parse_x86_arg_list(func_name: &str) -> Option<(CallingConvention, Vec<&str>)>
 
             // Only accept the result if all nesting was balanced
             if template_depth == 0 && paren_depth == 0 {
-                return Some((calling_convention, args));
+                return Some((is_method, args));
             }
         }
     }
@@ -0,0 +1,201 @@
+use minidump::{MinidumpModuleList, UnifiedMemoryList};
+use minidump_common::format::MINIDUMP_EXCEPTION;
+use minidump_unwind::SymbolProvider;
+use tracing::debug;
+
+use crate::image_memory::ImageMemory;
+use crate::CxxException;
+
+// # Decoding MSVC C++ exceptions
+//
+// When a C++ exception is thrown, the MSVC runtime raises a structured exception
+// with code 0xE06D7363 ("msc") and the following parameters:
+//
+// * `[0]` a magic number identifying the EH version
+// * `[1]` a pointer to the thrown object
+// * `[2]` a pointer to the `ThrowInfo` describing the thrown type
+// * `[3]` (64-bit only) the base address of the image containing the `ThrowInfo`
+//
+// The `ThrowInfo` points at a `CatchableTypeArray`, which lists every type the
+// exception can be caught as (the thrown type first, followed by its base
+// classes). Each `CatchableType` points at a `TypeDescriptor` containing the
+// decorated type name.
+//
+// On 32-bit all of these "pointers" are real pointers. When the image base
+// parameter is present they are instead 32-bit offsets from that base. Either
+// way every field we care about is 32 bits wide.
+//
+// These structures live in the read-only data of the module that threw, which
+// is usually *not* captured in a minidump, so they're read with `ImageMemory`.
+
+/// The exception code used by the MSVC runtime for C++ exceptions.
+const CXX_EXCEPTION_CODE: u32 = 0xe06d7363;
+
+/// Values of `ExceptionInformation[0]` used by the various MSVC EH versions.
+const CXX_EXCEPTION_MAGICS: [u64; 4] = [0x19930520, 0x19930521, 0x19930522, 0x01994000];
+
+/// Sanity limit on the number of catchable types.
+const MAX_CATCHABLE_TYPES: u32 = 64;
+
+/// Maximum length of a type name or `what()` message.
+const MAX_STRING_LEN: usize = 1024;
+
+/// Offset of `CatchableTypeArray` in `ThrowInfo`.
+const THROW_INFO_CATCHABLE_TYPES_OFFSET: u64 = 12;
+
+/// Offset of the `TypeDescriptor` pointer in `CatchableType`.
+const CATCHABLE_TYPE_DESCRIPTOR_OFFSET: u64 = 4;
+
+/// Decode the C++ exception described by `exception`, if it is one.
+///
+/// `pointer_size` is the size of a pointer in the crashing process.
+pub async fn decode_cxx_exception<P>(
+    exception: &MINIDUMP_EXCEPTION,
+    pointer_size: u64,
+    memory_list: &UnifiedMemoryList<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+) -> Option<CxxException>
+where
+    P: SymbolProvider + Sync,
+{
+    if exception.exception_code != CXX_EXCEPTION_CODE || exception.number_parameters < 3 {
+        return None;
+    }
+    let params = &exception.exception_information;
+    if !CXX_EXCEPTION_MAGICS.contains(&params[0]) {
+        debug!("unknown C++ exception magic {:#x}", params[0]);
+        return None;
+    }
+
+    let thrown_object = params[1];
+    let throw_info = params[2];
+    let image_base = (exception.number_parameters >= 4 && params[3] != 0).then_some(params[3]);
+    // Rethrows (`throw;`) with no active exception have a null ThrowInfo.
+    if throw_info == 0 {
+        return None;
+    }
+
+    let mut memory = ImageMemory::new(memory_list, modules, symbol_provider);
+    let resolve = |value: u32| match image_base {
+        Some(base) => base.wrapping_add(value as u64),
+        None => value as u64,
+    };
+
+    let array = resolve(
+        memory
+            .read_u32(throw_info.checked_add(THROW_INFO_CATCHABLE_TYPES_OFFSET)?)
+            .await?,
+    );
+    let count = memory.read_u32(array).await?.min(MAX_CATCHABLE_TYPES);
+
+    let mut catchable_types = Vec::new();
+    for i in 0..count as u64 {
+        let Some(entry) = array.checked_add(4 + i * 4) else {
+            break;
+        };
+        let Some(catchable_type) = memory.read_u32(entry).await.map(resolve) else {
+            break;
+        };
+        let Some(descriptor_offset) = catchable_type.checked_add(CATCHABLE_TYPE_DESCRIPTOR_OFFSET)
+        else {
+            break;
+        };
+        let Some(type_descriptor) = memory.read_u32(descriptor_offset).await.map(resolve) else {
+            break;
+        };
+        // The name follows the vftable pointer and the `spare` pointer.
+        let Some(name_address) = type_descriptor.checked_add(2 * pointer_size) else {
+            break;
+        };
+        let Some(name) = memory.read_cstr(name_address, MAX_STRING_LEN).await else {
+            break;
+        };
+        catchable_types.push(undecorate_type_name(&name));
+    }
+
+    if catchable_types.is_empty() {
+        return None;
+    }
+
+    // MSVC's std::exception is `{ vftable, { const char* _What, bool _DoFree } }`.
+    let what = if catchable_types.iter().any(|t| t == "std::exception") {
+        let what_ptr = match (pointer_size, thrown_object.checked_add(pointer_size)) {
+            (_, None) => None,
+            (8, Some(address)) => memory.read_u64(address).await,
+            (_, Some(address)) => memory.read_u32(address).await.map(u64::from),
+        };
+        match what_ptr {
+            Some(ptr) if ptr != 0 => memory.read_cstr(ptr, MAX_STRING_LEN).await,
+            _ => None,
+        }
+    } else {
+        None
+    };
+
+    Some(CxxException {
+        thrown_object: thrown_object.into(),
+        throw_info: throw_info.into(),
+        catchable_types,
+        what,
+    })
+}
+
+/// Turn a decorated RTTI type name (e.g. `.?AVbad_alloc@std@@`) into a readable one.
+///
+/// Names which can't be handled by this simple undecorator (templates, nested
+/// functions, etc.) are returned as-is.
+pub fn undecorate_type_name(decorated: &str) -> String {
+    let body = decorated
+        .strip_prefix(".?AV")
+        .or_else(|| decorated.strip_prefix(".?AU"))
+        .or_else(|| decorated.strip_prefix(".?AW4"));
+    let Some(body) = body.and_then(|b| b.strip_suffix("@@")) else {
+        return match decorated {
+            // Primitive types thrown directly.
+            ".H" => "int".into(),
+            ".I" => "unsigned int".into(),
+            ".J" => "long".into(),
+            ".K" => "unsigned long".into(),
+            ".N" => "double".into(),
+            ".M" => "float".into(),
+            ".D" => "char".into(),
+            ".PEAD" | ".PAD" => "char *".into(),
+            ".PEBD" | ".PBD" => "char const *".into(),
+            ".PEA_W" | ".PA_W" => "wchar_t *".into(),
+            ".PEB_W" | ".PB_W" => "wchar_t const *".into(),
+            _ => decorated.into(),
+        };
+    };
+    if body.is_empty() || body.contains(['?', '$']) {
+        return decorated.into();
+    }
+    body.rsplit('@').collect::<Vec<_>>().join("::")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undecorate() {
+        assert_eq!(
+            undecorate_type_name(".?AVbad_alloc@std@@"),
+            "std::bad_alloc"
+        );
+        assert_eq!(
+            undecorate_type_name(".?AVexception@std@@"),
+            "std::exception"
+        );
+        assert_eq!(
+            undecorate_type_name(".?AUMyError@detail@app@@"),
+            "app::detail::MyError"
+        );
+        assert_eq!(undecorate_type_name(".?AVFoo@@"), "Foo");
+        assert_eq!(undecorate_type_name(".H"), "int");
+        assert_eq!(
+            undecorate_type_name(".?AV?$basic_string@DU?$char_traits@D@std@@@std@@"),
+            ".?AV?$basic_string@DU?$char_traits@D@std@@@std@@"
+        );
+    }
+}
@@ -0,0 +1,436 @@
+//! Disassembly of the code around the crash and the crashing thread's return addresses.
+//!
+//! The code is read from the dump's memory if it was captured, and otherwise from the
+//! module binaries (see [`ImageMemory`]). Like [`crate::op_analysis`], the supported
+//! architectures depend on the crate's features.
+
+use breakpad_symbols::SimpleFrame;
+use minidump::system_info::Cpu;
+use minidump::{MinidumpModuleList, UnifiedMemoryList};
+use minidump_unwind::{CallStack, SymbolProvider};
+
+use crate::image_memory::ImageMemory;
+use crate::register_annotations::symbolize;
+use crate::{DisassembledInstruction, Disassembly, DisassemblyWindow};
+
+/// The maximum number of frames whose return addresses get disassembled.
+const MAX_DISASSEMBLED_FRAMES: usize = 32;
+
+/// How far back from the window's address we look for the start of a function.
+const MAX_FUNCTION_LOOKBACK: u64 = 0x1000;
+
+/// How many starting points to try when the instructions before the window's address
+/// can't be decoded from the start of the function.
+const MAX_RESYNC_CANDIDATES: usize = 16;
+
+/// An instruction decoded by one of the architecture-specific decoders.
+struct Decoded {
+    len: usize,
+    text: String,
+    /// The target of a direct branch or call.
+    branch_target: Option<u64>,
+}
+
+/// Disassemble `instructions` instructions before and after the crashing instruction of
+/// `stack`, and around the return addresses of its other frames if `return_addresses`.
+///
+/// Returns `None` if disassembling `cpu` isn't supported.
+#[allow(clippy::too_many_arguments)]
+pub async fn disassemble_thread<P>(
+    threads_index: usize,
+    stack: &CallStack,
+    cpu: Cpu,
+    instructions: usize,
+    return_addresses: bool,
+    memory_list: &UnifiedMemoryList<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+) -> Option<Disassembly>
+where
+    P: SymbolProvider + Sync,
+{
+    let max_len = max_instruction_len(cpu)?;
+    let frame_count = if return_addresses {
+        MAX_DISASSEMBLED_FRAMES
+    } else {
+        1
+    };
+
+    let mut memory = ImageMemory::new(memory_list, modules, symbol_provider);
+    let mut windows = Vec::new();
+    for (i, frame) in stack.frames.iter().take(frame_count).enumerate() {
+        let address = frame.context.get_instruction_pointer();
+        let function_base = match modules.module_at_address(address) {
+            Some(module) => {
+                let mut symbol = SimpleFrame::with_instruction(address);
+                let _ = symbol_provider.fill_symbol(module, &mut symbol).await;
+                symbol.function_base
+            }
+            None => None,
+        };
+
+        // Read the code from as far back as we can (within the memory region or section
+        // of `address`), so that there's room to resynchronize the decoding before the
+        // window's address.
+        let lookback = match function_base.and_then(|base| address.checked_sub(base)) {
+            Some(offset) if offset <= MAX_FUNCTION_LOOKBACK => offset,
+            _ => instructions.saturating_mul(max_len) as u64,
+        };
+        let window_len = instructions.saturating_add(1).saturating_mul(max_len);
+        let Some((start, bytes)) = memory.read_around(address, lookback, window_len).await else {
+            continue;
+        };
+
+        let decoded = decode_window(cpu, &bytes, start, address, function_base, instructions);
+        if decoded.is_empty() {
+            continue;
+        }
+        let mut window_instructions = Vec::with_capacity(decoded.len());
+        for (address, decoded) in decoded {
+            let offset = (address - start) as usize;
+            let branch_target_symbol = match decoded.branch_target {
+                Some(target) => symbolize(target, modules, symbol_provider)
+                    .await
+                    .map(|target| target.to_string()),
+                None => None,
+            };
+            window_instructions.push(DisassembledInstruction {
+                address: address.into(),
+                bytes: bytes[offset..offset + decoded.len].to_vec(),
+                text: decoded.text,
+                branch_target: decoded.branch_target.map(Into::into),
+                branch_target_symbol,
+            });
+        }
+        windows.push(DisassemblyWindow {
+            frame: i,
+            address: address.into(),
+            instructions: window_instructions,
+        });
+    }
+
+    Some(Disassembly {
+        threads_index,
+        windows,
+    })
+}
+
+/// Decode up to `instructions` instructions before `address` and `address` plus up to
+/// `instructions` after it, from `bytes` read at `start`.
+///
+/// For variable-length instruction sets decoding backwards is ambiguous, so we look for
+/// the earliest starting point whose instructions line up with `address`, trying the
+/// start of the function first. x86 decoding realigns within a few instructions, so
+/// only the first few starting points are tried. ARM64 instructions all have the same
+/// length, so the first starting point always lines up.
+fn decode_window(
+    cpu: Cpu,
+    bytes: &[u8],
+    start: u64,
+    address: u64,
+    function_base: Option<u64>,
+    instructions: usize,
+) -> Vec<(u64, Decoded)> {
+    let back = (address - start) as usize;
+    let candidates = function_base
+        .filter(|&base| base >= start && base <= address)
+        .map(|base| (base - start) as usize)
+        .into_iter()
+        .chain((0..=back).take(MAX_RESYNC_CANDIDATES));
+
+    let mut before = Vec::new();
+    for candidate in candidates {
+        if let Some(offsets) = decode_lengths_until(cpu, bytes, candidate, back) {
+            before = offsets;
+            break;
+        }
+    }
+    let skip = before.len().saturating_sub(instructions);
+
+    let mut window = Vec::new();
+    for &offset in &before[skip..] {
+        let Some(decoded) = decode(cpu, &bytes[offset..], start + offset as u64) else {
+            return Vec::new();
+        };
+        window.push((start + offset as u64, decoded));
+    }
+
+    let mut offset = back;
+    for _ in 0..=instructions {
+        let Some(decoded) = bytes
+            .get(offset..)
+            .and_then(|code| decode(cpu, code, start + offset as u64))
+        else {
+            break;
+        };
+        let len = decoded.len;
+        window.push((start + offset as u64, decoded));
+        offset += len;
+    }
+    window
+}
+
+/// Find the offsets of the instructions from `bytes[from..]` up to `bytes[..to]`, if the
+/// instructions end exactly there.
+///
+/// This only decodes the instruction lengths, as most starting points are thrown away.
+fn decode_lengths_until(cpu: Cpu, bytes: &[u8], from: usize, to: usize) -> Option<Vec<usize>> {
+    let mut offsets = Vec::new();
+    let mut offset = from;
+    while offset < to {
+        offsets.push(offset);
+        offset += decode_len(cpu, bytes.get(offset..)?)?;
+    }
+    (offset == to).then_some(offsets)
+}
+
+/// The maximum length of an instruction, or `None` if `cpu` isn't supported.
+fn max_instruction_len(cpu: Cpu) -> Option<usize> {
+    match cpu {
+        #[cfg(feature = "disasm_amd64")]
+        Cpu::X86_64 => Some(amd64::MAX_INSTRUCTION_LEN),
+        #[cfg(feature = "disasm_x86")]
+        Cpu::X86 => Some(x86::MAX_INSTRUCTION_LEN),
+        #[cfg(feature = "disasm_arm64")]
+        Cpu::Arm64 => Some(arm64::INSTRUCTION_LEN),
+        _ => None,
+    }
+}
+
+fn decode_len(cpu: Cpu, bytes: &[u8]) -> Option<usize> {
+    match cpu {
+        #[cfg(feature = "disasm_amd64")]
+        Cpu::X86_64 => amd64::decode_len(bytes),
+        #[cfg(feature = "disasm_x86")]
+        Cpu::X86 => x86::decode_len(bytes),
+        #[cfg(feature = "disasm_arm64")]
+        Cpu::Arm64 => arm64::decode_len(bytes),
+        _ => {
+            let _ = bytes;
+            None
+        }
+    }
+}
+
+fn decode(cpu: Cpu, bytes: &[u8], address: u64) -> Option<Decoded> {
+    match cpu {
+        #[cfg(feature = "disasm_amd64")]
+        Cpu::X86_64 => amd64::decode(bytes, address),
+        #[cfg(feature = "disasm_x86")]
+        Cpu::X86 => x86::decode(bytes, address),
+        #[cfg(feature = "disasm_arm64")]
+        Cpu::Arm64 => arm64::decode(bytes, address),
+        _ => {
+            let _ = (bytes, address);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "disasm_amd64")]
+mod amd64 {
+    use super::Decoded;
+    use yaxpeax_arch::LengthedInstruction;
+    use yaxpeax_x86::amd64::{InstDecoder, Opcode, Operand};
+
+    pub const MAX_INSTRUCTION_LEN: usize = 15;
+
+    pub fn decode_len(bytes: &[u8]) -> Option<usize> {
+        let instruction = InstDecoder::default().decode_slice(bytes).ok()?;
+        Some(instruction.len().to_const() as usize)
+    }
+
+    pub fn decode(bytes: &[u8], address: u64) -> Option<Decoded> {
+        let instruction = InstDecoder::default().decode_slice(bytes).ok()?;
+        let len = instruction.len().to_const() as usize;
+        let opcode = instruction.opcode();
+        let is_branch = opcode.is_jcc()
+            || matches!(
+                opcode,
+                Opcode::CALL
+                    | Opcode::JMP
+                    | Opcode::LOOP
+                    | Opcode::LOOPZ
+                    | Opcode::LOOPNZ
+                    | Opcode::JRCXZ
+            );
+        let displacement = match instruction.operand(0) {
+            Operand::ImmediateI8 { imm } if is_branch => Some(imm as i64),
+            Operand::ImmediateI32 { imm } if is_branch => Some(imm as i64),
+            _ => None,
+        };
+        Some(Decoded {
+            len,
+            text: instruction.to_string(),
+            branch_target: displacement
+                .map(|displacement| (address + len as u64).wrapping_add(displacement as u64)),
+        })
+    }
+}
+
+#[cfg(feature = "disasm_x86")]
+mod x86 {
+    use super::Decoded;
+    use yaxpeax_arch::LengthedInstruction;
+    use yaxpeax_x86::protected_mode::{InstDecoder, Opcode, Operand};
+
+    pub const MAX_INSTRUCTION_LEN: usize = 15;
+
+    pub fn decode_len(bytes: &[u8]) -> Option<usize> {
+        let instruction = InstDecoder::default().decode_slice(bytes).ok()?;
+        Some(instruction.len().to_const() as usize)
+    }
+
+    pub fn decode(bytes: &[u8], address: u64) -> Option<Decoded> {
+        let instruction = InstDecoder::default().decode_slice(bytes).ok()?;
+        let len = instruction.len().to_const() as usize;
+        let opcode = instruction.opcode();
+        let is_branch = opcode.is_jcc()
+            || matches!(
+                opcode,
+                Opcode::CALL
+                    | Opcode::JMP
+                    | Opcode::LOOP
+                    | Opcode::LOOPZ
+                    | Opcode::LOOPNZ
+                    | Opcode::JECXZ
+            );
+        let displacement = match instruction.operand(0) {
+            Operand::ImmediateI8 { imm } if is_branch => Some(imm as i32),
+            Operand::ImmediateI16 { imm } if is_branch => Some(imm as i32),
+            Operand::ImmediateI32 { imm } if is_branch => Some(imm),
+            _ => None,
+        };
+        Some(Decoded {
+            len,
+            text: instruction.to_string(),
+            branch_target: displacement.map(|displacement| {
+                (address as u32)
+                    .wrapping_add(len as u32)
+                    .wrapping_add(displacement as u32) as u64
+            }),
+        })
+    }
+}
+
+#[cfg(feature = "disasm_arm64")]
+mod arm64 {
+    use super::Decoded;
+    use yaxpeax_arch::{Decoder, U8Reader};
+    use yaxpeax_arm::armv8::a64::{InstDecoder, Opcode, Operand};
+
+    pub const INSTRUCTION_LEN: usize = 4;
+
+    /// All instructions have the same length, so this doesn't need to decode them.
+    pub fn decode_len(bytes: &[u8]) -> Option<usize> {
+        (bytes.len() >= INSTRUCTION_LEN).then_some(INSTRUCTION_LEN)
+    }
+
+    pub fn decode(bytes: &[u8], address: u64) -> Option<Decoded> {
+        let mut reader = U8Reader::new(bytes);
+        let instruction = InstDecoder::default().decode(&mut reader).ok()?;
+        let is_branch = matches!(
+            instruction.opcode,
+            Opcode::B
+                | Opcode::BL
+                | Opcode::Bcc(_)
+                | Opcode::BCcc(_)
+                | Opcode::CBZ
+                | Opcode::CBNZ
+                | Opcode::TBZ
+                | Opcode::TBNZ
+        );
+        let displacement = instruction
+            .operands
+            .iter()
+            .find_map(|operand| match *operand {
+                Operand::PCOffset(offset) if is_branch => Some(offset),
+                _ => None,
+            });
+        Some(Decoded {
+            len: INSTRUCTION_LEN,
+            text: instruction.to_string(),
+            branch_target: displacement
+                .map(|displacement| address.wrapping_add(displacement as u64)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "disasm_amd64")]
+    fn amd64_window() {
+        // push rbp; mov rbp, rsp; call +0; mov eax, [rax]; pop rbp; ret
+        let code = [
+            0x55, 0x48, 0x89, 0xe5, 0xe8, 0x00, 0x00, 0x00, 0x00, 0x8b, 0x00, 0x5d, 0xc3,
+        ];
+        let start = 0x1000;
+
+        let window = decode_window(Cpu::X86_64, &code, start, 0x1009, None, 2);
+        let addresses = window
+            .iter()
+            .map(|(address, _)| *address)
+            .collect::<Vec<_>>();
+        assert_eq!(addresses, vec![0x1001, 0x1004, 0x1009, 0x100b, 0x100c]);
+        assert_eq!(window[1].1.branch_target, Some(0x1009));
+        assert_eq!(window[2].1.text, "mov eax, dword [rax]");
+
+        // Starting at the function base gets the whole prologue.
+        let window = decode_window(Cpu::X86_64, &code[1..], start + 1, 0x1009, Some(0x1001), 8);
+        assert_eq!(window.first().unwrap().0, 0x1001);
+    }
+
+    #[test]
+    #[cfg(feature = "disasm_x86")]
+    fn x86_window() {
+        // push ebp; mov ebp, esp; call +0; mov eax, [eax]; pop ebp; ret
+        let code = [
+            0x55, 0x89, 0xe5, 0xe8, 0x00, 0x00, 0x00, 0x00, 0x8b, 0x00, 0x5d, 0xc3,
+        ];
+        let start = 0x1000;
+
+        let window = decode_window(Cpu::X86, &code, start, 0x1008, None, 2);
+        let addresses = window
+            .iter()
+            .map(|(address, _)| *address)
+            .collect::<Vec<_>>();
+        assert_eq!(addresses, vec![0x1001, 0x1003, 0x1008, 0x100a, 0x100b]);
+        assert_eq!(window[1].1.branch_target, Some(0x1008));
+        assert_eq!(window[2].1.text, "mov eax, dword [eax]");
+
+        // Branch targets wrap around the 32-bit address space.
+        let window = decode_window(Cpu::X86, &code[3..8], 0xffff_fffb, 0xffff_fffb, None, 0);
+        assert_eq!(window[0].1.branch_target, Some(0));
+    }
+
+    #[test]
+    #[cfg(feature = "disasm_arm64")]
+    fn arm64_window() {
+        // stp x29, x30, [sp, #-0x10]!; mov x29, sp; bl +8; ldr x0, [x0];
+        // ldp x29, x30, [sp], #0x10; ret
+        let code = [
+            0xa9bf7bfdu32,
+            0x910003fd,
+            0x94000002,
+            0xf9400000,
+            0xa8c17bfd,
+            0xd65f03c0,
+        ]
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect::<Vec<_>>();
+        let start = 0x1000;
+
+        let window = decode_window(Cpu::Arm64, &code, start, 0x100c, None, 2);
+        let addresses = window
+            .iter()
+            .map(|(address, _)| *address)
+            .collect::<Vec<_>>();
+        assert_eq!(addresses, vec![0x1004, 0x1008, 0x100c, 0x1010, 0x1014]);
+        assert_eq!(window[1].1.branch_target, Some(0x1010));
+        assert_eq!(window[2].1.text, "ldr x0, [x0]");
+    }
+}
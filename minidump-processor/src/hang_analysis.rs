@@ -0,0 +1,552 @@
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
+
+use minidump::system_info::{Os, PointerWidth};
+use minidump::{MinidumpRawContext, UnifiedMemoryList};
+use minidump_unwind::{CallStack, StackFrame, SystemInfo};
+
+use crate::{HangAnalysis, ThreadWait, WaitKind};
+
+// # Analyzing hangs
+//
+// A hang dump is usually taken by a watchdog, so there's no exception to look at
+// and all the interesting information is in *where* the threads are. We look at
+// the top frames of every thread and see if any of them is a known wait primitive
+// (from a configurable table).
+//
+// For some primitives we also know the layout of the lock, and can read the id of
+// the thread owning it. The lock is the first argument of these primitives, so we
+// read it from the first argument register of the ABI (only known if the primitive
+// is the top frame, or the register was recovered) or the first word on the caller's
+// stack for x86. Reading an owner from anything else is asking for trouble: thread
+// ids are small integers, so almost any pointer eventually leads to something that
+// looks like one. Even then, a lock is only accepted if its fields are consistent
+// with a held lock, and the owner it implies is another thread in the dump.
+//
+// With lock owners we get a wait-for graph (thread -> owning thread), and any
+// cycle in it is a deadlock. Every thread waits on at most one lock, so every node
+// has at most one outgoing edge, which makes finding cycles straightforward.
+
+/// How many of the top frames of each thread to check for wait primitives.
+const MAX_WAIT_FRAMES: usize = 8;
+
+/// The layout of a lock, describing how to read its owner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockLayout {
+    /// A Windows `CRITICAL_SECTION` (`OwningThread` holds the owner's thread id).
+    CriticalSection,
+    /// A glibc `pthread_mutex_t` (`__owner` holds the owner's tid).
+    ///
+    /// This is also used for `__lll_lock_wait`, as `__lock` is the first field.
+    GlibcMutex,
+}
+
+impl LockLayout {
+    fn supports_os(&self, os: Os) -> bool {
+        match self {
+            LockLayout::CriticalSection => os == Os::Windows,
+            LockLayout::GlibcMutex => os == Os::Linux,
+        }
+    }
+
+    /// Read the owner of the lock at `address`, if it looks like a held lock of this layout.
+    fn read_owner(
+        &self,
+        memory_list: &UnifiedMemoryList,
+        pointer_width: PointerWidth,
+        address: u64,
+    ) -> Option<u32> {
+        let read_u32 = |addr: u64| {
+            memory_list
+                .memory_at_address(addr)?
+                .get_memory_at_address::<u32>(addr)
+        };
+        let read_ptr = |addr: u64| {
+            let memory = memory_list.memory_at_address(addr)?;
+            match pointer_width {
+                PointerWidth::Bits32 => memory.get_memory_at_address::<u32>(addr).map(u64::from),
+                _ => memory.get_memory_at_address::<u64>(addr),
+            }
+        };
+
+        match self {
+            LockLayout::CriticalSection => {
+                // DebugInfo, LockCount, RecursionCount, OwningThread, ...
+                let ptr_size = match pointer_width {
+                    PointerWidth::Bits32 => 4,
+                    _ => 8,
+                };
+                let debug_info = read_ptr(address)?;
+                let lock_count = read_u32(address.checked_add(ptr_size)?)? as i32;
+                let recursion_count = read_u32(address.checked_add(ptr_size + 4)?)? as i32;
+                let owner = read_ptr(address.checked_add(ptr_size + 8)?)?;
+                // A free critical section has a LockCount of -1, and a contended one is
+                // below that. A held one records its owner, so don't guess if OwningThread
+                // isn't set.
+                (lock_count < -1 && recursion_count != 0 && owner != 0).then_some(())?;
+                // DebugInfo is either in the dump (it's allocated by the loader), or -1 for
+                // critical sections initialized with CRITICAL_SECTION_NO_DEBUG_INFO.
+                let no_debug_info = match pointer_width {
+                    PointerWidth::Bits32 => u64::from(u32::MAX),
+                    _ => u64::MAX,
+                };
+                (debug_info == no_debug_info
+                    || memory_list.memory_at_address(debug_info).is_some())
+                .then_some(())?;
+                u32::try_from(owner).ok()
+            }
+            LockLayout::GlibcMutex => {
+                // __lock, __count, __owner, ...
+                let lock = read_u32(address)?;
+                let owner = read_u32(address.checked_add(8)?)?;
+                (lock != 0 && owner != 0).then_some(())?;
+                Some(owner)
+            }
+        }
+    }
+}
+
+/// A function which indicates that a thread is blocked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaitPrimitive {
+    /// The function name, matched exactly or followed by an argument list.
+    pub function: Cow<'static, str>,
+    /// The kind of primitive.
+    pub kind: WaitKind,
+    /// The layout of the lock passed to the function, if the owner can be read from it.
+    pub lock: Option<LockLayout>,
+}
+
+impl WaitPrimitive {
+    pub const fn new(function: &'static str, kind: WaitKind, lock: Option<LockLayout>) -> Self {
+        WaitPrimitive {
+            function: Cow::Borrowed(function),
+            kind,
+            lock,
+        }
+    }
+
+    /// Whether the symbolicated function `name` is this primitive.
+    fn matches(&self, name: &str) -> bool {
+        name.strip_prefix(&*self.function)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('('))
+    }
+}
+
+/// The wait primitives used by default.
+pub static DEFAULT_WAIT_PRIMITIVES: &[WaitPrimitive] = {
+    use LockLayout::{CriticalSection as CriticalSectionLock, GlibcMutex};
+    use WaitKind::*;
+    &[
+        // Windows
+        WaitPrimitive::new(
+            "RtlEnterCriticalSection",
+            CriticalSection,
+            Some(CriticalSectionLock),
+        ),
+        WaitPrimitive::new(
+            "EnterCriticalSection",
+            CriticalSection,
+            Some(CriticalSectionLock),
+        ),
+        WaitPrimitive::new(
+            "RtlpEnterCriticalSectionContended",
+            CriticalSection,
+            Some(CriticalSectionLock),
+        ),
+        WaitPrimitive::new(
+            "RtlpWaitOnCriticalSection",
+            CriticalSection,
+            Some(CriticalSectionLock),
+        ),
+        WaitPrimitive::new("AcquireSRWLockExclusive", RwLock, None),
+        WaitPrimitive::new("AcquireSRWLockShared", RwLock, None),
+        WaitPrimitive::new("RtlAcquireSRWLockExclusive", RwLock, None),
+        WaitPrimitive::new("RtlAcquireSRWLockShared", RwLock, None),
+        WaitPrimitive::new("SleepConditionVariableCS", ConditionVariable, None),
+        WaitPrimitive::new("SleepConditionVariableSRW", ConditionVariable, None),
+        WaitPrimitive::new("WaitForSingleObject", Object, None),
+        WaitPrimitive::new("WaitForSingleObjectEx", Object, None),
+        WaitPrimitive::new("WaitForMultipleObjects", Object, None),
+        WaitPrimitive::new("WaitForMultipleObjectsEx", Object, None),
+        WaitPrimitive::new("MsgWaitForMultipleObjects", Object, None),
+        WaitPrimitive::new("MsgWaitForMultipleObjectsEx", Object, None),
+        WaitPrimitive::new("NtWaitForSingleObject", Object, None),
+        WaitPrimitive::new("NtWaitForMultipleObjects", Object, None),
+        WaitPrimitive::new("NtWaitForKeyedEvent", Object, None),
+        WaitPrimitive::new("NtUserMsgWaitForMultipleObjectsEx", Object, None),
+        WaitPrimitive::new("NtWaitForAlertByThreadId", Address, None),
+        WaitPrimitive::new("RtlWaitOnAddress", Address, None),
+        WaitPrimitive::new("WaitOnAddress", Address, None),
+        WaitPrimitive::new("Sleep", Sleep, None),
+        WaitPrimitive::new("SleepEx", Sleep, None),
+        WaitPrimitive::new("NtDelayExecution", Sleep, None),
+        WaitPrimitive::new("GetQueuedCompletionStatus", Io, None),
+        WaitPrimitive::new("GetQueuedCompletionStatusEx", Io, None),
+        WaitPrimitive::new("NtRemoveIoCompletion", Io, None),
+        // Linux
+        WaitPrimitive::new("pthread_mutex_lock", Mutex, Some(GlibcMutex)),
+        WaitPrimitive::new("__pthread_mutex_lock", Mutex, Some(GlibcMutex)),
+        WaitPrimitive::new("___pthread_mutex_lock", Mutex, Some(GlibcMutex)),
+        WaitPrimitive::new("__pthread_mutex_lock_full", Mutex, Some(GlibcMutex)),
+        WaitPrimitive::new("__lll_lock_wait", Mutex, Some(GlibcMutex)),
+        WaitPrimitive::new("pthread_rwlock_rdlock", RwLock, None),
+        WaitPrimitive::new("pthread_rwlock_wrlock", RwLock, None),
+        WaitPrimitive::new("pthread_cond_wait", ConditionVariable, None),
+        WaitPrimitive::new("pthread_cond_timedwait", ConditionVariable, None),
+        WaitPrimitive::new("__pthread_cond_wait", ConditionVariable, None),
+        WaitPrimitive::new("___pthread_cond_timedwait64", ConditionVariable, None),
+        WaitPrimitive::new("futex_wait", Address, None),
+        WaitPrimitive::new("futex_wait_cancelable", Address, None),
+        WaitPrimitive::new("__futex_abstimed_wait_common", Address, None),
+        WaitPrimitive::new("__futex_abstimed_wait_cancelable64", Address, None),
+        WaitPrimitive::new("sem_wait", Object, None),
+        WaitPrimitive::new("nanosleep", Sleep, None),
+        WaitPrimitive::new("clock_nanosleep", Sleep, None),
+        WaitPrimitive::new("__clock_nanosleep", Sleep, None),
+        WaitPrimitive::new("usleep", Sleep, None),
+        WaitPrimitive::new("epoll_wait", Io, None),
+        WaitPrimitive::new("poll", Io, None),
+        WaitPrimitive::new("__poll", Io, None),
+        WaitPrimitive::new("select", Io, None),
+        // macOS
+        WaitPrimitive::new("__psynch_mutexwait", Mutex, None),
+        WaitPrimitive::new("_pthread_mutex_firstfit_lock_wait", Mutex, None),
+        WaitPrimitive::new("__psynch_rw_rdlock", RwLock, None),
+        WaitPrimitive::new("__psynch_rw_wrlock", RwLock, None),
+        WaitPrimitive::new("__psynch_cvwait", ConditionVariable, None),
+        WaitPrimitive::new("__ulock_wait", Address, None),
+        WaitPrimitive::new("__ulock_wait2", Address, None),
+        WaitPrimitive::new("__semwait_signal", Sleep, None),
+        WaitPrimitive::new("mach_msg_trap", Ipc, None),
+        WaitPrimitive::new("mach_msg2_trap", Ipc, None),
+        WaitPrimitive::new("kevent", Io, None),
+        WaitPrimitive::new("kevent64", Io, None),
+        WaitPrimitive::new("kevent_id", Io, None),
+        WaitPrimitive::new("__select", Io, None),
+    ]
+};
+
+/// Find which wait primitive `stack` is blocked in, returning it and the index of its frame.
+///
+/// If several of the top frames match, the outermost one wins, as higher-level functions
+/// (e.g. `EnterCriticalSection`) say more about what is being waited on than the system
+/// calls they end up in.
+fn find_wait<'p>(
+    stack: &CallStack,
+    primitives: &'p [WaitPrimitive],
+) -> Option<(usize, &'p WaitPrimitive)> {
+    stack
+        .frames
+        .iter()
+        .take(MAX_WAIT_FRAMES)
+        .enumerate()
+        .filter_map(|(idx, frame)| {
+            let names = frame
+                .inlines
+                .iter()
+                .map(|inline| inline.function_name.as_str())
+                .chain(frame.function_name.as_deref());
+            for name in names {
+                if let Some(primitive) = primitives.iter().find(|p| p.matches(name)) {
+                    return Some((idx, primitive));
+                }
+            }
+            None
+        })
+        .next_back()
+}
+
+/// Whether `frame` is a primitive with the lock layout `layout`.
+fn frame_has_lock(frame: &StackFrame, primitives: &[WaitPrimitive], layout: LockLayout) -> bool {
+    let names = frame
+        .inlines
+        .iter()
+        .map(|inline| inline.function_name.as_str())
+        .chain(frame.function_name.as_deref());
+    names
+        .filter_map(|name| primitives.iter().find(|p| p.matches(name)))
+        .any(|primitive| primitive.lock == Some(layout))
+}
+
+/// Read the first argument passed to the function of the frame at `frame_index`.
+///
+/// This is the first argument register of the ABI (if it's still known), or the
+/// first word on the caller's stack for x86.
+fn first_argument(
+    stack: &CallStack,
+    frame_index: usize,
+    memory_list: &UnifiedMemoryList,
+    os: Os,
+) -> Option<u64> {
+    let context = &stack.frames[frame_index].context;
+    let register = match context.raw {
+        MinidumpRawContext::Amd64(_) if os == Os::Windows => "rcx",
+        MinidumpRawContext::Amd64(_) => "rdi",
+        MinidumpRawContext::Arm64(_) | MinidumpRawContext::OldArm64(_) => "x0",
+        MinidumpRawContext::Arm(_) => "r0",
+        MinidumpRawContext::X86(_) => {
+            // cdecl and stdcall pass the lock at the top of the caller's stack.
+            let sp = stack
+                .frames
+                .get(frame_index + 1)?
+                .context
+                .get_stack_pointer();
+            return memory_list
+                .memory_at_address(sp)?
+                .get_memory_at_address::<u32>(sp)
+                .map(u64::from);
+        }
+        _ => return None,
+    };
+    context.get_register(register)
+}
+
+/// Try to find the lock a thread is blocked on and read its owner.
+///
+/// Only the lock argument of the frames which are a primitive with `layout` is
+/// considered, and the lock itself has to look plausible.
+fn find_lock_owner(
+    stack: &CallStack,
+    frame_index: usize,
+    layout: LockLayout,
+    primitives: &[WaitPrimitive],
+    memory_list: &UnifiedMemoryList,
+    system_info: &SystemInfo,
+    thread_ids: &HashMap<u32, usize>,
+) -> Option<(u64, u32)> {
+    let pointer_width = system_info.cpu.pointer_width();
+    stack.frames[..=frame_index]
+        .iter()
+        .enumerate()
+        .filter(|(_, frame)| frame_has_lock(frame, primitives, layout))
+        .filter_map(|(idx, _)| first_argument(stack, idx, memory_list, system_info.os))
+        .find_map(|address| {
+            let owner = layout.read_owner(memory_list, pointer_width, address)?;
+            (owner != stack.thread_id && thread_ids.contains_key(&owner))
+                .then_some((address, owner))
+        })
+}
+
+/// Find cycles in the wait-for graph given by `edges` (waiting thread -> owning thread).
+fn find_cycles(edges: &HashMap<u32, u32>) -> Vec<Vec<u32>> {
+    let mut cycles = Vec::new();
+    let mut visited = BTreeSet::new();
+
+    let mut starts = edges.keys().copied().collect::<Vec<_>>();
+    starts.sort_unstable();
+    for start in starts {
+        let mut path = Vec::new();
+        let mut node = start;
+        loop {
+            if let Some(pos) = path.iter().position(|&n| n == node) {
+                let mut cycle: Vec<u32> = path[pos..].to_vec();
+                // Start each cycle at its lowest thread id to make the output stable.
+                let min_pos = cycle
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, &tid)| tid)
+                    .map(|(idx, _)| idx)
+                    .unwrap_or_default();
+                cycle.rotate_left(min_pos);
+                cycles.push(cycle);
+                break;
+            }
+            if !visited.insert(node) {
+                break;
+            }
+            path.push(node);
+            match edges.get(&node) {
+                Some(&next) => node = next,
+                None => break,
+            }
+        }
+    }
+    cycles
+}
+
+/// Find the threads blocked on known wait primitives, and any deadlocks between them.
+pub fn analyze_waits(
+    threads: &[CallStack],
+    memory_list: &UnifiedMemoryList,
+    system_info: &SystemInfo,
+    primitives: &[WaitPrimitive],
+) -> Option<HangAnalysis> {
+    if primitives.is_empty() {
+        return None;
+    }
+
+    let thread_ids = threads
+        .iter()
+        .enumerate()
+        .map(|(idx, stack)| (stack.thread_id, idx))
+        .collect::<HashMap<_, _>>();
+
+    let mut waiting_threads = Vec::new();
+    for (thread_index, stack) in threads.iter().enumerate() {
+        let Some((frame_index, primitive)) = find_wait(stack, primitives) else {
+            continue;
+        };
+
+        let lock = primitive
+            .lock
+            .filter(|layout| layout.supports_os(system_info.os))
+            .and_then(|layout| {
+                find_lock_owner(
+                    stack,
+                    frame_index,
+                    layout,
+                    primitives,
+                    memory_list,
+                    system_info,
+                    &thread_ids,
+                )
+            });
+
+        waiting_threads.push(ThreadWait {
+            thread_index,
+            thread_id: stack.thread_id,
+            kind: primitive.kind,
+            function: primitive.function.clone(),
+            frame_index,
+            lock_address: lock.map(|(address, _)| address.into()),
+            lock_owner: lock.map(|(_, owner)| owner),
+        });
+    }
+
+    if waiting_threads.is_empty() {
+        return None;
+    }
+
+    let edges = waiting_threads
+        .iter()
+        .filter_map(|wait| Some((wait.thread_id, wait.lock_owner?)))
+        .collect::<HashMap<_, _>>();
+    let deadlocks = find_cycles(&edges);
+
+    Some(HangAnalysis {
+        waiting_threads,
+        deadlocks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minidump::format::CONTEXT_AMD64;
+    use minidump::{CpuContext, MinidumpContext, MinidumpMemory, MinidumpMemoryList};
+
+    #[test]
+    fn primitive_matching() {
+        let primitive = WaitPrimitive::new("pthread_mutex_lock", WaitKind::Mutex, None);
+        assert!(primitive.matches("pthread_mutex_lock"));
+        assert!(primitive.matches("pthread_mutex_lock(pthread_mutex_t*)"));
+        assert!(!primitive.matches("pthread_mutex_lock_full"));
+        assert!(!primitive.matches("__pthread_mutex_lock"));
+    }
+
+    fn memory_list(base_address: u64, bytes: &[u8]) -> UnifiedMemoryList<'_> {
+        let memory = MinidumpMemory {
+            desc: Default::default(),
+            base_address,
+            size: bytes.len() as u64,
+            bytes,
+            endian: scroll::LE,
+        };
+        UnifiedMemoryList::Memory(MinidumpMemoryList::from_regions(vec![memory]))
+    }
+
+    #[test]
+    fn critical_section_owner() {
+        let critical_section =
+            |debug_info: u64, lock_count: i32, recursion_count: i32, owner: u64| {
+                let mut bytes = Vec::new();
+                bytes.extend_from_slice(&debug_info.to_le_bytes());
+                bytes.extend_from_slice(&lock_count.to_le_bytes());
+                bytes.extend_from_slice(&recursion_count.to_le_bytes());
+                bytes.extend_from_slice(&owner.to_le_bytes());
+                bytes
+            };
+        let read_owner = |bytes: &[u8]| {
+            LockLayout::CriticalSection.read_owner(
+                &memory_list(0x1000, bytes),
+                PointerWidth::Bits64,
+                0x1000,
+            )
+        };
+
+        assert_eq!(read_owner(&critical_section(0x1000, -2, 1, 7)), Some(7));
+        assert_eq!(read_owner(&critical_section(u64::MAX, -2, 1, 7)), Some(7));
+        // Free
+        assert_eq!(read_owner(&critical_section(0x1000, -1, 0, 0)), None);
+        // Held, but the owner wasn't recorded
+        assert_eq!(read_owner(&critical_section(0x1000, -2, 1, 0)), None);
+        // Not a critical section at all
+        assert_eq!(read_owner(&critical_section(0x1000, -1, 1, 7)), None);
+        assert_eq!(read_owner(&critical_section(0x1000, -2, 0, 7)), None);
+        assert_eq!(read_owner(&critical_section(0, -2, 1, 7)), None);
+    }
+
+    #[test]
+    fn stray_lock_owner() {
+        // A held mutex owned by thread 2 at 0x1000, followed by a stray small
+        // integer which looks like thread 2's id at 0x1028.
+        let mut bytes = Vec::new();
+        for word in [1u32, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        let memory_list = memory_list(0x1000, &bytes);
+
+        let system_info = SystemInfo {
+            os: Os::Linux,
+            os_version: None,
+            os_build: None,
+            cpu: minidump::system_info::Cpu::X86_64,
+            cpu_info: None,
+            cpu_microcode_version: None,
+            cpu_count: 1,
+        };
+        let stack = |thread_id: u32, regs: &[(&str, u64)]| {
+            let mut raw = CONTEXT_AMD64::default();
+            for &(reg, value) in regs {
+                raw.set_register(reg, value).unwrap();
+            }
+            let context = MinidumpContext::from_raw(MinidumpRawContext::Amd64(raw));
+            let mut stack = CallStack::with_context(context);
+            stack.thread_id = thread_id;
+            stack.frames[0].function_name = Some("pthread_mutex_lock".to_owned());
+            stack
+        };
+        let lock_owner = |regs: &[(&str, u64)]| {
+            let threads = [stack(1, regs), stack(2, &[])];
+            analyze_waits(
+                &threads,
+                &memory_list,
+                &system_info,
+                DEFAULT_WAIT_PRIMITIVES,
+            )
+            .unwrap()
+            .waiting_threads[0]
+                .lock_owner
+        };
+
+        assert_eq!(lock_owner(&[("rdi", 0x1000)]), Some(2));
+        // Only the lock argument is considered, not other registers.
+        assert_eq!(lock_owner(&[("rdi", 0x2000), ("rbx", 0x1000)]), None);
+        // The argument points at something which isn't a held lock.
+        assert_eq!(lock_owner(&[("rdi", 0x1020)]), None);
+    }
+
+    #[test]
+    fn cycles() {
+        let edges = HashMap::from([(5, 3), (3, 9), (9, 5), (1, 3), (7, 8)]);
+        assert_eq!(find_cycles(&edges), vec![vec![3, 9, 5]]);
+
+        let edges = HashMap::from([(1, 2), (2, 1), (3, 4), (4, 3)]);
+        assert_eq!(find_cycles(&edges), vec![vec![1, 2], vec![3, 4]]);
+
+        let edges = HashMap::from([(1, 2), (2, 3)]);
+        assert!(find_cycles(&edges).is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+// # Hardware fault heuristics
+//
+// Some crashes aren't caused by software bugs at all, but by defective, overclocked
+// or buggy CPUs. These show up as crashes which make no sense: an address which is
+// one flipped bit away from a sensible one, or an instruction which can't have
+// caused the reported exception. Neither is conclusive on its own (our bit-flip and
+// instruction analyses have false positives too), but together with a CPU that is
+// known to misbehave they are a good hint that a crash isn't worth debugging.
+//
+// CPU models are identified by their x86 vendor, family and model (plus an optional
+// range of steppings), as reported by Linux's /proc/cpuinfo or by the minidump's
+// system info. Many errata are fixed (or mitigated) by a microcode update, so a
+// CPU whose microcode is known to be at least that version doesn't match.
+
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+
+use minidump::system_info::Cpu;
+use minidump::{MinidumpLinuxCpuInfo, MinidumpSystemInfo};
+use minidump_common::format as md;
+use scroll::{Pread, LE};
+
+/// The vendor, family, model and stepping of an x86 CPU.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CpuIdentity {
+    /// The CPUID vendor string (e.g. `GenuineIntel`), if known.
+    pub vendor: Option<String>,
+    pub family: u32,
+    pub model: u32,
+    pub stepping: u32,
+}
+
+impl CpuIdentity {
+    /// Identify the CPU from Linux's cpuinfo or, failing that, from the system info.
+    pub fn new(
+        system_info: &MinidumpSystemInfo,
+        linux_cpu_info: &MinidumpLinuxCpuInfo,
+    ) -> Option<Self> {
+        if !matches!(system_info.cpu, Cpu::X86 | Cpu::X86_64) {
+            return None;
+        }
+        Self::from_linux_cpu_info(linux_cpu_info).or_else(|| Self::from_system_info(system_info))
+    }
+
+    /// Read the first processor's entry of /proc/cpuinfo.
+    fn from_linux_cpu_info(linux_cpu_info: &MinidumpLinuxCpuInfo) -> Option<Self> {
+        let mut vendor = None;
+        let (mut family, mut model, mut stepping) = (None, None, None);
+        for (key, val) in linux_cpu_info.iter() {
+            let (Ok(key), Ok(val)) = (key.to_str(), val.to_str()) else {
+                continue;
+            };
+            let number = || val.trim().parse::<u32>().ok();
+            match key.trim() {
+                // The start of the second processor's entry.
+                "processor" if family.is_some() => break,
+                "vendor_id" => vendor = Some(val.trim().to_owned()),
+                "cpu family" => family = number(),
+                "model" => model = number(),
+                "stepping" => stepping = number(),
+                _ => {}
+            }
+        }
+        Some(CpuIdentity {
+            vendor,
+            family: family?,
+            model: model?,
+            stepping: stepping?,
+        })
+    }
+
+    fn from_system_info(system_info: &MinidumpSystemInfo) -> Option<Self> {
+        // Only 32-bit x86 dumps have the vendor (the rest of the union is processor
+        // features for everyone else).
+        let vendor = (system_info.cpu == Cpu::X86)
+            .then(|| {
+                system_info
+                    .raw
+                    .cpu
+                    .data
+                    .pread_with::<md::X86CpuInfo>(0, LE)
+                    .ok()
+            })
+            .flatten()
+            .map(|info| {
+                info.vendor_id
+                    .iter()
+                    .flat_map(|i| i.to_le_bytes())
+                    .map(char::from)
+                    .collect::<String>()
+            });
+        let revision = system_info.raw.processor_revision as u32;
+        Some(CpuIdentity {
+            vendor,
+            family: system_info.raw.processor_level as u32,
+            model: (revision >> 8) & 0xff,
+            stepping: revision & 0xff,
+        })
+    }
+}
+
+/// A known erratum of a CPU model which can make correct software crash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuErratum {
+    /// A short name for the erratum, reported in the output.
+    pub name: Cow<'static, str>,
+    /// The CPUID vendor string of the affected CPUs (e.g. `GenuineIntel`).
+    ///
+    /// The vendor isn't always known, in which case only the family and model are
+    /// compared (they don't overlap between Intel and AMD in practice).
+    pub vendor: Cow<'static, str>,
+    /// The affected family (as reported by the OS, including the extended family).
+    pub family: u32,
+    /// The affected model (as reported by the OS, including the extended model).
+    pub model: u32,
+    /// The affected steppings, or `None` for all of them.
+    pub steppings: Option<RangeInclusive<u32>>,
+    /// The first microcode version which fixes (or mitigates) the erratum, if any.
+    pub fixed_in_microcode: Option<u64>,
+}
+
+impl CpuErratum {
+    pub const fn new(
+        name: &'static str,
+        vendor: &'static str,
+        family: u32,
+        model: u32,
+        fixed_in_microcode: Option<u64>,
+    ) -> Self {
+        CpuErratum {
+            name: Cow::Borrowed(name),
+            vendor: Cow::Borrowed(vendor),
+            family,
+            model,
+            steppings: None,
+            fixed_in_microcode,
+        }
+    }
+
+    /// Whether `cpu` running `microcode_version` is affected by this erratum.
+    ///
+    /// If the microcode version is unknown, the CPU is assumed to be affected.
+    pub(crate) fn matches(&self, cpu: &CpuIdentity, microcode_version: Option<u64>) -> bool {
+        let vendor_matches = cpu.vendor.as_ref().is_none_or(|v| *v == self.vendor);
+        let stepping_matches = self
+            .steppings
+            .as_ref()
+            .is_none_or(|steppings| steppings.contains(&cpu.stepping));
+        let fixed = matches!(
+            (self.fixed_in_microcode, microcode_version),
+            (Some(fixed), Some(version)) if version >= fixed
+        );
+        vendor_matches
+            && cpu.family == self.family
+            && cpu.model == self.model
+            && stepping_matches
+            && !fixed
+    }
+}
+
+/// The CPU errata used by default.
+///
+/// Sources:
+/// * Intel's statement on 13th/14th generation desktop processor instability (Vmin shift),
+///   mitigated by microcode 0x12B
+pub static DEFAULT_CPU_ERRATA: &[CpuErratum] = &[
+    CpuErratum::new(
+        "intel-raptor-lake-vmin-shift",
+        "GenuineIntel",
+        6,
+        0xb7,
+        Some(0x12b),
+    ),
+    CpuErratum::new(
+        "intel-raptor-lake-vmin-shift",
+        "GenuineIntel",
+        6,
+        0xbf,
+        Some(0x12b),
+    ),
+];
+
+/// Find the names of the errata in `errata` which affect `cpu`.
+pub(crate) fn find_errata(
+    errata: &[CpuErratum],
+    cpu: &CpuIdentity,
+    microcode_version: Option<u64>,
+) -> Vec<Cow<'static, str>> {
+    let mut names = Vec::new();
+    for erratum in errata {
+        if erratum.matches(cpu, microcode_version) && !names.contains(&erratum.name) {
+            names.push(erratum.name.clone());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raptor_lake(vendor: Option<&str>) -> CpuIdentity {
+        CpuIdentity {
+            vendor: vendor.map(String::from),
+            family: 6,
+            model: 0xb7,
+            stepping: 1,
+        }
+    }
+
+    #[test]
+    fn erratum_matches() {
+        let erratum = &DEFAULT_CPU_ERRATA[0];
+        let cpu = raptor_lake(Some("GenuineIntel"));
+        assert!(erratum.matches(&cpu, Some(0x129)));
+        // Unknown microcode and vendor.
+        assert!(erratum.matches(&raptor_lake(None), None));
+        // Fixed by the microcode.
+        assert!(!erratum.matches(&cpu, Some(0x12b)));
+        assert!(!erratum.matches(&raptor_lake(Some("AuthenticAMD")), None));
+        assert!(!erratum.matches(&CpuIdentity { model: 0xba, ..cpu }, None));
+    }
+
+    #[test]
+    fn erratum_steppings() {
+        let erratum = CpuErratum {
+            steppings: Some(2..=3),
+            ..CpuErratum::new("test", "GenuineIntel", 6, 0xb7, None)
+        };
+        assert!(!erratum.matches(&raptor_lake(None), None));
+        let cpu = CpuIdentity {
+            stepping: 3,
+            ..raptor_lake(None)
+        };
+        assert!(erratum.matches(&cpu, None));
+    }
+
+    #[test]
+    fn errata_names_deduplicated() {
+        let cpu = raptor_lake(None);
+        assert_eq!(
+            find_errata(DEFAULT_CPU_ERRATA, &cpu, Some(0x129)),
+            vec![Cow::Borrowed("intel-raptor-lake-vmin-shift")]
+        );
+        assert!(find_errata(&[], &cpu, None).is_empty());
+    }
+}
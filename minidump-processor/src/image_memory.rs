@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+use minidump::{MinidumpModuleList, Module, UnifiedMemoryList};
+use minidump_unwind::{FileKind, SymbolProvider};
+use scroll::{Pread, LE};
+use tracing::trace;
+
+/// Reads memory of the crashing process, from the dump or from module binaries.
+///
+/// Code and read-only data are usually *not* captured in a minidump, so if an
+/// address isn't in the dump's memory we map it back into the binary of the
+/// module containing it (if the symbol provider can find it). PE and ELF
+/// binaries are supported.
+pub struct ImageMemory<'a, 'mdmp, P> {
+    memory_list: &'a UnifiedMemoryList<'mdmp>,
+    modules: &'a MinidumpModuleList,
+    symbol_provider: &'a P,
+    /// Loaded module binaries, keyed by module base address.
+    images: HashMap<u64, Option<ModuleImage<File>>>,
+}
+
+impl<'a, 'mdmp, P> ImageMemory<'a, 'mdmp, P>
+where
+    P: SymbolProvider + Sync,
+{
+    pub fn new(
+        memory_list: &'a UnifiedMemoryList<'mdmp>,
+        modules: &'a MinidumpModuleList,
+        symbol_provider: &'a P,
+    ) -> Self {
+        ImageMemory {
+            memory_list,
+            modules,
+            symbol_provider,
+            images: HashMap::new(),
+        }
+    }
+
+    /// The binary of the module at `base`, loading it on first use.
+    async fn image(&mut self, base: u64) -> Option<&mut ModuleImage<File>> {
+        if !self.images.contains_key(&base) {
+            let module = self.modules.module_at_address(base)?;
+            let image = match self
+                .symbol_provider
+                .get_file_path(module, FileKind::Binary)
+                .await
+            {
+                Ok(path) => File::open(&path).ok().and_then(ModuleImage::parse),
+                Err(e) => {
+                    trace!("no binary for {}: {e}", module.code_file());
+                    None
+                }
+            };
+            self.images.insert(base, image);
+        }
+        self.images.get_mut(&base)?.as_mut()
+    }
+
+    /// Read up to `max_len` contiguous bytes at `address`.
+    pub async fn read(&mut self, address: u64, max_len: usize) -> Option<Vec<u8>> {
+        Some(self.read_around(address, 0, max_len).await?.1)
+    }
+
+    /// Read the bytes from `before` bytes before `address` to `after` bytes after it,
+    /// clamped to the memory region or image section containing `address`.
+    ///
+    /// Returns the address of the first byte read and the bytes.
+    pub async fn read_around(
+        &mut self,
+        address: u64,
+        before: u64,
+        after: usize,
+    ) -> Option<(u64, Vec<u8>)> {
+        if let Some(memory) = self.memory_list.memory_at_address(address) {
+            let base = memory.base_address();
+            let start = address.saturating_sub(before).max(base);
+            let bytes = &memory.bytes()[(start - base) as usize..];
+            let len = ((address - start) as usize).saturating_add(after);
+            return Some((start, bytes[..bytes.len().min(len)].to_vec()));
+        }
+
+        let base = self.modules.module_at_address(address)?.base_address();
+        let image = self.image(base).await?;
+        let (rva, bytes) = image.bytes_around_rva(address - base, before, after)?;
+        Some((base + rva, bytes))
+    }
+
+    pub async fn read_u32(&mut self, address: u64) -> Option<u32> {
+        self.read(address, 4).await?.pread_with(0, LE).ok()
+    }
+
+    pub async fn read_u64(&mut self, address: u64) -> Option<u64> {
+        self.read(address, 8).await?.pread_with(0, LE).ok()
+    }
+
+    /// Read a NUL-terminated string of at most `max_len` bytes at `address`.
+    pub async fn read_cstr(&mut self, address: u64, max_len: usize) -> Option<String> {
+        let bytes = self.read(address, max_len).await?;
+        let len = bytes.iter().position(|&b| b == 0)?;
+        Some(String::from_utf8_lossy(&bytes[..len]).into_owned())
+    }
+}
+
+/// Just enough of a PE or ELF image to map RVAs to file contents.
+///
+/// Only the headers are read up front; section contents are read from the file on demand,
+/// so looking up a few values doesn't load an entire (possibly huge) binary into memory.
+struct ModuleImage<R> {
+    file: R,
+    /// `(virtual address, virtual size, file offset, file size)` of each section (PE)
+    /// or loadable segment (ELF), with virtual addresses relative to the image base.
+    sections: Vec<(u64, u64, u64, u64)>,
+}
+
+/// Read up to `len` bytes at `offset` of `file`.
+fn read_at<R: Read + Seek>(file: &mut R, offset: u64, len: usize) -> Option<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut bytes = Vec::new();
+    file.take(len as u64).read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+impl<R: Read + Seek> ModuleImage<R> {
+    fn parse(mut file: R) -> Option<Self> {
+        let ident = read_at(&mut file, 0, 4)?;
+        let sections = if ident == b"\x7fELF" {
+            Self::parse_elf(&mut file)?
+        } else {
+            Self::parse_pe(&mut file)?
+        };
+        Some(ModuleImage { file, sections })
+    }
+
+    fn parse_pe(file: &mut R) -> Option<Vec<(u64, u64, u64, u64)>> {
+        let pe_offset = read_at(file, 0x3c, 4)?.pread_with::<u32>(0, LE).ok()?;
+        let header = read_at(file, pe_offset as u64, 24)?;
+        if header.get(..4)? != b"PE\0\0" {
+            return None;
+        }
+        let section_count = header.pread_with::<u16>(4 + 2, LE).ok()?;
+        let optional_header_size = header.pread_with::<u16>(4 + 16, LE).ok()?;
+        let section_table = pe_offset as u64 + 24 + optional_header_size as u64;
+        let section_table = read_at(file, section_table, section_count as usize * 40)?;
+
+        (0..section_count as usize)
+            .map(|i| {
+                let header = i * 40;
+                let field = |offset| section_table.pread_with::<u32>(header + offset, LE).ok();
+                Some((
+                    field(12)? as u64,
+                    field(8)? as u64,
+                    field(20)? as u64,
+                    field(16)? as u64,
+                ))
+            })
+            .collect()
+    }
+
+    /// Only little-endian images are supported.
+    fn parse_elf(file: &mut R) -> Option<Vec<(u64, u64, u64, u64)>> {
+        const ELFCLASS64: u8 = 2;
+        const ELFDATA2LSB: u8 = 1;
+        const PT_LOAD: u32 = 1;
+
+        let data = read_at(file, 0, 0x40)?;
+        if *data.get(5)? != ELFDATA2LSB {
+            return None;
+        }
+        let is_64 = *data.get(4)? == ELFCLASS64;
+        let (phoff, phentsize, phnum) = if is_64 {
+            (
+                data.pread_with::<u64>(0x20, LE).ok()?,
+                data.pread_with::<u16>(0x36, LE).ok()?,
+                data.pread_with::<u16>(0x38, LE).ok()?,
+            )
+        } else {
+            (
+                data.pread_with::<u32>(0x1c, LE).ok()? as u64,
+                data.pread_with::<u16>(0x2a, LE).ok()?,
+                data.pread_with::<u16>(0x2c, LE).ok()?,
+            )
+        };
+        let headers_size = (phnum as usize).checked_mul(phentsize as usize)?;
+        let headers = read_at(file, phoff, headers_size)?;
+
+        let mut segments = Vec::new();
+        for i in 0..phnum as usize {
+            let header = i.checked_mul(phentsize as usize)?;
+            if headers.pread_with::<u32>(header, LE).ok()? != PT_LOAD {
+                continue;
+            }
+            let segment = if is_64 {
+                let field = |offset| {
+                    headers
+                        .pread_with::<u64>(header.checked_add(offset)?, LE)
+                        .ok()
+                };
+                (field(16)?, field(40)?, field(8)?, field(32)?)
+            } else {
+                let field = |offset| {
+                    headers
+                        .pread_with::<u32>(header.checked_add(offset)?, LE)
+                        .ok()
+                };
+                (
+                    field(8)? as u64,
+                    field(20)? as u64,
+                    field(4)? as u64,
+                    field(16)? as u64,
+                )
+            };
+            segments.push(segment);
+        }
+
+        // The module is mapped at the page containing its lowest segment.
+        let image_base = segments.iter().map(|&(va, ..)| va).min()? & !0xfff;
+        for segment in &mut segments {
+            segment.0 -= image_base;
+        }
+        Some(segments)
+    }
+
+    /// The file-backed part of the section containing `rva`, as `(rva, file offset, size)`.
+    fn file_range(&self, rva: u64) -> Option<(u64, u64, u64)> {
+        let &(va, _, file_offset, file_size) =
+            self.sections
+                .iter()
+                .find(|&&(va, virtual_size, _, file_size)| {
+                    rva >= va && rva - va < virtual_size.max(file_size)
+                })?;
+        if rva - va >= file_size {
+            // In the zero-filled tail of the section.
+            return None;
+        }
+        Some((va, file_offset, file_size))
+    }
+
+    /// Read the file contents from `before` bytes before `rva` to `after` bytes after it,
+    /// clamped to the section containing `rva`.
+    fn bytes_around_rva(&mut self, rva: u64, before: u64, after: usize) -> Option<(u64, Vec<u8>)> {
+        let (va, file_offset, file_size) = self.file_range(rva)?;
+        let start_rva = rva.saturating_sub(before).max(va);
+        let offset = start_rva - va;
+        let start = file_offset.checked_add(offset)?;
+        let max_len = ((rva - start_rva) as usize).saturating_add(after);
+        let len = usize::try_from(file_size - offset).map_or(max_len, |len| len.min(max_len));
+        let bytes = read_at(&mut self.file, start, len).filter(|bytes| !bytes.is_empty())?;
+        Some((start_rva, bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn pe_image_rva() {
+        // A DOS header pointing at a PE header with no optional header and a single
+        // `.rdata` section at RVA 0x2000, whose raw data is at file offset 0x200.
+        let mut data = vec![0u8; 0x210];
+        data[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        data[0x40..0x44].copy_from_slice(b"PE\0\0");
+        data[0x46..0x48].copy_from_slice(&1u16.to_le_bytes());
+        let section = 0x40 + 4 + 20;
+        data[section..section + 8].copy_from_slice(b".rdata\0\0");
+        data[section + 8..section + 12].copy_from_slice(&0x1000u32.to_le_bytes());
+        data[section + 12..section + 16].copy_from_slice(&0x2000u32.to_le_bytes());
+        data[section + 16..section + 20].copy_from_slice(&0x10u32.to_le_bytes());
+        data[section + 20..section + 24].copy_from_slice(&0x200u32.to_le_bytes());
+        data[0x200..0x210].copy_from_slice(b"hello, world!\0\0\0");
+
+        let mut image = ModuleImage::parse(Cursor::new(data)).unwrap();
+        assert_eq!(
+            image.bytes_around_rva(0x2007, 0, 0x100).unwrap().1,
+            b"world!\0\0\0"
+        );
+        // Clamped to the start of the section.
+        assert_eq!(
+            image.bytes_around_rva(0x2007, 0x100, 5).unwrap(),
+            (0x2000, b"hello, world".to_vec())
+        );
+        // Outside of any section.
+        assert_eq!(image.bytes_around_rva(0x1000, 0, 0x100), None);
+        // In the zero-filled tail of the section.
+        assert_eq!(image.bytes_around_rva(0x2100, 0, 0x100), None);
+    }
+
+    #[test]
+    fn elf_image_rva() {
+        // A non-PIE ELF64 with a single PT_LOAD segment at 0x401000, whose data is at
+        // file offset 0x100, in an image mapped at 0x401000.
+        let mut data = vec![0u8; 0x110];
+        data[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        data[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes());
+        data[0x36..0x38].copy_from_slice(&0x38u16.to_le_bytes());
+        data[0x38..0x3a].copy_from_slice(&1u16.to_le_bytes());
+        let phdr = 0x40;
+        data[phdr..phdr + 4].copy_from_slice(&1u32.to_le_bytes());
+        data[phdr + 8..phdr + 16].copy_from_slice(&0x100u64.to_le_bytes());
+        data[phdr + 16..phdr + 24].copy_from_slice(&0x401000u64.to_le_bytes());
+        data[phdr + 32..phdr + 40].copy_from_slice(&0x10u64.to_le_bytes());
+        data[phdr + 40..phdr + 48].copy_from_slice(&0x1000u64.to_le_bytes());
+        data[0x100..0x110].copy_from_slice(b"hello, world!\0\0\0");
+
+        let mut image = ModuleImage::parse(Cursor::new(data)).unwrap();
+        assert_eq!(
+            image.bytes_around_rva(0x7, 0, 0x100).unwrap().1,
+            b"world!\0\0\0"
+        );
+        // In the zero-filled tail of the segment.
+        assert_eq!(image.bytes_around_rva(0x100, 0, 0x100), None);
+        // Outside of any segment.
+        assert_eq!(image.bytes_around_rva(0x1000, 0, 0x100), None);
+    }
+
+    #[test]
+    fn malformed_elf_image() {
+        let mut data = vec![0u8; 0x110];
+        data[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        data[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes());
+        data[0x36..0x38].copy_from_slice(&0x38u16.to_le_bytes());
+        data[0x38..0x3a].copy_from_slice(&1u16.to_le_bytes());
+        let phdr = 0x40;
+        data[phdr..phdr + 4].copy_from_slice(&1u32.to_le_bytes());
+        data[phdr + 8..phdr + 16].copy_from_slice(&u64::MAX.to_le_bytes());
+        data[phdr + 16..phdr + 24].copy_from_slice(&0x401000u64.to_le_bytes());
+        data[phdr + 32..phdr + 40].copy_from_slice(&u64::MAX.to_le_bytes());
+        data[phdr + 40..phdr + 48].copy_from_slice(&0x1000u64.to_le_bytes());
+
+        // A segment whose file offset overflows when added to the RVA.
+        let mut image = ModuleImage::parse(Cursor::new(data.clone())).unwrap();
+        assert_eq!(image.bytes_around_rva(0x7, 0, 0x100), None);
+
+        // Program headers at the very end of the address space.
+        data[0x20..0x28].copy_from_slice(&(u64::MAX - 8).to_le_bytes());
+        data[0x38..0x3a].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(ModuleImage::parse(Cursor::new(data.clone())).is_none());
+
+        // Program headers which are larger than the file.
+        data[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes());
+        data[0x36..0x38].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(ModuleImage::parse(Cursor::new(data)).is_none());
+    }
+}
@@ -83,10 +83,23 @@ async fn main() -> Result<(), ()> {
 doc_comment::doctest!("../README.md");
 
 mod arg_recovery;
+mod cxx_exception;
+mod disassembly;
 mod evil;
+mod hang_analysis;
+mod hardware_fault;
+mod image_memory;
 mod op_analysis;
+mod process_parameters;
 mod process_state;
 mod processor;
+mod recursion;
+mod register_annotations;
+mod stack_memory_dump;
+mod stowed_exception;
 
+pub use crate::hang_analysis::{LockLayout, WaitPrimitive, DEFAULT_WAIT_PRIMITIVES};
+pub use crate::hardware_fault::{CpuErratum, DEFAULT_CPU_ERRATA};
+pub use crate::process_parameters::{DEFAULT_REDACTED_ENVIRONMENT_VARIABLES, REDACTED_VALUE};
 pub use crate::process_state::*;
 pub use crate::processor::*;
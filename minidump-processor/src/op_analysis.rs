@@ -10,6 +10,8 @@
 //! a list of currently available architectures and enabling features:
 //!
 //! - `disasm_amd64`: enable analysis of Amd64 instructions (on by default)
+//! - `disasm_x86`: enable analysis of 32-bit x86 instructions (on by default)
+//! - `disasm_arm64`: enable analysis of ARM64 instructions (on by default)
 //!
 //! The functions in this module will generally return `OpAnalysisError::UnsupportedCpuArch` if
 //! support for the target CPU is not available.
@@ -37,6 +39,10 @@ pub enum OpAnalysisError {
     /// An instruction accesses memory using a register with invalid contents
     #[error("a register used by the instruction had an invalid value")]
     RegisterInvalid,
+    /// An instruction accesses memory relative to a segment whose base is unknown
+    #[cfg(feature = "disasm_x86")]
+    #[error("the base of a segment used by the instruction is unknown")]
+    SegmentBaseUnknown,
 }
 
 /// The results of analyzing a CPU instruction
@@ -90,6 +96,36 @@ pub struct MemoryAccessList {
     pub accesses: Vec<MemoryAccess>,
 }
 
+impl MemoryAccessList {
+    /// Check if an access with a given address and type is on the list
+    pub fn contains_access(&self, address: u64, access_type: MemoryAccessType) -> bool {
+        self.iter().any(|access| {
+            let Some(size) = access.size else {
+                // We don't care about the rare case where access size is unknown
+                return false;
+            };
+            let lower_bound = access.address_info.address;
+            let (upper_bound, overflowed) =
+                access.address_info.address.overflowing_add(size as u64);
+            access.access_type == access_type
+                && match overflowed {
+                    true => lower_bound <= address || address < upper_bound,
+                    false => lower_bound <= address && address < upper_bound,
+                }
+        })
+    }
+
+    /// Get an iterator of the memory access vector
+    pub fn iter(&self) -> std::slice::Iter<'_, MemoryAccess> {
+        self.accesses.iter()
+    }
+
+    /// Check if there is any memory access in the list
+    pub fn is_empty(&self) -> bool {
+        self.accesses.is_empty()
+    }
+}
+
 /// Details about a memory access performed by an instruction
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct MemoryAccess {
@@ -171,12 +207,22 @@ impl std::fmt::Display for MemoryAccessType {
 ///
 /// Note that even if this function doesn't return an error, individual pieces of information
 /// may still be missing from the returned `OpAnalysis` structure.
+///
+/// `thread_environment_block` is the address of the thread's TEB on Windows, which is needed to
+/// resolve `fs`-relative memory accesses on 32-bit x86.
 pub fn analyze_thread_context(
     context: &MinidumpContext,
     memory_list: &minidump::UnifiedMemoryList,
     stack_memory: Option<UnifiedMemory>,
+    thread_environment_block: Option<u64>,
 ) -> Result<OpAnalysis, OpAnalysisError> {
     let instruction_bytes = get_thread_instruction_bytes(context, memory_list)?;
+    // The stack memory is only needed by the x86 and amd64 analyses, and the TEB only by
+    // the x86 one.
+    #[cfg(not(any(feature = "disasm_amd64", feature = "disasm_x86")))]
+    let _ = stack_memory;
+    #[cfg(not(feature = "disasm_x86"))]
+    let _ = thread_environment_block;
 
     match context.raw {
         #[cfg(feature = "disasm_amd64")]
@@ -186,6 +232,18 @@ pub fn analyze_thread_context(
             Some(memory_list),
             stack_memory,
         ),
+        #[cfg(feature = "disasm_x86")]
+        MinidumpRawContext::X86(_) => self::x86::analyze_instruction(
+            context,
+            instruction_bytes,
+            Some(memory_list),
+            stack_memory,
+            thread_environment_block,
+        ),
+        #[cfg(feature = "disasm_arm64")]
+        MinidumpRawContext::Arm64(_) | MinidumpRawContext::OldArm64(_) => {
+            self::arm64::analyze_instruction(context, instruction_bytes)
+        }
         _ => Err(OpAnalysisError::UnsupportedCpuArch),
     }
 }
@@ -381,34 +439,6 @@ mod amd64 {
             Ok(access_list)
         }
 
-        /// Check if an access with a given address and type is on the list
-        pub fn contains_access(&self, address: u64, access_type: MemoryAccessType) -> bool {
-            self.iter().any(|access| {
-                let Some(size) = access.size else {
-                    // We don't care about the rare case where access size is unknown
-                    return false;
-                };
-                let lower_bound = access.address_info.address;
-                let (upper_bound, overflowed) =
-                    access.address_info.address.overflowing_add(size as u64);
-                access.access_type == access_type
-                    && match overflowed {
-                        true => lower_bound <= address || address < upper_bound,
-                        false => lower_bound <= address && address < upper_bound,
-                    }
-            })
-        }
-
-        /// Get an iterator of the memory access vector
-        pub fn iter(&self) -> std::slice::Iter<'_, MemoryAccess> {
-            self.accesses.iter()
-        }
-
-        /// Check if there is any memory access in the list
-        pub fn is_empty(&self) -> bool {
-            self.accesses.is_empty()
-        }
-
         fn add_derivable_opcode_accesses(
             &mut self,
             opcode: AccessDerivableOpcode,
@@ -861,6 +891,733 @@ mod amd64 {
     }
 }
 
+/// Analysis tools for the 32-bit x86 architecture
+///
+/// This follows the Amd64 analysis, but addresses are 32 bits wide and segment-relative
+/// operands have to be resolved.
+#[cfg(feature = "disasm_x86")]
+mod x86 {
+    use super::*;
+    use yaxpeax_x86::protected_mode::{
+        DecodeError, InstDecoder, Instruction, Opcode, Operand, RegSpec, Segment,
+    };
+
+    /// x86-specific instruction analysis
+    ///
+    /// Uses yaxpeax-x86 to disassemble the given `instruction_bytes`, and then uses the registers
+    /// contained in `context` to determine useful information about the given instruction.
+    ///
+    /// `fs_base` is the base address of the `fs` segment (the thread's TEB on Windows), used
+    /// for `fs`-relative operands. Other segments are assumed to be flat, except for `gs`,
+    /// whose base we can't know.
+    pub fn analyze_instruction(
+        context: &MinidumpContext,
+        instruction_bytes: &[u8],
+        memory_list: Option<&minidump::UnifiedMemoryList>,
+        stack_memory: Option<minidump::UnifiedMemory>,
+        fs_base: Option<u64>,
+    ) -> Result<OpAnalysis, OpAnalysisError> {
+        let instruction = InstDecoder::default()
+            .decode_slice(instruction_bytes)
+            .map_err(|error| match error {
+                DecodeError::ExhaustedInput => OpAnalysisError::InstructionTruncated,
+                e => OpAnalysisError::DecodeFailed(e.into()),
+            })?;
+
+        let memory_access_list = memory_accesses(&instruction, context, fs_base)
+            .map_err(|e| tracing::warn!("failed to determine instruction memory access: {}", e))
+            .ok();
+
+        let instruction_pointer_update =
+            instruction_pointer_update(&instruction, context, fs_base, memory_list, stack_memory)
+                .map_err(|e| {
+                    tracing::warn!("failed to determine instruction pointer updates: {}", e)
+                })
+                .ok()
+                .flatten();
+
+        let mut registers = BTreeSet::new();
+        for idx in 0..instruction.operand_count() {
+            if let Some(info) = MemoryOperandInfo::from_operand(instruction.operand(idx)) {
+                registers.extend(info.base_reg.map(|reg| reg.name()));
+                registers.extend(info.index_reg.map(|reg| reg.name()));
+            }
+        }
+
+        Ok(OpAnalysis {
+            instruction_str: instruction.to_string(),
+            instruction_properties: InstructionProperties {
+                is_access_derivable: is_access_derivable(instruction.opcode()),
+                // TODO: check if the divisor is zero
+                is_division: matches!(instruction.opcode(), Opcode::DIV | Opcode::IDIV),
+                is_privileged: is_privileged(instruction.opcode()),
+                // Non-canonical addresses are an Amd64 thing.
+                is_only_gpf_when_non_canonical: false,
+            },
+            memory_access_list,
+            instruction_pointer_update,
+            registers,
+        })
+    }
+
+    /// Whether we know the memory access behaviour of every operand of `opcode`.
+    fn is_access_derivable(opcode: Opcode) -> bool {
+        operand_access_type(opcode, 0).is_some()
+    }
+
+    /// The direction of the memory access of operand `idx`, for the opcodes whose
+    /// behaviour we can derive, or `None` for the others.
+    ///
+    /// `lea` doesn't access memory at all, which is reported as `Some(None)`.
+    fn operand_access_type(opcode: Opcode, idx: u8) -> Option<Option<MemoryAccessType>> {
+        use MemoryAccessType::*;
+        let access_type = match opcode {
+            Opcode::MOV
+            | Opcode::MOVAPS
+            | Opcode::MOVUPS
+            | Opcode::MOVSS
+            | Opcode::MOVSD
+            | Opcode::MOVZX
+            | Opcode::MOVSX
+            | Opcode::POP => {
+                if idx == 0 {
+                    Write
+                } else {
+                    Read
+                }
+            }
+            Opcode::ADD
+            | Opcode::ADC
+            | Opcode::SUB
+            | Opcode::SBB
+            | Opcode::AND
+            | Opcode::OR
+            | Opcode::XOR
+            | Opcode::INC
+            | Opcode::DEC
+            | Opcode::NEG
+            | Opcode::NOT => {
+                if idx == 0 {
+                    ReadWrite
+                } else {
+                    Read
+                }
+            }
+            Opcode::CMP
+            | Opcode::TEST
+            | Opcode::UCOMISS
+            | Opcode::PUSH
+            | Opcode::CALL
+            | Opcode::CALLF
+            | Opcode::JMP
+            | Opcode::JMPF
+            | Opcode::RETURN
+            | Opcode::RETF => Read,
+            Opcode::LEA => return Some(None),
+            opcode if opcode.is_jcc() => Read,
+            _ => return None,
+        };
+        Some(Some(access_type))
+    }
+
+    // TODO: Use `yaxpeax` to check for all possible privileged instructions
+    fn is_privileged(opcode: Opcode) -> bool {
+        matches!(
+            opcode,
+            Opcode::CLI
+                | Opcode::CLTS
+                | Opcode::HLT
+                | Opcode::IN
+                | Opcode::INS
+                | Opcode::INT
+                | Opcode::INTO
+                | Opcode::INVD
+                | Opcode::INVLPG
+                | Opcode::IRET
+                | Opcode::IRETD
+                | Opcode::LGDT
+                | Opcode::LIDT
+                | Opcode::LLDT
+                | Opcode::LMSW
+                | Opcode::LTR
+                | Opcode::MONITOR
+                | Opcode::MWAIT
+                | Opcode::OUT
+                | Opcode::OUTS
+                | Opcode::RDMSR
+                | Opcode::RDPMC
+                | Opcode::STI
+                | Opcode::SYSEXIT
+                | Opcode::WBINVD
+                | Opcode::WRMSR
+                | Opcode::XSETBV
+        )
+    }
+
+    /// Determine the memory accesses implied by the given instruction and context
+    fn memory_accesses(
+        instruction: &Instruction,
+        context: &MinidumpContext,
+        fs_base: Option<u64>,
+    ) -> Result<MemoryAccessList, OpAnalysisError> {
+        let mut accesses = Vec::new();
+        // Shortcut -- If the instruction doesn't access memory, just return
+        let Some(mem_size) = instruction.mem_size().map(|size| size.bytes_size()) else {
+            return Ok(MemoryAccessList { accesses });
+        };
+
+        for idx in 0..instruction.operand_count() {
+            let access_type = match operand_access_type(instruction.opcode(), idx) {
+                Some(Some(access_type)) => access_type,
+                Some(None) => continue,
+                None => MemoryAccessType::Underivable,
+            };
+            if let Some(address_info) = address_info(instruction, idx, context, fs_base)? {
+                accesses.push(MemoryAccess {
+                    address_info,
+                    size: mem_size,
+                    access_type,
+                });
+            }
+        }
+
+        let mut push_implicit_access = |address: u64, access_type| {
+            accesses.push(MemoryAccess {
+                address_info: MemoryAddressInfo {
+                    address,
+                    is_likely_null_pointer_dereference: address == 0,
+                    is_likely_guard_page: false,
+                },
+                size: Some(4),
+                access_type,
+            })
+        };
+        match instruction.opcode() {
+            Opcode::CALL | Opcode::PUSH => {
+                if let Ok(esp) = context.get_regspec(RegSpec::esp()) {
+                    push_implicit_access(
+                        (esp as u32).wrapping_sub(4) as u64,
+                        MemoryAccessType::Write,
+                    );
+                }
+            }
+            Opcode::POP | Opcode::RETF | Opcode::RETURN => {
+                if let Ok(esp) = context.get_regspec(RegSpec::esp()) {
+                    push_implicit_access(esp, MemoryAccessType::Read);
+                }
+            }
+            _ => (),
+        }
+        Ok(MemoryAccessList { accesses })
+    }
+
+    fn instruction_pointer_update(
+        instruction: &Instruction,
+        context: &MinidumpContext,
+        fs_base: Option<u64>,
+        memory_list: Option<&minidump::UnifiedMemoryList>,
+        stack_memory: Option<minidump::UnifiedMemory>,
+    ) -> Result<Option<InstructionPointerUpdate>, OpAnalysisError> {
+        let eip_update = |address| {
+            Some(InstructionPointerUpdate::Update {
+                address_info: MemoryAddressInfo {
+                    address,
+                    is_likely_null_pointer_dereference: address == 0,
+                    is_likely_guard_page: false,
+                },
+            })
+        };
+
+        match instruction.opcode() {
+            Opcode::CALL | Opcode::CALLF | Opcode::JMP | Opcode::JMPF | Opcode::JMPE => {
+                // We assume that relative offsets (for CALL, JMP) and absolute values (CALLF,
+                // JMPF) will be valid, so we don't check immediate operands, only registers.
+                if let Operand::Register { reg } = instruction.operand(0) {
+                    return Ok(eip_update(context.get_regspec(reg)?));
+                }
+                // If the operand was some sort of register dereference, try to get the
+                // _actual_ address from the memory list.
+                if let Some(address_info) = address_info(instruction, 0, context, fs_base)? {
+                    if let Some(address) = memory_list
+                        .and_then(|ml| ml.memory_at_address(address_info.address))
+                        .and_then(|mem| mem.get_memory_at_address::<u32>(address_info.address))
+                    {
+                        return Ok(eip_update(address as u64));
+                    }
+                }
+                Ok(None)
+            }
+            Opcode::RETURN | Opcode::RETF | Opcode::IRET | Opcode::IRETD => {
+                // Use the return address (from the stack)
+                if let (Ok(esp), Some(stack)) = (context.get_regspec(RegSpec::esp()), &stack_memory)
+                {
+                    if let Some(address) = stack.get_memory_at_address::<u32>(esp) {
+                        return Ok(eip_update(address as u64));
+                    }
+                }
+                Ok(None)
+            }
+            // For `jcc` opcodes, eip update is left undetermined as it is cumbersome to determine
+            opcode if opcode.is_jcc() => Ok(None),
+            _ => Ok(Some(InstructionPointerUpdate::NoUpdate)),
+        }
+    }
+
+    /// The address accessed by memory operand `idx` of `instruction`, if it is one.
+    fn address_info(
+        instruction: &Instruction,
+        idx: u8,
+        context: &MinidumpContext,
+        fs_base: Option<u64>,
+    ) -> Result<Option<MemoryAddressInfo>, OpAnalysisError> {
+        let Some(op_info) = MemoryOperandInfo::from_operand(instruction.operand(idx)) else {
+            return Ok(None);
+        };
+
+        let segment_base = match instruction.segment_override_for_op(idx) {
+            Some(Segment::FS) => fs_base.ok_or(OpAnalysisError::SegmentBaseUnknown)?,
+            Some(Segment::GS) => return Err(OpAnalysisError::SegmentBaseUnknown),
+            _ => 0,
+        };
+
+        let mut address: u32 = 0;
+        let mut is_likely_null_pointer_dereference = false;
+        if let Some(reg) = op_info.base_reg {
+            let base = context.get_regspec(reg)? as u32;
+            address = base;
+            // If the base contains zero, this is very likely a dereference of a null pointer
+            // plus an offset
+            is_likely_null_pointer_dereference = base == 0 && segment_base == 0;
+        }
+        if let Some(reg) = op_info.index_reg {
+            let index = context.get_regspec(reg)? as u32;
+            let scale = op_info.scale.unwrap_or(1);
+            address = address.wrapping_add(index.wrapping_mul(scale.into()));
+        }
+        address = address
+            .wrapping_add(op_info.disp)
+            .wrapping_add(segment_base as u32);
+
+        Ok(Some(MemoryAddressInfo {
+            address: address as u64,
+            is_likely_null_pointer_dereference,
+            is_likely_guard_page: false,
+        }))
+    }
+
+    #[derive(Default)]
+    struct MemoryOperandInfo {
+        base_reg: Option<RegSpec>,
+        index_reg: Option<RegSpec>,
+        scale: Option<u8>,
+        disp: u32,
+    }
+
+    impl MemoryOperandInfo {
+        fn from_operand(op: Operand) -> Option<Self> {
+            let mut info = MemoryOperandInfo::default();
+            match op {
+                Operand::AbsoluteU16 { addr } => info.disp = addr as u32,
+                Operand::AbsoluteU32 { addr } => info.disp = addr,
+                Operand::MemDeref { base } => {
+                    info.base_reg = Some(base);
+                }
+                Operand::Disp { base, disp } => {
+                    info.base_reg = Some(base);
+                    info.disp = disp as u32;
+                }
+                Operand::MemIndexScale { index, scale } => {
+                    info.index_reg = Some(index);
+                    info.scale = Some(scale);
+                }
+                Operand::MemIndexScaleDisp { index, scale, disp } => {
+                    info.index_reg = Some(index);
+                    info.scale = Some(scale);
+                    info.disp = disp as u32;
+                }
+                Operand::MemBaseIndexScale { base, index, scale } => {
+                    info.base_reg = Some(base);
+                    info.index_reg = Some(index);
+                    info.scale = Some(scale);
+                }
+                Operand::MemBaseIndexScaleDisp {
+                    base,
+                    index,
+                    scale,
+                    disp,
+                } => {
+                    info.base_reg = Some(base);
+                    info.index_reg = Some(index);
+                    info.scale = Some(scale);
+                    info.disp = disp as u32;
+                }
+                _ => return None,
+            }
+            Some(info)
+        }
+    }
+
+    trait ContextExt {
+        fn get_regspec(&self, regspec: RegSpec) -> Result<u64, OpAnalysisError>;
+    }
+
+    impl ContextExt for MinidumpContext {
+        fn get_regspec(&self, regspec: RegSpec) -> Result<u64, OpAnalysisError> {
+            self.get_register(regspec.name())
+                .ok_or(OpAnalysisError::RegisterInvalid)
+        }
+    }
+}
+
+/// Analysis tools for the ARM64 architecture
+///
+/// Instructions are decoded with yaxpeax-arm. The memory accesses of scalar loads, stores
+/// and atomics are computed from their operands, while other instructions with a memory
+/// operand (like vector loads and stores) are only known to access memory somewhere.
+#[cfg(feature = "disasm_arm64")]
+mod arm64 {
+    use super::*;
+    use yaxpeax_arm::armv8::a64::{
+        DecodeError, InstDecoder, Instruction, Opcode, Operand, SIMDSizeCode, ShiftStyle, SizeCode,
+    };
+
+    /// Register names, as used by `MinidumpContext::get_register`.
+    const REGISTERS: [&str; 32] = [
+        "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11", "x12", "x13",
+        "x14", "x15", "x16", "x17", "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26",
+        "x27", "x28", "fp", "lr", "sp",
+    ];
+
+    /// ARM64-specific instruction analysis
+    ///
+    /// Uses yaxpeax-arm to disassemble the given `instruction_bytes`, and then uses the
+    /// registers contained in `context` to determine useful information about it.
+    pub fn analyze_instruction(
+        context: &MinidumpContext,
+        instruction_bytes: &[u8],
+    ) -> Result<OpAnalysis, OpAnalysisError> {
+        let instruction = decode_instruction(instruction_bytes)?;
+        let access = MemoryAccessKind::from_instruction(&instruction);
+
+        let memory_access_list = match &access {
+            MemoryAccessKind::None => Some(MemoryAccessList {
+                accesses: Vec::new(),
+            }),
+            MemoryAccessKind::Unknown => None,
+            MemoryAccessKind::Access { .. } => access
+                .accesses(&instruction, context)
+                .map_err(|e| tracing::warn!("failed to determine instruction memory access: {}", e))
+                .ok(),
+        };
+
+        let branch_register = branch_register(&instruction);
+        let instruction_pointer_update = match branch_register {
+            Some(reg) => get_register(context, reg, true)
+                .map_err(|e| {
+                    tracing::warn!("failed to determine instruction pointer updates: {}", e)
+                })
+                .ok()
+                .map(|address| InstructionPointerUpdate::Update {
+                    address_info: MemoryAddressInfo {
+                        address,
+                        is_likely_null_pointer_dereference: address == 0,
+                        is_likely_guard_page: false,
+                    },
+                }),
+            // Direct branches are assumed to be valid, and conditional ones are cumbersome
+            // to evaluate.
+            None if is_branch(instruction.opcode) => None,
+            None => Some(InstructionPointerUpdate::NoUpdate),
+        };
+
+        let mut registers = BTreeSet::new();
+        for operand in &instruction.operands {
+            match *operand {
+                Operand::RegPreIndex(base, ..) | Operand::RegPostIndex(base, _) => {
+                    registers.insert(REGISTERS[base as usize]);
+                }
+                Operand::RegRegOffset(base, index, ..) | Operand::RegPostIndexReg(base, index) => {
+                    registers.insert(REGISTERS[base as usize]);
+                    if index != 31 {
+                        registers.insert(REGISTERS[index as usize]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(reg) = branch_register {
+            registers.insert(REGISTERS[reg as usize]);
+        }
+
+        Ok(OpAnalysis {
+            instruction_str: instruction.to_string(),
+            instruction_properties: InstructionProperties {
+                is_access_derivable: !matches!(access, MemoryAccessKind::Unknown),
+                // Integer division by zero doesn't trap on ARM64.
+                is_division: false,
+                is_privileged: is_privileged(&instruction),
+                // Non-canonical addresses are an amd64 thing.
+                is_only_gpf_when_non_canonical: false,
+            },
+            memory_access_list,
+            instruction_pointer_update,
+            registers,
+        })
+    }
+
+    /// Decode the given ARM64 instruction using yaxpeax-arm
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the instruction could not be decoded, or because the given
+    /// byte buffer is shorter than an instruction.
+    fn decode_instruction(bytes: &[u8]) -> Result<Instruction, OpAnalysisError> {
+        use yaxpeax_arch::{Decoder, U8Reader};
+        let mut reader = U8Reader::new(bytes);
+        InstDecoder::default()
+            .decode(&mut reader)
+            .map_err(|error| match error {
+                DecodeError::ExhaustedInput => OpAnalysisError::InstructionTruncated,
+                e => OpAnalysisError::DecodeFailed(e.into()),
+            })
+    }
+
+    /// How an instruction accesses memory.
+    enum MemoryAccessKind {
+        None,
+        /// The instruction accesses memory, but we don't know how.
+        Unknown,
+        Access {
+            access_type: MemoryAccessType,
+            /// The size of each register's access, if it's not the size of the register.
+            size: Option<u8>,
+            /// 2 for pair loads and stores, which access consecutive memory.
+            count: u8,
+        },
+    }
+
+    impl MemoryAccessKind {
+        fn from_instruction(instruction: &Instruction) -> Self {
+            use MemoryAccessType::{Read, ReadWrite, Write};
+            use Opcode::*;
+
+            // Prefetches don't fault.
+            if matches!(instruction.opcode, PRFM | PRFUM) {
+                return MemoryAccessKind::None;
+            }
+            if memory_operand(instruction).is_none() {
+                return MemoryAccessKind::None;
+            }
+            let (access_type, size, count) = match instruction.opcode {
+                LDR | LDUR | LDTR | LDXR | LDAXR | LDAR | LDLAR | LDAPR | LDAPUR | LDRAA
+                | LDRAB => (Read, None, 1),
+                LDRB | LDRSB | LDTRB | LDTRSB | LDURB | LDURSB | LDXRB | LDAXRB | LDARB
+                | LDLARB | LDAPRB | LDAPURB | LDAPURSB => (Read, Some(1), 1),
+                LDRH | LDRSH | LDTRH | LDTRSH | LDURH | LDURSH | LDXRH | LDAXRH | LDARH
+                | LDLARH | LDAPRH | LDAPURH | LDAPURSH => (Read, Some(2), 1),
+                LDRSW | LDTRSW | LDURSW | LDAPURSW => (Read, Some(4), 1),
+                LDP | LDNP | LDXP | LDAXP => (Read, None, 2),
+                LDPSW => (Read, Some(4), 2),
+                STR | STUR | STTR | STXR | STLXR | STLR | STLLR | STLUR => (Write, None, 1),
+                STRB | STTRB | STURB | STXRB | STLXRB | STLRB | STLLRB | STLURB => {
+                    (Write, Some(1), 1)
+                }
+                STRH | STTRH | STURH | STXRH | STLXRH | STLRH | STLLRH | STLURH => {
+                    (Write, Some(2), 1)
+                }
+                STP | STNP | STXP | STLXP => (Write, None, 2),
+                SWP(_) | LDADD(_) | LDCLR(_) | LDEOR(_) | LDSET(_) | LDSMAX(_) | LDSMIN(_)
+                | LDUMAX(_) | LDUMIN(_) | CAS(_) => (ReadWrite, None, 1),
+                SWPB(_) | LDADDB(_) | LDCLRB(_) | LDEORB(_) | LDSETB(_) | LDSMAXB(_)
+                | LDSMINB(_) | LDUMAXB(_) | LDUMINB(_) | CASB(_) => (ReadWrite, Some(1), 1),
+                SWPH(_) | LDADDH(_) | LDCLRH(_) | LDEORH(_) | LDSETH(_) | LDSMAXH(_)
+                | LDSMINH(_) | LDUMAXH(_) | LDUMINH(_) | CASH(_) => (ReadWrite, Some(2), 1),
+                CASP(_) => (ReadWrite, None, 2),
+                // Vector and tag loads and stores.
+                _ => return MemoryAccessKind::Unknown,
+            };
+            MemoryAccessKind::Access {
+                access_type,
+                size,
+                count,
+            }
+        }
+
+        fn accesses(
+            &self,
+            instruction: &Instruction,
+            context: &MinidumpContext,
+        ) -> Result<MemoryAccessList, OpAnalysisError> {
+            let MemoryAccessKind::Access {
+                access_type,
+                size,
+                count,
+            } = *self
+            else {
+                return Ok(MemoryAccessList {
+                    accesses: Vec::new(),
+                });
+            };
+            let size = size
+                .or_else(|| data_register_size(instruction))
+                .ok_or(OpAnalysisError::RegisterInvalid)?;
+
+            let (base, address) = match memory_operand(instruction) {
+                Some(Operand::RegPreIndex(base, offset, _)) => {
+                    let base = get_register(context, base, true)?;
+                    (base, base.wrapping_add(offset as i64 as u64))
+                }
+                Some(Operand::RegPostIndex(base, _)) | Some(Operand::RegPostIndexReg(base, _)) => {
+                    let base = get_register(context, base, true)?;
+                    (base, base)
+                }
+                Some(Operand::RegRegOffset(base, index, index_size, extend, shift)) => {
+                    let base = get_register(context, base, true)?;
+                    let index = get_register(context, index, false)?;
+                    let index = match (extend, index_size) {
+                        (ShiftStyle::SXTW, _) => index as u32 as i32 as i64 as u64,
+                        (_, SizeCode::W) => index as u32 as u64,
+                        _ => index,
+                    };
+                    (base, base.wrapping_add(index << shift))
+                }
+                Some(Operand::PCOffset(offset)) => {
+                    let pc = context.get_instruction_pointer();
+                    (pc, pc.wrapping_add(offset as u64))
+                }
+                _ => return Err(OpAnalysisError::RegisterInvalid),
+            };
+
+            let accesses = (0..count as u64)
+                .map(|i| MemoryAccess {
+                    address_info: MemoryAddressInfo {
+                        address: address.wrapping_add(i * size as u64),
+                        // If the base contains zero, this is very likely a dereference of a
+                        // null pointer plus an offset
+                        is_likely_null_pointer_dereference: base == 0,
+                        is_likely_guard_page: false,
+                    },
+                    size: Some(size),
+                    access_type,
+                })
+                .collect();
+            Ok(MemoryAccessList { accesses })
+        }
+    }
+
+    /// The operand of `instruction` giving the address of its memory access, if any.
+    fn memory_operand(instruction: &Instruction) -> Option<Operand> {
+        instruction.operands.iter().copied().find(|operand| {
+            matches!(
+                operand,
+                Operand::RegPreIndex(..)
+                    | Operand::RegPostIndex(..)
+                    | Operand::RegPostIndexReg(..)
+                    | Operand::RegRegOffset(..)
+            ) || matches!(operand, Operand::PCOffset(_))
+                && matches!(instruction.opcode, Opcode::LDR | Opcode::LDRSW)
+        })
+    }
+
+    /// The size of the data register of a load or store: the last register before the
+    /// memory operand (the first ones may be status or comparison registers).
+    fn data_register_size(instruction: &Instruction) -> Option<u8> {
+        instruction
+            .operands
+            .iter()
+            .rev()
+            .find_map(|operand| match operand {
+                Operand::Register(SizeCode::X, _) | Operand::RegisterPair(SizeCode::X, _) => {
+                    Some(8)
+                }
+                Operand::Register(SizeCode::W, _) | Operand::RegisterPair(SizeCode::W, _) => {
+                    Some(4)
+                }
+                Operand::SIMDRegister(size, _) => Some(match size {
+                    SIMDSizeCode::B => 1,
+                    SIMDSizeCode::H => 2,
+                    SIMDSizeCode::S => 4,
+                    SIMDSizeCode::D => 8,
+                    SIMDSizeCode::Q => 16,
+                }),
+                _ => None,
+            })
+    }
+
+    /// The register holding the target of an indirect branch.
+    fn branch_register(instruction: &Instruction) -> Option<u16> {
+        match (instruction.opcode, instruction.operands[0]) {
+            (Opcode::BR | Opcode::BLR | Opcode::RET, Operand::Register(_, reg)) => Some(reg),
+            _ => None,
+        }
+    }
+
+    fn is_branch(opcode: Opcode) -> bool {
+        use Opcode::*;
+        matches!(
+            opcode,
+            B | BL
+                | Bcc(_)
+                | BCcc(_)
+                | CBZ
+                | CBNZ
+                | TBZ
+                | TBNZ
+                | BR
+                | BLR
+                | RET
+                | ERET
+                | DRPS
+                | BLRAA
+                | BLRAAZ
+                | BLRAB
+                | BLRABZ
+                | BRAA
+                | BRAAZ
+                | BRAB
+                | BRABZ
+                | RETAA
+                | RETAB
+                | ERETAA
+                | ERETAB
+        )
+    }
+
+    /// Whether `instruction` can't be executed at EL0.
+    ///
+    /// System register accesses and other system instructions are only allowed at EL0 if
+    /// their op1 is 3. Hints and barriers have their own opcodes, and are always allowed.
+    fn is_privileged(instruction: &Instruction) -> bool {
+        use Opcode::*;
+        match (instruction.opcode, instruction.operands[0]) {
+            (ERET | ERETAA | ERETAB | DRPS | HVC | SMC | DCPS1 | DCPS2 | DCPS3, _) => true,
+            (SYS(ops) | SYSL(ops), _) => ops.op1() != 3,
+            (MSR, Operand::PstateField(field)) => field >> 3 != 3,
+            (MSR, Operand::SystemReg(reg)) => (reg >> 11) & 0b111 != 3,
+            (MRS, _) => match instruction.operands[1] {
+                Operand::SystemReg(reg) => (reg >> 11) & 0b111 != 3,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Get the value of general purpose register `reg`, where 31 is either `sp` or the
+    /// zero register.
+    fn get_register(
+        context: &MinidumpContext,
+        reg: u16,
+        is_sp: bool,
+    ) -> Result<u64, OpAnalysisError> {
+        if reg == 31 && !is_sp {
+            return Ok(0);
+        }
+        context
+            .get_register(REGISTERS[reg as usize])
+            .ok_or(OpAnalysisError::RegisterInvalid)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[cfg(feature = "disasm_amd64")]
@@ -1188,4 +1945,390 @@ mod tests {
             access_test(&data);
         }
     }
+
+    #[cfg(feature = "disasm_x86")]
+    mod x86 {
+        use minidump::{format::CONTEXT_X86, CpuContext, MinidumpContext, MinidumpRawContext};
+
+        use crate::op_analysis::{MemoryAccessType, OpAnalysis, OpAnalysisError};
+
+        fn analyze(
+            bytes: &[u8],
+            regs: &[(&str, u32)],
+            fs_base: Option<u64>,
+        ) -> Result<OpAnalysis, OpAnalysisError> {
+            let mut context_raw = CONTEXT_X86::default();
+            for &(name, value) in regs.iter() {
+                assert_ne!(name, "eip", "you may not specify a value for 'eip'");
+                context_raw.set_register(name, value).unwrap();
+            }
+            let context = MinidumpContext::from_raw(MinidumpRawContext::X86(context_raw));
+            crate::op_analysis::x86::analyze_instruction(&context, bytes, None, None, fs_base)
+        }
+
+        /// The `(address, size, access type)` of each memory access of the instruction.
+        fn accesses(
+            bytes: &[u8],
+            regs: &[(&str, u32)],
+            fs_base: Option<u64>,
+        ) -> Vec<(u64, u8, MemoryAccessType)> {
+            analyze(bytes, regs, fs_base)
+                .unwrap()
+                .memory_access_list
+                .unwrap()
+                .iter()
+                .map(|access| {
+                    (
+                        access.address_info.address,
+                        access.size.unwrap(),
+                        access.access_type,
+                    )
+                })
+                .collect()
+        }
+
+        #[test]
+        fn test_reg_deref() {
+            // mov al, [ebx]
+            assert_eq!(
+                accesses(&[0x8a, 0x03], &[("ebx", 0xbadc0ffe)], None),
+                vec![(0xbadc0ffe, 1, MemoryAccessType::Read)]
+            );
+            // mov [ebx], eax
+            assert_eq!(
+                accesses(&[0x89, 0x03], &[("ebx", 0xbadc0ffe)], None),
+                vec![(0xbadc0ffe, 4, MemoryAccessType::Write)]
+            );
+        }
+
+        #[test]
+        fn test_base_index_scale_disp() {
+            // mov eax, [ebx + 0x10]
+            assert_eq!(
+                accesses(&[0x8b, 0x43, 0x10], &[("ebx", 0x1000)], None),
+                vec![(0x1010, 4, MemoryAccessType::Read)]
+            );
+            // mov eax, [ebx + ecx * 4 + 0x8]
+            assert_eq!(
+                accesses(
+                    &[0x8b, 0x44, 0x8b, 0x08],
+                    &[("ebx", 0x1000), ("ecx", 0x10)],
+                    None
+                ),
+                vec![(0x1048, 4, MemoryAccessType::Read)]
+            );
+            // Addresses wrap around at 32 bits: add dword [ebx + 0x20], 1
+            assert_eq!(
+                accesses(&[0x83, 0x43, 0x20, 0x01], &[("ebx", 0xfffffff0)], None),
+                vec![(0x10, 4, MemoryAccessType::ReadWrite)]
+            );
+        }
+
+        #[test]
+        fn test_segment_relative() {
+            // mov eax, fs:[0x18]
+            let mov_fs = [0x64, 0xa1, 0x18, 0x00, 0x00, 0x00];
+            assert_eq!(
+                accesses(&mov_fs, &[], Some(0x7ffde000)),
+                vec![(0x7ffde018, 4, MemoryAccessType::Read)]
+            );
+            // Without the TEB we can't tell where the access went.
+            let op_analysis = analyze(&mov_fs, &[], None).unwrap();
+            assert!(op_analysis.memory_access_list.is_none());
+
+            // mov eax, gs:[0x18]
+            let op_analysis = analyze(&[0x65, 0xa1, 0x18, 0x00, 0x00, 0x00], &[], None).unwrap();
+            assert!(op_analysis.memory_access_list.is_none());
+        }
+
+        #[test]
+        fn test_implicit_stack_accesses() {
+            // push dword [eax]
+            assert_eq!(
+                accesses(&[0xff, 0x30], &[("eax", 0x1000), ("esp", 0x8000)], None),
+                vec![
+                    (0x1000, 4, MemoryAccessType::Read),
+                    (0x7ffc, 4, MemoryAccessType::Write)
+                ]
+            );
+            // pop eax
+            assert_eq!(
+                accesses(&[0x58], &[("esp", 0x8000)], None),
+                vec![(0x8000, 4, MemoryAccessType::Read)]
+            );
+        }
+
+        #[test]
+        fn test_null_pointer() {
+            // mov eax, [ecx + 0x10]
+            let op_analysis = analyze(&[0x8b, 0x41, 0x10], &[("ecx", 0)], None).unwrap();
+            let access_list = op_analysis.memory_access_list.unwrap();
+            let access = access_list.iter().next().unwrap();
+            assert_eq!(access.address_info.address, 0x10);
+            assert!(access.address_info.is_likely_null_pointer_dereference);
+            assert!(op_analysis.registers.contains("ecx"));
+        }
+
+        #[test]
+        fn test_instruction_properties() {
+            // div ecx
+            let op_analysis = analyze(&[0xf7, 0xf1], &[], None).unwrap();
+            assert!(op_analysis.instruction_properties.is_division);
+            assert!(!op_analysis.instruction_properties.is_privileged);
+            // hlt
+            let op_analysis = analyze(&[0xf4], &[], None).unwrap();
+            assert!(op_analysis.instruction_properties.is_privileged);
+            assert!(
+                !op_analysis
+                    .instruction_properties
+                    .is_only_gpf_when_non_canonical
+            );
+            // Truncated instruction
+            assert!(matches!(
+                analyze(&[0x8b], &[], None),
+                Err(OpAnalysisError::InstructionTruncated)
+            ));
+        }
+    }
+
+    #[cfg(feature = "disasm_arm64")]
+    mod arm64 {
+        use crate::op_analysis::{InstructionPointerUpdate, MemoryAccessType, OpAnalysis};
+        use minidump::{format::CONTEXT_ARM64, CpuContext, MinidumpContext, MinidumpRawContext};
+
+        fn analyze(word: u32, regs: &[(&str, u64)]) -> OpAnalysis {
+            let mut context_raw = CONTEXT_ARM64 {
+                pc: 0x1000,
+                ..Default::default()
+            };
+            for &(name, value) in regs {
+                context_raw.set_register(name, value).unwrap();
+            }
+            let context = MinidumpContext::from_raw(MinidumpRawContext::Arm64(context_raw));
+            crate::op_analysis::arm64::analyze_instruction(&context, &word.to_le_bytes()).unwrap()
+        }
+
+        /// The `(address, size, access type)` of each memory access of `analysis`.
+        fn accesses(analysis: &OpAnalysis) -> Vec<(u64, u8, MemoryAccessType)> {
+            analysis
+                .memory_access_list
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|access| {
+                    (
+                        access.address_info.address,
+                        access.size.unwrap(),
+                        access.access_type,
+                    )
+                })
+                .collect()
+        }
+
+        #[test]
+        fn test_unsigned_offset() {
+            // ldr x0, [x1, #0x10]
+            let analysis = analyze(0xf9400820, &[("x1", 0x1000)]);
+            assert_eq!(analysis.instruction_str, "ldr x0, [x1, #0x10]");
+            assert_eq!(accesses(&analysis), [(0x1010, 8, MemoryAccessType::Read)]);
+            assert!(analysis.registers.contains("x1"));
+
+            // strb w0, [sp, #0x3]
+            let analysis = analyze(0x39000fe0, &[("sp", 0x2000)]);
+            assert_eq!(analysis.instruction_str, "strb w0, [sp, #0x3]");
+            assert_eq!(accesses(&analysis), [(0x2003, 1, MemoryAccessType::Write)]);
+
+            // ldr q0, [x2]
+            let analysis = analyze(0x3dc00040, &[("x2", 0x3000)]);
+            assert_eq!(analysis.instruction_str, "ldr q0, [x2]");
+            assert_eq!(accesses(&analysis), [(0x3000, 16, MemoryAccessType::Read)]);
+        }
+
+        #[test]
+        fn test_pre_post_index() {
+            // str w2, [x3, #-0x8]!
+            let analysis = analyze(0xb81f8c62, &[("x3", 0x1008)]);
+            assert_eq!(analysis.instruction_str, "str w2, [x3, #-0x8]!");
+            assert_eq!(accesses(&analysis), [(0x1000, 4, MemoryAccessType::Write)]);
+
+            // ldr x0, [x1], #0x8
+            let analysis = analyze(0xf8408420, &[("x1", 0x1000)]);
+            assert_eq!(analysis.instruction_str, "ldr x0, [x1], #0x8");
+            assert_eq!(accesses(&analysis), [(0x1000, 8, MemoryAccessType::Read)]);
+
+            // ldursw x0, [x1, #-0x4]
+            let analysis = analyze(0xb89fc020, &[("x1", 0x1004)]);
+            assert_eq!(analysis.instruction_str, "ldursw x0, [x1, #-0x4]");
+            assert_eq!(accesses(&analysis), [(0x1000, 4, MemoryAccessType::Read)]);
+        }
+
+        #[test]
+        fn test_register_offset() {
+            // ldr x0, [x1, x2, lsl #3]
+            let analysis = analyze(0xf8627820, &[("x1", 0x1000), ("x2", 0x10)]);
+            assert_eq!(analysis.instruction_str, "ldr x0, [x1, x2, lsl #3]");
+            assert_eq!(accesses(&analysis), [(0x1080, 8, MemoryAccessType::Read)]);
+            assert!(analysis.registers.contains("x2"));
+
+            // ldrb w0, [x1, w2, uxtw]
+            let analysis = analyze(0x38624820, &[("x1", 0x1000), ("x2", 0xffff_ffff_0000_0010)]);
+            assert_eq!(analysis.instruction_str, "ldrb w0, [x1, w2, uxtw]");
+            assert_eq!(accesses(&analysis), [(0x1010, 1, MemoryAccessType::Read)]);
+
+            // ldr w0, [x1, w2, sxtw #2]
+            let analysis = analyze(0xb862d820, &[("x1", 0x1000), ("x2", 0xffff_fffc)]);
+            assert_eq!(analysis.instruction_str, "ldr w0, [x1, w2, sxtw #2]");
+            assert_eq!(accesses(&analysis), [(0xff0, 4, MemoryAccessType::Read)]);
+        }
+
+        #[test]
+        fn test_pair() {
+            // stp x29, x30, [sp, #-0x10]!
+            let analysis = analyze(0xa9bf7bfd, &[("sp", 0x2010)]);
+            assert_eq!(analysis.instruction_str, "stp x29, x30, [sp, #-0x10]!");
+            assert_eq!(
+                accesses(&analysis),
+                [
+                    (0x2000, 8, MemoryAccessType::Write),
+                    (0x2008, 8, MemoryAccessType::Write)
+                ]
+            );
+
+            // ldp x29, x30, [sp], #0x10
+            let analysis = analyze(0xa8c17bfd, &[("sp", 0x2000)]);
+            assert_eq!(analysis.instruction_str, "ldp x29, x30, [sp], #0x10");
+            assert_eq!(
+                accesses(&analysis),
+                [
+                    (0x2000, 8, MemoryAccessType::Read),
+                    (0x2008, 8, MemoryAccessType::Read)
+                ]
+            );
+
+            // ldp w0, w1, [x2, #0x8]
+            let analysis = analyze(0x29410440, &[("x2", 0x1000)]);
+            assert_eq!(analysis.instruction_str, "ldp w0, w1, [x2, #0x8]");
+            assert_eq!(
+                accesses(&analysis),
+                [
+                    (0x1008, 4, MemoryAccessType::Read),
+                    (0x100c, 4, MemoryAccessType::Read)
+                ]
+            );
+        }
+
+        #[test]
+        fn test_literal_exclusive_atomic() {
+            // ldr x0, $+0x40
+            let analysis = analyze(0x58000200, &[]);
+            assert_eq!(analysis.instruction_str, "ldr x0, $+0x40");
+            assert_eq!(accesses(&analysis), [(0x1040, 8, MemoryAccessType::Read)]);
+
+            // ldxr x0, [x1]
+            let analysis = analyze(0xc85f7c20, &[("x1", 0x1000)]);
+            assert_eq!(analysis.instruction_str, "ldxr x0, [x1]");
+            assert_eq!(accesses(&analysis), [(0x1000, 8, MemoryAccessType::Read)]);
+
+            // stlr w0, [x1]
+            let analysis = analyze(0x889ffc20, &[("x1", 0x1000)]);
+            assert_eq!(analysis.instruction_str, "stlr w0, [x1]");
+            assert_eq!(accesses(&analysis), [(0x1000, 4, MemoryAccessType::Write)]);
+
+            // ldadd w1, w2, [x0]
+            let analysis = analyze(0xb8210002, &[("x0", 0x1000)]);
+            assert_eq!(analysis.instruction_str, "ldadd w1, w2, [x0]");
+            assert_eq!(
+                accesses(&analysis),
+                [(0x1000, 4, MemoryAccessType::ReadWrite)]
+            );
+
+            // casal x0, x1, [x2]
+            let analysis = analyze(0xc8e0fc41, &[("x2", 0x1000)]);
+            assert_eq!(analysis.instruction_str, "casal x0, x1, [x2]");
+            assert_eq!(
+                accesses(&analysis),
+                [(0x1000, 8, MemoryAccessType::ReadWrite)]
+            );
+        }
+
+        #[test]
+        fn test_null_pointer() {
+            // ldr x0, [x1, #0x10]
+            let analysis = analyze(0xf9400820, &[("x1", 0)]);
+            let access = analysis.memory_access_list.unwrap().accesses[0];
+            assert_eq!(access.address_info.address, 0x10);
+            assert!(access.address_info.is_likely_null_pointer_dereference);
+        }
+
+        #[test]
+        fn test_non_memory() {
+            // add x0, x1, x2
+            let analysis = analyze(0x8b020020, &[]);
+            assert!(analysis.memory_access_list.unwrap().is_empty());
+            assert!(analysis.instruction_properties.is_access_derivable);
+            assert!(matches!(
+                analysis.instruction_pointer_update,
+                Some(InstructionPointerUpdate::NoUpdate)
+            ));
+
+            // ld1 {v0.16b}, [x0] isn't decoded
+            let analysis = analyze(0x4c407000, &[]);
+            assert!(analysis.memory_access_list.is_none());
+            assert!(!analysis.instruction_properties.is_access_derivable);
+        }
+
+        #[test]
+        fn test_branches() {
+            // br x16
+            let analysis = analyze(0xd61f0200, &[("x16", 0xdead_0000)]);
+            assert_eq!(analysis.instruction_str, "br x16");
+            assert!(matches!(
+                analysis.instruction_pointer_update,
+                Some(InstructionPointerUpdate::Update { address_info })
+                    if address_info.address == 0xdead_0000
+            ));
+
+            // ret
+            let analysis = analyze(0xd65f03c0, &[("lr", 0)]);
+            assert_eq!(analysis.instruction_str, "ret");
+            assert!(matches!(
+                analysis.instruction_pointer_update,
+                Some(InstructionPointerUpdate::Update { address_info })
+                    if address_info.is_likely_null_pointer_dereference
+            ));
+
+            // bl $-0x10
+            let analysis = analyze(0x97fffffc, &[]);
+            assert_eq!(analysis.instruction_str, "bl $-0x10");
+            assert!(analysis.instruction_pointer_update.is_none());
+        }
+
+        #[test]
+        fn test_privileged() {
+            // msr sctlr_el1, x0
+            assert!(
+                analyze(0xd5181000, &[])
+                    .instruction_properties
+                    .is_privileged
+            );
+            // mrs x0, tpidr_el0
+            assert!(
+                !analyze(0xd53bd040, &[])
+                    .instruction_properties
+                    .is_privileged
+            );
+            // dmb ish
+            assert!(
+                !analyze(0xd5033bbf, &[])
+                    .instruction_properties
+                    .is_privileged
+            );
+            // eret
+            assert!(
+                analyze(0xd69f03e0, &[])
+                    .instruction_properties
+                    .is_privileged
+            );
+        }
+    }
 }
@@ -0,0 +1,87 @@
+use minidump::system_info::Cpu;
+use minidump::{
+    MinidumpProcessEnvironmentBlock, MinidumpProcessParameters, MinidumpThreadList,
+    UnifiedMemoryList,
+};
+use tracing::debug;
+
+use crate::ProcessorOptions;
+
+/// The value reported in place of redacted environment variables.
+pub const REDACTED_VALUE: &str = "<redacted>";
+
+/// Environment variables whose values are likely to be secrets.
+///
+/// A variable is redacted if its name contains any of these, ignoring case.
+pub static DEFAULT_REDACTED_ENVIRONMENT_VARIABLES: &[&str] = &[
+    "PASSWORD",
+    "PASSWD",
+    "SECRET",
+    "TOKEN",
+    "API_KEY",
+    "APIKEY",
+    "ACCESS_KEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+    "AUTH",
+    "COOKIE",
+    "SESSION",
+];
+
+/// Read the process parameters from the PEB, dropping or redacting the sensitive parts
+/// according to `options`.
+pub fn read_process_parameters(
+    threads: &MinidumpThreadList,
+    cpu: Cpu,
+    memory: &UnifiedMemoryList,
+    options: &ProcessorOptions,
+) -> Option<MinidumpProcessParameters> {
+    let peb = MinidumpProcessEnvironmentBlock::read(threads, cpu, memory)?;
+    debug!("found PEB at {:#x} (wow64: {})", peb.address, peb.wow64);
+    let mut params = peb.process_parameters?;
+
+    if !options.report_command_line {
+        params.command_line = None;
+    }
+    if options.report_environment {
+        if let Some(environment) = &mut params.environment {
+            redact_environment(environment, options.redacted_environment_variables);
+        }
+    } else {
+        params.environment = None;
+    }
+    Some(params)
+}
+
+fn redact_environment(environment: &mut [(String, String)], redacted: &[&str]) {
+    for (name, value) in environment {
+        let name = name.to_ascii_uppercase();
+        if redacted
+            .iter()
+            .any(|pattern| name.contains(&pattern.to_ascii_uppercase()))
+        {
+            *value = REDACTED_VALUE.to_owned();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redaction() {
+        let mut environment = vec![
+            ("PATH".to_owned(), "C:\\bin".to_owned()),
+            ("GitHub_Token".to_owned(), "hunter2".to_owned()),
+            ("DB_PASSWORD".to_owned(), "hunter2".to_owned()),
+        ];
+        redact_environment(&mut environment, DEFAULT_REDACTED_ENVIRONMENT_VARIABLES);
+        assert_eq!(environment[0].1, "C:\\bin");
+        assert_eq!(environment[1].1, REDACTED_VALUE);
+        assert_eq!(environment[2].1, REDACTED_VALUE);
+
+        redact_environment(&mut environment[..1], &["path"]);
+        assert_eq!(environment[0].1, REDACTED_VALUE);
+    }
+}
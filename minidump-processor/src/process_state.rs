@@ -229,6 +229,20 @@ pub struct ExceptionInfo {
     pub possible_bit_flips: Vec<PossibleBitFlip>,
     /// Whether the crash reason/address is inconsistent with crashing instruction and memory info
     pub inconsistencies: Vec<CrashInconsistency>,
+    /// Crash-related values which match a known allocator poison pattern.
+    ///
+    /// These are a strong hint that the crash was caused by a use-after-free or use of
+    /// uninitialized memory.
+    pub poison_pattern_matches: Vec<PoisonPatternMatch>,
+    /// Whether the crash is likely a stack overflow.
+    ///
+    /// This is set when the crashing access is just below the stack pointer, in the
+    /// stack's guard region (or when the crash reason explicitly says so).
+    pub is_likely_stack_overflow: bool,
+    /// The decoded C++ exception, if the crash is an unhandled MSVC C++ exception.
+    pub cxx_exception: Option<CxxException>,
+    /// Signs that the crash was caused by faulty hardware, if there are any.
+    pub hardware_fault: Option<HardwareFault>,
 }
 
 /// Info about a memory address that was adjusted from its reported value
@@ -300,6 +314,11 @@ mod confidence {
     // Detractors
     pub const POISON: f32 = MEDIUM;
     pub const ORIGINAL_LOW: f32 = MEDIUM;
+
+    // Hardware faults
+    pub const CPU_ERRATUM: f32 = MEDIUM;
+    pub const IMPOSSIBLE_INSTRUCTION: f32 = MEDIUM;
+    pub const ACCESS_NOT_FOUND: f32 = LOW;
 }
 
 impl BitFlipDetails {
@@ -347,6 +366,80 @@ pub struct PossibleBitFlip {
     pub confidence: Option<f32>,
 }
 
+/// Signs that a crash was caused by faulty hardware rather than by a software bug.
+///
+/// This is only reported for CPUs with a known erratum, or when the crashing instruction
+/// can't have caused the crash. A possible bit flip of the crash address raises the
+/// confidence, but isn't enough on its own.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct HardwareFault {
+    /// The known errata of the CPU which its microcode version doesn't fix.
+    ///
+    /// See [`ProcessorOptions::cpu_errata`][crate::ProcessorOptions::cpu_errata].
+    pub cpu_errata: Vec<Cow<'static, str>>,
+    /// The highest confidence among the possible bit flips of the crashing address.
+    pub bit_flip_confidence: Option<f32>,
+    /// Whether the crashing instruction can't have caused the crash.
+    ///
+    /// This is the case for the crash inconsistencies other than
+    /// [`CrashInconsistency::CrashingAccessNotFoundInMemoryAccesses`], which is a weaker signal.
+    pub impossible_instruction: bool,
+    /// Whether the crash address isn't accessed by the crashing instruction.
+    pub crashing_access_not_found: bool,
+    /// A confidence level between 0 and 1 for the crash being a hardware fault.
+    pub confidence: f32,
+}
+
+impl HardwareFault {
+    /// Combine the hardware fault signals, or return `None` if there is neither a CPU
+    /// erratum nor an impossible instruction.
+    pub fn new(
+        cpu_errata: Vec<Cow<'static, str>>,
+        possible_bit_flips: &[PossibleBitFlip],
+        inconsistencies: &[CrashInconsistency],
+    ) -> Option<Self> {
+        use confidence::*;
+
+        let bit_flip_confidence = possible_bit_flips
+            .iter()
+            .filter_map(|b| b.confidence)
+            .max_by(f32::total_cmp);
+        let crashing_access_not_found = inconsistencies.iter().any(|i| {
+            matches!(
+                i,
+                CrashInconsistency::CrashingAccessNotFoundInMemoryAccesses
+            )
+        });
+        let impossible_instruction = inconsistencies.iter().any(|i| {
+            !matches!(
+                i,
+                CrashInconsistency::CrashingAccessNotFoundInMemoryAccesses
+            )
+        });
+        if cpu_errata.is_empty() && !impossible_instruction {
+            return None;
+        }
+
+        let mut values = Vec::with_capacity(4);
+        values.extend(cpu_errata.iter().map(|_| CPU_ERRATUM));
+        values.extend(bit_flip_confidence);
+        if impossible_instruction {
+            values.push(IMPOSSIBLE_INSTRUCTION);
+        }
+        if crashing_access_not_found {
+            values.push(ACCESS_NOT_FOUND);
+        }
+
+        Some(HardwareFault {
+            cpu_errata,
+            bit_flip_confidence,
+            impossible_instruction,
+            crashing_access_not_found,
+            confidence: combine(&values),
+        })
+    }
+}
+
 /// The maximum distance between addresses to consider them "nearby" when calculating bit flip
 /// heuristics with regard to register contents.
 const NEARBY_REGISTER_DISTANCE: u64 = 1 << 12;
@@ -422,6 +515,247 @@ impl PossibleBitFlip {
     }
 }
 
+/// The state of memory that an allocator poison pattern indicates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoisonKind {
+    /// The memory was freed (i.e. a use-after-free).
+    Freed,
+    /// The memory was allocated but never initialized.
+    Uninitialized,
+    /// The memory is padding/guard bytes around an allocation (i.e. a buffer overflow).
+    Guard,
+    /// The pattern is a well-known marker without a specific meaning.
+    Unknown,
+}
+
+impl std::fmt::Display for PoisonKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PoisonKind::Freed => "freed memory",
+            PoisonKind::Uninitialized => "uninitialized memory",
+            PoisonKind::Guard => "guard memory",
+            PoisonKind::Unknown => "unknown",
+        })
+    }
+}
+
+/// Where a value matching a poison pattern was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoisonSource {
+    /// The crash address.
+    CrashAddress,
+    /// The adjusted crash address (see [`AdjustedAddress`]).
+    AdjustedAddress,
+    /// A register used by the crashing instruction.
+    Register,
+}
+
+/// A crash-related value which matched an allocator poison pattern (see
+/// [`ProcessorOptions::poison_patterns`][crate::ProcessorOptions::poison_patterns]).
+///
+/// Reported in [`ExceptionInfo::poison_pattern_matches`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct PoisonPatternMatch {
+    /// The value which matched the poison pattern.
+    pub value: Address,
+    /// Where the value was found.
+    pub source: PoisonSource,
+    /// The register which held the value, if `source` is [`PoisonSource::Register`].
+    pub source_register: Option<&'static str>,
+    /// The name of the matched pattern (e.g. "mozjemalloc-freed").
+    pub pattern: Cow<'static, str>,
+    /// What the matched pattern indicates about the memory.
+    pub kind: PoisonKind,
+}
+
+/// What a register of the crashing thread's context points to.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RegisterAnnotation {
+    /// The name of the register.
+    ///
+    /// This isn't serialized, since annotations are output keyed by register.
+    #[serde(skip)]
+    pub register: &'static str,
+    /// The value of the register.
+    pub value: Address,
+    /// What the value points to.
+    #[serde(flatten)]
+    pub target: RegisterTarget,
+}
+
+/// What a register or stack word points to, see [`RegisterAnnotation`] and [`StackMemoryDump`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RegisterTarget {
+    /// Null, or close enough to it to likely be a null pointer with an offset.
+    Null,
+    /// A known allocator poison pattern.
+    Poison {
+        /// The name of the matched pattern (e.g. "mozjemalloc-freed").
+        pattern: Cow<'static, str>,
+        /// What the matched pattern indicates about the memory.
+        poison_kind: PoisonKind,
+    },
+    /// A non-canonical address, which can't be dereferenced.
+    NonCanonical,
+    /// An address in the stack of a thread.
+    Stack {
+        /// The index of the thread in [`ProcessState::threads`].
+        threads_index: usize,
+        thread_id: u32,
+    },
+    /// An address in a loaded module.
+    Module {
+        /// The base name of the module.
+        module: String,
+        module_offset: Address,
+        /// The function containing the address, if the module has symbols.
+        function: Option<String>,
+        function_offset: Option<Address>,
+    },
+    /// An address in some other mapped region of memory.
+    Mapped {
+        /// The protection of the region, like "rw-".
+        protection: String,
+        /// The name of the region (e.g. the file backing it), if known.
+        name: Option<String>,
+    },
+}
+
+impl std::fmt::Display for RegisterTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegisterTarget::Null => write!(f, "null"),
+            RegisterTarget::Poison {
+                pattern,
+                poison_kind,
+            } => write!(f, "poison {pattern} ({poison_kind})"),
+            RegisterTarget::NonCanonical => write!(f, "non-canonical"),
+            RegisterTarget::Stack {
+                threads_index,
+                thread_id,
+            } => write!(f, "stack of thread {threads_index} (tid {thread_id})"),
+            RegisterTarget::Module {
+                module,
+                module_offset,
+                function,
+                function_offset,
+            } => match (function, function_offset) {
+                (Some(function), Some(offset)) => {
+                    write!(f, "{module}!{function} + {:#x}", offset.0)
+                }
+                _ => write!(f, "{module} + {:#x}", module_offset.0),
+            },
+            RegisterTarget::Mapped { protection, name } => {
+                write!(f, "mapped {protection}")?;
+                if let Some(name) = name {
+                    write!(f, " {name}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The stack memory of the crashing thread, annotated word by word.
+///
+/// This is like WinDbg's `dps`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct StackMemoryDump {
+    /// The index of the thread in [`ProcessState::threads`].
+    pub threads_index: usize,
+    /// The pointer-sized words of the stack, starting at the stack pointer.
+    pub words: Vec<StackWord>,
+}
+
+/// One word of a [`StackMemoryDump`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct StackWord {
+    /// The address of this word in the stack.
+    pub address: Address,
+    /// The contents of this word.
+    pub value: Address,
+    /// The index of the frame whose part of the stack this word is in.
+    ///
+    /// A frame's part of the stack starts at its stack pointer and ends at its caller's.
+    /// Words in the parts of the stack of frames dropped by
+    /// [`ProcessorOptions::collapse_recursion`][crate::ProcessorOptions] have no frame.
+    pub frame: Option<usize>,
+    /// Whether this word is the return address to the caller of `frame`.
+    pub is_return_address: bool,
+    /// What the value points to, if anything known (null values aren't annotated).
+    #[serde(flatten)]
+    pub target: Option<RegisterTarget>,
+}
+
+/// Disassembly of the crashing thread's code.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Disassembly {
+    /// The index of the thread in [`ProcessState::threads`].
+    pub threads_index: usize,
+    /// The disassembled windows, the first one around the crashing instruction and the
+    /// others (if requested) around the return addresses of the other frames.
+    pub windows: Vec<DisassemblyWindow>,
+}
+
+/// The instructions around one address of a [`Disassembly`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DisassemblyWindow {
+    /// The index of the frame whose instruction pointer this window is around.
+    pub frame: usize,
+    /// The frame's instruction pointer (the crashing instruction or a return address).
+    pub address: Address,
+    pub instructions: Vec<DisassembledInstruction>,
+}
+
+/// One instruction of a [`DisassemblyWindow`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DisassembledInstruction {
+    pub address: Address,
+    #[serde(serialize_with = "serialize_hex_bytes")]
+    pub bytes: Vec<u8>,
+    pub text: String,
+    /// The target of a direct branch or call.
+    pub branch_target: Option<Address>,
+    /// The module and function `branch_target` is in, like `libxul.so!foo + 0x10`.
+    pub branch_target_symbol: Option<String>,
+}
+
+fn serialize_hex_bytes<S: serde::Serializer>(
+    bytes: &[u8],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex_bytes(bytes))
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// An MSVC C++ exception, decoded from the exception parameters.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CxxException {
+    /// The address of the thrown object.
+    pub thrown_object: Address,
+    /// The address of the `ThrowInfo` describing the thrown type.
+    pub throw_info: Address,
+    /// The types the exception can be caught as.
+    ///
+    /// The first entry is the type of the thrown object, followed by its base classes.
+    pub catchable_types: Vec<String>,
+    /// The result of `std::exception::what()`, if the thrown object derives from it.
+    pub what: Option<String>,
+}
+
+impl CxxException {
+    /// The type of the thrown object.
+    pub fn thrown_type(&self) -> Option<&str> {
+        self.catchable_types.first().map(String::as_str)
+    }
+}
+
 #[derive(serde::Serialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum CrashInconsistency {
@@ -454,6 +788,114 @@ impl std::fmt::Display for CrashInconsistency {
     }
 }
 
+/// The kind of primitive a thread is blocked on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitKind {
+    /// A mutex (e.g. `pthread_mutex_lock`).
+    Mutex,
+    /// A Windows critical section.
+    CriticalSection,
+    /// A reader-writer lock (including Windows SRW locks).
+    RwLock,
+    /// A condition variable.
+    ConditionVariable,
+    /// A wait on a memory address (e.g. futexes, `WaitOnAddress`, `__ulock_wait`).
+    Address,
+    /// A wait on a kernel object (e.g. `WaitForSingleObject`, semaphores).
+    Object,
+    /// An explicit sleep.
+    Sleep,
+    /// A wait for I/O (e.g. `poll`, `epoll_wait`, I/O completion ports).
+    Io,
+    /// A wait for inter-process communication (e.g. `mach_msg`).
+    Ipc,
+}
+
+impl std::fmt::Display for WaitKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            WaitKind::Mutex => "mutex",
+            WaitKind::CriticalSection => "critical section",
+            WaitKind::RwLock => "rwlock",
+            WaitKind::ConditionVariable => "condition variable",
+            WaitKind::Address => "address wait",
+            WaitKind::Object => "object wait",
+            WaitKind::Sleep => "sleep",
+            WaitKind::Io => "I/O",
+            WaitKind::Ipc => "IPC",
+        })
+    }
+}
+
+/// A thread which is blocked on a known wait primitive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadWait {
+    /// The index of the thread in [`ProcessState::threads`].
+    pub thread_index: usize,
+    /// The id of the thread.
+    pub thread_id: u32,
+    /// The kind of primitive the thread is blocked on.
+    pub kind: WaitKind,
+    /// The name of the wait primitive the thread is blocked in.
+    pub function: Cow<'static, str>,
+    /// The index of the wait primitive's frame in the thread's frames.
+    pub frame_index: usize,
+    /// The address of the lock being waited on, if it could be found.
+    pub lock_address: Option<Address>,
+    /// The id of the thread owning the lock, if it could be read.
+    pub lock_owner: Option<u32>,
+}
+
+/// The result of analyzing which threads are blocked, and on what.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HangAnalysis {
+    /// All threads which are blocked on a known wait primitive.
+    pub waiting_threads: Vec<ThreadWait>,
+    /// Cycles in the wait-for graph (i.e. deadlocks).
+    ///
+    /// Each cycle is a list of thread ids, where every thread waits on a lock owned by the
+    /// next one, and the last waits on a lock owned by the first.
+    pub deadlocks: Vec<Vec<u32>>,
+}
+
+/// A WinRT exception stowed away by the runtime before raising STATUS_STOWED_EXCEPTION.
+#[derive(Debug, Clone)]
+pub struct StowedException {
+    /// The HRESULT of the exception.
+    pub result_code: u32,
+    /// `result_code` decoded as a Windows error.
+    pub reason: StowedExceptionReason,
+    /// The thread which originated the exception.
+    pub thread_id: u32,
+    /// The address at which the exception was originated, if a stack trace was captured.
+    pub exception_address: Option<Address>,
+    /// The error message, if the exception has one instead of a stack trace.
+    pub message: Option<String>,
+    /// The four-character code of the nested exception's type (e.g. "W32E"), if any.
+    pub nested_exception_type: Option<String>,
+    /// The symbolicated stack trace captured when the exception was originated.
+    pub stack: CallStack,
+}
+
+/// The decoded HRESULT of a [`StowedException`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StowedExceptionReason {
+    /// A Win32 error code wrapped in a `FACILITY_WIN32` HRESULT (see `HRESULT_FROM_WIN32`).
+    Win32(minidump_common::errors::WinErrorWindows),
+    /// Any other error code.
+    Other(CrashReason),
+}
+
+impl std::fmt::Display for StowedExceptionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StowedExceptionReason::Win32(error) => write!(f, "FACILITY_WIN32 / {:?}", error),
+            StowedExceptionReason::Other(reason) => reason.fmt(f),
+        }
+    }
+}
+
 /// The state of a process as recorded by a `Minidump`.
 #[derive(Debug, Clone)]
 pub struct ProcessState {
@@ -503,6 +945,34 @@ pub struct ProcessState {
     pub unimplemented_streams: Vec<MinidumpUnimplementedStream>,
     pub symbol_stats: HashMap<String, SymbolStats>,
     pub linux_memory_map_count: Option<usize>,
+    /// Which threads are blocked on known wait primitives, and any deadlocks between them.
+    ///
+    /// This is `None` if no thread was found to be waiting.
+    pub hang_analysis: Option<HangAnalysis>,
+    /// WinRT exceptions stowed by the runtime, each with its own stack.
+    ///
+    /// These are only present for STATUS_STOWED_EXCEPTION crashes.
+    pub stowed_exceptions: Vec<StowedException>,
+    /// The parameters a Windows process was started with, read from its PEB.
+    ///
+    /// This is only available for full-memory dumps. The command line and environment are
+    /// only included if [`ProcessorOptions`][crate::ProcessorOptions] asks for them.
+    pub process_parameters: Option<MinidumpProcessParameters>,
+    /// What the registers of the crashing thread's context point to.
+    ///
+    /// Registers whose values don't point anywhere known are left out. Only filled in if
+    /// [`ProcessorOptions::annotate_registers`][crate::ProcessorOptions] is set.
+    pub register_annotations: Vec<RegisterAnnotation>,
+    /// The annotated stack memory of the crashing thread.
+    ///
+    /// Only present if [`ProcessorOptions::annotate_stack_memory`][crate::ProcessorOptions]
+    /// is set.
+    pub stack_memory_dump: Option<StackMemoryDump>,
+    /// Disassembly around the crashing instruction and (optionally) return addresses.
+    ///
+    /// Only present if [`ProcessorOptions::disassembly_window`][crate::ProcessorOptions]
+    /// is set and the CPU is supported.
+    pub disassembly: Option<Disassembly>,
 }
 
 fn json_registers(ctx: &MinidumpContext) -> serde_json::Value {
@@ -644,6 +1114,28 @@ impl ProcessState {
                 }
             }
 
+            if let Some(cxx_exception) = &crash_info.cxx_exception {
+                writeln!(
+                    f,
+                    "C++ exception: {}",
+                    cxx_exception.thrown_type().unwrap_or("<unknown type>")
+                )?;
+                if let Some(what) = &cxx_exception.what {
+                    writeln!(f, "  what(): {what:?}")?;
+                }
+                if cxx_exception.catchable_types.len() > 1 {
+                    writeln!(
+                        f,
+                        "  Catchable as: {}",
+                        cxx_exception.catchable_types[1..].join(", ")
+                    )?;
+                }
+            }
+
+            if crash_info.is_likely_stack_overflow {
+                writeln!(f, "Crash is likely a stack overflow")?;
+            }
+
             if !crash_info.possible_bit_flips.is_empty() {
                 writeln!(f, "Crashing address may be the result of a flipped bit:")?;
                 let mut bit_flips_with_confidence = crash_info
@@ -670,6 +1162,53 @@ impl ProcessState {
                     )?;
                 }
             }
+            if let Some(hardware_fault) = &crash_info.hardware_fault {
+                writeln!(
+                    f,
+                    "Crash may be caused by faulty hardware ({:.3}):",
+                    hardware_fault.confidence
+                )?;
+                if !hardware_fault.cpu_errata.is_empty() {
+                    writeln!(
+                        f,
+                        "  CPU is affected by known errata: {}",
+                        hardware_fault.cpu_errata.join(", ")
+                    )?;
+                }
+                if let Some(confidence) = hardware_fault.bit_flip_confidence {
+                    writeln!(
+                        f,
+                        "  Crashing address may have a flipped bit ({confidence:.3})"
+                    )?;
+                }
+                if hardware_fault.impossible_instruction {
+                    writeln!(f, "  Crashing instruction can't have caused the crash")?;
+                }
+                if hardware_fault.crashing_access_not_found {
+                    writeln!(f, "  Crashing instruction doesn't access the crash address")?;
+                }
+            }
+            if !crash_info.poison_pattern_matches.is_empty() {
+                writeln!(
+                    f,
+                    "Crash may involve poisoned memory (possible use-after-free):"
+                )?;
+                for (idx, m) in crash_info.poison_pattern_matches.iter().enumerate() {
+                    let source = match (m.source, m.source_register) {
+                        (PoisonSource::CrashAddress, _) => "crash address",
+                        (PoisonSource::AdjustedAddress, _) => "adjusted address",
+                        (PoisonSource::Register, Some(name)) => name,
+                        (PoisonSource::Register, None) => "register",
+                    };
+                    writeln!(
+                        f,
+                        "  {idx}. {source}={value} matches {pattern} ({kind})",
+                        value = m.value,
+                        pattern = m.pattern,
+                        kind = m.kind,
+                    )?;
+                }
+            }
             if !crash_info.inconsistencies.is_empty() {
                 writeln!(f, "Crash is inconsistent:")?;
                 for inconsistency in &crash_info.inconsistencies {
@@ -723,6 +1262,25 @@ impl ProcessState {
             )?;
             writeln!(f)?;
         }
+        if let Some(ref params) = self.process_parameters {
+            writeln!(f, "Process parameters:")?;
+            if let Some(ref val) = params.image_path_name {
+                writeln!(f, "  image path: {val}")?;
+            }
+            if let Some(ref val) = params.command_line {
+                writeln!(f, "  command line: {val}")?;
+            }
+            if let Some(ref val) = params.current_directory {
+                writeln!(f, "  current directory: {val}")?;
+            }
+            if let Some(ref environment) = params.environment {
+                writeln!(f, "  environment:")?;
+                for (name, value) in environment {
+                    writeln!(f, "    {name}={value}")?;
+                }
+            }
+            writeln!(f)?;
+        }
         if let Some(ref time) = self.process_create_time {
             let uptime = self.time.duration_since(*time).unwrap_or_default();
             writeln!(f, "Process uptime: {} seconds", uptime.as_secs())?;
@@ -736,6 +1294,33 @@ impl ProcessState {
             writeln!(f)?;
         }
 
+        if let Some(ref hang_analysis) = self.hang_analysis {
+            writeln!(f, "Blocked threads:")?;
+            for wait in &hang_analysis.waiting_threads {
+                writeln!(
+                    f,
+                    "  Thread {} - tid: {} blocked in {} ({})",
+                    wait.thread_index, wait.thread_id, wait.function, wait.kind
+                )?;
+                if let Some(lock_address) = wait.lock_address {
+                    write!(f, "    lock: {lock_address}")?;
+                    if let Some(owner) = wait.lock_owner {
+                        write!(f, ", owned by tid: {owner}")?;
+                    }
+                    writeln!(f)?;
+                }
+            }
+            for cycle in &hang_analysis.deadlocks {
+                let tids = cycle
+                    .iter()
+                    .chain(cycle.first())
+                    .map(|tid| format!("tid {tid}"))
+                    .collect::<Vec<_>>();
+                writeln!(f, "Deadlock detected: {}", tids.join(" -> "))?;
+            }
+            writeln!(f)?;
+        }
+
         if let Some(requesting_thread) = self.requesting_thread {
             let stack = &self.threads[requesting_thread];
             writeln!(
@@ -754,6 +1339,83 @@ impl ProcessState {
             writeln!(f)?;
         }
 
+        if !self.register_annotations.is_empty() {
+            writeln!(f, "Register annotations:")?;
+            for annotation in &self.register_annotations {
+                writeln!(
+                    f,
+                    "  {: >6} = {}: {}",
+                    annotation.register, annotation.value, annotation.target
+                )?;
+            }
+            writeln!(f)?;
+        }
+
+        if let Some(dump) = &self.stack_memory_dump {
+            writeln!(f, "Stack memory of thread {}:", dump.threads_index)?;
+            for word in &dump.words {
+                let frame = word.frame.map(|i| format!("#{i}")).unwrap_or_default();
+                write!(f, "  {} {} {: >4}", word.address, word.value, frame)?;
+                if word.is_return_address {
+                    write!(f, "  return address")?;
+                }
+                if let Some(target) = &word.target {
+                    write!(f, "  {target}")?;
+                }
+                writeln!(f)?;
+            }
+            writeln!(f)?;
+        }
+
+        if let Some(disassembly) = &self.disassembly {
+            for window in &disassembly.windows {
+                writeln!(
+                    f,
+                    "Disassembly of thread {} frame {} around {}:",
+                    disassembly.threads_index, window.frame, window.address
+                )?;
+                for instruction in &window.instructions {
+                    let marker = if instruction.address == window.address {
+                        ">"
+                    } else {
+                        " "
+                    };
+                    write!(
+                        f,
+                        "  {} {} {: <30} {}",
+                        marker,
+                        instruction.address,
+                        hex_bytes(&instruction.bytes),
+                        instruction.text
+                    )?;
+                    if let Some(target) = instruction.branch_target {
+                        write!(f, "  ; {target}")?;
+                        if let Some(symbol) = &instruction.branch_target_symbol {
+                            write!(f, " {symbol}")?;
+                        }
+                    }
+                    writeln!(f)?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        for (i, stowed) in self.stowed_exceptions.iter().enumerate() {
+            writeln!(
+                f,
+                "Stowed exception {} - tid: {} - {} ({:#010x})",
+                i, stowed.thread_id, stowed.reason, stowed.result_code
+            )?;
+            if let Some(message) = &stowed.message {
+                writeln!(f, "  message: {message:?}")?;
+            }
+            if let Some(nested) = &stowed.nested_exception_type {
+                writeln!(f, "  nested exception type: {nested}")?;
+            }
+            stowed.stack.print(f)?;
+            writeln!(f)?;
+        }
+
         // We're done if this is a brief report!
         if brief {
             return Ok(());
@@ -874,6 +1536,68 @@ Unknown streams encountered:
             Address(address).to_string()
         }
 
+        let json_frames = |stack: &CallStack| {
+            stack.frames.iter().enumerate().map(|(idx, frame)| json!({
+                "frame": idx,
+                // optional
+                "module": frame.module.as_ref().map(|module| basename(&module.name)),
+                // optional
+                "function": frame.function_name,
+                // optional
+                "file": frame.source_file_name,
+                // optional
+                "line": frame.source_line,
+                "offset": json_hex(frame.instruction),
+                // optional
+                "inlines": if !frame.inlines.is_empty() {
+                    Some(frame.inlines.iter().map(|frame| {
+                        json!({
+                            "function": frame.function_name,
+                            "file": frame.source_file_name,
+                            "line": frame.source_line,
+                        })
+                    }).collect::<Vec<_>>())
+                } else {
+                    None
+                },
+                // optional
+                "module_offset": frame
+                    .module
+                    .as_ref()
+                    .map(|module| frame.instruction - module.raw.base_of_image)
+                    .map(json_hex),
+                // optional
+                "unloaded_modules": if frame.unloaded_modules.is_empty() {
+                    None
+                } else {
+                    Some(frame.unloaded_modules.iter().map(|(module, offsets)| json!({
+                        "module": module,
+                        "offsets": offsets.iter().copied().map(json_hex).collect::<Vec<_>>(),
+                    })).collect::<Vec<_>>())
+                },
+                // optional
+                "function_offset": frame
+                    .function_base
+                    .map(|func_base| frame.instruction - func_base)
+                    .map(json_hex),
+                "missing_symbols": frame.function_name.is_none(),
+                // optional
+                "arguments": frame.arguments.as_ref().map(|arguments| json!({
+                    // cdecl | windows_thiscall | other_thiscall | sysv | win64
+                    "calling_convention": arguments.calling_convention.as_str(),
+                    "args": arguments.args.iter().map(|arg| json!({
+                        "name": arg.name,
+                        // optional
+                        "value": arg.value.map(json_hex),
+                        // context | stack | home_space | none
+                        "trust": arg.trust.as_str(),
+                    })).collect::<Vec<_>>(),
+                })),
+                // none | scan | cfi_scan | frame_pointer | cfi | context | prewalked
+                "trust": frame.trust.as_str()
+            })).collect::<Vec<_>>()
+        };
+
         let mut output = json!({
             // Currently unused, we either produce no output or successful output.
             // OK | ERROR_* | SYMBOL_SUPPLIER_INTERRUPTED
@@ -944,9 +1668,27 @@ Unknown streams encountered:
                 "possible_bit_flips": self.exception_info.as_ref().and_then(|info| {
                     (!info.possible_bit_flips.is_empty()).then_some(&info.possible_bit_flips)
                 }),
+                "poison_pattern_matches": self.exception_info.as_ref().and_then(|info| {
+                    (!info.poison_pattern_matches.is_empty()).then_some(&info.poison_pattern_matches)
+                }),
                 "crash_inconsistencies": self.exception_info.as_ref().map(|info| {
                     &info.inconsistencies
                 }),
+                "is_likely_stack_overflow": self.exception_info.as_ref().map(|info| {
+                    info.is_likely_stack_overflow
+                }),
+                "hardware_fault": self.exception_info.as_ref().and_then(|info| {
+                    info.hardware_fault.as_ref()
+                }),
+                "cxx_exception": self.exception_info.as_ref().and_then(|info| {
+                    info.cxx_exception.as_ref().map(|cxx| json!({
+                        "type": cxx.thrown_type(),
+                        "catchable_types": cxx.catchable_types,
+                        "what": cxx.what,
+                        "thrown_object": cxx.thrown_object,
+                        "throw_info": cxx.throw_info,
+                    }))
+                }),
                 // thread index | null
                 "crashing_thread": self.requesting_thread,
                 "assertion": self.assertion,
@@ -985,6 +1727,18 @@ Unknown streams encountered:
             })),
             // optional
             "mac_boot_args": self.mac_boot_args.as_ref().map(|info| info.bootargs.as_ref()),
+            // optional
+            "process_parameters": self.process_parameters.as_ref().map(|params| json!({
+                "image_path": params.image_path_name,
+                "command_line": params.command_line,
+                "current_directory": params.current_directory,
+                "environment": params.environment.as_ref().map(|environment| {
+                    environment
+                        .iter()
+                        .map(|(name, value)| json!({ "name": name, "value": value }))
+                        .collect::<Vec<_>>()
+                }),
+            })),
 
             // optional
             "linux_memory_map_count": self.linux_memory_map_count,
@@ -1043,6 +1797,48 @@ Unknown streams encountered:
                 })
             }).collect::<Vec<_>>(),
             "pid": self.process_id,
+            // optional
+            "hang_analysis": self.hang_analysis.as_ref().map(|hang_analysis| json!({
+                "waiting_threads": hang_analysis.waiting_threads.iter().map(|wait| json!({
+                    "threads_index": wait.thread_index,
+                    "thread_id": wait.thread_id,
+                    "kind": wait.kind,
+                    "function": wait.function,
+                    "frame": wait.frame_index,
+                    // optional
+                    "lock_address": wait.lock_address,
+                    // optional
+                    "lock_owner": wait.lock_owner,
+                })).collect::<Vec<_>>(),
+                "deadlocks": hang_analysis.deadlocks,
+            })),
+            // optional
+            "register_annotations": (!self.register_annotations.is_empty()).then(|| {
+                self.register_annotations
+                    .iter()
+                    .map(|annotation| (annotation.register.to_owned(), json!(annotation)))
+                    .collect::<serde_json::Map<_, _>>()
+            }),
+            // optional
+            "stack_memory_dump": self.stack_memory_dump,
+            // optional
+            "disassembly": self.disassembly,
+            // optional
+            "stowed_exceptions": (!self.stowed_exceptions.is_empty()).then(|| {
+                self.stowed_exceptions.iter().map(|stowed| json!({
+                    "result_code": json_hex(stowed.result_code as u64),
+                    "reason": stowed.reason.to_string(),
+                    "thread_id": stowed.thread_id,
+                    // optional
+                    "exception_address": stowed.exception_address,
+                    // optional
+                    "message": stowed.message,
+                    // optional
+                    "nested_exception_type": stowed.nested_exception_type,
+                    "frame_count": stowed.stack.frames.len(),
+                    "frames": json_frames(&stowed.stack),
+                })).collect::<Vec<_>>()
+            }),
             "thread_count": self.threads.len(),
             "threads": self.threads.iter().map(|thread| json!({
                 "frame_count": thread.frames.len(),
@@ -1051,53 +1847,44 @@ Unknown streams encountered:
                 // optional
                 "thread_name": thread.thread_name,
                 "thread_id" : thread.thread_id,
-                "frames": thread.frames.iter().enumerate().map(|(idx, frame)| json!({
-                    "frame": idx,
-                    // optional
-                    "module": frame.module.as_ref().map(|module| basename(&module.name)),
-                    // optional
-                    "function": frame.function_name,
-                    // optional
-                    "file": frame.source_file_name,
-                    // optional
-                    "line": frame.source_line,
-                    "offset": json_hex(frame.instruction),
-                    // optional
-                    "inlines": if !frame.inlines.is_empty() {
-                        Some(frame.inlines.iter().map(|frame| {
-                            json!({
-                                "function": frame.function_name,
-                                "file": frame.source_file_name,
-                                "line": frame.source_line,
-                            })
-                        }).collect::<Vec<_>>())
-                    } else {
-                        None
-                    },
+                // optional
+                "recursion": thread.recursion.as_ref().map(|recursion| json!({
+                    "start_frame": recursion.start_frame,
+                    "period": recursion.period,
+                    "repeat_count": recursion.repeat_count,
+                    "depth": recursion.depth,
+                    "collapsed_frames": recursion.collapsed_frames,
+                })),
+                // optional
+                "teb": thread.thread_environment_block.as_ref().map(|teb| json!({
+                    "address": json_hex(teb.address),
+                    "wow64": teb.wow64,
+                    "stack_base": json_hex(teb.stack_base),
+                    "stack_limit": json_hex(teb.stack_limit),
                     // optional
-                    "module_offset": frame
-                        .module
-                        .as_ref()
-                        .map(|module| frame.instruction - module.raw.base_of_image)
-                        .map(json_hex),
+                    "deallocation_stack": teb.deallocation_stack.map(json_hex),
+                    "fiber_data": json_hex(teb.fiber_data),
                     // optional
-                    "unloaded_modules": if frame.unloaded_modules.is_empty() {
-                        None
-                    } else {
-                        Some(frame.unloaded_modules.iter().map(|(module, offsets)| json!({
-                            "module": module,
-                            "offsets": offsets.iter().copied().map(json_hex).collect::<Vec<_>>(),
-                        })).collect::<Vec<_>>())
-                    },
+                    "last_status_value": teb.last_status_value
+                        .map(|status| CrashReason::from_windows_code(status).to_string()),
+                    // optional, only the non-null slots are listed
+                    "tls_slots": teb.tls_slots.as_ref().map(|slots| {
+                        slots
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, &value)| value != 0)
+                            .map(|(index, &value)| json!({
+                                "index": index,
+                                "value": json_hex(value),
+                            }))
+                            .collect::<Vec<_>>()
+                    }),
                     // optional
-                    "function_offset": frame
-                        .function_base
-                        .map(|func_base| frame.instruction - func_base)
-                        .map(json_hex),
-                    "missing_symbols": frame.function_name.is_none(),
-                    // none | scan | cfi_scan | frame_pointer | cfi | context | prewalked
-                    "trust": frame.trust.as_str()
-                })).collect::<Vec<_>>(),
+                    "stack_pointer_in_bounds": thread.frames.first().map(|frame| {
+                        teb.stack_contains(frame.context.get_stack_pointer())
+                    }),
+                })),
+                "frames": json_frames(thread),
             })).collect::<Vec<_>>(),
 
             "unloaded_modules": self.unloaded_modules.iter().map(|module| json!({
@@ -13,10 +13,12 @@ use minidump_unwind::{
     walk_stack, CallStack, CallStackInfo, FrameTrust, StackFrame, SymbolProvider, SystemInfo,
 };
 
+use crate::hardware_fault::CpuIdentity;
 use crate::op_analysis::MemoryAddressInfo;
 use crate::process_state::{LinuxStandardBase, ProcessState};
 use crate::{
-    arg_recovery, evil, AdjustedAddress, CrashInconsistency, LinuxProcLimits, LinuxProcStatus,
+    arg_recovery, evil, hang_analysis, recursion, AdjustedAddress, CrashInconsistency,
+    HardwareFault, LinuxProcLimits, LinuxProcStatus,
 };
 
 /// Configuration of the processor's exact behaviour.
@@ -63,10 +65,94 @@ pub struct ProcessorOptions<'a> {
 
     /// **\[UNSTABLE\]** Whether to try to heuristically recover function arguments in backtraces.
     ///
-    /// Currently this only work for x86, and assumes everything is either cdecl or thiscall
-    /// (inferred from whether the symbol name looks like a static function or a method).
+    /// Currently this only works for x86, where everything is assumed to be either cdecl or
+    /// thiscall (inferred from whether the symbol name looks like a static function or a
+    /// method), and amd64, using the System V or Win64 convention depending on the OS.
     pub recover_function_args: bool,
 
+    /// The allocator poison patterns to look for in the crash address and crashing registers.
+    ///
+    /// Matches are reported in [`ExceptionInfo::poison_pattern_matches`][crate::ExceptionInfo]
+    /// and usually indicate a use-after-free or use of uninitialized memory. Defaults to
+    /// [`poison::DEFAULT_POISON_PATTERNS`]; set this to an empty slice to disable the check.
+    pub poison_patterns: &'a [poison::PoisonPattern],
+
+    /// **\[UNSTABLE\]** Whether to collapse recursive frames in backtraces.
+    ///
+    /// Recursion is always detected and reported in [`CallStack::recursion`], but when this
+    /// is set only the first repetition of the cycle is kept in [`CallStack::frames`].
+    pub collapse_recursion: bool,
+
+    /// **\[UNSTABLE\]** Whether to drop frames found outside of a thread's stack.
+    ///
+    /// The stack bounds are taken from the thread's TEB on Windows. Stack scanning can
+    /// easily wander into neighbouring stacks (such as those of other fibers), so with this
+    /// set the backtrace is cut off at the first frame whose stack pointer is out of bounds.
+    pub truncate_out_of_stack_frames: bool,
+
+    /// The functions which indicate that a thread is blocked, for hang analysis.
+    ///
+    /// Hang analysis only runs on dumps without an exception, which are usually taken of
+    /// hung processes. The results are reported in [`ProcessState::hang_analysis`]. Defaults to
+    /// [`DEFAULT_WAIT_PRIMITIVES`][crate::DEFAULT_WAIT_PRIMITIVES]; set this to an empty
+    /// slice to disable the analysis.
+    pub wait_primitives: &'a [crate::WaitPrimitive],
+
+    /// Whether to report the command line of Windows processes.
+    ///
+    /// This is read from the PEB, so it's only available for full-memory dumps. It's off by
+    /// default because command lines often contain user data. The image path and current
+    /// directory are always reported in [`ProcessState::process_parameters`].
+    pub report_command_line: bool,
+
+    /// Whether to report the environment variables of Windows processes.
+    ///
+    /// Like [`report_command_line`][Self::report_command_line] this is read from the PEB and
+    /// off by default. Variables named in
+    /// [`redacted_environment_variables`][Self::redacted_environment_variables] have their
+    /// values replaced with [`REDACTED_VALUE`][crate::REDACTED_VALUE].
+    pub report_environment: bool,
+
+    /// Case-insensitive substrings of the names of environment variables to redact.
+    ///
+    /// Defaults to
+    /// [`DEFAULT_REDACTED_ENVIRONMENT_VARIABLES`][crate::DEFAULT_REDACTED_ENVIRONMENT_VARIABLES].
+    pub redacted_environment_variables: &'a [&'a str],
+
+    /// Whether to annotate what the registers of the crashing thread point to.
+    ///
+    /// Each register is classified as null, a poison pattern, a non-canonical address or a
+    /// pointer into a stack, module or other mapped region. The result is reported in
+    /// [`ProcessState::register_annotations`]. This is off by default.
+    pub annotate_registers: bool,
+
+    /// Whether to dump the crashing thread's stack memory, annotating every word.
+    ///
+    /// The words are classified like the registers in
+    /// [`ProcessState::register_annotations`], and are attributed to the frames whose part
+    /// of the stack they're in. The result is reported in
+    /// [`ProcessState::stack_memory_dump`]. This is off by default because it's large.
+    pub annotate_stack_memory: bool,
+
+    /// The number of instructions to disassemble before and after the crashing instruction.
+    ///
+    /// The code is read from the dump, or from the module binaries if the symbol provider
+    /// can find them, and direct branch targets are symbolized. The result is reported in
+    /// [`ProcessState::disassembly`]. 0 disables this.
+    pub disassembly_window: usize,
+
+    /// Whether to also disassemble around the return address of every frame of the
+    /// crashing thread, with the same window as [`ProcessorOptions::disassembly_window`].
+    pub disassemble_return_addresses: bool,
+
+    /// The known CPU errata to look for when assessing whether a crash is a hardware fault.
+    ///
+    /// Matches are combined with bit flips and crash inconsistencies into
+    /// [`ExceptionInfo::hardware_fault`][crate::ExceptionInfo]. Defaults to
+    /// [`DEFAULT_CPU_ERRATA`][crate::DEFAULT_CPU_ERRATA]; set this to an empty slice to
+    /// only report hardware faults for impossible crashing instructions.
+    pub cpu_errata: &'a [crate::CpuErratum],
+
     /// Set this value to subscribe to live statistics during the processing.
     ///
     /// See [`PendingProcessorStats`] and [`PendingProcessorStatSubscriptions`].
@@ -279,6 +365,18 @@ impl ProcessorOptions<'_> {
     ///
     /// * `evil_json: None`
     /// * `recover_function_args: false`
+    /// * `poison_patterns: DEFAULT_POISON_PATTERNS`
+    /// * `collapse_recursion: false`
+    /// * `truncate_out_of_stack_frames: false`
+    /// * `wait_primitives: DEFAULT_WAIT_PRIMITIVES`
+    /// * `report_command_line: false`
+    /// * `report_environment: false`
+    /// * `redacted_environment_variables: DEFAULT_REDACTED_ENVIRONMENT_VARIABLES`
+    /// * `annotate_registers: false`
+    /// * `annotate_stack_memory: false`
+    /// * `disassembly_window: 0`
+    /// * `disassemble_return_addresses: false`
+    /// * `cpu_errata: DEFAULT_CPU_ERRATA`
     ///
     /// Unlike stable_all, you shouldn't expect this to change its results much.
     ///
@@ -292,6 +390,18 @@ impl ProcessorOptions<'_> {
         ProcessorOptions {
             evil_json: None,
             recover_function_args: false,
+            poison_patterns: poison::DEFAULT_POISON_PATTERNS,
+            collapse_recursion: false,
+            truncate_out_of_stack_frames: false,
+            wait_primitives: crate::DEFAULT_WAIT_PRIMITIVES,
+            report_command_line: false,
+            report_environment: false,
+            redacted_environment_variables: crate::DEFAULT_REDACTED_ENVIRONMENT_VARIABLES,
+            annotate_registers: false,
+            annotate_stack_memory: false,
+            disassembly_window: 0,
+            disassemble_return_addresses: false,
+            cpu_errata: crate::DEFAULT_CPU_ERRATA,
             stat_reporter: None,
         }
     }
@@ -300,6 +410,18 @@ impl ProcessorOptions<'_> {
     ///
     /// * `evil_json: None`
     /// * `recover_function_args: false`
+    /// * `poison_patterns: DEFAULT_POISON_PATTERNS`
+    /// * `collapse_recursion: false`
+    /// * `truncate_out_of_stack_frames: false`
+    /// * `wait_primitives: DEFAULT_WAIT_PRIMITIVES`
+    /// * `report_command_line: false`
+    /// * `report_environment: false`
+    /// * `redacted_environment_variables: DEFAULT_REDACTED_ENVIRONMENT_VARIABLES`
+    /// * `annotate_registers: false`
+    /// * `annotate_stack_memory: false`
+    /// * `disassembly_window: 0`
+    /// * `disassemble_return_addresses: false`
+    /// * `cpu_errata: DEFAULT_CPU_ERRATA`
     ///
     /// (At this precise moment this is identical to stable_basic, but may diverge
     /// as we introduce more features.)
@@ -311,6 +433,18 @@ impl ProcessorOptions<'_> {
         ProcessorOptions {
             evil_json: None,
             recover_function_args: false,
+            poison_patterns: poison::DEFAULT_POISON_PATTERNS,
+            collapse_recursion: false,
+            truncate_out_of_stack_frames: false,
+            wait_primitives: crate::DEFAULT_WAIT_PRIMITIVES,
+            report_command_line: false,
+            report_environment: false,
+            redacted_environment_variables: crate::DEFAULT_REDACTED_ENVIRONMENT_VARIABLES,
+            annotate_registers: false,
+            annotate_stack_memory: false,
+            disassembly_window: 0,
+            disassemble_return_addresses: false,
+            cpu_errata: crate::DEFAULT_CPU_ERRATA,
             stat_reporter: None,
         }
     }
@@ -319,6 +453,18 @@ impl ProcessorOptions<'_> {
     ///
     /// * `evil_json: None`
     /// * `recover_function_args: true`
+    /// * `poison_patterns: DEFAULT_POISON_PATTERNS`
+    /// * `collapse_recursion: true`
+    /// * `truncate_out_of_stack_frames: true`
+    /// * `wait_primitives: DEFAULT_WAIT_PRIMITIVES`
+    /// * `report_command_line: false`
+    /// * `report_environment: false`
+    /// * `redacted_environment_variables: DEFAULT_REDACTED_ENVIRONMENT_VARIABLES`
+    /// * `annotate_registers: false`
+    /// * `annotate_stack_memory: false`
+    /// * `disassembly_window: 0`
+    /// * `disassemble_return_addresses: false`
+    /// * `cpu_errata: DEFAULT_CPU_ERRATA`
     ///
     /// (evil_json is still "disabled" because you need to give it needs a path.)
     ///
@@ -327,6 +473,18 @@ impl ProcessorOptions<'_> {
         ProcessorOptions {
             evil_json: None,
             recover_function_args: true,
+            poison_patterns: poison::DEFAULT_POISON_PATTERNS,
+            collapse_recursion: true,
+            truncate_out_of_stack_frames: true,
+            wait_primitives: crate::DEFAULT_WAIT_PRIMITIVES,
+            report_command_line: false,
+            report_environment: false,
+            redacted_environment_variables: crate::DEFAULT_REDACTED_ENVIRONMENT_VARIABLES,
+            annotate_registers: false,
+            annotate_stack_memory: false,
+            disassembly_window: 0,
+            disassemble_return_addresses: false,
+            cpu_errata: crate::DEFAULT_CPU_ERRATA,
             stat_reporter: None,
         }
     }
@@ -454,8 +612,11 @@ where
 
     if let Some(details) = &mut exception_details {
         info.check_for_bitflips(details);
+        info.check_for_poison_patterns(details);
         info.check_for_guard_pages(details);
+        info.check_for_stack_overflow(details);
         info.check_for_crash_inconsistencies(details);
+        info.check_for_hardware_fault(details);
     }
     info.into_process_state(dump, symbol_provider, exception_details)
         .await
@@ -471,6 +632,7 @@ struct MinidumpInfo<'a> {
     linux_proc_status: Option<LinuxProcStatus>,
     linux_proc_limits: Option<LinuxProcLimits>,
     system_info: SystemInfo,
+    cpu_identity: Option<CpuIdentity>,
     mac_crash_info: Option<Vec<RawMacCrashInfo>>,
     mac_boot_args: Option<MinidumpMacBootargs>,
     misc_info: Option<MinidumpMiscInfo>,
@@ -540,6 +702,7 @@ impl<'a> MinidumpInfo<'a> {
         // pull out the things it cares about is simple and effective.
 
         let cpu_microcode_version = get_microcode_version(&linux_cpu_info, &evil);
+        let cpu_identity = CpuIdentity::new(&dump_system_info, &linux_cpu_info);
 
         let linux_standard_base = linux_standard_base.map(LinuxStandardBase::from);
         let linux_proc_status = linux_proc_status.map(LinuxProcStatus::from);
@@ -606,6 +769,7 @@ impl<'a> MinidumpInfo<'a> {
             linux_proc_status,
             linux_proc_limits,
             system_info,
+            cpu_identity,
             mac_crash_info,
             mac_boot_args,
             misc_info,
@@ -635,10 +799,18 @@ impl<'a> MinidumpInfo<'a> {
         let reason = exception.get_crash_reason(self.system_info.os, self.system_info.cpu);
         let address = exception.get_crash_address(self.system_info.os, self.system_info.cpu);
 
-        let stack_memory_ref = self
+        let crashing_thread = self
             .thread_list
-            .get_thread(exception.get_crashing_thread_id())
-            .and_then(|thread| thread.stack_memory(&self.memory_list));
+            .get_thread(exception.get_crashing_thread_id());
+        let stack_memory_ref =
+            crashing_thread.and_then(|thread| thread.stack_memory(&self.memory_list));
+        // 32-bit x86 code on Windows addresses the TEB through the fs segment. In WOW64
+        // processes the thread's TEB is the 64-bit one, and fs points at the 32-bit TEB.
+        let thread_environment_block = crashing_thread
+            .filter(|_| self.system_info.os == system_info::Os::Windows)
+            .and_then(|thread| thread.teb(self.system_info.cpu, &self.memory_list))
+            .filter(|teb| teb.pointer_width == PointerWidth::Bits32)
+            .map(|teb| teb.address);
 
         let context = exception.context(&self.dump_system_info, self.misc_info.as_ref());
 
@@ -651,6 +823,7 @@ impl<'a> MinidumpInfo<'a> {
                 context,
                 &self.memory_list,
                 stack_memory_ref,
+                thread_environment_block,
             ) {
                 Ok(op_analysis) => {
                     let access_addresses =
@@ -778,55 +951,148 @@ impl<'a> MinidumpInfo<'a> {
         }
     }
 
+    /// Check whether the exception address or crashing registers contain allocator poison.
+    ///
+    /// Matches will be added to `exception_details`.
+    pub fn check_for_poison_patterns(&self, exception_details: &mut ExceptionDetails<'a>) {
+        use crate::PoisonSource;
+
+        let patterns = self.options.poison_patterns;
+        if patterns.is_empty() {
+            return;
+        }
+
+        let register_size = match self.system_info.cpu.pointer_width() {
+            PointerWidth::Bits32 => 4,
+            _ => 8,
+        };
+
+        let info = &mut exception_details.info;
+        let mut candidates = vec![(info.address.0, PoisonSource::CrashAddress, None)];
+        if let Some(AdjustedAddress::NonCanonical(address)) = &info.adjusted_address {
+            candidates.push((address.0, PoisonSource::AdjustedAddress, None));
+        }
+        if let Some(context) = exception_details.context.as_deref() {
+            for reg in &exception_details.instruction_registers {
+                if let Some(value) = context.get_register(reg) {
+                    candidates.push((value, PoisonSource::Register, Some(*reg)));
+                }
+            }
+        }
+
+        for (value, source, source_register) in candidates {
+            if let Some(pattern) = poison::find_match(patterns, value, register_size) {
+                info.poison_pattern_matches.push(crate::PoisonPatternMatch {
+                    value: value.into(),
+                    source,
+                    source_register,
+                    pattern: pattern.name.clone(),
+                    kind: pattern.kind,
+                });
+            }
+        }
+    }
+
     /// Check whether memory accesses are accessing likely guard pages.
     pub fn check_for_guard_pages(&self, exception_details: &mut ExceptionDetails<'a>) {
-        const GUARD_MEMORY_MAX_SIZE: u64 = 2 << 14;
-
         if let Some(access_list) = &mut exception_details.info.memory_access_list {
             for access in &mut access_list.accesses {
-                let Some(info) = self
-                    .memory_info
-                    .memory_info_at_address(access.address_info.address)
-                else {
-                    continue;
-                };
-                let Some(range) = info.memory_range() else {
-                    continue;
-                };
-
-                fn is_accessible(range: &UnifiedMemoryInfo) -> bool {
-                    range.is_readable() || range.is_writable() || range.is_executable()
+                if self.is_likely_guard_page(access.address_info.address) {
+                    access.address_info.is_likely_guard_page = true;
                 }
+            }
+        }
+    }
 
-                let is_adjacent_to_accessible_memory = || {
-                    for region in self.memory_info.by_addr() {
-                        let Some(other_range) = region.memory_range() else {
-                            continue;
-                        };
-                        if other_range.end + 1 == range.start && is_accessible(&region) {
-                            return true;
-                        }
-                        if range.end + 1 == other_range.start {
-                            // At this point we won't encounter any other relevant regions as we're
-                            // iterating by address, so return.
-                            return is_accessible(&region);
-                        }
-                    }
-                    false
-                };
+    /// Returns whether `address` falls in a likely guard page.
+    fn is_likely_guard_page(&self, address: u64) -> bool {
+        const GUARD_MEMORY_MAX_SIZE: u64 = 2 << 14;
 
-                // As a heuristic, we consider any mapped memory to be a guard page if it:
-                // * has no permissions,
-                // * is less than `GUARD_MEMORY_MAX_SIZE`, and
-                // * is adjacent to a region with permissions.
-                if !is_accessible(&info)
-                    && range.end - range.start < GUARD_MEMORY_MAX_SIZE
-                    && is_adjacent_to_accessible_memory()
-                {
-                    access.address_info.is_likely_guard_page = true;
+        let Some(info) = self.memory_info.memory_info_at_address(address) else {
+            return false;
+        };
+        let Some(range) = info.memory_range() else {
+            return false;
+        };
+
+        fn is_accessible(range: &UnifiedMemoryInfo) -> bool {
+            range.is_readable() || range.is_writable() || range.is_executable()
+        }
+
+        let is_adjacent_to_accessible_memory = || {
+            for region in self.memory_info.by_addr() {
+                let Some(other_range) = region.memory_range() else {
+                    continue;
+                };
+                if other_range.end + 1 == range.start && is_accessible(&region) {
+                    return true;
+                }
+                if range.end + 1 == other_range.start {
+                    // At this point we won't encounter any other relevant regions as we're
+                    // iterating by address, so return.
+                    return is_accessible(&region);
                 }
             }
+            false
+        };
+
+        // As a heuristic, we consider any mapped memory to be a guard page if it:
+        // * has no permissions,
+        // * is less than `GUARD_MEMORY_MAX_SIZE`, and
+        // * is adjacent to a region with permissions.
+        !is_accessible(&info)
+            && range.end - range.start < GUARD_MEMORY_MAX_SIZE
+            && is_adjacent_to_accessible_memory()
+    }
+
+    /// Check whether the crash is likely to be a stack overflow.
+    ///
+    /// This is the case if the crash reason says so, or if the crashing access is just below
+    /// the stack pointer and falls in a likely guard page (or in the unmapped gap directly
+    /// below the stack's mapping, which is how Linux guards stacks).
+    pub fn check_for_stack_overflow(&self, exception_details: &mut ExceptionDetails<'a>) {
+        use minidump_common::errors::{ExceptionCodeWindows, NtStatusWindows};
+
+        /// How far below the stack pointer a crashing access may be to count as an overflow.
+        ///
+        /// Large stack frames are probed a page at a time, so this only needs to cover a
+        /// single (large) frame.
+        const STACK_OVERFLOW_MAX_DISTANCE: u64 = 1 << 16;
+
+        let info = &mut exception_details.info;
+        if matches!(
+            info.reason,
+            CrashReason::WindowsGeneral(ExceptionCodeWindows::EXCEPTION_STACK_OVERFLOW)
+                | CrashReason::WindowsNtStatus(NtStatusWindows::STATUS_STACK_OVERFLOW)
+        ) {
+            info.is_likely_stack_overflow = true;
+            return;
+        }
+
+        let Some(context) = exception_details.context.as_deref() else {
+            return;
+        };
+        let stack_pointer = context.get_stack_pointer();
+        let stack_region = self
+            .memory_info
+            .memory_info_at_address(stack_pointer)
+            .and_then(|region| region.memory_range());
+
+        let mut addresses = vec![info.address.0];
+        if let Some(access_list) = &info.memory_access_list {
+            addresses.extend(access_list.iter().map(|a| a.address_info.address));
         }
+
+        info.is_likely_stack_overflow = addresses.into_iter().any(|address| {
+            if address > stack_pointer || stack_pointer - address > STACK_OVERFLOW_MAX_DISTANCE {
+                return false;
+            }
+            let below_stack_mapping = self.memory_info.memory_info_at_address(address).is_none()
+                && stack_region
+                    .as_ref()
+                    .is_some_and(|range| address < range.start);
+            below_stack_mapping || self.is_likely_guard_page(address)
+        });
     }
 
     /// Check for inconsistencies between crash reason and crashing instruction
@@ -914,6 +1180,26 @@ impl<'a> MinidumpInfo<'a> {
         exception_details.info.inconsistencies = inconsistencies;
     }
 
+    /// Combine the signs of a hardware fault: known errata of the CPU, bit flips and
+    /// crash inconsistencies.
+    pub fn check_for_hardware_fault(&self, exception_details: &mut ExceptionDetails) {
+        let cpu_errata = self
+            .cpu_identity
+            .as_ref()
+            .map(|cpu| {
+                crate::hardware_fault::find_errata(
+                    self.options.cpu_errata,
+                    cpu,
+                    self.system_info.cpu_microcode_version,
+                )
+            })
+            .unwrap_or_default();
+
+        let info = &mut exception_details.info;
+        info.hardware_fault =
+            HardwareFault::new(cpu_errata, &info.possible_bit_flips, &info.inconsistencies);
+    }
+
     /// Returns whether non-canonical address is not used in accesses of crashing instruction
     /// Returns false if there is insufficient information to determine
     fn non_canonical_address_is_not_among_accesses(
@@ -1021,11 +1307,29 @@ impl<'a> MinidumpInfo<'a> {
     {
         let crashing_thread_id = self.exception.as_ref().map(|e| e.get_crashing_thread_id());
 
-        let (exception_info, exception_context) = match exception_details {
+        let (mut exception_info, exception_context) = match exception_details {
             Some(details) => (Some(details.info), details.context),
             None => (None, None),
         };
 
+        let pointer_size = self
+            .system_info
+            .cpu
+            .pointer_width()
+            .size_in_bytes()
+            .unwrap_or(8) as u64;
+
+        if let (Some(info), Some(exception)) = (&mut exception_info, &self.exception) {
+            info.cxx_exception = crate::cxx_exception::decode_cxx_exception(
+                &exception.raw.exception_record,
+                pointer_size,
+                &self.memory_list,
+                &self.modules,
+                symbol_provider,
+            )
+            .await;
+        }
+
         let mut requesting_thread = None;
 
         let threads = self
@@ -1074,10 +1378,25 @@ impl<'a> MinidumpInfo<'a> {
                     thread_id: id,
                     thread_name: name,
                     last_error_value: thread.last_error(self.system_info.cpu, &self.memory_list),
+                    recursion: None,
+                    thread_environment_block: (self.system_info.os == system_info::Os::Windows)
+                        .then(|| thread.teb(self.system_info.cpu, &self.memory_list))
+                        .flatten(),
                 }
             })
             .collect();
 
+        let process_parameters = if self.system_info.os == system_info::Os::Windows {
+            crate::process_parameters::read_process_parameters(
+                &self.thread_list,
+                self.system_info.cpu,
+                &self.memory_list,
+                &self.options,
+            )
+        } else {
+            None
+        };
+
         // Collect up info on unimplemented/unknown modules
         let unknown_streams = dump.unknown_streams().collect();
         let unimplemented_streams = dump.unimplemented_streams().collect();
@@ -1120,6 +1439,12 @@ impl<'a> MinidumpInfo<'a> {
             unimplemented_streams,
             symbol_stats,
             linux_memory_map_count: self.linux_memory_map_count,
+            hang_analysis: None,
+            stowed_exceptions: Vec::new(),
+            process_parameters,
+            register_annotations: Vec::new(),
+            stack_memory_dump: None,
+            disassembly: None,
         };
 
         // Report the unwalked result
@@ -1141,24 +1466,7 @@ impl<'a> MinidumpInfo<'a> {
                     .zip(self.thread_list.threads.iter())
                     .enumerate()
                     .map(|(i, (stack, thread))| async move {
-                        let mut stack_memory = thread.stack_memory(memory_list);
-                        // Always choose the memory region that is referenced by the context,
-                        // as the `exception_context` may refer to a different memory region than
-                        // the `thread_context`, which in turn would fail to stack walk.
-                        let stack_ptr = stack
-                            .frames
-                            .first()
-                            .map(|ctx_frame| ctx_frame.context.get_stack_pointer());
-                        if let Some(stack_ptr) = stack_ptr {
-                            let contains_stack_ptr = stack_memory
-                                .as_ref()
-                                .and_then(|memory| memory.get_memory_at_address::<u64>(stack_ptr))
-                                .is_some();
-                            if !contains_stack_ptr {
-                                stack_memory =
-                                    memory_list.memory_at_address(stack_ptr).or(stack_memory);
-                            }
-                        }
+                        let stack_memory = select_stack_memory(thread, stack, memory_list);
 
                         walk_stack(
                             i,
@@ -1175,6 +1483,10 @@ impl<'a> MinidumpInfo<'a> {
                         )
                         .await;
 
+                        if options.truncate_out_of_stack_frames {
+                            truncate_at_out_of_bounds_frame(stack);
+                        }
+
                         for frame in &mut stack.frames {
                             // If the frame doesn't have a loaded module, try to find an unloaded module
                             // that overlaps with its address range. The may be multiple, so record all
@@ -1196,9 +1508,11 @@ impl<'a> MinidumpInfo<'a> {
                         }
 
                         if options.recover_function_args {
-                            arg_recovery::fill_arguments(stack, stack_memory);
+                            arg_recovery::fill_arguments(stack, stack_memory, system_info.os);
                         }
 
+                        recursion::fill_recursion(stack);
+
                         // Report the unwalked result
                         if let Some(reporter) = options.stat_reporter {
                             reporter.inc_processed_threads();
@@ -1210,6 +1524,113 @@ impl<'a> MinidumpInfo<'a> {
             .await
         };
 
+        let sources = crate::register_annotations::AnnotationSources {
+            cpu: state.system_info.cpu,
+            threads: &state.threads,
+            thread_list: &self.thread_list,
+            modules: &state.modules,
+            memory_info: &self.memory_info,
+            poison_patterns: self.options.poison_patterns,
+        };
+        let crashing_context = state
+            .requesting_thread
+            .and_then(|i| state.threads[i].frames.first())
+            .map(|frame| &frame.context);
+        let register_annotations = match crashing_context {
+            Some(context) if self.options.annotate_registers => {
+                crate::register_annotations::annotate_registers(context, &sources, symbol_provider)
+                    .await
+            }
+            _ => Vec::new(),
+        };
+        // The stack memory is dumped before collapsing recursion, so that the words in the
+        // parts of the stack of the collapsed frames can be attributed properly.
+        let mut stack_memory_dump = match state.requesting_thread {
+            Some(i) if self.options.annotate_stack_memory => {
+                let stack = &state.threads[i];
+                match select_stack_memory(&self.thread_list.threads[i], stack, &self.memory_list) {
+                    Some(memory) => {
+                        crate::stack_memory_dump::dump_stack_memory(
+                            i,
+                            stack,
+                            memory,
+                            pointer_size as u8,
+                            &sources,
+                            symbol_provider,
+                        )
+                        .await
+                    }
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+        if self.options.collapse_recursion {
+            for (i, stack) in state.threads.iter_mut().enumerate() {
+                let Some(removed) = recursion::collapse_recursion(stack) else {
+                    continue;
+                };
+                if let Some(dump) = stack_memory_dump
+                    .as_mut()
+                    .filter(|dump| dump.threads_index == i)
+                {
+                    crate::stack_memory_dump::remove_frames(dump, removed);
+                }
+            }
+        }
+
+        // Only hang dumps (which have no exception) are analyzed, in crash dumps
+        // idle threads would just look blocked.
+        if self.exception.is_none() {
+            state.hang_analysis = hang_analysis::analyze_waits(
+                &state.threads,
+                &self.memory_list,
+                &state.system_info,
+                self.options.wait_primitives,
+            );
+        }
+
+        // The crashing thread's context is the template for the stowed stacks' frames.
+        let crashing_context = state
+            .requesting_thread
+            .and_then(|i| state.threads[i].frames.first())
+            .map(|frame| &frame.context);
+        let stowed_exceptions = match (&self.exception, crashing_context) {
+            (Some(exception), Some(context)) => {
+                crate::stowed_exception::decode_stowed_exceptions(
+                    &exception.raw.exception_record,
+                    context,
+                    pointer_size,
+                    &self.memory_list,
+                    &state.modules,
+                    symbol_provider,
+                )
+                .await
+            }
+            _ => Vec::new(),
+        };
+
+        state.stowed_exceptions = stowed_exceptions;
+        state.register_annotations = register_annotations;
+        let disassembly = match state.requesting_thread {
+            Some(i) if self.options.disassembly_window > 0 => {
+                crate::disassembly::disassemble_thread(
+                    i,
+                    &state.threads[i],
+                    state.system_info.cpu,
+                    self.options.disassembly_window,
+                    self.options.disassemble_return_addresses,
+                    &self.memory_list,
+                    &state.modules,
+                    symbol_provider,
+                )
+                .await
+            }
+            _ => None,
+        };
+        state.stack_memory_dump = stack_memory_dump;
+        state.disassembly = disassembly;
+
         let symbol_stats = symbol_provider.stats();
         state.symbol_stats = symbol_stats;
 
@@ -1229,6 +1650,10 @@ impl crate::ExceptionInfo {
             instruction_pointer_update: None,
             possible_bit_flips: Default::default(),
             inconsistencies: Default::default(),
+            poison_pattern_matches: Default::default(),
+            is_likely_stack_overflow: false,
+            cxx_exception: None,
+            hardware_fault: None,
         }
     }
 
@@ -1248,6 +1673,10 @@ impl crate::ExceptionInfo {
             instruction_pointer_update: op_analysis.instruction_pointer_update,
             possible_bit_flips: Default::default(),
             inconsistencies: Default::default(),
+            poison_pattern_matches: Default::default(),
+            is_likely_stack_overflow: false,
+            cxx_exception: None,
+            hardware_fault: None,
         }
     }
 }
@@ -1258,6 +1687,68 @@ struct ExceptionDetails<'a> {
     instruction_registers: BTreeSet<&'static str>,
 }
 
+/// Find the stack memory of `thread`.
+///
+/// Always choose the memory region that is referenced by the context, as the
+/// `exception_context` may refer to a different memory region than the `thread_context`,
+/// which in turn would fail to stack walk.
+fn select_stack_memory<'mem, 'a>(
+    thread: &'mem MinidumpThread<'a>,
+    stack: &CallStack,
+    memory_list: &'mem UnifiedMemoryList<'a>,
+) -> Option<UnifiedMemory<'mem, 'a>> {
+    let stack_memory = thread.stack_memory(memory_list);
+    let stack_ptr = stack
+        .frames
+        .first()
+        .map(|ctx_frame| ctx_frame.context.get_stack_pointer());
+    if let Some(stack_ptr) = stack_ptr {
+        let contains_stack_ptr = stack_memory
+            .as_ref()
+            .and_then(|memory| memory.get_memory_at_address::<u64>(stack_ptr))
+            .is_some();
+        if !contains_stack_ptr {
+            return memory_list.memory_at_address(stack_ptr).or(stack_memory);
+        }
+    }
+    stack_memory
+}
+
+/// Drop the frames the unwinder found outside of the thread's stack, as given by its TEB.
+///
+/// Stack scanning can easily wander off the end of the current stack, especially with
+/// fibers, whose stacks are often allocated right next to each other. If the context's
+/// own stack pointer is out of bounds the TEB doesn't describe this stack, so nothing
+/// is dropped.
+fn truncate_at_out_of_bounds_frame(stack: &mut CallStack) {
+    let Some(teb) = &stack.thread_environment_block else {
+        return;
+    };
+    let in_bounds = |frame: &StackFrame| teb.stack_contains(frame.context.get_stack_pointer());
+    match stack.frames.first() {
+        Some(frame) if in_bounds(frame) => {}
+        Some(frame) => {
+            tracing::warn!(
+                "stack pointer {:#x} of thread {} is outside of its stack ({:#x}-{:#x})",
+                frame.context.get_stack_pointer(),
+                stack.thread_id,
+                teb.stack_limit,
+                teb.stack_base,
+            );
+            return;
+        }
+        None => return,
+    }
+    if let Some(idx) = stack.frames.iter().position(|frame| !in_bounds(frame)) {
+        tracing::debug!(
+            "dropping {} frames outside of the stack of thread {}",
+            stack.frames.len() - idx,
+            stack.thread_id
+        );
+        stack.frames.truncate(idx);
+    }
+}
+
 /// If a non-canonical access caused a crash, return the real address
 ///
 /// Amd64 has the concept of a "canonical addressing", which requires that the upper 16 bits of
@@ -1454,6 +1945,112 @@ pub mod memory_operation {
     }
 }
 
+/// Allocator poison pattern detection.
+pub mod poison {
+    use std::borrow::Cow;
+
+    use crate::PoisonKind;
+
+    /// The maximum distance from a poison value for an address to still be considered poisoned.
+    ///
+    /// Poisoned pointers are rarely dereferenced as-is: usually a field at some offset is
+    /// accessed, so we allow for that.
+    pub const MAX_POISON_OFFSET: u64 = 1 << 12;
+
+    /// A byte pattern that an allocator (or runtime) writes into memory to mark its state.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct PoisonPattern {
+        /// A short name for the pattern, reported in the output.
+        pub name: Cow<'static, str>,
+        /// The repeating unit of the pattern (e.g. `0xe5` or `0xfeee`).
+        pub unit: u64,
+        /// The size of `unit` in bytes; the unit is repeated to fill a register.
+        pub unit_size: u8,
+        /// What the pattern indicates about the memory it was found in.
+        pub kind: PoisonKind,
+    }
+
+    impl PoisonPattern {
+        pub const fn new(name: &'static str, unit: u64, unit_size: u8, kind: PoisonKind) -> Self {
+            PoisonPattern {
+                name: Cow::Borrowed(name),
+                unit,
+                unit_size,
+                kind,
+            }
+        }
+
+        /// Repeat the unit to fill `size` bytes.
+        fn repeated(&self, size: u8) -> u64 {
+            let unit_size = self.unit_size.clamp(1, 8);
+            let unit_mask = u64::MAX >> (64 - unit_size as u32 * 8);
+            let mut value = 0u64;
+            let mut filled = 0;
+            while filled < size {
+                value =
+                    value.checked_shl(unit_size as u32 * 8).unwrap_or(0) | (self.unit & unit_mask);
+                filled += unit_size;
+            }
+            value
+        }
+
+        /// Whether `value` (read from a register of `register_size` bytes) is this pattern,
+        /// possibly with a small offset applied.
+        pub fn matches(&self, value: u64, register_size: u8) -> bool {
+            let near = |poison: u64| value.abs_diff(poison) <= MAX_POISON_OFFSET;
+            if self.unit_size > register_size {
+                return false;
+            }
+            if near(self.repeated(register_size)) {
+                return true;
+            }
+            // 32-bit values are commonly loaded zero-extended into 64-bit registers.
+            register_size == 8 && self.unit_size <= 4 && near(self.repeated(4))
+        }
+    }
+
+    /// Find the first pattern in `patterns` which matches `value`.
+    pub fn find_match(
+        patterns: &[PoisonPattern],
+        value: u64,
+        register_size: u8,
+    ) -> Option<&PoisonPattern> {
+        patterns.iter().find(|p| p.matches(value, register_size))
+    }
+
+    /// The poison patterns used by default.
+    ///
+    /// Sources:
+    /// * mozjemalloc: <https://searchfox.org/mozilla-central/source/memory/build/mozjemalloc.cpp>
+    /// * SpiderMonkey: <https://searchfox.org/mozilla-central/source/js/src/util/Poison.h>
+    /// * jemalloc `opt.junk`
+    /// * the MSVC debug CRT and Windows debug heap
+    pub static DEFAULT_POISON_PATTERNS: &[PoisonPattern] = &[
+        PoisonPattern::new("mozjemalloc-freed", 0xe5, 1, PoisonKind::Freed),
+        PoisonPattern::new("mozjemalloc-alloc-junk", 0xe4, 1, PoisonKind::Uninitialized),
+        PoisonPattern::new("jemalloc-freed", 0x5a, 1, PoisonKind::Freed),
+        PoisonPattern::new("jemalloc-alloc-junk", 0xa5, 1, PoisonKind::Uninitialized),
+        PoisonPattern::new("js-swept-nursery", 0x2b, 1, PoisonKind::Freed),
+        PoisonPattern::new("js-swept-tenured", 0x4b, 1, PoisonKind::Freed),
+        PoisonPattern::new("js-freed-heap-ptr", 0x6b, 1, PoisonKind::Freed),
+        PoisonPattern::new("js-freed-chunk", 0x8b, 1, PoisonKind::Freed),
+        PoisonPattern::new("js-freed-arena", 0x9b, 1, PoisonKind::Freed),
+        PoisonPattern::new("msvc-dead-land", 0xdd, 1, PoisonKind::Freed),
+        PoisonPattern::new("msvc-clean-land", 0xcd, 1, PoisonKind::Uninitialized),
+        PoisonPattern::new("msvc-uninit-stack", 0xcc, 1, PoisonKind::Uninitialized),
+        PoisonPattern::new("msvc-no-mans-land", 0xfd, 1, PoisonKind::Guard),
+        PoisonPattern::new("win-heap-freed", 0xfeee, 2, PoisonKind::Freed),
+        PoisonPattern::new("win-heap-guard", 0xab, 1, PoisonKind::Guard),
+        PoisonPattern::new(
+            "win-local-alloc-uninit",
+            0xbaadf00d,
+            4,
+            PoisonKind::Uninitialized,
+        ),
+        PoisonPattern::new("deadbeef", 0xdeadbeef, 4, PoisonKind::Unknown),
+    ];
+}
+
 /// Bit-flip detection.
 mod bitflip {
     use super::*;
@@ -0,0 +1,207 @@
+use std::ops::Range;
+
+use minidump_unwind::{CallStack, Recursion};
+
+// # Detecting recursion
+//
+// Stack overflows are very often caused by unbounded recursion, which results
+// in thousands of identical frames. These make the output enormous and the
+// interesting frames (the ones that kicked off the recursion) hard to find.
+//
+// We identify frames by their instruction address. For a true recursion every
+// repetition of the cycle has exactly the same return addresses, so this is both
+// precise and doesn't depend on symbols being available. A cycle of period `p`
+// starting at frame `i` is then just a run where `frames[j] == frames[j + p]`.
+//
+// For every period up to `MAX_PERIOD` we look for the longest such run, and keep
+// the one covering the most frames. On ties the smaller period wins, since a
+// cycle of period `p` also trivially matches any multiple of `p`.
+
+/// The longest cycle we'll look for.
+const MAX_PERIOD: usize = 64;
+
+/// The minimum number of complete repetitions for a cycle to count as recursion.
+const MIN_REPEATS: usize = 3;
+
+/// Find the most significant recursive region of `stack`.
+pub fn find_recursion(stack: &CallStack) -> Option<Recursion> {
+    let addrs = stack
+        .frames
+        .iter()
+        .map(|frame| frame.instruction)
+        .collect::<Vec<_>>();
+
+    let mut best: Option<Recursion> = None;
+    for period in 1..=MAX_PERIOD {
+        if period * MIN_REPEATS > addrs.len() {
+            break;
+        }
+
+        let mut run = 0;
+        for j in 0..addrs.len() - period {
+            if addrs[j] == addrs[j + period] {
+                run += 1;
+            } else {
+                run = 0;
+            }
+
+            // A run of `run` matches covers `run + period` frames. Only consider the
+            // region once it ends (or we hit the end of the stack).
+            let next = j + 1;
+            let ends_here = next == addrs.len() - period || addrs[next] != addrs[next + period];
+            if run == 0 || !ends_here {
+                continue;
+            }
+
+            let depth = run + period;
+            let repeat_count = depth / period;
+            if repeat_count < MIN_REPEATS {
+                continue;
+            }
+            if best.as_ref().is_none_or(|best| depth > best.depth) {
+                best = Some(Recursion {
+                    start_frame: j + 1 - run,
+                    period,
+                    repeat_count,
+                    depth,
+                    collapsed_frames: 0,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// Detect recursion in `stack` and record it.
+pub fn fill_recursion(stack: &mut CallStack) {
+    stack.recursion = find_recursion(stack);
+}
+
+/// Collapse the recursion recorded by [`fill_recursion`], keeping only the first
+/// repetition of the cycle.
+///
+/// Only complete repetitions are removed, so a partial cycle at the outer end of
+/// the region (where the recursion was entered) is kept.
+///
+/// Returns the indices the removed frames had before collapsing.
+pub fn collapse_recursion(stack: &mut CallStack) -> Option<Range<usize>> {
+    let recursion = stack.recursion.as_mut()?;
+    let first_repetition_end = recursion.start_frame + recursion.period;
+    let removed = first_repetition_end
+        ..first_repetition_end + recursion.period * (recursion.repeat_count - 1);
+    stack.frames.drain(removed.clone());
+    recursion.collapsed_frames = removed.len();
+    Some(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minidump::{MinidumpContext, MinidumpRawContext};
+    use minidump_unwind::{FrameTrust, StackFrame};
+
+    fn stack_from_addrs(addrs: &[u64]) -> CallStack {
+        let context = MinidumpContext::from_raw(MinidumpRawContext::Amd64(Default::default()));
+        let mut stack = CallStack::with_context(context);
+        stack.frames = addrs
+            .iter()
+            .map(|&addr| {
+                let mut frame = StackFrame::from_context(
+                    stack.frames[0].context.clone(),
+                    FrameTrust::CallFrameInfo,
+                );
+                frame.instruction = addr;
+                frame
+            })
+            .collect();
+        stack
+    }
+
+    #[test]
+    fn no_recursion() {
+        let stack = stack_from_addrs(&[1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(find_recursion(&stack), None);
+    }
+
+    #[test]
+    fn too_few_repeats() {
+        let stack = stack_from_addrs(&[1, 2, 3, 2, 3, 4]);
+        assert_eq!(find_recursion(&stack), None);
+    }
+
+    #[test]
+    fn direct_recursion() {
+        let stack = stack_from_addrs(&[1, 2, 2, 2, 2, 2, 3, 4]);
+        assert_eq!(
+            find_recursion(&stack),
+            Some(Recursion {
+                start_frame: 1,
+                period: 1,
+                repeat_count: 5,
+                depth: 5,
+                collapsed_frames: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        let stack = stack_from_addrs(&[1, 2, 3, 4, 2, 3, 4, 2, 3, 4, 2, 3, 5]);
+        assert_eq!(
+            find_recursion(&stack),
+            Some(Recursion {
+                start_frame: 1,
+                period: 3,
+                repeat_count: 3,
+                depth: 11,
+                collapsed_frames: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn prefers_smallest_period() {
+        let stack = stack_from_addrs(&[7; 12]);
+        let recursion = find_recursion(&stack).unwrap();
+        assert_eq!(recursion.period, 1);
+        assert_eq!(recursion.depth, 12);
+    }
+
+    #[test]
+    fn collapse() {
+        let mut stack = stack_from_addrs(&[1, 2, 3, 2, 3, 2, 3, 2, 3, 4]);
+        fill_recursion(&mut stack);
+        assert_eq!(collapse_recursion(&mut stack), Some(3..9));
+        let addrs = stack
+            .frames
+            .iter()
+            .map(|f| f.instruction)
+            .collect::<Vec<_>>();
+        assert_eq!(addrs, vec![1, 2, 3, 4]);
+        assert_eq!(
+            stack.recursion,
+            Some(Recursion {
+                start_frame: 1,
+                period: 2,
+                repeat_count: 4,
+                depth: 8,
+                collapsed_frames: 6,
+            })
+        );
+    }
+
+    #[test]
+    fn collapse_partial_cycle() {
+        // The recursion was entered through 3, so the cycle doesn't end evenly.
+        let mut stack = stack_from_addrs(&[1, 2, 3, 4, 2, 3, 4, 2, 3, 4, 2, 3, 5]);
+        fill_recursion(&mut stack);
+        assert_eq!(collapse_recursion(&mut stack), Some(4..10));
+        let addrs = stack
+            .frames
+            .iter()
+            .map(|f| f.instruction)
+            .collect::<Vec<_>>();
+        assert_eq!(addrs, vec![1, 2, 3, 4, 2, 3, 5]);
+        assert_eq!(stack.recursion.unwrap().collapsed_frames, 6);
+    }
+}
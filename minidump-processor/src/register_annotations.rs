@@ -0,0 +1,156 @@
+use breakpad_symbols::SimpleFrame;
+use minidump::system_info::Cpu;
+use minidump::{
+    MinidumpContext, MinidumpModuleList, MinidumpThreadList, Module, UnifiedMemoryInfo,
+    UnifiedMemoryInfoList,
+};
+use minidump_common::format::MemoryState;
+use minidump_common::utils::basename;
+use minidump_unwind::{is_non_canonical, CallStack, SymbolProvider};
+
+use crate::processor::poison::{self, PoisonPattern};
+use crate::{RegisterAnnotation, RegisterTarget};
+
+/// Values below this are considered null pointers (plus a field offset).
+const NULL_PAGE_SIZE: u64 = 0x1000;
+
+/// Registers which never hold pointers.
+const NON_POINTER_REGISTERS: &[&str] = &["eflags"];
+
+/// Everything needed to classify register values.
+pub struct AnnotationSources<'a> {
+    pub cpu: Cpu,
+    pub threads: &'a [CallStack],
+    pub thread_list: &'a MinidumpThreadList<'a>,
+    pub modules: &'a MinidumpModuleList,
+    pub memory_info: &'a UnifiedMemoryInfoList<'a>,
+    pub poison_patterns: &'a [PoisonPattern],
+}
+
+/// Classify what each valid register in `context` points to.
+pub async fn annotate_registers<P>(
+    context: &MinidumpContext,
+    sources: &AnnotationSources<'_>,
+    symbol_provider: &P,
+) -> Vec<RegisterAnnotation>
+where
+    P: SymbolProvider + Sync,
+{
+    let register_size = context.register_size() as u8;
+    let mut annotations = Vec::new();
+    for (register, value) in context.valid_registers() {
+        if NON_POINTER_REGISTERS.contains(&register) {
+            continue;
+        }
+        if let Some(target) = annotate_pointer(value, register_size, sources, symbol_provider).await
+        {
+            annotations.push(RegisterAnnotation {
+                register,
+                value: value.into(),
+                target,
+            });
+        }
+    }
+    annotations
+}
+
+/// Classify what `value` points to, if anything known.
+pub async fn annotate_pointer<P>(
+    value: u64,
+    register_size: u8,
+    sources: &AnnotationSources<'_>,
+    symbol_provider: &P,
+) -> Option<RegisterTarget>
+where
+    P: SymbolProvider + Sync,
+{
+    if let Some(target) = classify(value, register_size, sources) {
+        return Some(target);
+    }
+    if let Some(target) = symbolize(value, sources.modules, symbol_provider).await {
+        return Some(target);
+    }
+    mapped_region(value, sources.memory_info)
+}
+
+/// The classifications which don't need symbols.
+fn classify(value: u64, register_size: u8, sources: &AnnotationSources) -> Option<RegisterTarget> {
+    if value < NULL_PAGE_SIZE {
+        return Some(RegisterTarget::Null);
+    }
+    if let Some(pattern) = poison::find_match(sources.poison_patterns, value, register_size) {
+        return Some(RegisterTarget::Poison {
+            pattern: pattern.name.clone(),
+            poison_kind: pattern.kind,
+        });
+    }
+    if sources.cpu == Cpu::X86_64 && is_non_canonical(value) {
+        return Some(RegisterTarget::NonCanonical);
+    }
+    stack_of(value, sources)
+}
+
+/// Find the thread whose stack contains `value`.
+///
+/// The TEB's stack bounds are used if known, otherwise the stack memory saved in
+/// the minidump.
+fn stack_of(value: u64, sources: &AnnotationSources) -> Option<RegisterTarget> {
+    let threads_index = sources
+        .threads
+        .iter()
+        .zip(sources.thread_list.threads.iter())
+        .position(|(stack, thread)| match &stack.thread_environment_block {
+            Some(teb) => teb.stack_contains(value),
+            None => {
+                let start = thread.raw.stack.start_of_memory_range;
+                let size = thread.raw.stack.memory.data_size as u64;
+                size != 0 && (start..start.saturating_add(size)).contains(&value)
+            }
+        })?;
+    Some(RegisterTarget::Stack {
+        threads_index,
+        thread_id: sources.threads[threads_index].thread_id,
+    })
+}
+
+/// Find the module containing `value`, and the function if there are symbols for it.
+pub async fn symbolize<P>(
+    value: u64,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+) -> Option<RegisterTarget>
+where
+    P: SymbolProvider + Sync,
+{
+    let module = modules.module_at_address(value)?;
+    let mut frame = SimpleFrame::with_instruction(value);
+    // Missing symbols are fine, we'll just report the module offset.
+    let _ = symbol_provider.fill_symbol(module, &mut frame).await;
+    Some(RegisterTarget::Module {
+        module: basename(&module.code_file()).to_owned(),
+        module_offset: (value - module.base_address()).into(),
+        function_offset: frame.function_base.map(|base| (value - base).into()),
+        function: frame.function,
+    })
+}
+
+fn mapped_region(value: u64, memory_info: &UnifiedMemoryInfoList) -> Option<RegisterTarget> {
+    let info = memory_info.memory_info_at_address(value)?;
+    if let UnifiedMemoryInfo::Info(info) = info {
+        if info.state.contains(MemoryState::MEM_FREE) {
+            return None;
+        }
+    }
+    let flag = |set: bool, c: char| if set { c } else { '-' };
+    let protection = [
+        flag(info.is_readable(), 'r'),
+        flag(info.is_writable(), 'w'),
+        flag(info.is_executable(), 'x'),
+    ]
+    .iter()
+    .collect();
+    Some(RegisterTarget::Mapped {
+        protection,
+        name: info.name().map(|name| name.into_owned()),
+    })
+}
@@ -0,0 +1,109 @@
+use std::ops::Range;
+
+use minidump::UnifiedMemory;
+use minidump_unwind::{CallStack, SymbolProvider};
+
+use crate::register_annotations::{annotate_pointer, AnnotationSources};
+use crate::{RegisterTarget, StackMemoryDump, StackWord};
+
+/// The maximum number of words to dump.
+const MAX_STACK_WORDS: u64 = 4096;
+
+/// Dump the stack memory of `stack`, from its stack pointer up, annotating every word.
+///
+/// `stack` must have been walked already, as the frames' stack pointers are used to
+/// tell which frame each word belongs to. Recursion must not have been collapsed yet,
+/// see [`remove_frames`] for fixing up the frame indices afterwards.
+pub async fn dump_stack_memory<P>(
+    threads_index: usize,
+    stack: &CallStack,
+    memory: UnifiedMemory<'_, '_>,
+    word_size: u8,
+    sources: &AnnotationSources<'_>,
+    symbol_provider: &P,
+) -> Option<StackMemoryDump>
+where
+    P: SymbolProvider + Sync,
+{
+    let stack_pointer = stack.frames.first()?.context.get_stack_pointer();
+    let end = memory.base_address().checked_add(memory.size())?;
+    let word_count = (end.checked_sub(stack_pointer)? / word_size as u64).min(MAX_STACK_WORDS);
+
+    let mut words = Vec::new();
+    for i in 0..word_count {
+        let address = stack_pointer + i * word_size as u64;
+        let value = match word_size {
+            4 => memory.get_memory_at_address::<u32>(address).map(u64::from),
+            _ => memory.get_memory_at_address::<u64>(address),
+        };
+        let Some(value) = value else {
+            break;
+        };
+
+        let frame = stack
+            .frames
+            .iter()
+            .rposition(|frame| frame.context.get_stack_pointer() <= address);
+        let is_return_address = frame
+            .and_then(|frame| stack.frames.get(frame + 1))
+            .is_some_and(|caller| caller.context.get_instruction_pointer() == value);
+        let target = annotate_pointer(value, word_size, sources, symbol_provider)
+            .await
+            .filter(|target| *target != RegisterTarget::Null);
+
+        words.push(StackWord {
+            address: address.into(),
+            value: value.into(),
+            frame,
+            is_return_address,
+            target,
+        });
+    }
+
+    Some(StackMemoryDump {
+        threads_index,
+        words,
+    })
+}
+
+/// Update the frame indices of `dump` after the frames in `removed` were dropped.
+///
+/// Words in the part of the stack of a removed frame no longer belong to any frame.
+pub fn remove_frames(dump: &mut StackMemoryDump, removed: Range<usize>) {
+    for word in &mut dump.words {
+        word.frame = match word.frame {
+            Some(frame) if removed.contains(&frame) => None,
+            Some(frame) if frame >= removed.end => Some(frame - removed.len()),
+            frame => frame,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_collapsed_frames() {
+        let word = |frame| StackWord {
+            address: 0.into(),
+            value: 0.into(),
+            frame,
+            is_return_address: false,
+            target: None,
+        };
+        let mut dump = StackMemoryDump {
+            threads_index: 0,
+            words: vec![None, Some(0), Some(1), Some(2), Some(3), Some(4), Some(5)]
+                .into_iter()
+                .map(word)
+                .collect(),
+        };
+        remove_frames(&mut dump, 2..4);
+        let frames = dump.words.iter().map(|word| word.frame).collect::<Vec<_>>();
+        assert_eq!(
+            frames,
+            vec![None, Some(0), Some(1), None, None, Some(2), Some(3)]
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+
+use minidump::{
+    CpuContext, CrashReason, MinidumpContext, MinidumpContextValidity, MinidumpModuleList,
+    MinidumpRawContext, UnifiedMemoryList,
+};
+use minidump_common::errors::NtStatusWindows;
+use minidump_common::format::MINIDUMP_EXCEPTION;
+use minidump_unwind::{
+    symbolicate_stack, CallStack, CallStackInfo, FrameTrust, StackFrame, SymbolProvider,
+};
+use tracing::debug;
+
+use crate::{StowedException, StowedExceptionReason};
+
+// # Decoding WinRT stowed exceptions
+//
+// When a WinRT error goes unhandled, the runtime raises STATUS_STOWED_EXCEPTION
+// with the following parameters:
+//
+// * `[0]` a pointer to an array of pointers to `STOWED_EXCEPTION_INFORMATION_V*`
+// * `[1]` the number of entries in that array
+//
+// Every entry starts with a `{ ULONG Size; ULONG Signature; }` header, where the
+// signature says whether it's a V1 or a V2 struct. The rest looks like this
+// (offsets are given for 32-bit/64-bit):
+//
+// * `0x08`        `HRESULT ResultCode`
+// * `0x0c`        `ExceptionForm : 2` and `ThreadId : 30` bitfields
+// * `0x10`        either (form 1):
+//   * `0x10/0x10` `PVOID ExceptionAddress`
+//   * `0x14/0x18` `ULONG StackTraceWordSize`
+//   * `0x18/0x1c` `ULONG StackTraceWords`
+//   * `0x1c/0x20` `PVOID StackTrace`
+//
+//   or (form 2) `PWSTR ErrorText`
+// * V2 only, after the union:
+//   * `0x20/0x28` `ULONG NestedExceptionType`
+//   * `0x24/0x30` `PVOID NestedException`
+//
+// The stack trace is an array of return addresses, which we symbolicate into an
+// extra stack for each exception.
+
+/// `'SE01'`
+const STOWED_EXCEPTION_V1_SIGNATURE: u32 = 0x53453031;
+/// `'SE02'`
+const STOWED_EXCEPTION_V2_SIGNATURE: u32 = 0x53453032;
+
+/// `ExceptionForm` for exceptions which captured a stack trace.
+const EXCEPTION_FORM_STACK_TRACE: u32 = 1;
+/// `ExceptionForm` for exceptions which have an error message.
+const EXCEPTION_FORM_ERROR_TEXT: u32 = 2;
+
+/// Sanity limit on the number of stowed exceptions.
+const MAX_STOWED_EXCEPTIONS: u64 = 64;
+/// Sanity limit on the number of frames in a stowed stack trace.
+const MAX_STACK_TRACE_WORDS: u32 = 1024;
+/// Maximum length (in UTF-16 code units) of an error message.
+const MAX_ERROR_TEXT_LEN: usize = 1024;
+
+/// Decode the stowed exceptions of a STATUS_STOWED_EXCEPTION crash.
+///
+/// `context` is used as a template for the contexts of the stowed stacks' frames.
+/// Returns an empty list if the crash isn't a stowed exception.
+pub async fn decode_stowed_exceptions<P>(
+    exception: &MINIDUMP_EXCEPTION,
+    context: &MinidumpContext,
+    pointer_size: u64,
+    memory_list: &UnifiedMemoryList<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+) -> Vec<StowedException>
+where
+    P: SymbolProvider + Sync,
+{
+    if exception.exception_code != NtStatusWindows::STATUS_STOWED_EXCEPTION as u32
+        || exception.number_parameters < 2
+    {
+        return Vec::new();
+    }
+    let memory = StowedMemory {
+        memory_list,
+        pointer_size,
+    };
+    let array = exception.exception_information[0];
+    let count = exception.exception_information[1].min(MAX_STOWED_EXCEPTIONS);
+
+    let mut stowed_exceptions = Vec::new();
+    for i in 0..count {
+        let entry = i
+            .checked_mul(pointer_size)
+            .and_then(|offset| array.checked_add(offset));
+        let Some(address) = entry.and_then(|entry| memory.read_ptr(entry)) else {
+            debug!("stowed exception array is not in the dump");
+            break;
+        };
+        let Some(mut stowed) = memory.read_stowed_exception(address, context) else {
+            debug!("failed to read stowed exception at {address:#x}");
+            continue;
+        };
+        symbolicate_stack(&mut stowed.stack, modules, symbol_provider).await;
+        stowed_exceptions.push(stowed);
+    }
+    stowed_exceptions
+}
+
+struct StowedMemory<'a, 'mdmp> {
+    memory_list: &'a UnifiedMemoryList<'mdmp>,
+    pointer_size: u64,
+}
+
+impl StowedMemory<'_, '_> {
+    fn read_u16(&self, address: u64) -> Option<u16> {
+        self.memory_list
+            .memory_at_address(address)?
+            .get_memory_at_address(address)
+    }
+
+    fn read_u32(&self, address: u64) -> Option<u32> {
+        self.memory_list
+            .memory_at_address(address)?
+            .get_memory_at_address(address)
+    }
+
+    fn read_word(&self, address: u64, size: u64) -> Option<u64> {
+        match size {
+            8 => self
+                .memory_list
+                .memory_at_address(address)?
+                .get_memory_at_address(address),
+            _ => self.read_u32(address).map(u64::from),
+        }
+    }
+
+    fn read_ptr(&self, address: u64) -> Option<u64> {
+        self.read_word(address, self.pointer_size)
+    }
+
+    /// Read a NUL-terminated UTF-16 string at `address`.
+    fn read_wstr(&self, address: u64) -> Option<String> {
+        let units = (0..MAX_ERROR_TEXT_LEN as u64)
+            .map(|i| self.read_u16(address.checked_add(i * 2)?))
+            .take_while(|unit| *unit != Some(0))
+            .collect::<Option<Vec<u16>>>()?;
+        Some(String::from_utf16_lossy(&units))
+    }
+
+    fn read_stowed_exception(
+        &self,
+        address: u64,
+        template: &MinidumpContext,
+    ) -> Option<StowedException> {
+        let ptr = self.pointer_size;
+        let field = |offset: u64| address.checked_add(offset);
+        let signature = self.read_u32(field(4)?)?;
+        let version = match signature {
+            STOWED_EXCEPTION_V1_SIGNATURE => 1,
+            STOWED_EXCEPTION_V2_SIGNATURE => 2,
+            _ => return None,
+        };
+        let result_code = self.read_u32(field(8)?)?;
+        let form_and_thread = self.read_u32(field(12)?)?;
+        let form = form_and_thread & 0x3;
+        let thread_id = form_and_thread >> 2;
+
+        let union = field(16)?;
+        // The union is as large as the stack trace struct.
+        let union_field = |offset: u64| union.checked_add(offset);
+        let mut exception_address = None;
+        let mut message = None;
+        let mut stack = CallStack::with_info(thread_id, CallStackInfo::Ok);
+        match form {
+            EXCEPTION_FORM_STACK_TRACE => {
+                exception_address = self.read_ptr(union);
+                let word_size = self.read_u32(union_field(ptr)?)? as u64;
+                if word_size != 4 && word_size != 8 {
+                    debug!("invalid stowed stack trace word size {word_size}");
+                    return None;
+                }
+                let words = self
+                    .read_u32(union_field(ptr + 4)?)?
+                    .min(MAX_STACK_TRACE_WORDS);
+                let trace = self.read_ptr(union_field(ptr + 8)?)?;
+                stack.frames = (0..words as u64)
+                    .map_while(|i| self.read_word(trace.checked_add(i * word_size)?, word_size))
+                    .filter_map(|return_address| frame_at(template, return_address))
+                    .collect();
+            }
+            EXCEPTION_FORM_ERROR_TEXT => {
+                message = self
+                    .read_ptr(union)
+                    .filter(|&text| text != 0)
+                    .and_then(|text| self.read_wstr(text));
+            }
+            _ => {}
+        }
+        if stack.frames.is_empty() {
+            stack.info = CallStackInfo::MissingMemory;
+        }
+
+        let nested_exception_type = if version >= 2 {
+            union_field(ptr + 8 + ptr)
+                .and_then(|address| self.read_u32(address))
+                .filter(|&ty| ty != 0)
+                .map(fourcc)
+        } else {
+            None
+        };
+
+        Some(StowedException {
+            result_code,
+            reason: stowed_exception_reason(result_code),
+            thread_id,
+            exception_address: exception_address.map(Into::into),
+            message,
+            nested_exception_type,
+            stack,
+        })
+    }
+}
+
+/// Decode a stowed exception's HRESULT.
+///
+/// WinRT errors are very often Win32 errors wrapped with `HRESULT_FROM_WIN32`, which
+/// [`CrashReason::from_windows_error`] doesn't know about, so those are unwrapped here.
+fn stowed_exception_reason(result_code: u32) -> StowedExceptionReason {
+    const SEVERITY_ERROR: u32 = 0x8000_0000;
+    const FACILITY_WIN32: u32 = 7;
+
+    if result_code & SEVERITY_ERROR != 0 && (result_code >> 16) & 0x1fff == FACILITY_WIN32 {
+        if let CrashReason::WindowsWinError(error) =
+            CrashReason::from_windows_error(result_code & 0xffff)
+        {
+            return StowedExceptionReason::Win32(error);
+        }
+    }
+    StowedExceptionReason::Other(CrashReason::from_windows_error(result_code))
+}
+
+/// Render a four-character code (like `'W32E'`) as a string.
+fn fourcc(value: u32) -> String {
+    value
+        .to_be_bytes()
+        .iter()
+        .map(|&b| if b.is_ascii_graphic() { b as char } else { '?' })
+        .collect()
+}
+
+/// Create a frame for a return address from a stowed stack trace.
+///
+/// The frame's context is `template` with only the instruction pointer valid.
+fn frame_at(template: &MinidumpContext, return_address: u64) -> Option<StackFrame> {
+    let mut raw = template.raw.clone();
+    let (ip, call_size) = match &mut raw {
+        MinidumpRawContext::Amd64(ctx) => {
+            let ip = ctx.instruction_pointer_register_name();
+            ctx.set_register(ip, return_address)?;
+            (ip, 1)
+        }
+        MinidumpRawContext::X86(ctx) => {
+            let ip = ctx.instruction_pointer_register_name();
+            ctx.set_register(ip, return_address as u32)?;
+            (ip, 1)
+        }
+        MinidumpRawContext::Arm64(ctx) => {
+            let ip = ctx.instruction_pointer_register_name();
+            ctx.set_register(ip, return_address)?;
+            (ip, 4)
+        }
+        MinidumpRawContext::Arm(ctx) => {
+            let ip = ctx.instruction_pointer_register_name();
+            ctx.set_register(ip, return_address as u32)?;
+            (ip, 2)
+        }
+        _ => return None,
+    };
+    let context = MinidumpContext {
+        raw,
+        valid: MinidumpContextValidity::Some(HashSet::from([ip])),
+    };
+    let mut frame = StackFrame::from_context(context, FrameTrust::PreWalked);
+    // These are return addresses, so point back into the call instruction.
+    frame.instruction = return_address.saturating_sub(call_size);
+    Some(frame)
+}
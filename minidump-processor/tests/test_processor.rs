@@ -7,8 +7,13 @@ use minidump::{
     Module,
 };
 use minidump_common::format::MemoryProtection;
-use minidump_processor::{Limit, LinuxStandardBase, ProcessState};
-use minidump_unwind::{simple_symbol_supplier, CallStackInfo, FrameTrust, Symbolizer};
+use minidump_processor::{
+    Address, BitFlipDetails, CrashInconsistency, HardwareFault, Limit, LinuxStandardBase,
+    PoisonKind, PoisonSource, PossibleBitFlip, ProcessState, ProcessorOptions, WaitKind,
+};
+use minidump_unwind::{
+    simple_symbol_supplier, string_symbol_supplier, CallStackInfo, FrameTrust, Symbolizer,
+};
 use std::path::{Path, PathBuf};
 
 use minidump_synth::*;
@@ -372,6 +377,103 @@ async fn test_bit_flip() {
     assert_eq!(bf.details, Default::default());
 }
 
+/// A dump crashing a bit flip away from the heap, on a Raptor Lake CPU with the
+/// given microcode version.
+fn hardware_fault_dump(microcode: &str) -> Minidump<'static, Vec<u8>> {
+    let context = minidump_synth::amd64_context(Endian::Little, 0, 0);
+
+    let stack = Memory::with_section(Section::with_endian(Endian::Little), 0);
+    let heap_info = MemoryInfo::new(Endian::Little, 0x80000, 0x80000, 0, 8, 0, 0, 0);
+
+    let thread = Thread::new(Endian::Little, 1, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+        )
+        .set_platform_id(minidump_common::format::PlatformId::Linux as u32);
+
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    ex.exception_record.exception_address = 0x80400;
+
+    let cpu_info = format!(
+        "processor\t: 0\nvendor_id\t: GenuineIntel\ncpu family\t: 6\nmodel\t\t: 183\n\
+         stepping\t: 1\nmicrocode\t: {microcode}\n\n\
+         processor\t: 1\nvendor_id\t: GenuineIntel\ncpu family\t: 6\nmodel\t\t: 183\n"
+    );
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack)
+        .add_memory_info(heap_info)
+        .set_linux_cpu_info(cpu_info.as_bytes());
+    Minidump::read(dump.finish().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn test_hardware_fault() {
+    let process = |dump, options| async move {
+        let symbolizer = Symbolizer::new(simple_symbol_supplier(vec![]));
+        minidump_processor::process_minidump_with_options(&dump, &symbolizer, options)
+            .await
+            .unwrap()
+            .exception_info
+            .unwrap()
+            .hardware_fault
+    };
+
+    // The crash address is a bit flip away from the heap, on a CPU with an unfixed erratum.
+    let hardware_fault = process(hardware_fault_dump("0x129"), ProcessorOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        hardware_fault.cpu_errata,
+        vec!["intel-raptor-lake-vmin-shift"]
+    );
+    assert_eq!(hardware_fault.bit_flip_confidence, Some(0.25));
+    assert!(!hardware_fault.impossible_instruction);
+    assert!((hardware_fault.confidence - 0.625).abs() < 0.001);
+
+    // Fixed by the microcode, leaving only the bit flip, which isn't enough.
+    let hardware_fault = process(hardware_fault_dump("0x12b"), ProcessorOptions::default()).await;
+    assert_eq!(hardware_fault, None);
+
+    let mut options = ProcessorOptions::default();
+    options.cpu_errata = &[];
+    let hardware_fault = process(hardware_fault_dump("0x129"), options).await;
+    assert_eq!(hardware_fault, None);
+}
+
+#[test]
+fn test_hardware_fault_signals() {
+    let bit_flip = PossibleBitFlip {
+        address: Address(0x80000),
+        source_register: None,
+        details: BitFlipDetails::default(),
+        confidence: Some(0.25),
+    };
+
+    // A bit flip on its own is already reported in `possible_bit_flips`.
+    let hardware_fault = HardwareFault::new(
+        vec![],
+        std::slice::from_ref(&bit_flip),
+        &[CrashInconsistency::CrashingAccessNotFoundInMemoryAccesses],
+    );
+    assert_eq!(hardware_fault, None);
+
+    // An impossible instruction is enough without an erratum.
+    let hardware_fault = HardwareFault::new(
+        vec![],
+        std::slice::from_ref(&bit_flip),
+        &[CrashInconsistency::IntDivByZeroNotPossible],
+    )
+    .unwrap();
+    assert!(hardware_fault.impossible_instruction);
+    assert_eq!(hardware_fault.bit_flip_confidence, Some(0.25));
+}
+
 #[tokio::test]
 async fn test_no_bit_flip_32bit() {
     let context = minidump_synth::x86_context(Endian::Little, 0, 0);
@@ -435,6 +537,514 @@ async fn test_bit_flip_arm64() {
         .is_empty());
 }
 
+#[tokio::test]
+async fn test_poison_crash_address() {
+    let context = minidump_synth::amd64_context(Endian::Little, 0, 0);
+
+    let stack = Memory::with_section(Section::with_endian(Endian::Little), 0);
+
+    let thread = Thread::new(Endian::Little, 1, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little).set_processor_architecture(
+        minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+    );
+
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    ex.exception_record.exception_address = 0xe5e5e5e5e5e5e5f5;
+
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack);
+
+    let state = read_synth_dump(dump).await;
+
+    let matches = state
+        .exception_info
+        .expect("missing exception info")
+        .poison_pattern_matches;
+
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].value.0, 0xe5e5e5e5e5e5e5f5);
+    assert_eq!(matches[0].source, PoisonSource::CrashAddress);
+    assert_eq!(matches[0].pattern, "mozjemalloc-freed");
+    assert_eq!(matches[0].kind, PoisonKind::Freed);
+}
+
+#[tokio::test]
+async fn test_poison_register() {
+    let context = minidump_synth::amd64_context(Endian::Little, 0x2000, 0xdddddddddddddde0);
+
+    // The bytes here are the opcode `mov al, [rsp]`.
+    let memory = Memory::with_section(
+        Section::with_endian(Endian::Little).append_bytes(&[0x8a, 0x04, 0x24]),
+        0x2000,
+    );
+    let stack = Memory::with_section(Section::with_endian(Endian::Little), 0x1000);
+
+    let thread = Thread::new(Endian::Little, 1, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little).set_processor_architecture(
+        minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+    );
+
+    let context_label = context.file_offset();
+    let context_size = context.file_size();
+
+    let dump = SynthMinidump::with_endian(Endian::Little).add(context);
+
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    ex.exception_record.exception_address = 0;
+    ex.thread_context = (
+        context_size.value().unwrap() as u32,
+        context_label.value().unwrap() as u32,
+    );
+
+    let dump = dump
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_system_info(system_info)
+        .add_memory(memory)
+        .add_memory(stack);
+
+    let state = read_synth_dump(dump).await;
+
+    let matches = state
+        .exception_info
+        .expect("missing exception info")
+        .poison_pattern_matches;
+
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].source, PoisonSource::Register);
+    assert_eq!(matches[0].source_register, Some("rsp"));
+    assert_eq!(matches[0].pattern, "msvc-dead-land");
+    assert_eq!(matches[0].kind, PoisonKind::Freed);
+}
+
+#[tokio::test]
+async fn test_poison_disabled() {
+    let context = minidump_synth::amd64_context(Endian::Little, 0, 0);
+
+    let stack = Memory::with_section(Section::with_endian(Endian::Little), 0);
+
+    let thread = Thread::new(Endian::Little, 1, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little).set_processor_architecture(
+        minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+    );
+
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    ex.exception_record.exception_address = 0xe5e5e5e5e5e5e5e5;
+
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack);
+
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let mut options = ProcessorOptions::default();
+    options.poison_patterns = &[];
+    let state = minidump_processor::process_minidump_with_options(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![])),
+        options,
+    )
+    .await
+    .unwrap();
+
+    assert!(state
+        .exception_info
+        .expect("missing exception info")
+        .poison_pattern_matches
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_stack_overflow() {
+    let context = minidump_synth::amd64_context(Endian::Little, 0x2000, 0x81010);
+
+    // The bytes here are the opcode `mov al, [rsp - 0x20]`, which touches the guard page just
+    // below the stack.
+    let memory = Memory::with_section(
+        Section::with_endian(Endian::Little).append_bytes(&[0x8a, 0x44, 0x24, 0xe0]),
+        0x2000,
+    );
+    let stack = Memory::with_section(Section::with_endian(Endian::Little), 0x1000);
+    let stack_info = MemoryInfo::new(
+        Endian::Little,
+        0x81000,
+        0x81000,
+        0,
+        4096,
+        0,
+        MemoryProtection::PAGE_EXECUTE_READWRITE.bits(),
+        0,
+    );
+    let guard_page_info = MemoryInfo::new(
+        Endian::Little,
+        0x80000,
+        0x80000,
+        0,
+        4096,
+        0,
+        MemoryProtection::PAGE_NOACCESS.bits(),
+        0,
+    );
+
+    let thread = Thread::new(Endian::Little, 1, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little).set_processor_architecture(
+        minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+    );
+
+    let context_label = context.file_offset();
+    let context_size = context.file_size();
+
+    let dump = SynthMinidump::with_endian(Endian::Little).add(context);
+
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    ex.exception_record.exception_address = 0x80ff0;
+    // Point the exception context at the main exception context.
+    // This is (size, offset).
+    ex.thread_context = (
+        context_size.value().unwrap() as u32,
+        context_label.value().unwrap() as u32,
+    );
+
+    let dump = dump
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_system_info(system_info)
+        .add_memory(memory)
+        .add_memory(stack)
+        .add_memory_info(stack_info)
+        .add_memory_info(guard_page_info);
+
+    let state = read_synth_dump(dump).await;
+
+    assert!(
+        state
+            .exception_info
+            .expect("missing exception info")
+            .is_likely_stack_overflow
+    );
+}
+
+/// Process a dump of two threads blocked in `__lll_lock_wait`, optionally with an
+/// exception.
+async fn process_deadlock_dump(with_exception: bool) -> ProcessState {
+    // Two threads blocked in `__lll_lock_wait`, each waiting on a mutex owned by the other.
+    // For simplicity, each mutex lives at the top of the waiting thread's stack. Its address
+    // is the first argument, in rdi.
+    let module_name = DumpString::new("libc.so.6", Endian::Little);
+    let module = minidump_synth::Module::new(
+        Endian::Little,
+        0x7000_0000,
+        0x2000,
+        &module_name,
+        0,
+        0,
+        None,
+    );
+    let symbols = "MODULE Linux x86_64 000000000000000000000000000000000 libc.so.6\n\
+                   FUNC 1000 100 0 __lll_lock_wait\n";
+
+    let mutex = |owner: u32| {
+        Section::with_endian(Endian::Little)
+            .D32(2) // __lock
+            .D32(0) // __count
+            .D32(owner) // __owner
+            .append_repeated(0, 0x100)
+    };
+    let stack1 = Memory::with_section(mutex(2), 0x10000);
+    let stack2 = Memory::with_section(mutex(1), 0x20000);
+    let context = |rip: u64, rsp: u64| {
+        Section::with_endian(Endian::Little)
+            .append_repeated(0, 8 * 6) // p[1-6]_home
+            .D32(0x10001f) // context_flags: CONTEXT_AMD64_ALL
+            .D32(0) // mx_csr
+            .append_repeated(0, 2 * 6) // cs,ds,es,fs,gs,ss
+            .D32(0) // eflags
+            .append_repeated(0, 8 * 6) // dr0,1,2,3,6,7
+            .append_repeated(0, 8 * 4) // rax,rcx,rdx,rbx
+            .D64(rsp)
+            .D64(0) // rbp
+            .D64(0) // rsi
+            .D64(rsp) // rdi
+            .append_repeated(0, 8 * 8) // r8-r15
+            .D64(rip)
+            .append_repeated(0, 512) // float_save
+            .append_repeated(0, 16 * 26) // vector_register
+            .append_repeated(0, 8 * 6) // trailing stuff
+    };
+    let context1 = context(0x7000_1010, 0x10000);
+    let context2 = context(0x7000_1020, 0x20000);
+    let thread1 = Thread::new(Endian::Little, 1, &stack1, &context1);
+    let thread2 = Thread::new(Endian::Little, 2, &stack2, &context2);
+
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+        )
+        .set_platform_id(minidump_common::format::PlatformId::Linux as u32);
+
+    let mut dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread1)
+        .add_thread(thread2)
+        .add_module(module)
+        .add(module_name)
+        .add_system_info(system_info)
+        .add(context1)
+        .add(context2)
+        .add_memory(stack1)
+        .add_memory(stack2);
+    if with_exception {
+        let mut ex = Exception::new(Endian::Little);
+        ex.thread_id = 1;
+        dump = dump.add_exception(ex);
+    }
+
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(string_symbol_supplier(
+            [(String::from("libc.so.6"), String::from(symbols))].into(),
+        )),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_hang_analysis_deadlock() {
+    let state = process_deadlock_dump(false).await;
+    let hang_analysis = state.hang_analysis.expect("missing hang analysis");
+    assert_eq!(hang_analysis.waiting_threads.len(), 2);
+    for (wait, owner) in hang_analysis.waiting_threads.iter().zip([2, 1]) {
+        assert_eq!(wait.kind, WaitKind::Mutex);
+        assert_eq!(wait.function, "__lll_lock_wait");
+        assert_eq!(wait.frame_index, 0);
+        assert_eq!(wait.lock_owner, Some(owner));
+    }
+    assert_eq!(hang_analysis.deadlocks, vec![vec![1, 2]]);
+}
+
+#[tokio::test]
+async fn test_hang_analysis_skips_crashes() {
+    // Idle threads of a crashed process aren't reported as blocked.
+    let state = process_deadlock_dump(true).await;
+    assert!(state.exception_info.is_some());
+    assert_eq!(state.hang_analysis, None);
+}
+
+#[tokio::test]
+async fn test_cxx_exception() {
+    // An x64 `throw std::bad_alloc()`, with the EH data (as offsets from the image base)
+    // and the thrown object both captured in the dump.
+    let image_base = 0x1000_0000;
+    let image = Section::with_endian(Endian::Little)
+        // 0x00: ThrowInfo
+        .D32(0) // attributes
+        .D32(0) // pmfnUnwind
+        .D32(0) // pForwardCompat
+        .D32(0x20) // pCatchableTypeArray
+        .append_repeated(0, 0x10)
+        // 0x20: CatchableTypeArray
+        .D32(2)
+        .D32(0x30)
+        .D32(0x50)
+        .append_repeated(0, 4)
+        // 0x30: CatchableType for std::bad_alloc
+        .D32(0)
+        .D32(0x70)
+        .append_repeated(0, 0x18)
+        // 0x50: CatchableType for std::exception
+        .D32(0)
+        .D32(0xa0)
+        .append_repeated(0, 0x18)
+        // 0x70: TypeDescriptor for std::bad_alloc
+        .D64(0)
+        .D64(0)
+        .append_bytes(b".?AVbad_alloc@std@@\0")
+        .append_repeated(0, 0xc)
+        // 0xa0: TypeDescriptor for std::exception
+        .D64(0)
+        .D64(0)
+        .append_bytes(b".?AVexception@std@@\0")
+        .append_repeated(0, 0xc)
+        // 0xd0: the what() message
+        .append_bytes(b"bad allocation\0");
+    let image = Memory::with_section(image, image_base);
+
+    // The thrown object: { vftable, _What, _DoFree }
+    let stack = Section::with_endian(Endian::Little)
+        .D64(0)
+        .D64(image_base + 0xd0)
+        .D64(0);
+    let stack = Memory::with_section(stack, 0x8000);
+
+    let context = minidump_synth::amd64_context(Endian::Little, 0x2000, 0x8000);
+    let thread = Thread::new(Endian::Little, 1, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+        )
+        .set_platform_id(minidump_common::format::PlatformId::VER_PLATFORM_WIN32_NT as u32);
+
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    ex.exception_record.exception_code = 0xe06d7363;
+    ex.exception_record.number_parameters = 4;
+    ex.exception_record.exception_information[..4]
+        .copy_from_slice(&[0x19930520, 0x8000, image_base, image_base]);
+
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack)
+        .add_memory(image);
+
+    let state = read_synth_dump(dump).await;
+
+    let cxx_exception = state
+        .exception_info
+        .expect("missing exception info")
+        .cxx_exception
+        .expect("missing C++ exception");
+    assert_eq!(cxx_exception.thrown_type(), Some("std::bad_alloc"));
+    assert_eq!(
+        cxx_exception.catchable_types,
+        vec!["std::bad_alloc", "std::exception"]
+    );
+    assert_eq!(cxx_exception.what.as_deref(), Some("bad allocation"));
+    assert_eq!(cxx_exception.thrown_object.0, 0x8000);
+}
+
+#[tokio::test]
+async fn test_stowed_exceptions() {
+    let module_name = DumpString::new("app.dll", Endian::Little);
+    let module = minidump_synth::Module::new(
+        Endian::Little,
+        0x7000_0000,
+        0x2000,
+        &module_name,
+        0,
+        0,
+        None,
+    );
+    let symbols = "MODULE windows x86_64 000000000000000000000000000000000 app.dll\n\
+                   FUNC 1000 20 0 originate\n\
+                   FUNC 1020 20 0 caller\n";
+
+    let error_text = "Access denied\0"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<u8>>();
+    let stowed = Section::with_endian(Endian::Little)
+        // 0x5000: the array of stowed exception pointers
+        .D64(0x5010)
+        .D64(0x5060)
+        // 0x5010: a V2 stowed exception with a stack trace
+        .D32(0x38) // Size
+        .D32(0x53453032) // 'SE02'
+        .D32(0x80004005) // E_FAIL
+        .D32((7 << 2) | 1) // ThreadId, ExceptionForm
+        .D64(0x7000_1010) // ExceptionAddress
+        .D32(8) // StackTraceWordSize
+        .D32(2) // StackTraceWords
+        .D64(0x50a0) // StackTrace
+        .D32(0x57333245) // NestedExceptionType ('W32E')
+        .D32(0)
+        .D64(0) // NestedException
+        .append_repeated(0, 0x18)
+        // 0x5060: a V1 stowed exception with an error message
+        .D32(0x28) // Size
+        .D32(0x53453031) // 'SE01'
+        .D32(0x80070005) // E_ACCESSDENIED
+        .D32((7 << 2) | 2) // ThreadId, ExceptionForm
+        .D64(0x50c0) // ErrorText
+        .append_repeated(0, 0x28)
+        // 0x50a0: the stack trace
+        .D64(0x7000_1011)
+        .D64(0x7000_1021)
+        .append_repeated(0, 0x10)
+        // 0x50c0: the error message
+        .append_bytes(&error_text);
+    let stowed = Memory::with_section(stowed, 0x5000);
+
+    let context = minidump_synth::amd64_context(Endian::Little, 0x7000_1000, 0x8000);
+    let stack = Memory::with_section(Section::with_endian(Endian::Little).D64(0), 0x8000);
+    let thread = Thread::new(Endian::Little, 7, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+        )
+        .set_platform_id(minidump_common::format::PlatformId::VER_PLATFORM_WIN32_NT as u32);
+
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 7;
+    ex.exception_record.exception_code = 0xc000027b;
+    ex.exception_record.number_parameters = 2;
+    ex.exception_record.exception_information[..2].copy_from_slice(&[0x5000, 2]);
+
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_module(module)
+        .add(module_name)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack)
+        .add_memory(stowed);
+
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(string_symbol_supplier(
+            [(String::from("app.dll"), String::from(symbols))].into(),
+        )),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(state.stowed_exceptions.len(), 2);
+
+    let first = &state.stowed_exceptions[0];
+    assert_eq!(first.result_code, 0x80004005);
+    assert_eq!(first.thread_id, 7);
+    assert_eq!(first.exception_address.map(|a| a.0), Some(0x7000_1010));
+    assert_eq!(first.nested_exception_type.as_deref(), Some("W32E"));
+    assert_eq!(first.message, None);
+    let functions = first
+        .stack
+        .frames
+        .iter()
+        .map(|frame| frame.function_name.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(functions, vec![Some("originate"), Some("caller")]);
+    assert!(first
+        .stack
+        .frames
+        .iter()
+        .all(|frame| frame.trust == FrameTrust::PreWalked));
+
+    let second = &state.stowed_exceptions[1];
+    assert_eq!(second.result_code, 0x80070005);
+    assert_eq!(
+        second.reason.to_string(),
+        "FACILITY_WIN32 / ERROR_ACCESS_DENIED"
+    );
+    assert_eq!(second.message.as_deref(), Some("Access denied"));
+    assert_eq!(second.nested_exception_type, None);
+    assert!(second.stack.frames.is_empty());
+}
+
 #[tokio::test]
 async fn test_guard_pages() {
     let context = minidump_synth::amd64_context(Endian::Little, 0x2000, 0x81000);
@@ -508,3 +1118,832 @@ async fn test_guard_pages() {
     assert_eq!(access_list.accesses[0].address_info.address, 0x81000);
     assert!(access_list.accesses[0].address_info.is_likely_guard_page);
 }
+
+#[tokio::test]
+async fn test_process_parameters() {
+    fn utf16(string: &str) -> Vec<u8> {
+        string.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+    fn unicode_string(section: Section, string: &str, buffer: u64) -> Section {
+        let len = string.encode_utf16().count() as u16 * 2;
+        section.D16(len).D16(len).D32(0).D64(buffer)
+    }
+
+    let teb = Section::with_endian(Endian::Little)
+        .append_repeated(0, 0x30)
+        .D64(0x7ff0_0000) // NtTib.Self
+        .append_repeated(0, 0x28)
+        .D64(0x7ff1_0000); // ProcessEnvironmentBlock
+    let peb = Section::with_endian(Endian::Little)
+        .append_repeated(0, 0x10)
+        .D64(0x1_4000_0000) // ImageBaseAddress
+        .D64(0) // Ldr
+        .D64(0x5000); // ProcessParameters
+
+    let environment = utf16("PATH=C:\\bin\0API_TOKEN=hunter2\0\0");
+    let params = Section::with_endian(Endian::Little)
+        .D32(0x400) // MaximumLength
+        .D32(0x400) // Length
+        .D32(1) // Flags: normalized
+        .append_repeated(0, 0x2c);
+    let params = unicode_string(params, "C:\\work\\", 0x6000) // CurrentDirectory
+        .append_repeated(0, 0x18);
+    let params = unicode_string(params, "C:\\app.exe", 0x6100); // ImagePathName
+    let params = unicode_string(params, "app.exe --password hunter2", 0x6200) // CommandLine
+        .D64(0x7000) // Environment
+        .append_repeated(0, 0x368)
+        .D64(environment.len() as u64); // EnvironmentSize
+    let strings = Section::with_endian(Endian::Little)
+        .append_bytes(&utf16("C:\\work\\"))
+        .append_repeated(0, 0x100 - 16)
+        .append_bytes(&utf16("C:\\app.exe"))
+        .append_repeated(0, 0x100 - 20)
+        .append_bytes(&utf16("app.exe --password hunter2"));
+
+    let context = minidump_synth::amd64_context(Endian::Little, 0, 0x8000);
+    let stack = Memory::with_section(Section::with_endian(Endian::Little).D64(0), 0x8000);
+    let thread = Thread::with_teb(Endian::Little, 1, 0x7ff0_0000, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+        )
+        .set_platform_id(minidump_common::format::PlatformId::VER_PLATFORM_WIN32_NT as u32);
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack)
+        .add_memory(Memory::with_section(teb, 0x7ff0_0000))
+        .add_memory(Memory::with_section(peb, 0x7ff1_0000))
+        .add_memory(Memory::with_section(params, 0x5000))
+        .add_memory(Memory::with_section(strings, 0x6000))
+        .add_memory(Memory::with_section(
+            Section::with_endian(Endian::Little).append_bytes(&environment),
+            0x7000,
+        ));
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let symbolizer = Symbolizer::new(simple_symbol_supplier(vec![]));
+
+    // The command line and environment aren't reported by default.
+    let state = minidump_processor::process_minidump(&dump, &symbolizer)
+        .await
+        .unwrap();
+    let params = state.process_parameters.unwrap();
+    assert_eq!(params.image_path_name.as_deref(), Some("C:\\app.exe"));
+    assert_eq!(params.current_directory.as_deref(), Some("C:\\work\\"));
+    assert_eq!(params.command_line, None);
+    assert_eq!(params.environment, None);
+
+    let mut options = ProcessorOptions::default();
+    options.report_command_line = true;
+    options.report_environment = true;
+    let state = minidump_processor::process_minidump_with_options(&dump, &symbolizer, options)
+        .await
+        .unwrap();
+    let params = state.process_parameters.unwrap();
+    assert_eq!(
+        params.command_line.as_deref(),
+        Some("app.exe --password hunter2")
+    );
+    assert_eq!(
+        params.environment.unwrap(),
+        vec![
+            ("PATH".to_owned(), "C:\\bin".to_owned()),
+            (
+                "API_TOKEN".to_owned(),
+                minidump_processor::REDACTED_VALUE.to_owned()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_teb_stack_bounds() {
+    let module_name = DumpString::new("app.dll", Endian::Little);
+    let module = minidump_synth::Module::new(
+        Endian::Little,
+        0x7000_0000,
+        0x2000,
+        &module_name,
+        0,
+        0,
+        None,
+    );
+
+    // The TEB says the stack is 0x8000-0x8040, but the memory around it (say, the stack
+    // of another fiber) contains something that looks like a return address too.
+    let teb = Section::with_endian(Endian::Little)
+        .D64(0) // ExceptionList
+        .D64(0x8040) // StackBase
+        .D64(0x8000) // StackLimit
+        .D64(0) // SubSystemTib
+        .D64(0) // FiberData
+        .D64(0) // ArbitraryUserPointer
+        .D64(0x7ff0_0000); // Self
+    let stack = Section::with_endian(Endian::Little)
+        .append_repeated(0, 0x10)
+        .D64(0x7000_1100) // a return address in bounds
+        .append_repeated(0, 0x40)
+        .D64(0x7000_1200); // one out of bounds
+    let stack = Memory::with_section(stack, 0x8000);
+
+    let context = minidump_synth::amd64_context(Endian::Little, 0x7000_1000, 0x8000);
+    let thread = Thread::with_teb(Endian::Little, 1, 0x7ff0_0000, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+        )
+        .set_platform_id(minidump_common::format::PlatformId::VER_PLATFORM_WIN32_NT as u32);
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_module(module)
+        .add(module_name)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack)
+        .add_memory(Memory::with_section(teb, 0x7ff0_0000));
+
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let symbolizer = Symbolizer::new(simple_symbol_supplier(vec![]));
+    let instructions = |state: &ProcessState| {
+        state.threads[0]
+            .frames
+            .iter()
+            .map(|frame| frame.instruction)
+            .collect::<Vec<_>>()
+    };
+
+    let state = minidump_processor::process_minidump(&dump, &symbolizer)
+        .await
+        .unwrap();
+    let teb = state.threads[0].thread_environment_block.as_ref().unwrap();
+    assert_eq!(teb.stack_base, 0x8040);
+    assert_eq!(teb.stack_limit, 0x8000);
+    assert_eq!(
+        instructions(&state),
+        vec![0x7000_1000, 0x7000_10ff, 0x7000_11ff]
+    );
+
+    let mut options = ProcessorOptions::default();
+    options.truncate_out_of_stack_frames = true;
+    let state = minidump_processor::process_minidump_with_options(&dump, &symbolizer, options)
+        .await
+        .unwrap();
+    assert_eq!(instructions(&state), vec![0x7000_1000, 0x7000_10ff]);
+}
+
+#[tokio::test]
+async fn test_wow64_fs_access() {
+    // mov eax, dword fs:[0x30]
+    let code = Section::with_endian(Endian::Little).append_bytes(&[0x64, 0xa1, 0x30, 0, 0, 0]);
+    let code = Memory::with_section(code, 0x40_1000);
+
+    // The thread's TEB is the 64-bit one, followed by the 32-bit TEB that fs points at.
+    let teb = Section::with_endian(Endian::Little)
+        .append_repeated(0, 0x30)
+        .D64(0x7ff0_0000) // NtTib.Self (64-bit)
+        .append_repeated(0, 0x2018 - 0x38)
+        .D32(0x7ff0_2000); // NtTib.Self (32-bit)
+    let teb = Memory::with_section(teb, 0x7ff0_0000);
+
+    let context = minidump_synth::x86_context(Endian::Little, 0x40_1000, 0x8000);
+    let stack = Memory::with_section(Section::with_endian(Endian::Little).D32(0), 0x8000);
+    let thread = Thread::with_teb(Endian::Little, 1, 0x7ff0_0000, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_INTEL as u16,
+        )
+        .set_platform_id(minidump_common::format::PlatformId::VER_PLATFORM_WIN32_NT as u32);
+
+    let context_label = context.file_offset();
+    let context_size = context.file_size();
+
+    let dump = SynthMinidump::with_endian(Endian::Little).add(context);
+
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    ex.exception_record.exception_code = 0xc000_0005; // EXCEPTION_ACCESS_VIOLATION
+    ex.exception_record.exception_address = 0x40_1000;
+    ex.thread_context = (
+        context_size.value().unwrap() as u32,
+        context_label.value().unwrap() as u32,
+    );
+
+    let dump = dump
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_system_info(system_info)
+        .add_memory(stack)
+        .add_memory(code)
+        .add_memory(teb);
+
+    let state = read_synth_dump(dump).await;
+    let accesses = state
+        .exception_info
+        .unwrap()
+        .memory_access_list
+        .unwrap()
+        .accesses
+        .iter()
+        .map(|access| access.address_info.address)
+        .collect::<Vec<_>>();
+    assert_eq!(accesses, vec![0x7ff0_2030]);
+}
+
+#[tokio::test]
+async fn test_register_annotations() {
+    use minidump_processor::RegisterTarget;
+
+    let module_name = DumpString::new("libapp.so", Endian::Little);
+    let module = minidump_synth::Module::new(
+        Endian::Little,
+        0x7000_0000,
+        0x2000,
+        &module_name,
+        0,
+        0,
+        None,
+    );
+    let symbols = "MODULE Linux x86_64 000000000000000000000000000000000 libapp.so\n\
+                   FUNC 1000 20 0 crash\n";
+
+    // A CONTEXT_AMD64 with rax, rcx, rdx and rbx set.
+    let context = Section::with_endian(Endian::Little)
+        .append_repeated(0, 8 * 6) // p[1-6]_home
+        .D32(0x10001f) // context_flags: CONTEXT_AMD64_ALL
+        .D32(0) // mx_csr
+        .append_repeated(0, 2 * 6) // cs,ds,es,fs,gs,ss
+        .D32(0) // eflags
+        .append_repeated(0, 8 * 6) // dr0,1,2,3,6,7
+        .D64(0x10) // rax
+        .D64(0xe5e5_e5e5_e5e5_e5e5) // rcx
+        .D64(0x0001_0000_0000_0000) // rdx
+        .D64(0x1000_0010) // rbx
+        .D64(0x8000) // rsp
+        .append_repeated(0, 8 * 11) // rbp-r15
+        .D64(0x7000_1010) // rip
+        .append_repeated(0, 512) // float_save
+        .append_repeated(0, 16 * 26) // vector_register
+        .append_repeated(0, 8 * 6); // trailing stuff
+    let stack = Memory::with_section(Section::with_endian(Endian::Little).D64(0), 0x8000);
+    let thread = Thread::new(Endian::Little, 1, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+        )
+        .set_platform_id(minidump_common::format::PlatformId::Linux as u32);
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_module(module)
+        .add(module_name)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack)
+        .set_linux_maps(b"10000000-10001000 rw-p 00000000 00:00 0    [heap]\n");
+
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let symbolizer = Symbolizer::new(string_symbol_supplier(
+        [(String::from("libapp.so"), String::from(symbols))].into(),
+    ));
+    let state = minidump_processor::process_minidump(&dump, &symbolizer)
+        .await
+        .unwrap();
+    assert!(state.register_annotations.is_empty());
+
+    let mut options = ProcessorOptions::default();
+    options.annotate_registers = true;
+    let state = minidump_processor::process_minidump_with_options(&dump, &symbolizer, options)
+        .await
+        .unwrap();
+
+    let target = |register: &str| {
+        state
+            .register_annotations
+            .iter()
+            .find(|annotation| annotation.register == register)
+            .map(|annotation| &annotation.target)
+    };
+    assert_eq!(target("rax"), Some(&RegisterTarget::Null));
+    assert_eq!(
+        target("rcx"),
+        Some(&RegisterTarget::Poison {
+            pattern: "mozjemalloc-freed".into(),
+            poison_kind: PoisonKind::Freed,
+        })
+    );
+    assert_eq!(target("rdx"), Some(&RegisterTarget::NonCanonical));
+    assert_eq!(
+        target("rbx"),
+        Some(&RegisterTarget::Mapped {
+            protection: "rw-".into(),
+            name: Some("[heap]".into()),
+        })
+    );
+    assert_eq!(
+        target("rsp"),
+        Some(&RegisterTarget::Stack {
+            threads_index: 0,
+            thread_id: 1,
+        })
+    );
+    assert_eq!(
+        target("rip"),
+        Some(&RegisterTarget::Module {
+            module: "libapp.so".into(),
+            module_offset: 0x1010.into(),
+            function: Some("crash".into()),
+            function_offset: Some(0x10.into()),
+        })
+    );
+    assert_eq!(target("rip").unwrap().to_string(), "libapp.so!crash + 0x10");
+}
+
+#[tokio::test]
+async fn test_stack_memory_dump() {
+    use minidump_processor::RegisterTarget;
+
+    let module_name = DumpString::new("libapp.so", Endian::Little);
+    let module = minidump_synth::Module::new(
+        Endian::Little,
+        0x7000_0000,
+        0x2000,
+        &module_name,
+        0,
+        0,
+        None,
+    );
+    let symbols = "MODULE Linux x86_64 000000000000000000000000000000000 libapp.so\n\
+                   FUNC 1000 20 0 crash\n";
+
+    // A CONTEXT_AMD64 with a frame pointer, so that the caller is found at 0x8018.
+    let context = Section::with_endian(Endian::Little)
+        .append_repeated(0, 8 * 6) // p[1-6]_home
+        .D32(0x10001f) // context_flags: CONTEXT_AMD64_ALL
+        .D32(0) // mx_csr
+        .append_repeated(0, 2 * 6) // cs,ds,es,fs,gs,ss
+        .D32(0) // eflags
+        .append_repeated(0, 8 * 6) // dr0,1,2,3,6,7
+        .append_repeated(0, 8 * 4) // rax,rcx,rdx,rbx
+        .D64(0x8000) // rsp
+        .D64(0x8010) // rbp
+        .append_repeated(0, 8 * 10) // rsi-r15
+        .D64(0x7000_1010) // rip
+        .append_repeated(0, 512) // float_save
+        .append_repeated(0, 16 * 26) // vector_register
+        .append_repeated(0, 8 * 6); // trailing stuff
+    let stack = Section::with_endian(Endian::Little)
+        .D64(0) // 0x8000
+        .D64(0x1234) // 0x8008: some local
+        .D64(0) // 0x8010: saved rbp
+        .D64(0x7000_1018) // 0x8018: return address
+        .D64(0x8000); // 0x8020: a pointer into the stack
+    let stack = Memory::with_section(stack, 0x8000);
+    let thread = Thread::new(Endian::Little, 1, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+        )
+        .set_platform_id(minidump_common::format::PlatformId::Linux as u32);
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_module(module)
+        .add(module_name)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack);
+
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let symbolizer = Symbolizer::new(string_symbol_supplier(
+        [(String::from("libapp.so"), String::from(symbols))].into(),
+    ));
+
+    // Off by default.
+    let state = minidump_processor::process_minidump(&dump, &symbolizer)
+        .await
+        .unwrap();
+    assert!(state.stack_memory_dump.is_none());
+
+    let mut options = ProcessorOptions::default();
+    options.annotate_stack_memory = true;
+    let state = minidump_processor::process_minidump_with_options(&dump, &symbolizer, options)
+        .await
+        .unwrap();
+    let stack_dump = state.stack_memory_dump.unwrap();
+    assert_eq!(stack_dump.threads_index, 0);
+
+    let words = &stack_dump.words;
+    assert_eq!(words.len(), 5);
+    assert_eq!(
+        words
+            .iter()
+            .map(|word| (word.address.0, word.value.0, word.frame))
+            .collect::<Vec<_>>(),
+        vec![
+            (0x8000, 0, Some(0)),
+            (0x8008, 0x1234, Some(0)),
+            (0x8010, 0, Some(0)),
+            (0x8018, 0x7000_1018, Some(0)),
+            (0x8020, 0x8000, Some(1)),
+        ]
+    );
+    assert_eq!(words[1].target, None);
+    assert!(!words[1].is_return_address);
+    assert!(words[3].is_return_address);
+    assert_eq!(
+        words[3].target,
+        Some(RegisterTarget::Module {
+            module: "libapp.so".into(),
+            module_offset: 0x1018.into(),
+            function: Some("crash".into()),
+            function_offset: Some(0x18.into()),
+        })
+    );
+    assert_eq!(
+        words[4].target,
+        Some(RegisterTarget::Stack {
+            threads_index: 0,
+            thread_id: 1,
+        })
+    );
+}
+
+/// Process an amd64 dump whose crashing function `Foo::bar` has more arguments than
+/// argument registers, with function argument recovery enabled.
+async fn read_amd64_args_dump(platform: minidump_common::format::PlatformId) -> ProcessState {
+    let module_name = DumpString::new("libapp.so", Endian::Little);
+    let module = minidump_synth::Module::new(
+        Endian::Little,
+        0x7000_0000,
+        0x2000,
+        &module_name,
+        0,
+        0,
+        None,
+    );
+    let symbols = "MODULE Linux x86_64 000000000000000000000000000000000 libapp.so\n\
+                   FUNC 1000 18 0 Foo::bar(int, double, char*, long, long, long, long)\n\
+                   FUNC 1018 10 0 main(int, char**)\n";
+
+    // A CONTEXT_AMD64 with a frame pointer, so that the caller is found at 0x8018.
+    let context = Section::with_endian(Endian::Little)
+        .append_repeated(0, 8 * 6) // p[1-6]_home
+        .D32(0x10001f) // context_flags: CONTEXT_AMD64_ALL
+        .D32(0) // mx_csr
+        .append_repeated(0, 2 * 6) // cs,ds,es,fs,gs,ss
+        .D32(0) // eflags
+        .append_repeated(0, 8 * 6) // dr0,1,2,3,6,7
+        .D64(0) // rax
+        .D64(0x11) // rcx
+        .D64(0x22) // rdx
+        .D64(0) // rbx
+        .D64(0x8000) // rsp
+        .D64(0x8010) // rbp
+        .D64(0x33) // rsi
+        .D64(0x44) // rdi
+        .D64(0x55) // r8
+        .D64(0x66) // r9
+        .append_repeated(0, 8 * 6) // r10-r15
+        .D64(0x7000_1010) // rip
+        .append_repeated(0, 512) // float_save
+        .append_repeated(0, 16 * 26) // vector_register
+        .append_repeated(0, 8 * 6); // trailing stuff
+    let mut stack = Section::with_endian(Endian::Little)
+        .D64(0) // 0x8000
+        .D64(0x1234) // 0x8008: some local
+        .D64(0) // 0x8010: saved rbp
+        .D64(0x7000_1020); // 0x8018: return address
+                           // 0x8020: the caller's stack pointer, where the stack arguments start
+    for slot in 0..8 {
+        stack = stack.D64(0xa0 + slot);
+    }
+    let stack = Memory::with_section(stack, 0x8000);
+    let thread = Thread::new(Endian::Little, 1, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+        )
+        .set_platform_id(platform as u32);
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_module(module)
+        .add(module_name)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack);
+
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let symbolizer = Symbolizer::new(string_symbol_supplier(
+        [(String::from("libapp.so"), String::from(symbols))].into(),
+    ));
+    let mut options = ProcessorOptions::default();
+    options.recover_function_args = true;
+    minidump_processor::process_minidump_with_options(&dump, &symbolizer, options)
+        .await
+        .unwrap()
+}
+
+/// The `(name, value, trust)` of a recovered argument.
+type RecoveredArg = (String, Option<u64>, &'static str);
+
+fn recovered_args(state: &ProcessState) -> (&'static str, Vec<RecoveredArg>) {
+    let frames = &state.threads[0].frames;
+    assert_eq!(frames.len(), 2);
+    // None of the caller's arguments can be recovered, so it has none at all.
+    assert!(frames[1].arguments.is_none());
+
+    let arguments = frames[0].arguments.as_ref().unwrap();
+    (
+        arguments.calling_convention.as_str(),
+        arguments
+            .args
+            .iter()
+            .map(|arg| (arg.name.clone(), arg.value, arg.trust.as_str()))
+            .collect(),
+    )
+}
+
+#[tokio::test]
+async fn test_amd64_function_args_sysv() {
+    let state = read_amd64_args_dump(minidump_common::format::PlatformId::Linux).await;
+    let (calling_convention, args) = recovered_args(&state);
+    assert_eq!(calling_convention, "sysv");
+    assert_eq!(
+        args,
+        vec![
+            ("this".into(), Some(0x44), "context"),
+            ("int".into(), Some(0x33), "context"),
+            // Passed in xmm0.
+            ("double".into(), None, "none"),
+            ("char*".into(), Some(0x22), "context"),
+            ("long".into(), Some(0x11), "context"),
+            ("long".into(), Some(0x55), "context"),
+            ("long".into(), Some(0x66), "context"),
+            // Out of registers.
+            ("long".into(), Some(0xa0), "stack"),
+        ]
+    );
+
+    let mut json = Vec::new();
+    state.print_json(&mut json, false).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    let arguments = &json["crashing_thread"]["frames"][0]["arguments"];
+    assert_eq!(arguments["calling_convention"], "sysv");
+    assert_eq!(arguments["args"][0]["value"], "0x0000000000000044");
+    assert_eq!(arguments["args"][2]["value"], serde_json::Value::Null);
+}
+
+#[tokio::test]
+async fn test_amd64_function_args_win64() {
+    let state =
+        read_amd64_args_dump(minidump_common::format::PlatformId::VER_PLATFORM_WIN32_NT).await;
+    let (calling_convention, args) = recovered_args(&state);
+    assert_eq!(calling_convention, "win64");
+    assert_eq!(
+        args,
+        vec![
+            ("this".into(), Some(0x11), "context"),
+            ("int".into(), Some(0x22), "context"),
+            // Passed in xmm2, but it may have been spilled to its home space.
+            ("double".into(), Some(0xa2), "home_space"),
+            ("char*".into(), Some(0x66), "context"),
+            // Past the home space.
+            ("long".into(), Some(0xa4), "stack"),
+            ("long".into(), Some(0xa5), "stack"),
+            ("long".into(), Some(0xa6), "stack"),
+            ("long".into(), Some(0xa7), "stack"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_x86_function_args_thiscall() {
+    let module_name = DumpString::new("app.dll", Endian::Little);
+    let module = minidump_synth::Module::new(
+        Endian::Little,
+        0x7000_0000,
+        0x2000,
+        &module_name,
+        0,
+        0,
+        None,
+    );
+    let symbols = "MODULE windows x86 000000000000000000000000000000000 app.dll\n\
+                   FUNC 1000 18 0 Foo::bar(int)\n\
+                   FUNC 1018 10 0 main\n";
+
+    // A CONTEXT_X86 with a frame pointer, so that the caller is found at 0x800c.
+    let context = Section::with_endian(Endian::Little)
+        .D32(0x1003f) // context_flags: CONTEXT_X86_ALL
+        .append_repeated(0, 4 * 6) // dr0,1,2,3,6,7
+        .append_repeated(0, 112) // float_save
+        .append_repeated(0, 4 * 4) // gs,fs,es,ds
+        .D32(0) // edi
+        .D32(0) // esi
+        .D32(0) // ebx
+        .D32(0) // edx
+        .D32(0x11) // ecx
+        .D32(0x22) // eax
+        .D32(0x8008) // ebp
+        .D32(0x7000_1010) // eip
+        .D32(0) // cs
+        .D32(0) // eflags
+        .D32(0x8000) // esp
+        .D32(0) // ss
+        .append_repeated(0, 512); // extended_registers
+    let stack = Section::with_endian(Endian::Little)
+        .D32(0) // 0x8000
+        .D32(0) // 0x8004
+        .D32(0) // 0x8008: saved ebp
+        .D32(0x7000_1020) // 0x800c: return address
+        .D32(0xa0); // 0x8010: the int argument
+    let stack = Memory::with_section(stack, 0x8000);
+    let thread = Thread::new(Endian::Little, 1, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_INTEL as u16,
+        )
+        .set_platform_id(minidump_common::format::PlatformId::VER_PLATFORM_WIN32_NT as u32);
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_module(module)
+        .add(module_name)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack);
+
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let symbolizer = Symbolizer::new(string_symbol_supplier(
+        [(String::from("app.dll"), String::from(symbols))].into(),
+    ));
+    let mut options = ProcessorOptions::default();
+    options.recover_function_args = true;
+    let state = minidump_processor::process_minidump_with_options(&dump, &symbolizer, options)
+        .await
+        .unwrap();
+
+    let (calling_convention, args) = recovered_args(&state);
+    assert_eq!(calling_convention, "windows_thiscall");
+    assert_eq!(
+        args,
+        vec![
+            ("this".into(), Some(0x11), "context"),
+            ("int".into(), Some(0xa0), "stack"),
+        ]
+    );
+}
+
+#[cfg(feature = "disasm_amd64")]
+#[tokio::test]
+async fn test_disassembly_window() {
+    let module_name = DumpString::new("libapp.so", Endian::Little);
+    let module = minidump_synth::Module::new(
+        Endian::Little,
+        0x7000_0000,
+        0x2000,
+        &module_name,
+        0,
+        0,
+        None,
+    );
+    let symbols = "MODULE Linux x86_64 000000000000000000000000000000000 libapp.so\n\
+                   FUNC 1000 10 0 crash\n\
+                   FUNC 1010 10 0 callee\n";
+
+    let context = Section::with_endian(Endian::Little)
+        .append_repeated(0, 8 * 6) // p[1-6]_home
+        .D32(0x10001f) // context_flags: CONTEXT_AMD64_ALL
+        .D32(0) // mx_csr
+        .append_repeated(0, 2 * 6) // cs,ds,es,fs,gs,ss
+        .D32(0) // eflags
+        .append_repeated(0, 8 * 6) // dr0,1,2,3,6,7
+        .append_repeated(0, 8 * 4) // rax,rcx,rdx,rbx
+        .D64(0x8000) // rsp
+        .append_repeated(0, 8 * 11) // rbp-r15
+        .D64(0x7000_1009) // rip
+        .append_repeated(0, 512) // float_save
+        .append_repeated(0, 16 * 26) // vector_register
+        .append_repeated(0, 8 * 6); // trailing stuff
+    let code = Section::with_endian(Endian::Little).append_bytes(&[
+        0x55, // push rbp
+        0x48, 0x89, 0xe5, // mov rbp, rsp
+        0xe8, 0x07, 0x00, 0x00, 0x00, // call callee
+        0x8b, 0x00, // mov eax, [rax]
+        0x5d, // pop rbp
+        0xc3, // ret
+    ]);
+    let code = Memory::with_section(code, 0x7000_1000);
+    let stack = Memory::with_section(Section::with_endian(Endian::Little).D64(0), 0x8000);
+    let thread = Thread::new(Endian::Little, 1, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+        )
+        .set_platform_id(minidump_common::format::PlatformId::Linux as u32);
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_module(module)
+        .add(module_name)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack)
+        .add_memory(code);
+
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let symbolizer = Symbolizer::new(string_symbol_supplier(
+        [(String::from("libapp.so"), String::from(symbols))].into(),
+    ));
+    let mut options = ProcessorOptions::default();
+    options.disassembly_window = 2;
+    let state = minidump_processor::process_minidump_with_options(&dump, &symbolizer, options)
+        .await
+        .unwrap();
+
+    let disassembly = state.disassembly.unwrap();
+    assert_eq!(disassembly.threads_index, 0);
+    assert_eq!(disassembly.windows.len(), 1);
+    let window = &disassembly.windows[0];
+    assert_eq!(window.frame, 0);
+    assert_eq!(window.address.0, 0x7000_1009);
+    assert_eq!(
+        window
+            .instructions
+            .iter()
+            .map(|instruction| (instruction.address.0, instruction.text.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            (0x7000_1001, "mov rbp, rsp"),
+            (0x7000_1004, "call $+0x7"),
+            (0x7000_1009, "mov eax, dword [rax]"),
+            (0x7000_100b, "pop rbp"),
+            (0x7000_100c, "ret"),
+        ]
+    );
+    let call = &window.instructions[1];
+    assert_eq!(call.bytes, [0xe8, 0x07, 0x00, 0x00, 0x00]);
+    assert_eq!(call.branch_target.map(|target| target.0), Some(0x7000_1010));
+    assert_eq!(
+        call.branch_target_symbol.as_deref(),
+        Some("libapp.so!callee + 0x0")
+    );
+}
+
+#[cfg(feature = "disasm_amd64")]
+#[tokio::test]
+async fn test_disassembly_at_address_zero() {
+    let context = minidump_synth::amd64_context(Endian::Little, 0, 0x8000);
+    let code = Section::with_endian(Endian::Little).append_bytes(&[
+        0x8b, 0x00, // mov eax, [rax]
+        0xc3, // ret
+    ]);
+    let code = Memory::with_section(code, 0);
+    let stack = Memory::with_section(Section::with_endian(Endian::Little).D64(0), 0x8000);
+    let thread = Thread::new(Endian::Little, 1, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little)
+        .set_processor_architecture(
+            minidump_common::format::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16,
+        )
+        .set_platform_id(minidump_common::format::PlatformId::Linux as u32);
+    let mut ex = Exception::new(Endian::Little);
+    ex.thread_id = 1;
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_exception(ex)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack)
+        .add_memory(code);
+
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let symbolizer = Symbolizer::new(simple_symbol_supplier(vec![]));
+    let mut options = ProcessorOptions::default();
+    // Big enough to overflow when multiplied by the maximum instruction length.
+    options.disassembly_window = usize::MAX / 2;
+    let state = minidump_processor::process_minidump_with_options(&dump, &symbolizer, options)
+        .await
+        .unwrap();
+
+    let window = &state.disassembly.unwrap().windows[0];
+    assert_eq!(
+        window
+            .instructions
+            .iter()
+            .map(|instruction| (instruction.address.0, instruction.text.as_str()))
+            .collect::<Vec<_>>(),
+        vec![(0, "mov eax, dword [rax]"), (2, "ret")]
+    );
+}
@@ -16,8 +16,10 @@ use minidump_processor::{
     PendingProcessorStatSubscriptions, PendingProcessorStats, ProcessorOptions,
 };
 use minidump_unwind::{
-    debuginfo::DebugInfoSymbolProvider, http_symbol_supplier, simple_symbol_supplier,
-    MultiSymbolProvider, SymbolProvider, Symbolizer,
+    archive_symbol_supplier, debuginfo::DebugInfoSymbolProvider, debuginfod_symbol_supplier,
+    debuginfod_urls_from_env, http_symbol_supplier, is_symbol_archive, simple_symbol_supplier,
+    symstore_symbol_supplier, MultiSymbolProvider, SymbolProvider, SymbolServerCredentials,
+    Symbolizer,
 };
 
 use clap::{
@@ -120,7 +122,8 @@ struct Cli {
     ///  
     /// stable-all enables: nothing (currently identical to stable-basic)
     ///  
-    /// unstable-all enables: `--recover-function-args`
+    /// unstable-all enables: `--recover-function-args`, `--collapse-recursion`,
+    /// `--truncate-out-of-stack-frames`
     ///  
     /// minidump-stackwalk wants to be a reliable and stable tool, but we also want to be able
     /// to introduce new features which may be experimental or expensive. To balance these two
@@ -193,14 +196,83 @@ struct Cli {
 
     /// **UNSTABLE** Heuristically recover function arguments
     ///
-    /// This is an experimental feature, which currently only shows up in --human output.
+    /// This is an experimental feature, which supports x86 and amd64 frames whose function
+    /// names include the argument list.
     #[arg(long)]
     recover_function_args: bool,
 
+    /// **UNSTABLE** Collapse recursive frames in backtraces
+    ///
+    /// Recursion is always detected and reported, but with this flag only the first
+    /// repetition of a recursive cycle is included in the output.
+    #[arg(long)]
+    collapse_recursion: bool,
+
+    /// **UNSTABLE** Drop frames found outside of a thread's stack
+    ///
+    /// The stack bounds are taken from the thread's TEB on Windows. This keeps stack
+    /// scanning from wandering into neighbouring stacks, such as those of other fibers.
+    #[arg(long)]
+    truncate_out_of_stack_frames: bool,
+
+    /// Report the command line of Windows processes
+    ///
+    /// This is read from the process environment block, so it's only available for
+    /// full-memory dumps. It's off by default because command lines often contain user data.
+    #[arg(long)]
+    report_command_line: bool,
+
+    /// Report the environment variables of Windows processes
+    ///
+    /// Like --report-command-line this needs a full-memory dump. The values of variables
+    /// that look like they contain secrets (passwords, tokens, keys...) are redacted.
+    #[arg(long)]
+    report_environment: bool,
+
+    /// Annotate what the registers of the crashing thread point to
+    ///
+    /// Each register is classified as null, an allocator poison pattern, a non-canonical
+    /// address, or a pointer into a thread's stack, a module or another mapped region.
+    #[arg(long)]
+    annotate_registers: bool,
+
+    /// Dump the crashing thread's stack memory, annotating every word
+    ///
+    /// Each word is attributed to the frame whose part of the stack it's in, and is
+    /// classified like the register annotations (return addresses, pointers into modules
+    /// or stacks, poison patterns...). This makes the output a lot larger.
+    #[arg(long)]
+    annotate_stack_memory: bool,
+
+    /// Disassemble this many instructions before and after the crashing instruction
+    ///
+    /// The code is read from the minidump, or from the module binaries if they can be
+    /// found (see --symbols-url), and direct branch targets are symbolized.
+    /// Currently only supported for amd64.
+    #[arg(long, default_value_t = 0)]
+    disassembly_window: usize,
+
+    /// Also disassemble around the return address of every frame of the crashing thread
+    ///
+    /// This uses the window given by --disassembly-window.
+    #[arg(long)]
+    disassemble_return_addresses: bool,
+
     /// Use debug information from local files referred to by the minidump, if present.
     #[arg(long)]
     use_local_debuginfo: bool,
 
+    /// base URL of a debuginfod server to get missing binaries and debug info from
+    ///
+    /// Files are looked up by GNU build-id, so this only helps with Linux modules.
+    /// Implies --use-local-debuginfo. If multiple debuginfod-url values are provided,
+    /// they will each be tried in order until one resolves. If none are provided,
+    /// the servers in the DEBUGINFOD_URLS environment variable are used.
+    ///
+    /// Downloaded files are stored in --symbols-cache.
+    #[arg(long)]
+    debuginfod_url: Vec<String>,
+
     /// base URL from which URLs to symbol files can be constructed
     ///
     /// If multiple symbols-url values are provided, they will each be tried in order until
@@ -218,6 +290,44 @@ struct Cli {
     #[arg(verbatim_doc_comment)]
     symbols_url: Vec<String>,
 
+    /// path or URL of a Microsoft symbol store to get binaries and debug info from
+    ///
+    /// Symbol stores are directories (or HTTP servers) created by symstore.exe.
+    /// Two-tier stores (with an index2.txt), file.ptr redirections and
+    /// CAB-compressed files (e.g. foo.pd_) are supported. If multiple symstore values
+    /// are provided, they will each be tried in order until one resolves.
+    ///
+    /// Files which have to be downloaded or decompressed are stored in --symbols-cache.
+    #[arg(long)]
+    symstore: Vec<String>,
+
+    /// A file with credentials and headers to send to symbol servers
+    ///
+    /// The file has a section for each URL prefix, with `bearer`, `basic` and
+    /// `header` settings. `${NAME}` is replaced by the NAME environment variable,
+    /// so secrets can be kept out of the file:
+    ///
+    /// [https://symbols.example.com/private/]
+    /// bearer = ${PRIVATE_SYMBOLS_TOKEN}
+    /// header = X-Client: minidump-stackwalk
+    ///
+    /// [https://partner.example.org/]
+    /// basic = username:${PARTNER_PASSWORD}
+    ///
+    /// These apply to --symbols-url, --debuginfod-url and --symstore servers.
+    /// Credentials are redacted from logs and reported symbol URLs.
+    #[arg(long)]
+    #[arg(verbatim_doc_comment)]
+    symbols_auth_config: Option<PathBuf>,
+
+    /// A netrc file with logins for symbol servers
+    ///
+    /// Each machine's login and password are sent as basic authentication to
+    /// symbol servers with that host, unless --symbols-auth-config has credentials
+    /// for them.
+    #[arg(long)]
+    symbols_netrc: Option<PathBuf>,
+
     /// A directory in which downloaded symbols can be stored
     ///
     /// Symbol files can be very large, so we recommend placing cached files in your
@@ -253,6 +363,39 @@ struct Cli {
     #[arg(long, default_value_t = 1000)]
     symbols_download_timeout_secs: u64,
 
+    /// How many times to retry a symbol download that failed with a server error
+    ///
+    /// Downloads which fail with a 5xx response, a timeout or a connection error are
+    /// retried after waiting 1 second, then twice as long before each further retry.
+    #[arg(long, default_value_t = 0)]
+    symbols_download_retries: u32,
+
+    /// The maximum number of symbol files to download at once
+    ///
+    /// By default there is no limit.
+    #[arg(long)]
+    symbols_max_concurrent_downloads: Option<usize>,
+
+    /// Remember symbol files that --symbols-url servers don't have for this many seconds
+    ///
+    /// Missing files are recorded in symbols-cache, so that later runs sharing the cache
+    /// don't ask the servers for them again until this time has passed. Only files the
+    /// servers answered don't exist (404 or 410) are remembered, not server errors.
+    ///
+    /// By default missing files are only remembered for the current run.
+    #[arg(long)]
+    symbols_negative_cache_ttl_secs: Option<u64>,
+
+    /// Also look for compressed symbol files on --symbols-url servers
+    ///
+    /// Servers which don't have foo.sym are also asked for foo.sym.gz and foo.sym.zst,
+    /// which costs two more requests per server for every missing symbol file.
+    /// Downloaded files are decompressed before they're saved in symbols-cache.
+    ///
+    /// Servers can always send symbol files with a gzip or zstd Content-Encoding.
+    #[arg(long)]
+    symbols_compressed: bool,
+
     /// Path to the minidump file to analyze
     minidump: PathBuf,
 
@@ -260,6 +403,9 @@ struct Cli {
     ///
     /// If multiple symbols-path values are provided, all symbol files will be merged
     /// into minidump-stackwalk's symbol database.
+    ///
+    /// Zip, tar and gzipped tar archives laid out like a symbol directory are
+    /// also accepted, and read without extracting them.
     #[arg(long)]
     symbols_path: Vec<PathBuf>,
 
@@ -267,6 +413,9 @@ struct Cli {
     ///
     /// If multiple symbols-path-legacy values are provided, all symbol files will be merged
     /// into minidump-stackwalk's symbol database.
+    ///
+    /// Zip, tar and gzipped tar archives laid out like a symbol directory are
+    /// also accepted, and read without extracting them.
     symbols_path_legacy: Vec<PathBuf>,
 }
 
@@ -341,6 +490,9 @@ async fn main_result() -> std::io::Result<()> {
 
     let mut symbols_paths = cli.symbols_path;
     symbols_paths.extend(cli.symbols_path_legacy);
+    let (symbols_archives, symbols_paths): (Vec<_>, Vec<_>) = symbols_paths
+        .into_iter()
+        .partition(|path| path.is_file() && is_symbol_archive(path));
 
     // Default to env::temp_dir()/rust-minidump-cache
     let symbols_cache = cli
@@ -352,6 +504,30 @@ async fn main_result() -> std::io::Result<()> {
 
     let timeout = Duration::from_secs(cli.symbols_download_timeout_secs);
 
+    let mut credentials = SymbolServerCredentials::new();
+    if let Some(path) = &cli.symbols_auth_config {
+        match SymbolServerCredentials::from_config_file(path) {
+            Ok(config) => credentials.extend(config),
+            Err(e) => {
+                error!(
+                    "Error reading symbols auth config {}: {}",
+                    path.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(path) = &cli.symbols_netrc {
+        match SymbolServerCredentials::from_netrc_file(path) {
+            Ok(netrc) => credentials.extend(netrc),
+            Err(e) => {
+                error!("Error reading symbols netrc {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     // Determine the kind of output we're producing -- dump, json, human, or cyborg (both).
     // Although we have a --human argument it's mostly just there to make the documentation
     // more clear. human output is enabled by default, and --json disables it.
@@ -390,6 +566,14 @@ async fn main_result() -> std::io::Result<()> {
     // Now overload the defaults
     options.evil_json = cli.evil_json.as_deref();
     options.recover_function_args = cli.recover_function_args;
+    options.collapse_recursion |= cli.collapse_recursion;
+    options.truncate_out_of_stack_frames |= cli.truncate_out_of_stack_frames;
+    options.report_command_line = cli.report_command_line;
+    options.report_environment = cli.report_environment;
+    options.annotate_registers = cli.annotate_registers;
+    options.annotate_stack_memory = cli.annotate_stack_memory;
+    options.disassembly_window = cli.disassembly_window;
+    options.disassemble_return_addresses = cli.disassemble_return_addresses;
 
     // Register for instractive updates, if we want them
     let interactive_enabled = !json && !cli.no_interactive && cli.output_file.is_none();
@@ -427,7 +611,7 @@ async fn main_result() -> std::io::Result<()> {
 
             let modules = dump.get_stream::<MinidumpModuleList>().unwrap_or_default();
 
-            if cli.use_local_debuginfo {
+            if cli.use_local_debuginfo || !cli.debuginfod_url.is_empty() {
                 let system_info = match dump.get_stream::<MinidumpSystemInfo>() {
                     Err(e) => {
                         error!("Error getting system info stream from dump (required for local debug info): {}", e);
@@ -435,25 +619,59 @@ async fn main_result() -> std::io::Result<()> {
                     }
                     Ok(s) => s,
                 };
-                provider.add(Box::new(
-                    DebugInfoSymbolProvider::new(&system_info, &modules).await,
-                ));
+                let mut debuginfod_urls = cli.debuginfod_url;
+                if debuginfod_urls.is_empty() {
+                    debuginfod_urls = debuginfod_urls_from_env();
+                }
+                let mut builder = DebugInfoSymbolProvider::builder();
+                if !debuginfod_urls.is_empty() {
+                    builder = builder.supplier(
+                        debuginfod_symbol_supplier(
+                            debuginfod_urls,
+                            symbols_cache.clone(),
+                            symbols_tmp.clone(),
+                            timeout,
+                        )
+                        .credentials(credentials.clone()),
+                    );
+                }
+                provider.add(Box::new(builder.build(&system_info, &modules).await));
+            }
+
+            if !symbols_archives.is_empty() {
+                provider.add(Box::new(Symbolizer::new(archive_symbol_supplier(
+                    symbols_archives,
+                )?)));
             }
 
             if !cli.symbols_url.is_empty() {
-                provider.add(Box::new(Symbolizer::new(http_symbol_supplier(
-                    symbols_paths,
-                    cli.symbols_url,
-                    symbols_cache,
-                    symbols_tmp,
-                    timeout,
-                ))));
+                provider.add(Box::new(Symbolizer::new(
+                    http_symbol_supplier(
+                        symbols_paths,
+                        cli.symbols_url,
+                        symbols_cache.clone(),
+                        symbols_tmp.clone(),
+                        timeout,
+                    )
+                    .credentials(credentials.clone())
+                    .retries(cli.symbols_download_retries, Duration::from_secs(1))
+                    .max_concurrent_downloads(cli.symbols_max_concurrent_downloads)
+                    .negative_cache(cli.symbols_negative_cache_ttl_secs.map(Duration::from_secs))
+                    .compressed_symbols(cli.symbols_compressed),
+                )));
             } else if !symbols_paths.is_empty() {
                 provider.add(Box::new(Symbolizer::new(simple_symbol_supplier(
                     symbols_paths,
                 ))));
             }
 
+            if !cli.symstore.is_empty() {
+                provider.add(Box::new(Symbolizer::new(
+                    symstore_symbol_supplier(cli.symstore, symbols_cache, symbols_tmp, timeout)
+                        .credentials(credentials),
+                )));
+            }
+
             let interactive_ui = processor_stats
                 .as_ref()
                 .map(|processor_stats| InterativeUi {
@@ -1104,6 +1104,13 @@ pub struct Thread {
 
 impl Thread {
     pub fn new<T>(endian: Endian, id: u32, stack: &Memory, context: &T) -> Thread
+    where
+        T: DumpSection,
+    {
+        Thread::with_teb(endian, id, 0, stack, context)
+    }
+
+    pub fn with_teb<T>(endian: Endian, id: u32, teb: u64, stack: &Memory, context: &T) -> Thread
     where
         T: DumpSection,
     {
@@ -1112,7 +1119,7 @@ impl Thread {
             .D32(0) // suspend_count
             .D32(0) // priority_class
             .D32(0) // priority
-            .D64(0) // teb
+            .D64(teb)
             .cite_memory(stack)
             .cite_location(context);
         Thread { section }
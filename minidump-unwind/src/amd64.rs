@@ -372,7 +372,8 @@ fn stack_seems_valid(
         .is_some()
 }
 
-fn is_non_canonical(ptr: Pointer) -> bool {
+/// Whether `ptr` is a non-canonical amd64 address, which can never be valid.
+pub fn is_non_canonical(ptr: Pointer) -> bool {
     // x64 has the notion of a "canonical address", as a result of only 48 bits
     // of a pointer actually being used, because this is all that a 4-level page
     // table can support. A canonical address copies bit 47 to all the otherwise
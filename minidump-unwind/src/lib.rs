@@ -24,6 +24,7 @@ use std::convert::TryFrom;
 use std::io::{self, Write};
 use tracing::trace;
 
+pub use crate::amd64::is_non_canonical;
 pub use crate::symbols::*;
 pub use crate::system_info::*;
 
@@ -104,6 +105,49 @@ pub enum CallingConvention {
     Cdecl,
     WindowsThisCall,
     OtherThisCall,
+    /// The amd64 System V convention (rdi, rsi, rdx, rcx, r8, r9, then the stack).
+    SysV,
+    /// The Windows x64 convention (rcx, rdx, r8, r9 with home space, then the stack).
+    Win64,
+}
+
+impl CallingConvention {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            CallingConvention::Cdecl => "cdecl",
+            CallingConvention::WindowsThisCall => "windows_thiscall",
+            CallingConvention::OtherThisCall => "other_thiscall",
+            CallingConvention::SysV => "sysv",
+            CallingConvention::Win64 => "win64",
+        }
+    }
+}
+
+/// How the value of a function argument was recovered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentTrust {
+    /// Read from a register of the frame's context as saved in the minidump.
+    ///
+    /// The function may have reused the register since it was called.
+    Context,
+    /// Read from the arguments the caller passed on the stack.
+    Stack,
+    /// Read from the Win64 home space of a register argument, which only holds
+    /// the argument if the function spilled it there.
+    HomeSpace,
+    /// The value couldn't be recovered.
+    None,
+}
+
+impl ArgumentTrust {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ArgumentTrust::Context => "context",
+            ArgumentTrust::Stack => "stack",
+            ArgumentTrust::HomeSpace => "home_space",
+            ArgumentTrust::None => "none",
+        }
+    }
 }
 
 /// Arguments for this function
@@ -123,6 +167,8 @@ pub struct FunctionArg {
     pub name: String,
     /// The value of the argument.
     pub value: Option<u64>,
+    /// How `value` was recovered.
+    pub trust: ArgumentTrust,
 }
 
 /// A stack frame for an inlined function.
@@ -334,6 +380,24 @@ pub enum CallStackInfo {
     DumpThreadSkipped,
 }
 
+/// A region of a `CallStack` in which a cycle of frames repeats (i.e. recursion).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recursion {
+    /// The index in `CallStack::frames` of the innermost frame of the recursive region.
+    ///
+    /// The frames of one cycle are `frames[start_frame..start_frame + period]`.
+    pub start_frame: usize,
+    /// The number of frames in one cycle (1 for a function directly calling itself).
+    pub period: usize,
+    /// The number of complete repetitions of the cycle.
+    pub repeat_count: usize,
+    /// The total number of frames in the recursive region, before any collapsing.
+    pub depth: usize,
+    /// The number of frames which were removed from `CallStack::frames` after the first
+    /// cycle, or 0 if the recursion wasn't collapsed.
+    pub collapsed_frames: usize,
+}
+
 /// A stack of `StackFrame`s produced as a result of unwinding a thread.
 #[derive(Debug, Clone)]
 pub struct CallStack {
@@ -350,6 +414,10 @@ pub struct CallStack {
     pub thread_name: Option<String>,
     /// The GetLastError() value stored in the TEB.
     pub last_error_value: Option<CrashReason>,
+    /// The most significant recursion found in the frames, if any.
+    pub recursion: Option<Recursion>,
+    /// The thread's TEB, if this is a Windows thread and its memory is in the minidump.
+    pub thread_environment_block: Option<MinidumpThreadEnvironmentBlock>,
 }
 
 impl CallStack {
@@ -363,6 +431,8 @@ impl CallStack {
             thread_id: 0,
             thread_name: None,
             last_error_value: None,
+            recursion: None,
+            thread_environment_block: None,
         }
     }
 
@@ -374,6 +444,8 @@ impl CallStack {
             thread_id: id,
             thread_name: None,
             last_error_value: None,
+            recursion: None,
+            thread_environment_block: None,
         }
     }
 
@@ -416,7 +488,17 @@ impl CallStack {
             writeln!(f, "<no frames>")?;
         }
         let mut frame_count = 0;
-        for frame in &self.frames {
+        for (stack_idx, frame) in self.frames.iter().enumerate() {
+            if let Some(recursion) = &self.recursion {
+                if recursion.start_frame == stack_idx {
+                    writeln!(
+                        f,
+                        "    -- Recursion: {}-frame cycle repeated {} times ({} frames) --",
+                        recursion.period, recursion.repeat_count, recursion.depth
+                    )?;
+                }
+            }
+
             // First print out inlines
             for inline in &frame.inlines {
                 // Frame number
@@ -527,15 +609,26 @@ impl CallStack {
                     CallingConvention::OtherThisCall => {
                         "non-windows thiscall [C++ member function]"
                     }
+                    CallingConvention::SysV => "amd64 System V",
+                    CallingConvention::Win64 => "Win64",
                 };
 
                 writeln!(f, "    Arguments (assuming {cc_summary})")?;
                 for (idx, arg) in args.args.iter().enumerate() {
                     if let Some(val) = arg.value {
+                        let trust = arg.trust.as_str();
                         if pointer_width == 4 {
-                            writeln!(f, "        arg {} ({}) = 0x{:08x}", idx, arg.name, val)?;
+                            writeln!(
+                                f,
+                                "        arg {} ({}) = 0x{:08x} [{}]",
+                                idx, arg.name, val, trust
+                            )?;
                         } else {
-                            writeln!(f, "        arg {} ({}) = 0x{:016x}", idx, arg.name, val)?;
+                            writeln!(
+                                f,
+                                "        arg {} ({}) = 0x{:016x} [{}]",
+                                idx, arg.name, val, trust
+                            )?;
                         }
                     } else {
                         writeln!(f, "        arg {} ({}) = <unknown>", idx, arg.name)?;
@@ -545,6 +638,18 @@ impl CallStack {
                 // it more readable.
                 writeln!(f)?;
             }
+
+            if let Some(recursion) = &self.recursion {
+                if recursion.collapsed_frames > 0
+                    && recursion.start_frame + recursion.period == stack_idx + 1
+                {
+                    writeln!(
+                        f,
+                        "    -- {} more recursive frames collapsed --",
+                        recursion.collapsed_frames
+                    )?;
+                }
+            }
         }
         Ok(())
     }
@@ -803,6 +908,22 @@ pub async fn walk_stack<P>(
     );
 }
 
+/// Symbolicate the frames of a stack which was captured by something other than
+/// [`walk_stack`], such as a stack trace saved by the crashed program itself.
+///
+/// No new frames are unwound.
+pub async fn symbolicate_stack<P>(
+    stack: &mut CallStack,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+) where
+    P: SymbolProvider + Sync,
+{
+    for frame in &mut stack.frames {
+        fill_source_line_info(frame, modules, symbol_provider).await;
+    }
+}
+
 /// Checks if we can dismiss the validity of an instruction based on our symbols,
 /// to refine the quality of each unwinder's instruction_seems_valid implementation.
 async fn instruction_seems_valid_by_symbols<P>(
@@ -1,10 +1,12 @@
 //! This module provides a `SymbolProvider` which uses local binary debuginfo.
 
-use super::{async_trait, FileError, FileKind, FillSymbolError, FrameSymbolizer, FrameWalker};
+use super::{
+    async_trait, FileError, FileKind, FillSymbolError, FrameSymbolizer, FrameWalker, SymbolSupplier,
+};
 use cachemap2::CacheMap;
 use framehop::Unwinder;
 use memmap2::Mmap;
-use minidump::{MinidumpModuleList, MinidumpSystemInfo, Module};
+use minidump::{MinidumpModule, MinidumpModuleList, MinidumpSystemInfo, Module};
 use object::read::{macho::FatArch, Architecture};
 use std::cell::UnsafeCell;
 use std::fs::File;
@@ -25,6 +27,7 @@ pub struct DebugInfoSymbolProvider {
 pub struct DebugInfoSymbolProviderBuilder {
     #[cfg(feature = "debuginfo-symbols")]
     enable_symbols: bool,
+    supplier: Option<Box<dyn SymbolSupplier + Send + Sync>>,
 }
 
 type ModuleData = std::borrow::Cow<'static, [u8]>;
@@ -173,16 +176,19 @@ mod wholesym_symbol_interface {
     }
 
     impl Impl {
-        pub async fn new(modules: &MinidumpModuleList) -> Self {
+        pub async fn new(modules: &[(&MinidumpModule, ModuleFiles)]) -> Self {
             let mut symbols = HashMap::new();
             let symbol_manager = SymbolManager::with_config(SymbolManagerConfig::new());
-            for module in modules.iter() {
-                let path = effective_debug_file(module, false);
+            for (module, files) in modules {
+                let path = match &files.symbols {
+                    Some(path) => path,
+                    None => continue,
+                };
                 if let Ok(sm) = symbol_manager
-                    .load_symbol_map_for_binary_at_path(&path, None)
+                    .load_symbol_map_for_binary_at_path(path, None)
                     .await
                 {
-                    symbols.insert(module.into(), Mutex::new(sm));
+                    symbols.insert((*module).into(), Mutex::new(sm));
                 }
             }
             Impl { symbols }
@@ -381,12 +387,55 @@ fn effective_debug_file(module: &dyn Module, unwind_info: bool) -> PathBuf {
     code_file_path.to_owned()
 }
 
+/// The files to read a module's debug info from.
+struct ModuleFiles {
+    /// The file with symbols, if symbols are enabled.
+    symbols: Option<PathBuf>,
+    /// The file with unwind info.
+    unwind: PathBuf,
+}
+
+impl ModuleFiles {
+    /// Find the files of `module`, which are looked up with `supplier` if
+    /// they aren't on the local system.
+    async fn find(
+        module: &(dyn Module + Sync),
+        symbols: bool,
+        supplier: Option<&(dyn SymbolSupplier + Send + Sync)>,
+    ) -> Self {
+        let mut files = ModuleFiles {
+            symbols: symbols.then(|| effective_debug_file(module, false)),
+            unwind: effective_debug_file(module, true),
+        };
+        let supplier = match supplier {
+            Some(supplier) => supplier,
+            None => return files,
+        };
+        if let Some(path) = &mut files.symbols {
+            if !path.exists() {
+                for file_kind in [FileKind::ExtraDebugInfo, FileKind::Binary] {
+                    if let Ok(found) = supplier.locate_file(module, file_kind).await {
+                        *path = found;
+                        break;
+                    }
+                }
+            }
+        }
+        if !files.unwind.exists() {
+            if let Ok(found) = supplier.locate_file(module, FileKind::Binary).await {
+                files.unwind = found;
+            }
+        }
+        files
+    }
+}
+
 fn load_unwind_module(
     module: &dyn Module,
+    path: &Path,
     arch: Architecture,
 ) -> Option<(Mmap, framehop::Module<ModuleData>)> {
-    let path = effective_debug_file(module, true);
-    let file = match File::open(&path) {
+    let file = match File::open(path) {
         Ok(file) => file,
         Err(e) => {
             tracing::warn!("failed to open {} for debug info: {e}", path.display());
@@ -415,13 +464,13 @@ fn load_unwind_module(
             return None;
         }
         Ok(object::read::FileKind::MachOFat64) => get_fat_macho_data(
-            &path,
+            path,
             data,
             object::read::macho::MachOFatFile64::parse(data),
             arch,
         )?,
         Ok(object::read::FileKind::MachOFat32) => get_fat_macho_data(
-            &path,
+            path,
             data,
             object::read::macho::MachOFatFile32::parse(data),
             arch,
@@ -487,6 +536,7 @@ impl Default for DebugInfoSymbolProviderBuilder {
         DebugInfoSymbolProviderBuilder {
             #[cfg(feature = "debuginfo-symbols")]
             enable_symbols: true,
+            supplier: None,
         }
     }
 }
@@ -510,6 +560,16 @@ impl DebugInfoSymbolProviderBuilder {
         self
     }
 
+    /// Look up the binaries and debug info of modules which aren't on the local system with
+    /// `supplier` (see [`SymbolSupplier::locate_file`]), e.g. to download them from a debuginfod
+    /// server.
+    ///
+    /// By default, only files on the local system are used.
+    pub fn supplier(mut self, supplier: impl SymbolSupplier + Send + Sync + 'static) -> Self {
+        self.supplier = Some(Box::new(supplier));
+        self
+    }
+
     /// Create the DebugInfoSymbolProvider.
     pub async fn build(
         self,
@@ -524,18 +584,33 @@ impl DebugInfoSymbolProviderBuilder {
             _ => unimplemented!(),
         };
 
+        #[cfg(feature = "debuginfo-symbols")]
+        let enable_symbols = self.enable_symbols;
+        #[cfg(not(feature = "debuginfo-symbols"))]
+        let enable_symbols = false;
+
+        // Look for the files of all modules at once, as the supplier may have
+        // to download them.
+        let supplier = self.supplier.as_deref();
+        let module_files: Vec<_> =
+            futures_util::future::join_all(modules.iter().map(|module| async move {
+                let files = ModuleFiles::find(module, enable_symbols, supplier).await;
+                (module, files)
+            }))
+            .await;
+
         #[cfg(not(feature = "debuginfo-symbols"))]
         let symbols: Box<dyn SymbolInterface + Send + Sync> = Box::new(NoSymbols);
 
         #[cfg(feature = "debuginfo-symbols")]
-        let symbols: Box<dyn SymbolInterface + Send + Sync> = if self.enable_symbols {
-            Box::new(wholesym_symbol_interface::Impl::new(modules).await)
+        let symbols: Box<dyn SymbolInterface + Send + Sync> = if enable_symbols {
+            Box::new(wholesym_symbol_interface::Impl::new(&module_files).await)
         } else {
             Box::new(NoSymbols)
         };
 
-        for module in modules.iter() {
-            if let Some((mapped, fhmodule)) = load_unwind_module(module, arch) {
+        for (module, files) in &module_files {
+            if let Some((mapped, fhmodule)) = load_unwind_module(*module, &files.unwind, arch) {
                 mapped_modules.push(mapped);
                 unwinder.add_module(fhmodule);
             }
@@ -22,7 +22,15 @@
 //!
 //! * [http_symbol_supplier][] - a [SymbolSupplier][] that can find symbols over HTTP (and cache).
 //!   Requires the `http` feature to be enabled.
+//! * [debuginfod_symbol_supplier][] - a [SymbolSupplier][] that can find binaries and debug info
+//!   on debuginfod servers, for the `debuginfo` provider. Requires the `http` feature to be
+//!   enabled.
+//! * [symstore_symbol_supplier][] - a [SymbolSupplier][] that can find binaries and debug info
+//!   in Microsoft symbol stores, on disk or over HTTP. Requires the `symstore` feature to be
+//!   enabled.
 //! * [simple_symbol_supplier][] - a [SymbolSupplier][] that can find symbols on disk.
+//! * [archive_symbol_supplier][] - a [SymbolSupplier][] that can find symbols in zip and tar
+//!   archives. Requires the `archive` feature to be enabled.
 //! * [string_symbol_supplier][] - a mock [SymbolSupplier][] for tests.
 //!
 //!
@@ -54,8 +62,17 @@ use minidump::Module;
 
 pub use breakpad_symbols::{
     DebugInfoResult, FileError, FileKind, FillSymbolError, FrameSymbolizer, FrameWalker,
-    LocateSymbolsResult, PendingSymbolStats, SymbolError, SymbolFile, SymbolStats, SymbolSupplier,
-    Symbolizer,
+    LocateSymbolsResult, PendingSymbolStats, SymbolCacheLimits, SymbolError, SymbolFile,
+    SymbolStats, SymbolSupplier, Symbolizer, SymbolizerSession,
+};
+
+#[cfg(feature = "archive")]
+pub use breakpad_symbols::is_symbol_archive;
+
+#[cfg(feature = "http")]
+pub use breakpad_symbols::{
+    debuginfod_urls_from_env, redact_url, Authorization, CredentialsError, ServerCredentials,
+    ServerMatcher, SymbolServerCredentials,
 };
 
 #[cfg(feature = "debuginfo-unwind")]
@@ -125,12 +142,10 @@ pub trait SymbolProvider {
     /// It's als possible we'd want it to also be able to contain stats
     /// that don't really make sense in intermediate states.
     ///
-    /// In a world where you might want to have one SymbolSupplier shared
-    /// by multiple instances of `process` running in parallel, it's unclear
-    /// if this is the right abstraction. Perhaps we should have some kind
-    /// of "session" abstraction so you can get stats about each individual
-    /// processing task? Of course all pooling/caching between the tasks
-    /// muddies things too.
+    /// If one [`Symbolizer`] is shared by multiple instances of `process`
+    /// running in parallel, its stats cover all of them. Give each instance
+    /// its own [`SymbolizerSession`] to get stats about that processing task
+    /// only.
     fn stats(&self) -> HashMap<String, SymbolStats> {
         HashMap::new()
     }
@@ -293,6 +308,37 @@ impl SymbolProvider for Symbolizer {
     }
 }
 
+#[async_trait]
+impl SymbolProvider for SymbolizerSession {
+    async fn fill_symbol(
+        &self,
+        module: &(dyn Module + Sync),
+        frame: &mut (dyn FrameSymbolizer + Send),
+    ) -> Result<(), FillSymbolError> {
+        self.fill_symbol(module, frame).await
+    }
+    async fn walk_frame(
+        &self,
+        module: &(dyn Module + Sync),
+        walker: &mut (dyn FrameWalker + Send),
+    ) -> Option<()> {
+        self.walk_frame(module, walker).await
+    }
+    async fn get_file_path(
+        &self,
+        module: &(dyn Module + Sync),
+        file_kind: FileKind,
+    ) -> Result<PathBuf, FileError> {
+        self.get_file_path(module, file_kind).await
+    }
+    fn stats(&self) -> HashMap<String, SymbolStats> {
+        self.stats()
+    }
+    fn pending_stats(&self) -> PendingSymbolStats {
+        self.pending_stats()
+    }
+}
+
 /// Gets a SymbolSupplier that looks up symbols by path or with urls.
 ///
 /// * `symbols_paths` is a list of paths to check for symbol files. Paths
@@ -317,6 +363,9 @@ impl SymbolProvider for Symbolizer {
 ///   As of this writing, minidump-stackwalk defaults this to 1000 seconds. In
 ///   the event of a timeout, the supplier may still try to parse the truncated
 ///   download.
+///
+/// Credentials for the symbol servers can be added to the returned supplier
+/// with `credentials` (see [`SymbolServerCredentials`]).
 #[cfg(feature = "http")]
 pub fn http_symbol_supplier(
     symbol_paths: Vec<PathBuf>,
@@ -324,7 +373,7 @@ pub fn http_symbol_supplier(
     symbols_cache: PathBuf,
     symbols_tmp: PathBuf,
     timeout: std::time::Duration,
-) -> impl SymbolSupplier {
+) -> breakpad_symbols::HttpSymbolSupplier {
     breakpad_symbols::HttpSymbolSupplier::new(
         symbol_urls,
         symbols_cache,
@@ -334,6 +383,47 @@ pub fn http_symbol_supplier(
     )
 }
 
+/// Gets a SymbolSupplier that downloads binaries and debug info from debuginfod servers.
+///
+/// Only [`SymbolSupplier::locate_file`] is supported (debuginfod servers don't
+/// have breakpad symbols), so this is meant to be used with
+/// `debuginfo::DebugInfoSymbolProviderBuilder::supplier`.
+///
+/// `symbols_cache`, `symbols_tmp` and `timeout` are as for [`http_symbol_supplier`],
+/// and so are credentials.
+#[cfg(feature = "http")]
+pub fn debuginfod_symbol_supplier(
+    debuginfod_urls: Vec<String>,
+    symbols_cache: PathBuf,
+    symbols_tmp: PathBuf,
+    timeout: std::time::Duration,
+) -> breakpad_symbols::DebuginfodSymbolSupplier {
+    breakpad_symbols::DebuginfodSymbolSupplier::new(
+        debuginfod_urls,
+        symbols_cache,
+        symbols_tmp,
+        timeout,
+    )
+}
+
+/// Gets a SymbolSupplier that looks up files in Microsoft symbol stores.
+///
+/// Each of `symstores` is either a local path or an HTTP URL, and they are
+/// queried in order until one returns a payload. Two-tier stores, `file.ptr`
+/// redirections and CAB-compressed files are supported.
+///
+/// `symbols_cache`, `symbols_tmp` and `timeout` are as for [`http_symbol_supplier`],
+/// and so are credentials.
+#[cfg(feature = "symstore")]
+pub fn symstore_symbol_supplier(
+    symstores: Vec<String>,
+    symbols_cache: PathBuf,
+    symbols_tmp: PathBuf,
+    timeout: std::time::Duration,
+) -> breakpad_symbols::SymstoreSymbolSupplier {
+    breakpad_symbols::SymstoreSymbolSupplier::new(symstores, symbols_cache, symbols_tmp, timeout)
+}
+
 /// Gets a SymbolSupplier that looks up symbols by path.
 ///
 /// Paths are queried in order until one returns a payload.
@@ -341,6 +431,17 @@ pub fn simple_symbol_supplier(symbol_paths: Vec<PathBuf>) -> impl SymbolSupplier
     breakpad_symbols::SimpleSymbolSupplier::new(symbol_paths)
 }
 
+/// Gets a SymbolSupplier that looks up symbols in zip or tar archives.
+///
+/// Archives are queried in order until one returns a payload. Fails if any
+/// of the archives can't be read.
+#[cfg(feature = "archive")]
+pub fn archive_symbol_supplier(
+    archive_paths: Vec<PathBuf>,
+) -> std::io::Result<impl SymbolSupplier> {
+    breakpad_symbols::ArchiveSymbolSupplier::new(archive_paths)
+}
+
 /// Gets a mock SymbolSupplier that just maps module names
 /// to a string containing an entire breakpad .sym file, for tests.
 pub fn string_symbol_supplier(modules: HashMap<String, String>) -> impl SymbolSupplier {
@@ -11,7 +11,7 @@ use scroll::{Pread, BE, LE};
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::fmt;
 use std::fs::File;
 use std::io;
@@ -349,6 +349,96 @@ pub struct MinidumpThreadList<'a> {
     thread_ids: HashMap<u32, usize>,
 }
 
+/// The Windows Process Environment Block (PEB), read from process memory.
+///
+/// This is only available if the minidump contains the memory of the PEB, which
+/// usually means it's a full-memory dump. Use [`MinidumpProcessEnvironmentBlock::read`]
+/// to find it through the TEB of any of the process's threads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinidumpProcessEnvironmentBlock {
+    /// The address of the PEB.
+    pub address: u64,
+    /// Whether this is the 32-bit PEB of a WOW64 process.
+    ///
+    /// The TEB recorded for threads of WOW64 processes is the 64-bit one, in which case we
+    /// follow it to the 32-bit TEB and PEB.
+    pub wow64: bool,
+    /// The width of the pointers in the PEB.
+    pub pointer_width: PointerWidth,
+    /// Whether the process was being debugged.
+    pub being_debugged: bool,
+    /// The base address of the process's main image.
+    pub image_base_address: u64,
+    /// The address of the loader data (`PEB_LDR_DATA`).
+    pub ldr: u64,
+    /// The address of the process parameters (`RTL_USER_PROCESS_PARAMETERS`).
+    pub process_parameters_address: u64,
+    /// The process parameters, if their memory is present in the minidump.
+    pub process_parameters: Option<MinidumpProcessParameters>,
+}
+
+/// The parameters a Windows process was started with (`RTL_USER_PROCESS_PARAMETERS`).
+///
+/// Each string is `None` if it's null or its memory isn't present in the minidump.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MinidumpProcessParameters {
+    /// The full path of the main executable.
+    pub image_path_name: Option<String>,
+    /// The command line the process was started with.
+    pub command_line: Option<String>,
+    /// The current working directory of the process.
+    pub current_directory: Option<String>,
+    /// The title of the process's main window (or the shortcut it was started with).
+    pub window_title: Option<String>,
+    /// The environment variables of the process, as `(name, value)` pairs in block order.
+    ///
+    /// Windows keeps the per-drive current directories in the environment as variables
+    /// with names like `=C:`; these are included.
+    pub environment: Option<Vec<(String, String)>>,
+}
+
+/// A Windows Thread Environment Block (TEB), read from process memory.
+///
+/// Only the `NT_TIB` header is required to be present in the minidump; the fields
+/// further into the TEB are `None` if their memory is missing. Use
+/// [`MinidumpThread::teb`] to read it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinidumpThreadEnvironmentBlock {
+    /// The address of the TEB.
+    pub address: u64,
+    /// Whether this is the 32-bit TEB of a WOW64 thread.
+    pub wow64: bool,
+    /// The width of the pointers in the TEB.
+    pub pointer_width: PointerWidth,
+    /// The highest address of the thread's current stack (exclusive).
+    ///
+    /// For threads running a fiber, this is the fiber's stack.
+    pub stack_base: u64,
+    /// The lowest committed address of the thread's current stack.
+    pub stack_limit: u64,
+    /// The lowest reserved address of the thread's stack, including the guard pages.
+    pub deallocation_stack: Option<u64>,
+    /// The fiber data of the current fiber, if the thread was converted to a fiber.
+    pub fiber_data: u64,
+    /// The GetLastError() value.
+    pub last_error_value: Option<u32>,
+    /// The last NTSTATUS returned by a system call which failed.
+    pub last_status_value: Option<u32>,
+    /// The first 64 thread-local storage slots (as used by TlsAlloc/TlsGetValue).
+    pub tls_slots: Option<Vec<u64>>,
+}
+
+impl MinidumpThreadEnvironmentBlock {
+    /// Whether `address` is within the thread's current stack.
+    ///
+    /// The deallocation stack is used as the lower bound if it's known, because the
+    /// committed part of the stack grows into it.
+    pub fn stack_contains(&self, address: u64) -> bool {
+        let limit = self.deallocation_stack.unwrap_or(self.stack_limit);
+        (limit..self.stack_base).contains(&address)
+    }
+}
+
 /// The state of a thread from the process when the minidump was written.
 #[derive(Debug)]
 pub struct MinidumpThreadInfo {
@@ -2679,6 +2769,26 @@ impl MinidumpLinuxMapInfo<'_> {
         self.map.perms.contains(MMPermissions::EXECUTE)
     }
 
+    /// The name of this mapping: the path of the file backing it, or a pseudo-path like
+    /// `[heap]`. Returns `None` for anonymous mappings.
+    pub fn name(&self) -> Option<Cow<'_, str>> {
+        use procfs_core::process::MMapPath;
+
+        Some(match &self.map.pathname {
+            MMapPath::Path(path) => path.to_string_lossy(),
+            MMapPath::Heap => Cow::Borrowed("[heap]"),
+            MMapPath::Stack => Cow::Borrowed("[stack]"),
+            MMapPath::TStack(tid) => Cow::Owned(format!("[stack:{tid}]")),
+            MMapPath::Vdso => Cow::Borrowed("[vdso]"),
+            MMapPath::Vvar => Cow::Borrowed("[vvar]"),
+            MMapPath::Vsyscall => Cow::Borrowed("[vsyscall]"),
+            MMapPath::Rollup => Cow::Borrowed("[rollup]"),
+            MMapPath::Vsys(key) => Cow::Owned(format!("/SYSV{key:08x}")),
+            MMapPath::Other(name) => Cow::Borrowed(name.as_str()),
+            MMapPath::Anonymous => return None,
+        })
+    }
+
     #[cfg(test)]
     pub fn from_line(bytes: &[u8]) -> Option<Self> {
         let map = MemoryMaps::from_read(std::io::Cursor::new(bytes))
@@ -2828,6 +2938,16 @@ impl UnifiedMemoryInfo<'_> {
         /// Whether this memory range was executable.
         pub fn is_executable(&self) -> bool;
     }
+
+    /// The name of this memory range, if known.
+    ///
+    /// Only Linux mappings have names (see [`MinidumpLinuxMapInfo::name`]).
+    pub fn name(&self) -> Option<Cow<'_, str>> {
+        match self {
+            UnifiedMemoryInfo::Map(map) => map.name(),
+            UnifiedMemoryInfo::Info(_) => None,
+        }
+    }
 }
 
 impl<'a> MinidumpThread<'a> {
@@ -2970,6 +3090,271 @@ impl<'a> MinidumpThread<'a> {
 
         Some(CrashReason::from_windows_error(val))
     }
+
+    /// Read this thread's TEB.
+    ///
+    /// This requires the memory of the TEB to be present in the minidump, which is usually
+    /// the case for Windows minidumps which include thread data.
+    pub fn teb(
+        &self,
+        cpu: Cpu,
+        memory: &UnifiedMemoryList,
+    ) -> Option<MinidumpThreadEnvironmentBlock> {
+        let (address, width) = self.teb_layout(cpu, memory)?;
+        let wow64 = width == PointerWidth::Bits32 && address != self.raw.teb;
+        let field = |offset: u64| address.checked_add(offset);
+        let read_u32 = |offset: u64| -> Option<u32> {
+            let addr = field(offset)?;
+            memory.memory_at_address(addr)?.get_memory_at_address(addr)
+        };
+        let read_field = |offset: u64| read_ptr(memory, field(offset)?, width);
+        let ptr_size = width.size_in_bytes()? as u64;
+
+        // Offsets of (LastErrorValue, LastStatusValue, DeallocationStack, TlsSlots).
+        let (last_error_offset, last_status_offset, deallocation_stack_offset, tls_offset) =
+            match width {
+                PointerWidth::Bits32 => (0x34, 0xbf4, 0xe0c, 0xe10),
+                _ => (0x68, 0x1250, 0x1478, 0x1480),
+            };
+        let tls_slots = (0..TLS_MINIMUM_AVAILABLE)
+            .map(|i| read_field(i.checked_mul(ptr_size)?.checked_add(tls_offset)?))
+            .collect();
+
+        Some(MinidumpThreadEnvironmentBlock {
+            address,
+            wow64,
+            pointer_width: width,
+            stack_base: read_field(ptr_size)?,
+            stack_limit: read_field(2 * ptr_size)?,
+            deallocation_stack: read_field(deallocation_stack_offset),
+            fiber_data: read_field(4 * ptr_size)?,
+            last_error_value: read_u32(last_error_offset),
+            last_status_value: read_u32(last_status_offset),
+            tls_slots,
+        })
+    }
+
+    /// Find this thread's TEB, returning its address and the width of its pointers.
+    ///
+    /// The TEB's `NtTib.Self` field is checked to make sure we're actually looking at a
+    /// TEB of the expected layout. For 32-bit processes under WOW64 the recorded TEB may be
+    /// the 64-bit one, in which case the 32-bit TEB which follows it is returned instead.
+    fn teb_layout(&self, cpu: Cpu, memory: &UnifiedMemoryList) -> Option<(u64, PointerWidth)> {
+        let teb = self.raw.teb;
+        if teb == 0 {
+            return None;
+        }
+        let is_teb32 = |addr: u64| {
+            addr.checked_add(0x18)
+                .and_then(|self_ptr| read_ptr(memory, self_ptr, PointerWidth::Bits32))
+                == Some(addr)
+        };
+        let is_teb64 = |addr: u64| {
+            addr.checked_add(0x30)
+                .and_then(|self_ptr| read_ptr(memory, self_ptr, PointerWidth::Bits64))
+                == Some(addr)
+        };
+        match cpu.pointer_width() {
+            PointerWidth::Bits64 if is_teb64(teb) => Some((teb, PointerWidth::Bits64)),
+            PointerWidth::Bits32 if is_teb32(teb) => Some((teb, PointerWidth::Bits32)),
+            PointerWidth::Bits32 if is_teb64(teb) => {
+                // WOW64: the 32-bit TEB follows the 64-bit one.
+                let teb32 = teb.checked_add(WOW64_TEB32_OFFSET)?;
+                if is_teb32(teb32) {
+                    Some((teb32, PointerWidth::Bits32))
+                } else {
+                    Some((teb, PointerWidth::Bits64))
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The offset of a WOW64 thread's 32-bit TEB from its 64-bit TEB.
+const WOW64_TEB32_OFFSET: u64 = 0x2000;
+
+/// The number of TLS slots stored in the TEB itself.
+const TLS_MINIMUM_AVAILABLE: u64 = 64;
+
+/// Read a pointer of the given width from process memory.
+fn read_ptr(memory: &UnifiedMemoryList, addr: u64, width: PointerWidth) -> Option<u64> {
+    let region = memory.memory_at_address(addr)?;
+    match width {
+        PointerWidth::Bits32 => region.get_memory_at_address::<u32>(addr).map(u64::from),
+        PointerWidth::Bits64 => region.get_memory_at_address::<u64>(addr),
+        PointerWidth::Unknown => None,
+    }
+}
+
+/// Read `len` bytes of UTF-16 from process memory.
+fn read_utf16(memory: &UnifiedMemoryList, addr: u64, len: usize) -> Option<String> {
+    let region = memory.memory_at_address(addr)?;
+    let offset = usize::try_from(addr - region.base_address()).ok()?;
+    let bytes = region.bytes().get(offset..offset.checked_add(len)?)?;
+    let units = bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect::<Vec<_>>();
+    Some(String::from_utf16_lossy(&units))
+}
+
+/// The maximum size of an environment block we'll read, in bytes.
+const MAX_ENVIRONMENT_SIZE: usize = 1 << 20;
+
+/// Read a `NAME=VALUE\0...\0\0` environment block from process memory.
+///
+/// If `size` is unknown, the block is read up to its terminator or the end of the memory
+/// region containing it.
+fn read_environment_block(
+    memory: &UnifiedMemoryList,
+    addr: u64,
+    size: Option<usize>,
+) -> Option<Vec<(String, String)>> {
+    let region = memory.memory_at_address(addr)?;
+    let offset = usize::try_from(addr - region.base_address()).ok()?;
+    let available = region.bytes().get(offset..)?;
+    let len = size
+        .unwrap_or(available.len())
+        .min(available.len())
+        .min(MAX_ENVIRONMENT_SIZE);
+    let units = available[..len - len % 2]
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect::<Vec<_>>();
+
+    let vars = units
+        .split(|&unit| unit == 0)
+        .take_while(|var| !var.is_empty())
+        .map(|var| {
+            let var = String::from_utf16_lossy(var);
+            // Skip the first character so that the `=C:=C:\foo` variables are split correctly.
+            match var.char_indices().skip(1).find(|&(_, c)| c == '=') {
+                Some((idx, _)) => (var[..idx].to_owned(), var[idx + 1..].to_owned()),
+                None => (var, String::new()),
+            }
+        })
+        .collect();
+    Some(vars)
+}
+
+impl MinidumpProcessEnvironmentBlock {
+    /// Find and read the PEB through the TEB of the first thread for which it's possible.
+    ///
+    /// This requires the memory of the TEB and PEB to be present in the minidump, which is
+    /// generally only true of full-memory dumps.
+    pub fn read(
+        threads: &MinidumpThreadList,
+        cpu: Cpu,
+        memory: &UnifiedMemoryList,
+    ) -> Option<MinidumpProcessEnvironmentBlock> {
+        threads
+            .threads
+            .iter()
+            .find_map(|thread| Self::read_from_thread(thread, cpu, memory))
+    }
+
+    /// Read the PEB through the TEB of `thread`.
+    pub fn read_from_thread(
+        thread: &MinidumpThread,
+        cpu: Cpu,
+        memory: &UnifiedMemoryList,
+    ) -> Option<MinidumpProcessEnvironmentBlock> {
+        let (teb, width) = thread.teb_layout(cpu, memory)?;
+        let wow64 = width == PointerWidth::Bits32 && teb != thread.raw.teb;
+
+        // Offsets of (TEB.ProcessEnvironmentBlock, PEB.ImageBaseAddress, PEB.Ldr,
+        // PEB.ProcessParameters) for each layout.
+        let (peb_offset, image_base_offset, ldr_offset, params_offset) = match width {
+            PointerWidth::Bits32 => (0x30, 0x08, 0x0c, 0x10),
+            _ => (0x60, 0x10, 0x18, 0x20),
+        };
+        let address = read_ptr(memory, teb.checked_add(peb_offset)?, width)?;
+        let field = |offset: u64| address.checked_add(offset);
+        let being_debugged: u8 = memory
+            .memory_at_address(field(2)?)?
+            .get_memory_at_address(field(2)?)?;
+        let image_base_address = read_ptr(memory, field(image_base_offset)?, width)?;
+        let ldr = read_ptr(memory, field(ldr_offset)?, width)?;
+        let process_parameters_address = read_ptr(memory, field(params_offset)?, width)?;
+        let process_parameters =
+            MinidumpProcessParameters::read(memory, process_parameters_address, width);
+
+        Some(MinidumpProcessEnvironmentBlock {
+            address,
+            wow64,
+            pointer_width: width,
+            being_debugged: being_debugged != 0,
+            image_base_address,
+            ldr,
+            process_parameters_address,
+            process_parameters,
+        })
+    }
+}
+
+impl MinidumpProcessParameters {
+    /// Read the `RTL_USER_PROCESS_PARAMETERS` at `address`.
+    fn read(
+        memory: &UnifiedMemoryList,
+        address: u64,
+        width: PointerWidth,
+    ) -> Option<MinidumpProcessParameters> {
+        // Offsets of (Flags, CurrentDirectory, ImagePathName, CommandLine, Environment,
+        // WindowTitle, EnvironmentSize) and the size of a UNICODE_STRING for each layout.
+        let (flags, cur_dir, image_path, command_line, environment, title, env_size, ustr) =
+            match width {
+                PointerWidth::Bits32 => (0x08, 0x24, 0x38, 0x40, 0x48, 0x70, 0x290, 8),
+                _ => (0x08, 0x38, 0x60, 0x70, 0x80, 0xb0, 0x3f0, 16),
+            };
+        let pointer_size = ustr / 2;
+        let field = |offset: u64| address.checked_add(offset);
+        let region = memory.memory_at_address(address)?;
+        let length: u32 = region.get_memory_at_address(field(4)?)?;
+        let flags: u32 = region.get_memory_at_address(field(flags)?)?;
+        // Until the parameters are "normalized", the string buffers are offsets from the
+        // start of the parameters.
+        const RTL_USER_PROC_PARAMS_NORMALIZED: u32 = 0x1;
+        let normalized = flags & RTL_USER_PROC_PARAMS_NORMALIZED != 0;
+        let fixup = |ptr: u64| {
+            if normalized {
+                Some(ptr)
+            } else {
+                address.checked_add(ptr)
+            }
+        };
+
+        let unicode_string = |offset: u64| -> Option<String> {
+            let len: u16 = region.get_memory_at_address(field(offset)?)?;
+            let buffer = read_ptr(memory, field(offset.checked_add(pointer_size)?)?, width)?;
+            if buffer == 0 {
+                return None;
+            }
+            if len == 0 {
+                return Some(String::new());
+            }
+            read_utf16(memory, fixup(buffer)?, len as usize)
+        };
+
+        // EnvironmentSize was added in Vista, so check that the struct is large enough.
+        let environment_size = (length as u64 >= env_size + pointer_size)
+            .then(|| read_ptr(memory, field(env_size)?, width))
+            .flatten()
+            .and_then(|size| usize::try_from(size).ok())
+            .filter(|&size| size != 0);
+        let environment = field(environment)
+            .and_then(|environment| read_ptr(memory, environment, width))
+            .filter(|&env| env != 0)
+            .and_then(|env| read_environment_block(memory, fixup(env)?, environment_size));
+
+        Some(MinidumpProcessParameters {
+            image_path_name: unicode_string(image_path),
+            command_line: unicode_string(command_line),
+            current_directory: unicode_string(cur_dir),
+            window_title: unicode_string(title),
+            environment,
+        })
+    }
 }
 
 impl<'a> MinidumpStream<'a> for MinidumpThreadList<'a> {
@@ -7101,6 +7486,237 @@ mod test {
         assert_eq!(stack.size, 0x1000);
     }
 
+    fn utf16(string: &str) -> Vec<u8> {
+        string.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    /// Write `bytes` into `buf` at `offset`.
+    fn put(buf: &mut [u8], offset: usize, bytes: &[u8]) {
+        buf[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    #[test]
+    fn test_peb_amd64() {
+        let teb_addr = 0x7ff0_0000u64;
+        let mut teb = vec![0; 0x100];
+        put(&mut teb, 0x30, &teb_addr.to_le_bytes()); // NtTib.Self
+        put(&mut teb, 0x60, &0x7ff1_0000u64.to_le_bytes()); // ProcessEnvironmentBlock
+
+        let mut peb = vec![0; 0x100];
+        peb[2] = 1; // BeingDebugged
+        put(&mut peb, 0x10, &0x1_4000_0000u64.to_le_bytes()); // ImageBaseAddress
+        put(&mut peb, 0x18, &0x7ff2_0000u64.to_le_bytes()); // Ldr
+        put(&mut peb, 0x20, &0x5000u64.to_le_bytes()); // ProcessParameters
+
+        let strings = [
+            (0x38, "C:\\Users\\me\\"),
+            (0x60, "C:\\app\\app.exe"),
+            (0x70, "app.exe --flag"),
+            (0xb0, "App"),
+        ];
+        let mut params = vec![0; 0x800];
+        put(&mut params, 0x4, &0x400u32.to_le_bytes()); // Length
+        put(&mut params, 0x8, &1u32.to_le_bytes()); // Flags: normalized
+        for (i, (offset, string)) in strings.iter().enumerate() {
+            let buffer = 0x5400 + i * 0x80;
+            let string = utf16(string);
+            put(&mut params, *offset, &(string.len() as u16).to_le_bytes());
+            put(&mut params, offset + 8, &(buffer as u64).to_le_bytes());
+            put(&mut params, buffer - 0x5000, &string);
+        }
+        let environment = utf16("=C:=C:\\app\0PATH=C:\\bin\0EMPTY=\0\0");
+        put(&mut params, 0x80, &0x7000u64.to_le_bytes()); // Environment
+        put(
+            &mut params,
+            0x3f0,
+            &(environment.len() as u64).to_le_bytes(),
+        ); // EnvironmentSize
+
+        let context = minidump_synth::amd64_context(Endian::Little, 0, 0);
+        let stack = Memory::with_section(Section::with_endian(Endian::Little).D64(0), 0x1000);
+        let thread = Thread::with_teb(Endian::Little, 1, teb_addr, &stack, &context);
+        let arch = md::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16;
+        let system_info = SystemInfo::new(Endian::Little).set_processor_architecture(arch);
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_thread(thread)
+            .add(context)
+            .add_memory(stack)
+            .add_memory(Memory::with_section(
+                Section::with_endian(Endian::Little).append_bytes(&teb),
+                teb_addr,
+            ))
+            .add_memory(Memory::with_section(
+                Section::with_endian(Endian::Little).append_bytes(&peb),
+                0x7ff1_0000,
+            ))
+            .add_memory(Memory::with_section(
+                Section::with_endian(Endian::Little).append_bytes(&params),
+                0x5000,
+            ))
+            .add_memory(Memory::with_section(
+                Section::with_endian(Endian::Little).append_bytes(&environment),
+                0x7000,
+            ))
+            .add_system_info(system_info);
+        let dump = read_synth_dump(dump).unwrap();
+        let threads = dump.get_stream::<MinidumpThreadList<'_>>().unwrap();
+        let memory = dump.get_memory().unwrap();
+
+        let peb = MinidumpProcessEnvironmentBlock::read(&threads, Cpu::X86_64, &memory).unwrap();
+        assert_eq!(peb.address, 0x7ff1_0000);
+        assert!(!peb.wow64);
+        assert_eq!(peb.pointer_width, PointerWidth::Bits64);
+        assert!(peb.being_debugged);
+        assert_eq!(peb.image_base_address, 0x1_4000_0000);
+        assert_eq!(peb.ldr, 0x7ff2_0000);
+
+        let params = peb.process_parameters.unwrap();
+        assert_eq!(params.current_directory.as_deref(), Some("C:\\Users\\me\\"));
+        assert_eq!(params.image_path_name.as_deref(), Some("C:\\app\\app.exe"));
+        assert_eq!(params.command_line.as_deref(), Some("app.exe --flag"));
+        assert_eq!(params.window_title.as_deref(), Some("App"));
+        assert_eq!(
+            params.environment.unwrap(),
+            vec![
+                ("=C:".to_owned(), "C:\\app".to_owned()),
+                ("PATH".to_owned(), "C:\\bin".to_owned()),
+                ("EMPTY".to_owned(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_teb_amd64() {
+        let teb_addr = 0x7ff0_0000u64;
+        let mut teb = vec![0; 0x1800];
+        put(&mut teb, 0x08, &0x9000u64.to_le_bytes()); // NtTib.StackBase
+        put(&mut teb, 0x10, &0x8000u64.to_le_bytes()); // NtTib.StackLimit
+        put(&mut teb, 0x20, &0x1234u64.to_le_bytes()); // NtTib.FiberData
+        put(&mut teb, 0x30, &teb_addr.to_le_bytes()); // NtTib.Self
+        put(&mut teb, 0x68, &5u32.to_le_bytes()); // LastErrorValue
+        put(&mut teb, 0x1250, &0xc000_0022u32.to_le_bytes()); // LastStatusValue
+        put(&mut teb, 0x1478, &0x4000u64.to_le_bytes()); // DeallocationStack
+        put(&mut teb, 0x1480 + 3 * 8, &0xabcdu64.to_le_bytes()); // TlsSlots[3]
+
+        let context = minidump_synth::amd64_context(Endian::Little, 0, 0x8800);
+        let stack = Memory::with_section(Section::with_endian(Endian::Little).D64(0), 0x8800);
+        let thread = Thread::with_teb(Endian::Little, 1, teb_addr, &stack, &context);
+        let arch = md::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16;
+        let system_info = SystemInfo::new(Endian::Little).set_processor_architecture(arch);
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_thread(thread)
+            .add(context)
+            .add_memory(stack)
+            .add_memory(Memory::with_section(
+                Section::with_endian(Endian::Little).append_bytes(&teb),
+                teb_addr,
+            ))
+            .add_system_info(system_info);
+        let dump = read_synth_dump(dump).unwrap();
+        let threads = dump.get_stream::<MinidumpThreadList<'_>>().unwrap();
+        let memory = dump.get_memory().unwrap();
+
+        let teb = threads.threads[0].teb(Cpu::X86_64, &memory).unwrap();
+        assert_eq!(teb.address, teb_addr);
+        assert!(!teb.wow64);
+        assert_eq!(teb.stack_base, 0x9000);
+        assert_eq!(teb.stack_limit, 0x8000);
+        assert_eq!(teb.deallocation_stack, Some(0x4000));
+        assert_eq!(teb.fiber_data, 0x1234);
+        assert_eq!(teb.last_error_value, Some(5));
+        assert_eq!(teb.last_status_value, Some(0xc000_0022));
+        let tls_slots = teb.tls_slots.as_ref().unwrap();
+        assert_eq!(tls_slots.len(), 64);
+        assert_eq!(tls_slots[3], 0xabcd);
+        assert!(teb.stack_contains(0x8800));
+        assert!(teb.stack_contains(0x4000));
+        assert!(!teb.stack_contains(0x9000));
+        assert!(!teb.stack_contains(0x3ff8));
+    }
+
+    #[test]
+    fn test_peb_wow64() {
+        // The recorded TEB is the 64-bit one, followed by the 32-bit TEB.
+        let teb_addr = 0x7ff0_0000u64;
+        let mut teb = vec![0; 0x2100];
+        put(&mut teb, 0x30, &teb_addr.to_le_bytes()); // NtTib.Self (64-bit)
+        put(&mut teb, 0x2018, &(teb_addr as u32 + 0x2000).to_le_bytes()); // NtTib.Self (32-bit)
+        put(&mut teb, 0x2030, &0x7ff3_0000u32.to_le_bytes()); // ProcessEnvironmentBlock
+
+        let mut peb = vec![0; 0x100];
+        put(&mut peb, 0x08, &0x40_0000u32.to_le_bytes()); // ImageBaseAddress
+        put(&mut peb, 0x10, &0x5000u32.to_le_bytes()); // ProcessParameters
+
+        // Not normalized, so the buffers are offsets from the parameters.
+        let mut params = vec![0; 0x300];
+        // Too short to include all of EnvironmentSize, so it's ignored.
+        put(&mut params, 0x4, &0x292u32.to_le_bytes()); // Length
+        let command_line = utf16("app.exe /s");
+        put(
+            &mut params,
+            0x40,
+            &(command_line.len() as u16).to_le_bytes(),
+        );
+        put(&mut params, 0x44, &0x100u32.to_le_bytes());
+        put(&mut params, 0x100, &command_line);
+        put(&mut params, 0x74, &0x180u32.to_le_bytes()); // WindowTitle (empty)
+        put(&mut params, 0x48, &0x200u32.to_le_bytes()); // Environment
+        put(&mut params, 0x200, &utf16("A=1\0B=2\0\0"));
+        put(&mut params, 0x290, &4u32.to_le_bytes()); // EnvironmentSize
+
+        let context = minidump_synth::x86_context(Endian::Little, 0, 0);
+        let stack = Memory::with_section(Section::with_endian(Endian::Little).D32(0), 0x1000);
+        let thread = Thread::with_teb(Endian::Little, 1, teb_addr, &stack, &context);
+        let arch = md::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_INTEL as u16;
+        let system_info = SystemInfo::new(Endian::Little).set_processor_architecture(arch);
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_thread(thread)
+            .add(context)
+            .add_memory(stack)
+            .add_memory(Memory::with_section(
+                Section::with_endian(Endian::Little).append_bytes(&teb),
+                teb_addr,
+            ))
+            .add_memory(Memory::with_section(
+                Section::with_endian(Endian::Little).append_bytes(&peb),
+                0x7ff3_0000,
+            ))
+            .add_memory(Memory::with_section(
+                Section::with_endian(Endian::Little).append_bytes(&params),
+                0x5000,
+            ))
+            .add_system_info(system_info);
+        let dump = read_synth_dump(dump).unwrap();
+        let threads = dump.get_stream::<MinidumpThreadList<'_>>().unwrap();
+        let memory = dump.get_memory().unwrap();
+
+        let teb = threads.threads[0].teb(Cpu::X86, &memory).unwrap();
+        assert_eq!(teb.address, teb_addr + 0x2000);
+        assert!(teb.wow64);
+        assert_eq!(teb.pointer_width, PointerWidth::Bits32);
+        // The fields past the NT_TIB aren't in this dump.
+        assert_eq!(teb.deallocation_stack, None);
+        assert_eq!(teb.tls_slots, None);
+
+        let peb = MinidumpProcessEnvironmentBlock::read(&threads, Cpu::X86, &memory).unwrap();
+        assert_eq!(peb.address, 0x7ff3_0000);
+        assert!(peb.wow64);
+        assert_eq!(peb.pointer_width, PointerWidth::Bits32);
+        assert_eq!(peb.image_base_address, 0x40_0000);
+
+        let params = peb.process_parameters.unwrap();
+        assert_eq!(params.command_line.as_deref(), Some("app.exe /s"));
+        assert_eq!(params.window_title.as_deref(), Some(""));
+        assert_eq!(params.image_path_name, None);
+        assert_eq!(
+            params.environment.unwrap(),
+            vec![
+                ("A".to_owned(), "1".to_owned()),
+                ("B".to_owned(), "2".to_owned()),
+            ]
+        );
+    }
+
     #[test]
     fn test_crashpad_info_missing() {
         let dump = SynthMinidump::with_endian(Endian::Little);